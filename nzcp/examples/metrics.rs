@@ -0,0 +1,59 @@
+//! Serves pass verification alongside a Prometheus `/metrics` endpoint.
+//!
+//! ```sh
+//! cargo run --example metrics --features metrics
+//! curl -X POST --data 'NZCP:/1/2KCEVIQ...' localhost:3000/verify
+//! curl localhost:3000/metrics
+//! ```
+
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    routing::{get, post},
+    Router,
+};
+use nzcp::{PublicCovidPass, Verifier, VerifierMetrics};
+use prometheus::{Encoder, Registry, TextEncoder};
+
+struct AppState {
+    verifier: Verifier,
+    registry: Registry,
+}
+
+async fn verify(State(state): State<Arc<AppState>>, uri: String) -> (StatusCode, String) {
+    match state.verifier.verify_pass_uri::<PublicCovidPass>(uri.trim()).await {
+        Ok(pass) => (StatusCode::OK, format!("Valid pass for {}", pass.given_name)),
+        Err(error) => (StatusCode::UNPROCESSABLE_ENTITY, error.to_string()),
+    }
+}
+
+async fn serve_metrics(State(state): State<Arc<AppState>>) -> Result<String, StatusCode> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&state.registry.gather(), &mut buffer)
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?;
+    String::from_utf8(buffer).map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)
+}
+
+#[tokio::main]
+async fn main() {
+    let registry = Registry::new();
+    let metrics = VerifierMetrics::register(&registry).expect("metrics are registered once");
+    let state = Arc::new(AppState {
+        verifier: Verifier::default().with_metrics(Arc::new(metrics)),
+        registry,
+    });
+
+    let app = Router::new()
+        .route("/verify", post(verify))
+        .route("/metrics", get(serve_metrics))
+        .with_state(state);
+
+    let address = SocketAddr::from(([127, 0, 0, 1], 3000));
+    axum::Server::bind(&address)
+        .serve(app.into_make_service())
+        .await
+        .unwrap();
+}
@@ -0,0 +1,153 @@
+//! Blocking variants of the verification functions, for applications without an async runtime (requires the
+//! `blocking` feature).
+//!
+//! The async functions are driven on a current-thread tokio runtime shared by all blocking calls, so connections to
+//! issuers are reused between calls. Resolution still times out as configured on the HTTP client (e.g. with
+//! `reqwest::ClientBuilder::timeout` in `VerifierConfig::http_client`).
+
+use std::{future::Future, sync::OnceLock};
+
+use chrono::{DateTime, Utc};
+use tokio::runtime::{Builder, Handle, Runtime};
+
+use crate::{
+    error::NzcpError,
+    pass::{verify_pass_uri, verify_pass_with_config, Pass},
+    verifier::{VerifiedCredential, Verifier, VerifierConfig},
+};
+
+/// Run a future to completion on the shared runtime.
+///
+/// # Panics
+///
+/// If called from within an async runtime, where blocking would stall the runtime's other tasks.
+fn block_on<F: Future>(future: F) -> F::Output {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    assert!(
+        Handle::try_current().is_err(),
+        "blocking verification can't be used from within an async runtime, call the async functions instead"
+    );
+    RUNTIME
+        .get_or_init(|| {
+            Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .expect("failed to build the blocking verification runtime")
+        })
+        .block_on(future)
+}
+
+/// Verify a pass barcode URI like `verify_pass_uri`, blocking the current thread until done.
+///
+/// # Panics
+///
+/// If called from within an async runtime.
+pub fn verify_pass_uri_blocking<P: Pass>(uri: &str) -> Result<P, NzcpError> {
+    block_on(verify_pass_uri(uri))
+}
+
+/// Verify a pass barcode URI with the given options like `verify_pass_with_config`, blocking the current thread
+/// until done.
+///
+/// # Panics
+///
+/// If called from within an async runtime.
+pub fn verify_pass_with_config_blocking<P: Pass>(uri: &str, config: &VerifierConfig) -> Result<P, NzcpError> {
+    block_on(verify_pass_with_config(uri, config))
+}
+
+impl Verifier {
+    /// Verify a pass barcode URI like `verify_pass_uri`, blocking the current thread until done.
+    ///
+    /// # Panics
+    ///
+    /// If called from within an async runtime.
+    pub fn verify_pass_uri_blocking<P: Pass>(&self, uri: &str) -> Result<P, NzcpError> {
+        block_on(self.verify_pass_uri(uri))
+    }
+
+    /// Verify a pass barcode URI like `verify_credential_uri`, blocking the current thread until done.
+    ///
+    /// # Panics
+    ///
+    /// If called from within an async runtime.
+    pub fn verify_credential_uri_blocking<P: Pass>(&self, uri: &str) -> Result<VerifiedCredential<P>, NzcpError> {
+        block_on(self.verify_credential_uri(uri))
+    }
+
+    /// Verify a pass barcode URI like `verify_credential_uri_at`, blocking the current thread until done.
+    ///
+    /// # Panics
+    ///
+    /// If called from within an async runtime.
+    pub fn verify_credential_uri_at_blocking<P: Pass>(
+        &self,
+        uri: &str,
+        at: DateTime<Utc>,
+    ) -> Result<VerifiedCredential<P>, NzcpError> {
+        block_on(self.verify_credential_uri_at(uri, at))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        testing::{
+            example_issuer,
+            test_passes::{expired_pass, invalid_signature_pass},
+            test_vectors::scanned_at,
+            MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS,
+        },
+        PublicCovidPass,
+    };
+
+    fn verifier() -> Verifier {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver))
+    }
+
+    #[test]
+    fn verify_blocking() {
+        let verifier = verifier();
+
+        let pass = verifier
+            .verify_pass_uri_blocking::<PublicCovidPass>(VALID_PASS)
+            .unwrap();
+        assert_eq!(pass.given_name, "Jack");
+        let credential = verifier
+            .verify_credential_uri_blocking::<PublicCovidPass>(VALID_PASS)
+            .unwrap();
+        assert_eq!(credential.pass, pass);
+        let error = verifier
+            .verify_credential_uri_at_blocking::<PublicCovidPass>(expired_pass(), scanned_at())
+            .unwrap_err();
+        assert!(matches!(error, NzcpError::PassExpired { checked_at, .. } if checked_at == scanned_at()));
+
+        assert!(matches!(
+            verifier.verify_pass_uri_blocking::<PublicCovidPass>(invalid_signature_pass()),
+            Err(NzcpError::InvalidSignature(_))
+        ));
+
+        // from several threads at once
+        let verifier = Arc::new(verifier);
+        let threads = (0..4)
+            .map(|_| {
+                let verifier = Arc::clone(&verifier);
+                std::thread::spawn(move || verifier.verify_pass_uri_blocking::<PublicCovidPass>(VALID_PASS))
+            })
+            .collect::<Vec<_>>();
+        for thread in threads {
+            assert_eq!(thread.join().unwrap(), Ok(pass.clone()));
+        }
+    }
+
+    #[tokio::test]
+    #[should_panic(expected = "can't be used from within an async runtime")]
+    async fn panics_in_async_context() {
+        let _ = verifier().verify_pass_uri_blocking::<PublicCovidPass>(VALID_PASS);
+    }
+}
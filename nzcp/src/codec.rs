@@ -0,0 +1,209 @@
+//! Conversion between CBOR and JSON, e.g. for inspecting passes by hand.
+//!
+//! CBOR values map onto JSON as follows:
+//!
+//! - integers and floats become numbers, and text strings, arrays, booleans, and null are unchanged
+//! - byte strings become base64url (unpadded) strings
+//! - tagged values become `{ "_tag": n, "value": ... }` objects
+//! - map keys become strings, with integer keys written in decimal (e.g. the CWT claim `4` becomes `"4"`), and byte
+//!   string keys as base64url, failing for other keys or if two keys become the same string
+//!
+//! The conversion back from JSON can't tell which strings were byte strings or non-text keys, so they're encoded as
+//! text strings.
+
+use std::{collections::BTreeMap, sync::Arc};
+
+use serde_cbor::Value as CborValue;
+use serde_json::{Map, Number, Value as JsonValue};
+use thiserror::Error;
+
+const TAG_KEY: &str = "_tag";
+const TAGGED_VALUE_KEY: &str = "value";
+
+#[derive(Debug, Clone, Error)]
+pub enum CborCodecError {
+    #[error("Invalid CBOR: {0}")]
+    InvalidCbor(Arc<serde_cbor::Error>),
+    #[error("Integer {0} is too large to represent in JSON")]
+    IntegerOutOfRange(i128),
+    #[error("Non-finite floats can't be represented in JSON")]
+    NonFiniteFloat,
+    #[error("Unsupported map key {0}, only text, integer, and byte string keys can be represented in JSON")]
+    UnsupportedMapKey(String),
+    #[error("Duplicate map key {0:?} once converted to JSON")]
+    DuplicateMapKey(String),
+    #[error("Unsupported CBOR value")]
+    UnsupportedValue,
+}
+
+impl From<serde_cbor::Error> for CborCodecError {
+    fn from(error: serde_cbor::Error) -> Self {
+        CborCodecError::InvalidCbor(Arc::new(error))
+    }
+}
+
+/// Convert a single CBOR encoded item to JSON.
+pub fn cbor_to_json(cbor: &[u8]) -> Result<JsonValue, CborCodecError> {
+    to_json(serde_cbor::from_slice(cbor)?)
+}
+
+/// Encode JSON as CBOR, the reverse of `cbor_to_json` apart from byte strings and integer or byte string map keys,
+/// which are encoded as text strings.
+pub fn json_to_cbor(json: &JsonValue) -> Result<Vec<u8>, CborCodecError> {
+    Ok(serde_cbor::to_vec(&to_cbor(json))?)
+}
+
+/// Convert a decoded CBOR value to JSON, as `cbor_to_json`.
+pub(crate) fn to_json(value: CborValue) -> Result<JsonValue, CborCodecError> {
+    Ok(match value {
+        CborValue::Null => JsonValue::Null,
+        CborValue::Bool(value) => JsonValue::Bool(value),
+        CborValue::Integer(value) => {
+            let number = u64::try_from(value)
+                .map(Number::from)
+                .or_else(|_| i64::try_from(value).map(Number::from))
+                .map_err(|_| CborCodecError::IntegerOutOfRange(value))?;
+            JsonValue::Number(number)
+        }
+        CborValue::Float(value) => JsonValue::Number(Number::from_f64(value).ok_or(CborCodecError::NonFiniteFloat)?),
+        CborValue::Bytes(bytes) => JsonValue::String(base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)),
+        CborValue::Text(text) => JsonValue::String(text),
+        CborValue::Array(values) => JsonValue::Array(values.into_iter().map(to_json).collect::<Result<_, _>>()?),
+        CborValue::Map(entries) => {
+            let mut map = Map::new();
+            for (key, value) in entries {
+                let key = match key {
+                    CborValue::Integer(key) => key.to_string(),
+                    CborValue::Text(key) => key,
+                    CborValue::Bytes(key) => base64::encode_config(key, base64::URL_SAFE_NO_PAD),
+                    key => return Err(CborCodecError::UnsupportedMapKey(format!("{:?}", key))),
+                };
+                if map.contains_key(&key) {
+                    return Err(CborCodecError::DuplicateMapKey(key));
+                }
+                map.insert(key, to_json(value)?);
+            }
+            JsonValue::Object(map)
+        }
+        CborValue::Tag(tag, value) => {
+            let mut map = Map::new();
+            map.insert(String::from(TAG_KEY), JsonValue::from(tag));
+            map.insert(String::from(TAGGED_VALUE_KEY), to_json(*value)?);
+            JsonValue::Object(map)
+        }
+        _ => return Err(CborCodecError::UnsupportedValue),
+    })
+}
+
+fn to_cbor(json: &JsonValue) -> CborValue {
+    match json {
+        JsonValue::Null => CborValue::Null,
+        JsonValue::Bool(value) => CborValue::Bool(*value),
+        JsonValue::Number(number) => match (number.as_u64(), number.as_i64()) {
+            (Some(value), _) => CborValue::Integer(value.into()),
+            (_, Some(value)) => CborValue::Integer(value.into()),
+            // numbers are always representable as one of u64, i64, or f64
+            _ => CborValue::Float(number.as_f64().unwrap_or_default()),
+        },
+        JsonValue::String(text) => CborValue::Text(text.clone()),
+        JsonValue::Array(values) => CborValue::Array(values.iter().map(to_cbor).collect()),
+        JsonValue::Object(map) => match tagged(map) {
+            Some((tag, value)) => CborValue::Tag(tag, Box::new(to_cbor(value))),
+            None => CborValue::Map(
+                map.iter()
+                    .map(|(key, value)| (CborValue::Text(key.clone()), to_cbor(value)))
+                    .collect::<BTreeMap<_, _>>(),
+            ),
+        },
+    }
+}
+
+/// The tag and value of a `{ "_tag": n, "value": ... }` object.
+fn tagged(map: &Map<String, JsonValue>) -> Option<(u64, &JsonValue)> {
+    if map.len() != 2 {
+        return None;
+    }
+    Some((map.get(TAG_KEY)?.as_u64()?, map.get(TAGGED_VALUE_KEY)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::{payload::barcode::QrBarcode, testing::VALID_PASS};
+
+    #[test]
+    fn pass_to_json() {
+        let barcode: QrBarcode = VALID_PASS.parse().unwrap();
+        let json = cbor_to_json(&barcode.cbor).unwrap();
+
+        assert_eq!(json["_tag"], json!(18));
+        let cose = json["value"].as_array().unwrap();
+        // {1: -7, 4: h'6B65792D31'}
+        assert_eq!(cose[0], json!("ogRFa2V5LTEBJg"));
+        assert_eq!(cose[1], json!({}));
+
+        let claims = base64::decode_config(cose[2].as_str().unwrap(), base64::URL_SAFE_NO_PAD).unwrap();
+        let claims = cbor_to_json(&claims).unwrap();
+        assert_eq!(claims["1"], json!("did:web:nzcp.covid19.health.nz"));
+        assert_eq!(claims["4"], json!(1951416330));
+        assert_eq!(claims["vc"]["credentialSubject"]["givenName"], json!("Jack"));
+    }
+
+    #[test]
+    fn round_trip() {
+        let json = json!({
+            "array": [1, -1, 1.5, true, null, "text"],
+            "tagged": { "_tag": 1, "value": 1636321510 },
+            "not tagged": { "_tag": 1, "value": 1, "other": 2 },
+            "large": u64::MAX,
+            "small": i64::MIN,
+        });
+        let cbor = json_to_cbor(&json).unwrap();
+        assert_eq!(cbor_to_json(&cbor).unwrap(), json);
+
+        let value: CborValue = serde_cbor::from_slice(&cbor).unwrap();
+        let map = match value {
+            CborValue::Map(map) => map,
+            value => panic!("expected a map, found {:?}", value),
+        };
+        assert_eq!(
+            map[&CborValue::Text(String::from("tagged"))],
+            CborValue::Tag(1, Box::new(CborValue::Integer(1636321510)))
+        );
+    }
+
+    #[test]
+    fn unrepresentable_cbor() {
+        // -2^64
+        assert!(matches!(
+            cbor_to_json(&hex::decode("3bffffffffffffffff").unwrap()),
+            Err(CborCodecError::IntegerOutOfRange(_))
+        ));
+        // NaN
+        assert!(matches!(
+            cbor_to_json(&hex::decode("f97e00").unwrap()),
+            Err(CborCodecError::NonFiniteFloat)
+        ));
+        // {h'00': 1}
+        assert_eq!(
+            cbor_to_json(&hex::decode("a1410001").unwrap()).unwrap(),
+            json!({"AA": 1})
+        );
+        // {true: 1}
+        assert!(matches!(
+            cbor_to_json(&hex::decode("a1f501").unwrap()),
+            Err(CborCodecError::UnsupportedMapKey(_))
+        ));
+        // {1: 1, "1": 2}
+        assert!(matches!(
+            cbor_to_json(&hex::decode("a20101613102").unwrap()),
+            Err(CborCodecError::DuplicateMapKey(key)) if key == "1"
+        ));
+        assert!(matches!(
+            cbor_to_json(&hex::decode("a1").unwrap()),
+            Err(CborCodecError::InvalidCbor(_))
+        ));
+    }
+}
@@ -1,57 +1,72 @@
-use std::{fmt, str::FromStr};
+use std::{convert::TryFrom, fmt, str::FromStr};
 
-use did_web::DIDWeb;
 use p256::{ecdsa::VerifyingKey, elliptic_curve::generic_array::GenericArray, EncodedPoint};
 use serde::{
     de::{self, Visitor},
-    Deserialize, Deserializer,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
+use sha2::{Digest, Sha256};
 use ssi::{
-    did::{Document, VerificationMethod, DIDURL},
-    did_resolve::{DIDResolver, ResolutionInputMetadata},
+    did::{Document, VerificationMethod, VerificationMethodMap, DIDURL},
     jwk,
 };
 use thiserror::Error;
 
-const DID_WEB: &'static str = "did:web:";
+pub(crate) use self::resolver::{default_http_client, DEFAULT_MAX_DOCUMENT_SIZE};
+pub use self::{
+    certificate_pin::CertificatePin,
+    rate_limit::ResolutionRateLimiter,
+    resolver::{DocumentResolver, WebResolver},
+};
+
+mod certificate_pin;
+mod rate_limit;
+mod resolver;
+
+const DID_WEB: &str = "did:web:";
 
 #[doc(hidden)]
-#[derive(Debug, PartialEq, Eq, Clone, Copy)]
-pub enum DecentralizedIdentifier<'a> {
-    Web(&'a str),
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub enum DecentralizedIdentifier {
+    Web(String),
 }
 
-impl<'a> fmt::Display for DecentralizedIdentifier<'a> {
+impl fmt::Display for DecentralizedIdentifier {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         write!(f, "{}", self.did())
     }
 }
 
+impl Serialize for DecentralizedIdentifier {
+    /// Serialize the DID as a string, e.g. `did:web:nzcp.identity.health.nz`.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        serializer.serialize_str(&self.did())
+    }
+}
+
 struct DecentralizedIdentifierVisitor;
 
 impl<'de> Visitor<'de> for DecentralizedIdentifierVisitor {
-    type Value = DecentralizedIdentifier<'de>;
+    type Value = DecentralizedIdentifier;
 
     fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         formatter
             .write_str("a Decentralized Identifier who’s DID Method MUST correspond to web (starting with 'did:web:')")
     }
 
-    fn visit_borrowed_str<E>(self, did: &'de str) -> Result<Self::Value, E>
+    fn visit_str<E>(self, did: &str) -> Result<Self::Value, E>
     where
         E: de::Error,
     {
-        if let Some(did) = did.strip_prefix(DID_WEB) {
-            Ok(DecentralizedIdentifier::Web(did))
-        }
-        else {
-            Err(E::custom("invalid DID"))
-        }
+        DecentralizedIdentifier::try_from(did).map_err(E::custom)
     }
 }
 
-impl<'de: 'a, 'a> Deserialize<'de> for DecentralizedIdentifier<'a> {
-    fn deserialize<D>(deserializer: D) -> Result<DecentralizedIdentifier<'a>, D::Error>
+impl<'de> Deserialize<'de> for DecentralizedIdentifier {
+    fn deserialize<D>(deserializer: D) -> Result<DecentralizedIdentifier, D::Error>
     where
         D: Deserializer<'de>,
     {
@@ -59,20 +74,22 @@ impl<'de: 'a, 'a> Deserialize<'de> for DecentralizedIdentifier<'a> {
     }
 }
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum DecentralizedIdentifierError {
     #[error("DID resolution error: {0}")]
     ResolutionError(String),
+    #[error("the DID document was not found")]
+    DocumentNotFound,
     #[error("an empty DID resolution document was returned")]
     EmptyDocument,
     #[error("assertionMethod array was missing from the DID document")]
     MissingAssertionMethods,
-    #[error("assertionMethod with absolute key '' was missing from the DID document")]
-    MissingAssertionMethod(String),
+    #[error("expected '{expected}' but document '{document}' had no such assertion method")]
+    MissingAssertionMethod { expected: String, document: String },
     #[error("verificationMethod was missing from the DID document")]
     MissingVerificationMethods,
-    #[error("verificationMethod with the absolute key '' was missing from the DID document")]
-    MissingVerificationMethod(String),
+    #[error("expected '{expected}' but document '{document}' had no such verification method")]
+    MissingVerificationMethod { expected: String, document: String },
     #[error("verificationMethod type was not 'JsonWebKey2020'")]
     NotJsonWebKey2020,
     #[error("verificationMethod was missing publicKeyJwk")]
@@ -87,113 +104,668 @@ pub enum DecentralizedIdentifierError {
     JWKWrongCurve,
     #[error("publicKeyJwk was invalid")]
     InvalidJWK,
+    #[error("issuer {0} was not in the loaded snapshot")]
+    IssuerNotInSnapshot(String),
+    #[error("key '{0}' was not in the loaded JWKS")]
+    KeyNotInJwks(String),
+    #[error("the TLS certificate presented by {host} did not match any of its pins")]
+    CertificatePinMismatch { host: String },
+    #[error("refusing the redirect from {host}, whose certificates are pinned, to {location}")]
+    PinnedHostRedirected { host: String, location: String },
+    #[error("refusing to fetch the DID document from {0} without HTTPS")]
+    InsecureResolution(String),
+    #[error("the DID document was larger than the {0} byte limit")]
+    ResponseTooLarge(usize),
+    #[error("too many DID documents are being resolved, try again later")]
+    RateLimited,
+}
+
+/// The keys in a DID document, see `DecentralizedIdentifier::resolve_all_keys`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DocumentKeys {
+    /// The `kid` (the fragment of the verification method's ID) and key of each usable verification method.
+    pub keys: Vec<(String, VerifyingKey)>,
+
+    /// The `kid` of each verification method that was skipped, and why (e.g. `NotJsonWebKey2020`).
+    pub skipped: Vec<(String, DecentralizedIdentifierError)>,
+
+    /// A SHA-256 hash of the document's `verificationMethod` and `assertionMethod`, which changes whenever the
+    /// issuer's keys do but not with the rest of the document, so a document can be compared with a previous fetch to
+    /// detect key rotation.
+    pub fingerprint: [u8; 32],
+}
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum DecentralizedIdentifierParseError {
+    #[error("the DID was empty")]
+    Empty,
+    #[error("'{0}' is not a DID (must start with 'did:')")]
+    NotADid(String),
+    #[error("DID method '{0}' is not supported (must be 'web')")]
+    UnsupportedMethod(String),
+    #[error("the DID was missing a domain")]
+    MissingDomain,
+}
+
+/// The method of a DID, given it starts with `did:`, otherwise an error.
+fn did_method(did: &str) -> Result<&str, DecentralizedIdentifierParseError> {
+    use DecentralizedIdentifierParseError::*;
+
+    if did.is_empty() {
+        return Err(Empty);
+    }
+    let method = did.strip_prefix("did:").ok_or_else(|| NotADid(did.to_owned()))?;
+    Ok(method.split(':').next().unwrap_or_default())
+}
+
+impl TryFrom<&str> for DecentralizedIdentifier {
+    type Error = DecentralizedIdentifierParseError;
+
+    /// Parse a DID string (e.g. `did:web:nzcp.identity.health.nz`).
+    fn try_from(did: &str) -> Result<Self, Self::Error> {
+        Self::try_from(did.to_owned())
+    }
+}
+
+impl TryFrom<String> for DecentralizedIdentifier {
+    type Error = DecentralizedIdentifierParseError;
+
+    /// Parse a DID string (e.g. `did:web:nzcp.identity.health.nz`), reusing its allocation.
+    fn try_from(mut did: String) -> Result<Self, Self::Error> {
+        let method = did_method(&did)?;
+        if method != "web" {
+            return Err(DecentralizedIdentifierParseError::UnsupportedMethod(method.to_owned()));
+        }
+        // `did:web` without the colon has the method but no prefix to strip
+        if did.strip_prefix(DID_WEB).is_none_or(str::is_empty) {
+            return Err(DecentralizedIdentifierParseError::MissingDomain);
+        }
+
+        did.drain(..DID_WEB.len());
+        Ok(DecentralizedIdentifier::Web(did))
+    }
+}
+
+impl FromStr for DecentralizedIdentifier {
+    type Err = DecentralizedIdentifierParseError;
+
+    fn from_str(did: &str) -> Result<Self, Self::Err> {
+        Self::try_from(did)
+    }
 }
 
-impl<'a> DecentralizedIdentifier<'a> {
+impl DecentralizedIdentifier {
+    /// Parse a DID string, see `TryFrom<&str>`.
+    pub(crate) fn parse(did: &str) -> Option<Self> {
+        Self::try_from(did).ok()
+    }
+
+    /// Parse a DID URL (e.g. `did:web:nzcp.identity.health.nz#z12Kf7UQ`) into its DID and fragment, if any. An empty
+    /// fragment (a trailing `#`) is treated as none.
+    pub fn from_did_url(did_url: &str) -> Result<(Self, Option<String>), DecentralizedIdentifierParseError> {
+        let (did, fragment) = match did_url.split_once('#') {
+            Some((did, fragment)) => (did, Some(fragment).filter(|fragment| !fragment.is_empty())),
+            None => (did_url, None),
+        };
+        Ok((Self::try_from(did)?, fragment.map(str::to_owned)))
+    }
+
+    /// The DID URL of the key `kid` of this DID, the reverse of `from_did_url`.
+    pub(crate) fn did_url(&self, kid: &str) -> String {
+        format!("{}#{}", self.did(), kid)
+    }
+
     fn did(&self) -> String {
         match self {
             DecentralizedIdentifier::Web(did) => format!("{}{}", DID_WEB, did),
         }
     }
 
-    async fn resolve_document(&self) -> Result<Document, DecentralizedIdentifierError> {
-        // TODO: horrifically disgusting temporary work around for https://github.com/vaxxnz/nzcp-rust/issues/1
-        let (metadata, doc_data, _) = DIDWeb
-            .resolve_representation(&self.did(), &ResolutionInputMetadata::default())
-            .await;
-        let doc_opt: Option<serde_json::Value> = if doc_data.is_empty() {
-            None
-        }
-        else {
-            match serde_json::from_slice(&doc_data) {
-                Ok(doc) => doc,
-                Err(err) => return Err(DecentralizedIdentifierError::ResolutionError(err.to_string())),
-            }
-        };
-
-        let document = doc_opt
-            .and_then(|mut doc_opt| {
-                if let Some(id) = doc_opt.get_mut("@context") {
-                    match id {
-                        serde_json::Value::String(id) => {
-                            *id = String::from("https://www.w3.org/ns/did/v1");
-                            Some(
-                                serde_json::from_value(doc_opt)
-                                    .map_err(|err| DecentralizedIdentifierError::ResolutionError(err.to_string())),
-                            )
-                        }
-                        _ => None,
-                    }
+    /// The HTTPS URL of the DID document, see https://w3c-ccg.github.io/did-method-web/#read-resolve
+    pub(crate) fn document_url(&self) -> String {
+        match self {
+            DecentralizedIdentifier::Web(did) => {
+                let mut parts = did.split(':');
+                // a port is percent encoded, as the colon separates path segments
+                let domain = parts.next().unwrap_or_default().replace("%3A", ":");
+                let path = parts.collect::<Vec<_>>().join("/");
+                if path.is_empty() {
+                    format!("https://{}/.well-known/did.json", domain)
                 }
                 else {
-                    None
+                    format!("https://{}/{}/did.json", domain, path)
                 }
-            })
-            .transpose()?;
+            }
+        }
+    }
+
+    pub(crate) async fn resolve_document(
+        &self,
+        resolver: &dyn DocumentResolver,
+    ) -> Result<Document, DecentralizedIdentifierError> {
+        let doc_data = resolver.resolve_representation(self).await?;
+        parse_document(&doc_data)
+    }
 
-        // let (metadata, document, _) = DIDWeb.resolve(&self.did(), &ResolutionInputMetadata::default()).await;
+    pub async fn resolve_verifying_key(
+        &self,
+        kid: &str,
+        resolver: &dyn DocumentResolver,
+    ) -> Result<VerifyingKey, DecentralizedIdentifierError> {
+        let document = self.resolve_document(resolver).await?;
+        self.document_verifying_key(&document, kid)
+    }
 
-        if let Some(error) = metadata.error {
-            Err(DecentralizedIdentifierError::ResolutionError(error))
-        }
-        else if let Some(document) = document {
-            Ok(document)
-        }
-        else {
-            Err(DecentralizedIdentifierError::EmptyDocument)
-        }
+    /// Resolve the DID document once and get every P-256 `JsonWebKey2020` verification method of this DID in it, e.g.
+    /// to see which keys an issuer currently publishes during a rotation.
+    ///
+    /// Unsupported methods are skipped rather than failing, and listed in `DocumentKeys::skipped` (and logged with the
+    /// `tracing` feature). Methods of other DIDs are ignored. Passes are only accepted for keys that are also assertion
+    /// methods.
+    pub async fn resolve_all_keys(
+        &self,
+        resolver: &dyn DocumentResolver,
+    ) -> Result<DocumentKeys, DecentralizedIdentifierError> {
+        let document = self.resolve_document(resolver).await?;
+        Ok(self.document_keys(&document))
     }
 
-    pub async fn resolve_verifying_key(&self, kid: &str) -> Result<VerifyingKey, DecentralizedIdentifierError> {
-        let document = self.resolve_document().await?;
+    /// Get every key of this DID from an already resolved DID document, see `resolve_all_keys`.
+    pub(crate) fn document_keys(&self, document: &Document) -> DocumentKeys {
+        let mut keys = DocumentKeys {
+            keys: Vec::new(),
+            skipped: Vec::new(),
+            fingerprint: did_document_fingerprint(document),
+        };
+        for method in verification_method_maps(document) {
+            let kid = match DecentralizedIdentifier::from_did_url(&method.id) {
+                Ok((did, Some(kid))) if &did == self => kid,
+                _ => continue,
+            };
+            match method_verifying_key(method) {
+                Ok(key) => keys.keys.push((kid, key)),
+                Err(err) => {
+                    #[cfg(feature = "tracing")]
+                    tracing::warn!(did = %self, %kid, error = %err, "skipping unsupported verification method");
+                    keys.skipped.push((kid, err))
+                }
+            }
+        }
+        keys
+    }
 
-        let absolute_key = format!("{}#{}", self.did(), kid);
+    /// Get the verifying key for `kid` from an already resolved DID document.
+    pub(crate) fn document_verifying_key(
+        &self,
+        document: &Document,
+        kid: &str,
+    ) -> Result<VerifyingKey, DecentralizedIdentifierError> {
+        let absolute_key = self.did_url(kid);
         let absolute_key_url = DIDURL::from_str(&absolute_key).expect("invalid iss/kid DID");
 
 
         use DecentralizedIdentifierError::*;
-        let assertion_methods = document.assertion_method.ok_or(MissingAssertionMethods)?;
+        let assertion_methods = document.assertion_method.as_ref().ok_or(MissingAssertionMethods)?;
         if !assertion_methods.contains(&VerificationMethod::DIDURL(absolute_key_url)) {
-            return Err(MissingAssertionMethod(absolute_key));
+            return Err(MissingAssertionMethod {
+                expected: absolute_key,
+                document: document.id.clone(),
+            });
         }
 
         let verification_method = document
             .verification_method
+            .as_ref()
             .ok_or(MissingVerificationMethods)?
-            .into_iter()
+            .iter()
             .find_map(|method| match method {
-                VerificationMethod::Map(map) => (&map.id == &absolute_key).then(|| map),
+                VerificationMethod::Map(map) => (map.id == absolute_key).then_some(map),
                 _ => None,
             })
-            .ok_or(MissingVerificationMethod(absolute_key))?;
+            .ok_or_else(|| MissingVerificationMethod {
+                expected: absolute_key,
+                document: document.id.clone(),
+            })?;
+
+        method_verifying_key(verification_method)
+    }
+}
+
+/// The verification methods of a DID document given in full, rather than by reference.
+fn verification_method_maps(document: &Document) -> impl Iterator<Item = &VerificationMethodMap> {
+    document
+        .verification_method
+        .iter()
+        .flatten()
+        .filter_map(|method| match method {
+            VerificationMethod::Map(map) => Some(map),
+            _ => None,
+        })
+}
 
-        if verification_method.type_ != "JsonWebKey2020" {
-            Err(NotJsonWebKey2020)
+/// Get the key of every verification method in a DID document, along with its `kid` (the fragment of the method's
+/// ID, or the whole ID if it has none), without needing to know the structure of the document.
+///
+/// Only P-256 `JsonWebKey2020` keys are supported. Methods of other types yield `Err(NotJsonWebKey2020)`, and invalid
+/// keys the reason they're invalid, so they can be skipped. Unlike `DecentralizedIdentifier::resolve_all_keys`, the
+/// methods aren't checked to belong to the document's DID, nor to be assertion methods.
+pub fn public_keys_from_document<'a>(
+    document: &'a Document,
+) -> impl Iterator<Item = Result<(&'a str, VerifyingKey), DecentralizedIdentifierError>> + 'a {
+    verification_method_maps(document).map(|method| {
+        let kid = method
+            .id
+            .split_once('#')
+            .map_or(method.id.as_str(), |(_, fragment)| fragment);
+        method_verifying_key(method).map(|key| (kid, key))
+    })
+}
+
+/// Get the P-256 key of a `JsonWebKey2020` verification method.
+fn method_verifying_key(
+    verification_method: &VerificationMethodMap,
+) -> Result<VerifyingKey, DecentralizedIdentifierError> {
+    use DecentralizedIdentifierError::*;
+
+    if verification_method.type_ != "JsonWebKey2020" {
+        Err(NotJsonWebKey2020)
+    }
+    else if let Some(jwk) = &verification_method.public_key_jwk {
+        let ec = match &jwk.params {
+            jwk::Params::EC(ec) => ec,
+            _ => return Err(JWKNotEllipticCurve),
+        };
+
+        if ec.curve.as_deref() != Some("P-256") {
+            return Err(JWKWrongCurve);
         }
-        else if let Some(jwk) = verification_method.public_key_jwk {
-            let ec = match jwk.params {
-                jwk::Params::EC(ec) => ec,
-                _ => return Err(JWKNotEllipticCurve),
-            };
 
-            if ec.curve.as_deref() != Some("P-256") {
-                return Err(JWKWrongCurve);
+        let x = ec.x_coordinate.as_ref().ok_or(JWKMissingX)?;
+        let y = ec.y_coordinate.as_ref().ok_or(JWKMissingY)?;
+
+        jwk_verifying_key(&x.0, &y.0)
+    }
+    else {
+        Err(MissingJWK)
+    }
+}
+
+/// Get the P-256 verifying key from the decoded `x` and `y` coordinates of a JWK.
+pub(crate) fn jwk_verifying_key(x: &[u8], y: &[u8]) -> Result<VerifyingKey, DecentralizedIdentifierError> {
+    const COORDINATE_LENGTH: usize = 32;
+    if x.len() != COORDINATE_LENGTH || y.len() != COORDINATE_LENGTH {
+        return Err(DecentralizedIdentifierError::InvalidJWK);
+    }
+
+    let point = EncodedPoint::from_affine_coordinates(GenericArray::from_slice(x), GenericArray::from_slice(y), false);
+    VerifyingKey::from_encoded_point(&point).map_err(|_| DecentralizedIdentifierError::InvalidJWK)
+}
+
+/// Hash the verification and assertion methods of a DID document, see `DocumentKeys::fingerprint`.
+///
+/// The methods are hashed as JSON with the keys of every object sorted, so the fingerprint doesn't depend on how the
+/// issuer orders them.
+pub(crate) fn did_document_fingerprint(document: &Document) -> [u8; 32] {
+    fn canonicalize(value: serde_json::Value) -> serde_json::Value {
+        match value {
+            serde_json::Value::Object(object) => {
+                let mut entries = object.into_iter().collect::<Vec<_>>();
+                entries.sort_by(|(a, _), (b, _)| a.cmp(b));
+                entries
+                    .into_iter()
+                    .map(|(key, value)| (key, canonicalize(value)))
+                    .collect()
             }
+            serde_json::Value::Array(array) => array.into_iter().map(canonicalize).collect(),
+            value => value,
+        }
+    }
 
-            let x = ec.x_coordinate.ok_or(JWKMissingX)?;
-            let y = ec.y_coordinate.ok_or(JWKMissingY)?;
+    let methods = serde_json::json!({
+        "assertionMethod": document.assertion_method,
+        "verificationMethod": document.verification_method,
+    });
+    Sha256::digest(canonicalize(methods).to_string().as_bytes()).into()
+}
 
-            let point = EncodedPoint::from_affine_coordinates(
-                &GenericArray::from_slice(&x.0),
-                &GenericArray::from_slice(&y.0),
-                false,
-            );
-            let verifying_key = VerifyingKey::from_encoded_point(&point).map_err(|_| InvalidJWK)?;
+/// Replace a single string `@context` with the one `ssi` expects, as issuers publish `https://w3.org/ns/did/v1`
+/// rather than `https://www.w3.org/ns/did/v1`. Returns whether the document had a string `@context`.
+pub(crate) fn normalize_context(document: &mut serde_json::Value) -> bool {
+    match document.get_mut("@context") {
+        Some(serde_json::Value::String(context)) => {
+            *context = String::from("https://www.w3.org/ns/did/v1");
+            true
+        }
+        _ => false,
+    }
+}
 
-            Ok(verifying_key)
+/// Parse the JSON representation of a DID document.
+pub(crate) fn parse_document(doc_data: &[u8]) -> Result<Document, DecentralizedIdentifierError> {
+    // TODO: horrifically disgusting temporary work around for https://github.com/vaxxnz/nzcp-rust/issues/1
+    let doc_opt: Option<serde_json::Value> = if doc_data.is_empty() {
+        None
+    }
+    else {
+        match serde_json::from_slice(doc_data) {
+            Ok(doc) => doc,
+            Err(err) => return Err(DecentralizedIdentifierError::ResolutionError(err.to_string())),
         }
-        else {
-            Err(MissingJWK)
+    };
+
+    let mut document = match doc_opt {
+        Some(document) => document,
+        None => return Err(DecentralizedIdentifierError::EmptyDocument),
+    };
+    if !normalize_context(&mut document) {
+        return Err(DecentralizedIdentifierError::EmptyDocument);
+    }
+
+    serde_json::from_value(document).map_err(|err| DecentralizedIdentifierError::ResolutionError(err.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{
+        did_document, did_document_from_json, example_issuer, test_signing_key, MockDIDResolver, EXAMPLE_DID_DOCUMENT,
+    };
+
+    #[test]
+    fn document_url() {
+        assert_eq!(
+            DecentralizedIdentifier::parse("did:web:nzcp.identity.health.nz")
+                .unwrap()
+                .document_url(),
+            "https://nzcp.identity.health.nz/.well-known/did.json"
+        );
+        assert_eq!(
+            DecentralizedIdentifier::parse("did:web:example.com:issuers:nz")
+                .unwrap()
+                .document_url(),
+            "https://example.com/issuers/nz/did.json"
+        );
+        assert_eq!(
+            DecentralizedIdentifier::parse("did:web:localhost%3A8443")
+                .unwrap()
+                .document_url(),
+            "https://localhost:8443/.well-known/did.json"
+        );
+    }
+
+    #[test]
+    fn parse_did() {
+        use DecentralizedIdentifierParseError::*;
+
+        assert_eq!(
+            DecentralizedIdentifier::try_from("did:web:nzcp.identity.health.nz"),
+            Ok(DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz")))
+        );
+        assert_eq!(
+            "did:web:nzcp.identity.health.nz".parse(),
+            DecentralizedIdentifier::try_from(String::from("did:web:nzcp.identity.health.nz"))
+        );
+        assert_eq!(DecentralizedIdentifier::try_from(""), Err(Empty));
+        assert_eq!(
+            DecentralizedIdentifier::try_from("nzcp.identity.health.nz"),
+            Err(NotADid(String::from("nzcp.identity.health.nz")))
+        );
+        assert_eq!(
+            DecentralizedIdentifier::try_from("did:key:z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK"),
+            Err(UnsupportedMethod(String::from("key")))
+        );
+        assert_eq!(DecentralizedIdentifier::try_from("did:web:"), Err(MissingDomain));
+        assert_eq!(DecentralizedIdentifier::try_from("did:web"), Err(MissingDomain));
+        assert_eq!(
+            DecentralizedIdentifier::try_from("did:we"),
+            Err(UnsupportedMethod(String::from("we")))
+        );
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url("did:web#key-1"),
+            Err(MissingDomain)
+        );
+    }
+
+    #[test]
+    fn from_did_url() {
+        let issuer = DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz"));
+
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url("did:web:nzcp.identity.health.nz#z12Kf7UQ"),
+            Ok((issuer.clone(), Some(String::from("z12Kf7UQ"))))
+        );
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url(&issuer.did_url("key-1")),
+            Ok((issuer.clone(), Some(String::from("key-1"))))
+        );
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url("did:web:nzcp.identity.health.nz"),
+            Ok((issuer.clone(), None))
+        );
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url("did:web:nzcp.identity.health.nz#"),
+            Ok((issuer.clone(), None))
+        );
+        // only the first '#' separates the fragment
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url("did:web:nzcp.identity.health.nz#key-1#2"),
+            Ok((issuer, Some(String::from("key-1#2"))))
+        );
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url("did:web:#key-1"),
+            Err(DecentralizedIdentifierParseError::MissingDomain)
+        );
+        assert_eq!(
+            DecentralizedIdentifier::from_did_url("#key-1"),
+            Err(DecentralizedIdentifierParseError::Empty)
+        );
+    }
+
+    #[test]
+    fn hash_matches_eq() {
+        let issuers: std::collections::HashSet<_> = [
+            DecentralizedIdentifier::parse("did:web:nzcp.identity.health.nz").unwrap(),
+            DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz")),
+            DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz")),
+        ]
+        .into_iter()
+        .collect();
+
+        assert_eq!(issuers.len(), 2);
+    }
+
+    #[tokio::test]
+    async fn resolve_all_keys() {
+        let keys = [
+            ("key-1", test_signing_key(1).verifying_key()),
+            ("key-2", test_signing_key(2).verifying_key()),
+            ("key-3", test_signing_key(3).verifying_key()),
+        ];
+        let mut document: serde_json::Value = serde_json::from_str(&did_document(&example_issuer(), &keys)).unwrap();
+        let methods = document["verificationMethod"].as_array_mut().unwrap();
+        methods[1]["type"] = serde_json::json!("EcdsaSecp256r1VerificationKey2019");
+        methods[2]["id"] = serde_json::json!("did:web:nzcp.identity.health.nz#key-3");
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), &document.to_string());
+
+        let resolved = example_issuer().resolve_all_keys(&resolver).await.unwrap();
+        assert_eq!(
+            resolved.keys,
+            vec![(String::from("key-1"), test_signing_key(1).verifying_key())]
+        );
+        assert_eq!(
+            resolved.skipped,
+            vec![(String::from("key-2"), DecentralizedIdentifierError::NotJsonWebKey2020)]
+        );
+        assert_eq!(resolver.resolution_count(), 1);
+
+        let other_issuer = DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz"));
+        assert_eq!(
+            other_issuer.resolve_all_keys(&resolver).await,
+            Err(DecentralizedIdentifierError::DocumentNotFound)
+        );
+    }
+
+    #[test]
+    fn public_keys() {
+        let keys = [
+            ("key-1", test_signing_key(1).verifying_key()),
+            ("key-2", test_signing_key(2).verifying_key()),
+            ("key-3", test_signing_key(3).verifying_key()),
+        ];
+        let mut document: serde_json::Value = serde_json::from_str(&did_document(&example_issuer(), &keys)).unwrap();
+        let methods = document["verificationMethod"].as_array_mut().unwrap();
+        methods[1]["type"] = serde_json::json!("EcdsaSecp256r1VerificationKey2019");
+        methods[2]["publicKeyJwk"]["crv"] = serde_json::json!("P-384");
+        let document = parse_document(document.to_string().as_bytes()).unwrap();
+
+        assert_eq!(
+            public_keys_from_document(&document).collect::<Vec<_>>(),
+            vec![
+                Ok(("key-1", test_signing_key(1).verifying_key())),
+                Err(DecentralizedIdentifierError::NotJsonWebKey2020),
+                Err(DecentralizedIdentifierError::JWKWrongCurve),
+            ]
+        );
+
+        let document = parse_document(EXAMPLE_DID_DOCUMENT.as_bytes()).unwrap();
+        let keys = public_keys_from_document(&document)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(keys.len(), 1);
+        assert_eq!(keys[0].0, "key-1");
+    }
+
+    #[test]
+    fn document_fingerprint() {
+        let keys = [
+            ("key-1", test_signing_key(1).verifying_key()),
+            ("key-2", test_signing_key(2).verifying_key()),
+        ];
+        let document: serde_json::Value = serde_json::from_str(&did_document(&example_issuer(), &keys)).unwrap();
+        let fingerprint = |document: &serde_json::Value| {
+            did_document_fingerprint(&parse_document(document.to_string().as_bytes()).unwrap())
+        };
+        let original = fingerprint(&document);
+
+        // metadata doesn't matter
+        let mut updated = document.clone();
+        updated["updated"] = serde_json::json!("2021-11-02T00:00:00Z");
+        updated["@context"] = serde_json::json!("https://www.w3.org/ns/did/v1");
+        assert_eq!(fingerprint(&updated), original);
+
+        // keys and methods do
+        let rotated = did_document(&example_issuer(), &[("key-1", test_signing_key(1).verifying_key())]);
+        assert_ne!(fingerprint(&serde_json::from_str(&rotated).unwrap()), original);
+        let mut revoked = document.clone();
+        revoked["assertionMethod"].as_array_mut().unwrap().pop();
+        assert_ne!(fingerprint(&revoked), original);
+
+        assert_eq!(
+            example_issuer()
+                .document_keys(&parse_document(document.to_string().as_bytes()).unwrap())
+                .fingerprint,
+            original
+        );
+    }
+
+    #[tokio::test]
+    async fn document_contexts() {
+        use serde_json::json;
+
+        let resolve = |context: Option<serde_json::Value>| async move {
+            let mut document: serde_json::Value = serde_json::from_str(EXAMPLE_DID_DOCUMENT).unwrap();
+            match context {
+                Some(context) => document["@context"] = context,
+                None => {
+                    document.as_object_mut().unwrap().remove("@context");
+                }
+            }
+            let resolver = MockDIDResolver::new().with_raw_document(&example_issuer(), &document.to_string());
+            example_issuer().resolve_document(&resolver).await
+        };
+
+        // the specification's examples publish this rather than https://www.w3.org/ns/did/v1
+        let document = resolve(Some(json!("https://w3.org/ns/did/v1"))).await.unwrap();
+        assert_eq!(document.id, "did:web:nzcp.covid19.health.nz");
+        assert!(example_issuer().document_verifying_key(&document, "key-1").is_ok());
+        // any string is replaced
+        assert!(resolve(Some(json!("https://www.w3.org/ns/did/v1"))).await.is_ok());
+        assert!(resolve(Some(json!("https://example.com/contexts/v1"))).await.is_ok());
+
+        // only a single string context is accepted
+        for context in [
+            None,
+            Some(json!(["https://www.w3.org/ns/did/v1"])),
+            Some(json!([
+                "https://w3.org/ns/did/v1",
+                "https://w3id.org/security/suites/jws-2020/v1"
+            ])),
+            Some(json!({"@vocab": "https://www.w3.org/ns/did#"})),
+            Some(json!(null)),
+        ] {
+            assert_eq!(
+                resolve(context.clone()).await,
+                Err(DecentralizedIdentifierError::EmptyDocument),
+                "{:?}",
+                context
+            );
         }
     }
+
+    #[test]
+    fn parse_documents() {
+        assert_eq!(parse_document(b""), Err(DecentralizedIdentifierError::EmptyDocument));
+        assert_eq!(parse_document(b"[]"), Err(DecentralizedIdentifierError::EmptyDocument));
+        assert!(matches!(
+            parse_document(b"{\"@context\": "),
+            Err(DecentralizedIdentifierError::ResolutionError(_))
+        ));
+        // normalized, but not a DID document
+        assert!(matches!(
+            parse_document(br#"{"@context": "https://w3.org/ns/did/v1"}"#),
+            Err(DecentralizedIdentifierError::ResolutionError(_))
+        ));
+
+        let mut document: serde_json::Value = serde_json::from_str(EXAMPLE_DID_DOCUMENT).unwrap();
+        assert!(normalize_context(&mut document));
+        assert_eq!(document["@context"], "https://www.w3.org/ns/did/v1");
+        assert_eq!(
+            parse_document(EXAMPLE_DID_DOCUMENT.as_bytes()),
+            Ok(serde_json::from_value(document).unwrap())
+        );
+    }
+
+    #[test]
+    fn missing_methods_name_document() {
+        let other_issuer = DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz"));
+        let document = did_document(&other_issuer, &[("key-1", test_signing_key(1).verifying_key())]);
+        let error = example_issuer()
+            .document_verifying_key(&did_document_from_json(&document).unwrap(), "key-1")
+            .unwrap_err();
+        assert_eq!(
+            error,
+            DecentralizedIdentifierError::MissingAssertionMethod {
+                expected: String::from("did:web:nzcp.covid19.health.nz#key-1"),
+                document: String::from("did:web:nzcp.identity.health.nz"),
+            }
+        );
+        assert_eq!(
+            error.to_string(),
+            "expected 'did:web:nzcp.covid19.health.nz#key-1' but document 'did:web:nzcp.identity.health.nz' had no such \
+             assertion method"
+        );
+
+        let document = did_document(&example_issuer(), &[("key-1", test_signing_key(1).verifying_key())]).replace(
+            "\"id\":\"did:web:nzcp.covid19.health.nz#key-1\"",
+            "\"id\":\"did:web:nzcp.covid19.health.nz#key-2\"",
+        );
+        assert_eq!(
+            example_issuer()
+                .document_verifying_key(&did_document_from_json(&document).unwrap(), "key-1")
+                .unwrap_err()
+                .to_string(),
+            "expected 'did:web:nzcp.covid19.health.nz#key-1' but document 'did:web:nzcp.covid19.health.nz' had no such \
+             verification method"
+        );
+    }
 }
@@ -0,0 +1,129 @@
+use std::{convert::TryFrom, fmt};
+
+use sha2::{Digest, Sha256};
+
+/// The SHA-256 hash of a TLS certificate's SubjectPublicKeyInfo (as in HPKP's `pin-sha256`), used to restrict which
+/// certificates are accepted when fetching DID documents from a host, see `WebResolver::with_certificate_pin`.
+///
+/// The pin for a certificate can be generated with:
+///
+/// ```notrust
+/// openssl x509 -in cert.pem -pubkey -noout | openssl pkey -pubin -outform der | openssl dgst -sha256 -binary | base64
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct CertificatePin(pub [u8; 32]);
+
+impl CertificatePin {
+    /// The pin of a DER encoded SubjectPublicKeyInfo.
+    pub fn from_spki_der(spki: &[u8]) -> Self {
+        CertificatePin(Sha256::digest(spki).into())
+    }
+
+    /// The pin of a DER encoded X.509 certificate's public key, or `None` if the certificate is malformed.
+    pub fn from_certificate_der(certificate: &[u8]) -> Option<Self> {
+        subject_public_key_info(certificate).map(Self::from_spki_der)
+    }
+
+    /// Parse a base64 encoded pin.
+    pub fn from_base64(pin: &str) -> Option<Self> {
+        let bytes = base64::decode(pin).ok()?;
+        <[u8; 32]>::try_from(bytes.as_slice()).ok().map(CertificatePin)
+    }
+}
+
+impl fmt::Display for CertificatePin {
+    /// The base64 encoded pin.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", base64::encode(self.0))
+    }
+}
+
+const DER_SEQUENCE: u8 = 0x30;
+const DER_EXPLICIT_VERSION: u8 = 0xa0;
+
+/// A DER element split from the front of some input.
+struct DerElement<'a> {
+    tag: u8,
+    /// The whole element, including its tag and length.
+    element: &'a [u8],
+    contents: &'a [u8],
+    /// The input following the element.
+    rest: &'a [u8],
+}
+
+impl<'a> DerElement<'a> {
+    fn split(input: &'a [u8]) -> Option<Self> {
+        let (&tag, rest) = input.split_first()?;
+        let (&length, mut rest) = rest.split_first()?;
+
+        let length = if length & 0x80 == 0 {
+            usize::from(length)
+        }
+        else {
+            // long form, the low bits are the number of big-endian length bytes
+            let length_bytes = usize::from(length & 0x7f);
+            if length_bytes == 0 || length_bytes > std::mem::size_of::<usize>() || rest.len() < length_bytes {
+                return None;
+            }
+            let (length, remaining) = rest.split_at(length_bytes);
+            rest = remaining;
+            length.iter().fold(0, |length, &byte| length << 8 | usize::from(byte))
+        };
+
+        if rest.len() < length {
+            return None;
+        }
+        let header_length = input.len() - rest.len();
+        let (contents, rest) = rest.split_at(length);
+        Some(DerElement {
+            tag,
+            element: &input[..header_length + length],
+            contents,
+            rest,
+        })
+    }
+
+    fn sequence(input: &'a [u8]) -> Option<Self> {
+        Self::split(input).filter(|element| element.tag == DER_SEQUENCE)
+    }
+}
+
+/// The DER encoded SubjectPublicKeyInfo of a DER encoded X.509 certificate (RFC 5280, section 4.1).
+fn subject_public_key_info(certificate: &[u8]) -> Option<&[u8]> {
+    let certificate = DerElement::sequence(certificate)?;
+    let tbs_certificate = DerElement::sequence(certificate.contents)?;
+
+    // the version is optional, followed by the serial number, signature algorithm, issuer, validity, and subject
+    let first = DerElement::split(tbs_certificate.contents)?;
+    let skip = if first.tag == DER_EXPLICIT_VERSION { 5 } else { 4 };
+    let mut rest = first.rest;
+    for _ in 0..skip {
+        rest = DerElement::split(rest)?.rest;
+    }
+
+    DerElement::sequence(rest).map(|spki| spki.element)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn certificate_pin() {
+        let pem = include_str!("../../tests/data/localhost.pem");
+        let der = base64::decode(
+            pem.lines()
+                .filter(|line| !line.starts_with("-----"))
+                .collect::<String>(),
+        )
+        .unwrap();
+
+        // from openssl, as in the `CertificatePin` docs
+        let pin = CertificatePin::from_base64("u6PlIg6yRKANVI6zbW6a0Z/WnsnRIyc+qt17Exvv138=").unwrap();
+        assert_eq!(CertificatePin::from_certificate_der(&der), Some(pin));
+        assert_eq!(CertificatePin::from_base64(&pin.to_string()), Some(pin));
+
+        assert_eq!(CertificatePin::from_certificate_der(&der[..der.len() - 1]), None);
+        assert_eq!(CertificatePin::from_base64("abc="), None);
+    }
+}
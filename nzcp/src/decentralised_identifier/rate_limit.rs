@@ -0,0 +1,97 @@
+use tokio::{sync::Mutex, time::Instant};
+
+use super::DecentralizedIdentifierError;
+
+/// Limits how often DID documents are fetched, so a flood of passes with unknown keys can't overwhelm an issuer's DID
+/// endpoint, see `Verifier::with_resolution_rate_limiter`.
+///
+/// A token bucket holding up to `burst` tokens, refilled at `max_rate` tokens per second. Each resolution takes a
+/// token, and fails with `DecentralizedIdentifierError::RateLimited` when there are none left. Share one limiter
+/// (in an `Arc`) between verifiers to limit their combined resolutions.
+#[derive(Debug)]
+pub struct ResolutionRateLimiter {
+    max_rate: f64,
+    burst: usize,
+    bucket: Mutex<Bucket>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    tokens: f64,
+    refilled_at: Instant,
+}
+
+impl ResolutionRateLimiter {
+    /// Allow `max_rate` resolutions per second on average, and up to `burst` at once. The bucket starts full.
+    ///
+    /// # Panics
+    ///
+    /// If `max_rate` isn't a positive finite number, or `burst` is zero.
+    pub fn new(max_rate: f64, burst: usize) -> Self {
+        assert!(
+            max_rate.is_finite() && max_rate > 0.0,
+            "resolution rate must be positive, not {}",
+            max_rate
+        );
+        assert!(burst > 0, "resolution burst must be at least 1");
+
+        ResolutionRateLimiter {
+            max_rate,
+            burst,
+            bucket: Mutex::new(Bucket {
+                tokens: burst as f64,
+                refilled_at: Instant::now(),
+            }),
+        }
+    }
+
+    /// Take a token for a resolution, or fail with `RateLimited` if none are left.
+    pub async fn acquire(&self) -> Result<(), DecentralizedIdentifierError> {
+        let mut bucket = self.bucket.lock().await;
+        let now = Instant::now();
+        let refill = now.duration_since(bucket.refilled_at).as_secs_f64() * self.max_rate;
+        bucket.tokens = (bucket.tokens + refill).min(self.burst as f64);
+        bucket.refilled_at = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        }
+        else {
+            Err(DecentralizedIdentifierError::RateLimited)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test(start_paused = true)]
+    async fn token_bucket() {
+        let limiter = ResolutionRateLimiter::new(10.0, 2);
+
+        assert_eq!(limiter.acquire().await, Ok(()));
+        assert_eq!(limiter.acquire().await, Ok(()));
+        assert_eq!(limiter.acquire().await, Err(DecentralizedIdentifierError::RateLimited));
+
+        // one token is refilled every 100ms
+        tokio::time::advance(Duration::from_millis(150)).await;
+        assert_eq!(limiter.acquire().await, Ok(()));
+        assert_eq!(limiter.acquire().await, Err(DecentralizedIdentifierError::RateLimited));
+
+        // but never more than the burst
+        tokio::time::advance(Duration::from_millis(500)).await;
+        assert_eq!(limiter.acquire().await, Ok(()));
+        assert_eq!(limiter.acquire().await, Ok(()));
+        assert_eq!(limiter.acquire().await, Err(DecentralizedIdentifierError::RateLimited));
+    }
+
+    #[test]
+    #[should_panic(expected = "resolution rate must be positive")]
+    fn zero_rate() {
+        ResolutionRateLimiter::new(0.0, 1);
+    }
+}
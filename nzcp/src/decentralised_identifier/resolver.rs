@@ -0,0 +1,499 @@
+#[cfg(not(target_arch = "wasm32"))]
+use std::time::Duration;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    net::IpAddr,
+    sync::Arc,
+};
+
+use async_trait::async_trait;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::redirect;
+#[cfg(not(target_arch = "wasm32"))]
+use reqwest::tls::TlsInfo;
+use reqwest::{
+    header::{self, HeaderMap, HeaderName, HeaderValue},
+    Method, RequestBuilder, Response, StatusCode,
+};
+
+use super::{CertificatePin, DecentralizedIdentifier, DecentralizedIdentifierError};
+
+const USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
+
+#[cfg(not(target_arch = "wasm32"))]
+const CONNECT_TIMEOUT_SECS: u64 = 30;
+
+/// The most redirects followed fetching a DID document, as many as reqwest follows by default.
+const MAX_REDIRECTS: usize = 10;
+
+/// The largest DID document fetched when no limit is configured, 1 MiB.
+pub(crate) const DEFAULT_MAX_DOCUMENT_SIZE: usize = 1024 * 1024;
+
+/// Fetches the raw DID document of a decentralized identifier.
+///
+/// The verifier parses and validates the returned document, so implementations only need to retrieve it. This allows
+/// passes to be verified against documents from somewhere other than the issuer's web server (e.g. in tests).
+#[async_trait]
+pub trait DocumentResolver: Send + Sync {
+    /// Fetch the JSON representation of the DID document for `did`.
+    async fn resolve_representation(
+        &self,
+        did: &DecentralizedIdentifier,
+    ) -> Result<Vec<u8>, DecentralizedIdentifierError>;
+}
+
+/// The HTTP client used to fetch DID documents when none is configured: system TLS roots, no proxy, a 30 second
+/// connect timeout, and no redirects, which `WebResolver` follows itself. On wasm32 it uses the browser's `fetch`,
+/// which leaves all of these to the browser.
+pub(crate) fn default_http_client() -> reqwest::Client {
+    let builder = reqwest::Client::builder().user_agent(USER_AGENT);
+    #[cfg(not(target_arch = "wasm32"))]
+    let builder = builder
+        .no_proxy()
+        .connect_timeout(Duration::from_secs(CONNECT_TIMEOUT_SECS))
+        .redirect(redirect::Policy::none())
+        .tls_info(true);
+    builder.build().expect("failed to build the default HTTP client")
+}
+
+/// Called with each request for a DID document before it is sent, see `WebResolver::with_request_hook`.
+type RequestHook = dyn Fn(RequestBuilder) -> RequestBuilder + Send + Sync;
+
+/// Resolves `did:web` identifiers by fetching `/.well-known/did.json` from the issuer over HTTPS.
+#[derive(Clone)]
+pub struct WebResolver {
+    client: Arc<reqwest::Client>,
+    certificate_pins: HashMap<String, HashSet<CertificatePin>>,
+    headers: HeaderMap,
+    request_hook: Option<Arc<RequestHook>>,
+    force_https: bool,
+    max_document_size: usize,
+    document_url_overrides: HashMap<String, String>,
+}
+
+impl fmt::Debug for WebResolver {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("WebResolver")
+            .field("client", &self.client)
+            .field("certificate_pins", &self.certificate_pins)
+            .field("headers", &self.headers)
+            .field("request_hook", &self.request_hook.as_ref().map(|_| "<hook>"))
+            .field("force_https", &self.force_https)
+            .field("max_document_size", &self.max_document_size)
+            .field("document_url_overrides", &self.document_url_overrides)
+            .finish()
+    }
+}
+
+impl Default for WebResolver {
+    fn default() -> Self {
+        WebResolver::new(Arc::new(default_http_client()))
+    }
+}
+
+impl WebResolver {
+    /// Fetch DID documents with the given client (e.g. to add TLS roots or a proxy).
+    ///
+    /// The client must be built with `tls_info(true)` if any certificates are pinned. Build it with
+    /// `redirect(reqwest::redirect::Policy::none())` too: the resolver follows redirects itself, refusing any that
+    /// aren't allowed before they're requested. Redirects a client follows itself are only checked once the response
+    /// arrives, by when they've already been requested.
+    pub fn new(client: Arc<reqwest::Client>) -> Self {
+        WebResolver {
+            client,
+            certificate_pins: HashMap::new(),
+            headers: HeaderMap::new(),
+            request_hook: None,
+            force_https: true,
+            max_document_size: DEFAULT_MAX_DOCUMENT_SIZE,
+            document_url_overrides: HashMap::new(),
+        }
+    }
+
+    /// Whether DID documents must be fetched over HTTPS (defaults to true). When false, documents for `localhost`,
+    /// `127.0.0.1`, or `[::1]` are fetched over plain HTTP, e.g. to test against a local issuer. Other hosts always
+    /// require HTTPS, and redirects to plain HTTP elsewhere are refused without being followed.
+    pub fn with_force_https(mut self, force_https: bool) -> Self {
+        self.force_https = force_https;
+        self
+    }
+
+    /// The largest DID document in bytes that will be fetched (defaults to 1 MiB). Larger responses fail with
+    /// `ResponseTooLarge` as soon as the limit is passed, without reading the rest of the body.
+    pub fn with_max_document_size(mut self, max_document_size: usize) -> Self {
+        self.max_document_size = max_document_size;
+        self
+    }
+
+    /// Fetch the DID document for `did` (e.g. `did:web:nzcp.covid19.health.nz`) from `url` instead of the issuer, e.g.
+    /// to test against a local stub server. The URL is used as given, so must still be HTTPS unless HTTPS isn't forced
+    /// and it's on localhost.
+    pub fn with_document_url_override(mut self, did: &str, url: &str) -> Self {
+        self.document_url_overrides.insert(did.to_owned(), url.to_owned());
+        self
+    }
+
+    /// Whether `url` may be fetched, i.e. is HTTPS or, if HTTPS isn't forced, HTTP on localhost.
+    fn check_secure(&self, url: &reqwest::Url) -> Result<(), DecentralizedIdentifierError> {
+        match url.scheme() {
+            "https" => Ok(()),
+            "http" if !self.force_https && is_localhost(url) => Ok(()),
+            _ => Err(DecentralizedIdentifierError::InsecureResolution(url.to_string())),
+        }
+    }
+
+    /// Send a header with every request for a DID document (e.g. an identifying `User-Agent`, which replaces the
+    /// client's).
+    pub fn with_header(mut self, name: HeaderName, value: HeaderValue) -> Self {
+        self.headers.insert(name, value);
+        self
+    }
+
+    /// Call `hook` with each request for a DID document before it is sent, after the headers from `with_header`
+    /// have been added (e.g. to add a trace header per request).
+    ///
+    /// Resolution fails if the hook changes the request's URL, or the issuer redirects it to another origin, so hooked
+    /// requests are only ever sent to the issuer. Redirects are refused before they're followed, see
+    /// `WebResolver::new`.
+    pub fn with_request_hook<F>(mut self, hook: F) -> Self
+    where
+        F: Fn(RequestBuilder) -> RequestBuilder + Send + Sync + 'static,
+    {
+        self.request_hook = Some(Arc::new(hook));
+        self
+    }
+
+    fn build_request(&self, did: &DecentralizedIdentifier) -> Result<reqwest::Request, DecentralizedIdentifierError> {
+        use DecentralizedIdentifierError::ResolutionError;
+
+        let overridden = self.document_url_overrides.get(&did.to_string());
+        let mut url = reqwest::Url::parse(overridden.cloned().unwrap_or_else(|| did.document_url()).as_str())
+            .map_err(|err| ResolutionError(format!("Invalid DID document URL: {}", err)))?;
+        if overridden.is_none() && !self.force_https && is_localhost(&url) {
+            // https -> http is always an allowed scheme change
+            let _ = url.set_scheme("http");
+        }
+        self.check_secure(&url)?;
+        let mut request = self
+            .client
+            .get(url.clone())
+            .header(header::ACCEPT, "application/json")
+            .headers(self.headers.clone());
+        if let Some(hook) = &self.request_hook {
+            request = hook(request);
+        }
+
+        let request = request
+            .build()
+            .map_err(|err| ResolutionError(format!("Error building HTTP request: {}", err)))?;
+        if request.url() != &url {
+            return Err(ResolutionError(format!(
+                "the request hook changed the URL of {} to {}",
+                url,
+                request.url()
+            )));
+        }
+
+        Ok(request)
+    }
+
+    /// Only accept a certificate with the given public key from the host (e.g. `nzcp.identity.health.nz`), in
+    /// addition to the usual certificate validation.
+    ///
+    /// Once a host has a pin, its certificate must match one of its pins, so pin a backup key as well. Hosts without
+    /// pins are trusted as long as their certificate is valid. Redirects from a pinned host to any other host are
+    /// refused without being followed, as only the certificate of the host the document is finally fetched from can be
+    /// checked.
+    ///
+    /// On wasm32 the browser doesn't expose the certificate, so documents from pinned hosts are always refused.
+    pub fn with_certificate_pin(mut self, host: &str, pin: CertificatePin) -> Self {
+        self.certificate_pins
+            .entry(host.to_ascii_lowercase())
+            .or_default()
+            .insert(pin);
+        self
+    }
+
+    /// Whether a redirect from the `requested` URL to `location` may be followed, i.e. `location` is secure (see
+    /// `check_secure`), on the requested host if it's pinned, and of the same origin if there's a request hook.
+    fn check_redirect(
+        &self,
+        requested: &reqwest::Url,
+        location: &reqwest::Url,
+    ) -> Result<(), DecentralizedIdentifierError> {
+        self.check_secure(location)?;
+        let requested_host = requested.host_str().unwrap_or_default();
+        if location.host_str() != Some(requested_host) && self.certificate_pins.contains_key(requested_host) {
+            return Err(DecentralizedIdentifierError::PinnedHostRedirected {
+                host: requested_host.to_owned(),
+                location: location.to_string(),
+            });
+        }
+        if self.request_hook.is_some() && location.origin() != requested.origin() {
+            return Err(DecentralizedIdentifierError::ResolutionError(format!(
+                "the request hook's request for {} was redirected to {}",
+                requested, location
+            )));
+        }
+        Ok(())
+    }
+
+    /// Check the certificate the server presented against the host's pins, before its response is read. If the
+    /// `requested` URL's host is pinned, the response must come from that host rather than a redirect elsewhere.
+    fn check_certificate_pin(
+        &self,
+        requested: &reqwest::Url,
+        response: &reqwest::Response,
+    ) -> Result<(), DecentralizedIdentifierError> {
+        // the client may have followed redirects itself (as fetch always does on wasm32)
+        self.check_redirect(requested, response.url())?;
+
+        let host = response.url().host_str().unwrap_or_default();
+        let pins = match self.certificate_pins.get(host) {
+            Some(pins) => pins,
+            None => return Ok(()),
+        };
+
+        #[cfg(not(target_arch = "wasm32"))]
+        let pin = response
+            .extensions()
+            .get::<TlsInfo>()
+            .and_then(TlsInfo::peer_certificate)
+            .and_then(CertificatePin::from_certificate_der);
+        #[cfg(target_arch = "wasm32")]
+        let pin: Option<CertificatePin> = None;
+        match pin {
+            Some(pin) if pins.contains(&pin) => Ok(()),
+            _ => Err(DecentralizedIdentifierError::CertificatePinMismatch { host: host.to_owned() }),
+        }
+    }
+
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "fetch_did_document",
+            level = "debug",
+            skip_all,
+            fields(did = %did, http_status = tracing::field::Empty)
+        )
+    )]
+    async fn fetch_document(&self, did: &DecentralizedIdentifier) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        use DecentralizedIdentifierError::ResolutionError;
+
+        let mut request = self.build_request(did)?;
+        let url = request.url().clone();
+        let mut redirects = 0;
+        let response = loop {
+            let headers = request.headers().clone();
+            let response = self
+                .client
+                .execute(request)
+                .await
+                .map_err(|err| ResolutionError(format!("Error sending HTTP request: {}", err)))?;
+            self.check_certificate_pin(&url, &response)?;
+
+            // follow redirects here rather than in the client, so each is checked before it's requested
+            let location = match redirect_location(&response) {
+                Some(location) => location,
+                None => break response,
+            };
+            redirects += 1;
+            if redirects > MAX_REDIRECTS {
+                return Err(ResolutionError(format!(
+                    "more than {} redirects fetching {}",
+                    MAX_REDIRECTS, url
+                )));
+            }
+            self.check_redirect(&url, &location)?;
+            request = redirect_request(response.url(), location, headers);
+        };
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record("http_status", &response.status().as_u16());
+
+        if response.status() == StatusCode::NOT_FOUND {
+            return Err(DecentralizedIdentifierError::DocumentNotFound);
+        }
+        let response = response
+            .error_for_status()
+            .map_err(|err| ResolutionError(err.to_string()))?;
+
+        if response
+            .content_length()
+            .is_some_and(|length| length > self.max_document_size as u64)
+        {
+            return Err(DecentralizedIdentifierError::ResponseTooLarge(self.max_document_size));
+        }
+        self.read_document(response).await
+    }
+
+    /// Read the body of a response, failing once it's larger than the maximum document size. The body may be chunked
+    /// or not match its Content-Length, so this is checked as it is read too.
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn read_document(&self, mut response: Response) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        let mut document = Vec::new();
+        while let Some(chunk) = response.chunk().await.map_err(read_error)? {
+            if document.len() + chunk.len() > self.max_document_size {
+                return Err(DecentralizedIdentifierError::ResponseTooLarge(self.max_document_size));
+            }
+            document.extend_from_slice(&chunk);
+        }
+
+        Ok(document)
+    }
+
+    /// Read the body of a response, failing if it's larger than the maximum document size. `fetch` can't read the body
+    /// in chunks, so this is only checked once it's all been read.
+    #[cfg(target_arch = "wasm32")]
+    async fn read_document(&self, response: Response) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        let document = response.bytes().await.map_err(read_error)?;
+        if document.len() > self.max_document_size {
+            return Err(DecentralizedIdentifierError::ResponseTooLarge(self.max_document_size));
+        }
+
+        Ok(document.to_vec())
+    }
+}
+
+/// Where a response redirects to, `None` if it isn't a redirect (or doesn't say where to).
+fn redirect_location(response: &Response) -> Option<reqwest::Url> {
+    if !matches!(
+        response.status(),
+        StatusCode::MOVED_PERMANENTLY
+            | StatusCode::FOUND
+            | StatusCode::SEE_OTHER
+            | StatusCode::TEMPORARY_REDIRECT
+            | StatusCode::PERMANENT_REDIRECT
+    ) {
+        return None;
+    }
+    let location = response.headers().get(header::LOCATION)?.to_str().ok()?;
+    response.url().join(location).ok()
+}
+
+/// The request following a redirect from `previous` to `location`, with the previous request's headers, less any
+/// credentials when the redirect is to another host, as reqwest does when following redirects itself.
+fn redirect_request(previous: &reqwest::Url, location: reqwest::Url, mut headers: HeaderMap) -> reqwest::Request {
+    if location.host_str() != previous.host_str()
+        || location.port_or_known_default() != previous.port_or_known_default()
+    {
+        for name in [
+            header::AUTHORIZATION,
+            header::COOKIE,
+            header::PROXY_AUTHORIZATION,
+            header::WWW_AUTHENTICATE,
+        ] {
+            headers.remove(name);
+        }
+    }
+    let mut request = reqwest::Request::new(Method::GET, location);
+    *request.headers_mut() = headers;
+    request
+}
+
+fn read_error(error: reqwest::Error) -> DecentralizedIdentifierError {
+    DecentralizedIdentifierError::ResolutionError(format!("Error reading HTTP response: {}", error))
+}
+
+fn is_localhost(url: &reqwest::Url) -> bool {
+    let host = url.host_str().unwrap_or_default();
+    let address = host.trim_start_matches('[').trim_end_matches(']');
+    host.eq_ignore_ascii_case("localhost") || address.parse::<IpAddr>().is_ok_and(|address| address.is_loopback())
+}
+
+#[async_trait]
+impl DocumentResolver for WebResolver {
+    #[cfg(not(target_arch = "wasm32"))]
+    async fn resolve_representation(
+        &self,
+        did: &DecentralizedIdentifier,
+    ) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        self.fetch_document(did).await
+    }
+
+    /// `fetch` holds JavaScript values across awaits, so its futures aren't `Send`. Run it on the browser's event loop
+    /// and wait for the result instead.
+    #[cfg(target_arch = "wasm32")]
+    async fn resolve_representation(
+        &self,
+        did: &DecentralizedIdentifier,
+    ) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        let (sender, receiver) = tokio::sync::oneshot::channel();
+        let (resolver, did) = (self.clone(), did.clone());
+        wasm_bindgen_futures::spawn_local(async move {
+            let _ = sender.send(resolver.fetch_document(&did).await);
+        });
+        receiver.await.map_err(|_| {
+            DecentralizedIdentifierError::ResolutionError(String::from("the DID document fetch was cancelled"))
+        })?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn local_issuer() -> DecentralizedIdentifier {
+        DecentralizedIdentifier::Web(String::from("localhost%3A8080"))
+    }
+
+    #[test]
+    fn force_https() {
+        let resolver = WebResolver::default();
+        let request = resolver.build_request(&local_issuer()).unwrap();
+        assert_eq!(request.url().as_str(), "https://localhost:8080/.well-known/did.json");
+
+        let resolver = resolver.with_force_https(false);
+        let request = resolver.build_request(&local_issuer()).unwrap();
+        assert_eq!(request.url().as_str(), "http://localhost:8080/.well-known/did.json");
+        let request = resolver
+            .build_request(&DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz")))
+            .unwrap();
+        assert_eq!(request.url().scheme(), "https");
+    }
+
+    #[test]
+    fn document_url_overrides() {
+        let issuer = DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz"));
+        let resolver = WebResolver::default()
+            .with_document_url_override("did:web:nzcp.covid19.health.nz", "http://localhost:8080/did.json");
+
+        assert_eq!(
+            resolver.build_request(&issuer).unwrap_err(),
+            DecentralizedIdentifierError::InsecureResolution(String::from("http://localhost:8080/did.json"))
+        );
+        let resolver = resolver.with_force_https(false);
+        let request = resolver.build_request(&issuer).unwrap();
+        assert_eq!(request.url().as_str(), "http://localhost:8080/did.json");
+
+        // other issuers are unaffected
+        let request = resolver.build_request(&local_issuer()).unwrap();
+        assert_eq!(request.url().as_str(), "http://localhost:8080/.well-known/did.json");
+
+        let resolver = resolver.with_document_url_override("did:web:localhost%3A8080", "not a URL");
+        assert!(matches!(
+            resolver.build_request(&local_issuer()),
+            Err(DecentralizedIdentifierError::ResolutionError(_))
+        ));
+    }
+
+    #[test]
+    fn insecure_urls() {
+        let url = |url: &str| reqwest::Url::parse(url).unwrap();
+        let forced = WebResolver::default();
+        let unforced = WebResolver::default().with_force_https(false);
+
+        assert!(forced.check_secure(&url("https://example.com/")).is_ok());
+        assert_eq!(
+            forced.check_secure(&url("http://localhost/")),
+            Err(DecentralizedIdentifierError::InsecureResolution(String::from(
+                "http://localhost/"
+            )))
+        );
+        assert!(unforced.check_secure(&url("http://localhost/")).is_ok());
+        assert!(unforced.check_secure(&url("http://127.0.0.1:8080/")).is_ok());
+        assert!(unforced.check_secure(&url("http://[::1]/")).is_ok());
+        assert!(unforced.check_secure(&url("http://example.com/")).is_err());
+        assert!(unforced.check_secure(&url("ftp://localhost/")).is_err());
+    }
+}
@@ -1,27 +1,184 @@
+//! Errors returned while verifying passes, and by the rest of the public API.
+//!
+//! Every error type that can appear in a public `Result`, or nested inside another error, is re-exported here, so
+//! matching on the cause of a failed verification only needs imports from `nzcp::error`:
+//!
+//! ```
+//! use nzcp::error::{CoseVerificationError, DecentralizedIdentifierError, NzcpError};
+//!
+//! fn describe(error: &NzcpError) -> &'static str {
+//!     match error {
+//!         NzcpError::InvalidSignature(CoseVerificationError::DecentralizedIdentifierResolution(
+//!             DecentralizedIdentifierError::ResolutionError(_),
+//!         )) => "the issuer's keys couldn't be fetched, try again later",
+//!         NzcpError::InvalidSignature(CoseVerificationError::UntrustedIssuer(_)) => "not issued by a trusted issuer",
+//!         NzcpError::InvalidSignature(_) => "not authentic",
+//!         NzcpError::PassNotYetActive { .. } | NzcpError::PassTooFarInFuture { .. } => "not active yet",
+//!         NzcpError::PassExpired { .. } => "expired",
+//!         NzcpError::PassRevoked(_) => "revoked",
+//!         _ => "not a valid pass",
+//!     }
+//! }
+//! ```
+//!
+//! To log or count failures, `NzcpError::code` gives a stable `ErrorCode` (e.g. `signature.invalid`) that doesn't
+//! depend on the wording of error messages. The errors also implement `serde::Serialize`, as an object with a stable
+//! `kind`, the error's fields, and the error it wraps as its `source`, for structured logs.
+//!
+//! All of the errors are `Send + Sync + 'static`, so `?` converts them into a `Box<dyn std::error::Error + Send +
+//! Sync>` or an `anyhow::Error`, with the nested errors available from `source()`, and the error itself from
+//! `downcast_ref`:
+//!
+//! ```
+//! use nzcp::{
+//!     error::{NzcpError, QrBarcodeError},
+//!     verify_pass_uri, PublicCovidPass,
+//! };
+//!
+//! async fn given_name(uri: &str) -> anyhow::Result<String> {
+//!     let pass: PublicCovidPass = verify_pass_uri(uri).await?;
+//!     Ok(pass.given_name)
+//! }
+//!
+//! # let runtime = tokio::runtime::Builder::new_current_thread().build().unwrap();
+//! let error = runtime.block_on(given_name("https://example.com")).unwrap_err();
+//! assert_eq!(
+//!     error.downcast_ref::<NzcpError>(),
+//!     Some(&NzcpError::QrBarcode(QrBarcodeError::MissingNzcpPrefix))
+//! );
+//! ```
+
+use std::sync::Arc;
+
+use chrono::{DateTime, Utc};
 use thiserror::Error;
 
+pub use self::code::ErrorCode;
+#[cfg(any(test, feature = "signing"))]
+pub use crate::sign::SignError;
 pub use crate::{
-    decentralised_identifier::DecentralizedIdentifierError,
+    codec::CborCodecError,
+    decentralised_identifier::{DecentralizedIdentifierError, DecentralizedIdentifierParseError},
+    json::JsonConversionError,
+    jwks::{JwksError, JwksWarning},
+    pass::public_covid_pass::PublicCovidPassError,
     payload::{
-        barcode::QrBarcodeError, cose::signature::verify::CoseVerificationError, cwt::validation::CwtValidationError,
+        barcode::QrBarcodeError,
+        batch::BatchDecoderError,
+        canonical::CanonicalCborError,
+        cose::signature::verify::CoseVerificationError,
+        cwt::{validation::CwtValidationError, version::CredentialVersion},
+        inspector::DecodeError,
     },
+    revocation::RevokedPassRegistryError,
+    snapshot::IssuerSnapshotError,
+    trusted_issuers::TrustedIssuersError,
+    verifier::{KeyStoreError, VerifierConfigError},
 };
 
+mod code;
+mod serialize;
+
 /// An error that occurred while verifying a pass barcode.
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum NzcpError {
     /// The barcode string was invalid.
     #[error("Invalid QR barcode: {0:?}")]
     QrBarcode(#[from] QrBarcodeError),
     /// The payload could not be deserialized correctly.
+    ///
+    /// The CBOR error is not `Clone` itself, so is shared behind an `Arc`.
     #[error("Invalid payload: {0:?}")]
-    InvalidPayload(#[from] serde_cbor::Error),
+    InvalidPayload(Arc<serde_cbor::Error>),
+    /// The payload was not canonical CBOR, see `VerifierConfig::strict_cbor`.
+    #[error("Non-canonical payload: {0}")]
+    NonCanonicalCbor(#[from] CanonicalCborError),
     /// The signature of the COSE payload was invalid, or the issuer was not trusted.
     #[error("Invalid signature: {0:?}")]
     InvalidSignature(#[from] CoseVerificationError),
     /// The CWT claims were not valid.
     #[error("Invalid CWT: {0:?}")]
     InvalidCWT(#[from] CwtValidationError),
+    /// The credential's `vc.version` has a major version other than `CredentialVersion::SUPPORTED_MAJOR`, so its
+    /// claims may not mean what this library expects.
+    #[error(
+        "Unsupported credential version {0} (only {}.x.x is supported)",
+        CredentialVersion::SUPPORTED_MAJOR
+    )]
+    UnsupportedCredentialVersion(CredentialVersion),
+    /// The pass is authentic, but is not active yet.
+    #[error(
+        "Pass is not active until {} (checked at {})",
+        human_readable(not_before),
+        human_readable(checked_at)
+    )]
+    PassNotYetActive {
+        not_before: DateTime<Utc>,
+        checked_at: DateTime<Utc>,
+    },
+    /// The pass doesn't become active until further in the future than allowed, see `VerifierConfig::max_future_nbf`.
+    #[error(
+        "Pass is not active until {}, too long after it was checked at {}",
+        human_readable(not_before),
+        human_readable(checked_at)
+    )]
+    PassTooFarInFuture {
+        not_before: DateTime<Utc>,
+        checked_at: DateTime<Utc>,
+    },
+    /// The pass is authentic, but has expired.
+    #[error(
+        "Pass expired on {} (checked at {})",
+        human_readable(expiry),
+        human_readable(checked_at)
+    )]
+    PassExpired {
+        expiry: DateTime<Utc>,
+        checked_at: DateTime<Utc>,
+    },
+    /// The pass has been revoked, see `RevokedPassRegistry`.
+    #[error("Pass revoked: urn:uuid:{0}")]
+    PassRevoked(uuid::Uuid),
+    /// Verification didn't finish within the given time, see `Verifier::verify_pass_uri_with_timeout`.
+    ///
+    /// Only resolving the issuer's key (from the key store or its DID document) waits, the rest of verification runs
+    /// without yielding, so this means the key couldn't be resolved in time.
+    #[error("Timed out resolving the issuer's key after {0:?}")]
+    Timeout(std::time::Duration),
+    /// Every problem found with the pass, in the order they're checked, see
+    /// `Verifier::verify_credential_uri_all_errors`.
+    ///
+    /// Only built from those problems by `NzcpError::from`, which keeps a single problem as it is, so this holds more
+    /// than one.
+    #[error("{} problems: {}", .0.len(), display_all(.0))]
+    MultipleErrors(Vec<NzcpError>),
+}
+
+fn human_readable(date: &DateTime<Utc>) -> impl std::fmt::Display {
+    date.format("%-d %B %Y %H:%M:%S UTC")
+}
+
+fn display_all(errors: &[NzcpError]) -> String {
+    errors.iter().map(ToString::to_string).collect::<Vec<_>>().join("; ")
+}
+
+impl From<serde_cbor::Error> for NzcpError {
+    fn from(error: serde_cbor::Error) -> Self {
+        NzcpError::InvalidPayload(Arc::new(error))
+    }
+}
+
+/// The problems found by `Verifier::verify_credential_uri_all_errors` as one error, so `?` can return them from a
+/// function failing with an `NzcpError`: a single problem as itself, or more as `NzcpError::MultipleErrors`.
+impl From<Vec<NzcpError>> for NzcpError {
+    fn from(mut errors: Vec<NzcpError>) -> Self {
+        if errors.len() == 1 {
+            errors.remove(0)
+        }
+        else {
+            NzcpError::MultipleErrors(errors)
+        }
+    }
 }
 
 impl PartialEq for NzcpError {
@@ -30,11 +187,103 @@ impl PartialEq for NzcpError {
         match (self, other) {
             (QrBarcode(l0), QrBarcode(r0)) => l0 == r0,
             (InvalidPayload(l0), InvalidPayload(r0)) => l0.to_string() == r0.to_string(),
+            (NonCanonicalCbor(l0), NonCanonicalCbor(r0)) => l0 == r0,
             (InvalidSignature(l0), InvalidSignature(r0)) => l0 == r0,
             (InvalidCWT(l0), InvalidCWT(r0)) => l0 == r0,
+            (UnsupportedCredentialVersion(l0), UnsupportedCredentialVersion(r0)) => l0 == r0,
+            (
+                PassNotYetActive {
+                    not_before: l0,
+                    checked_at: l1,
+                },
+                PassNotYetActive {
+                    not_before: r0,
+                    checked_at: r1,
+                },
+            ) => l0 == r0 && l1 == r1,
+            (
+                PassTooFarInFuture {
+                    not_before: l0,
+                    checked_at: l1,
+                },
+                PassTooFarInFuture {
+                    not_before: r0,
+                    checked_at: r1,
+                },
+            ) => l0 == r0 && l1 == r1,
+            (
+                PassExpired {
+                    expiry: l0,
+                    checked_at: l1,
+                },
+                PassExpired {
+                    expiry: r0,
+                    checked_at: r1,
+                },
+            ) => l0 == r0 && l1 == r1,
+            (PassRevoked(l0), PassRevoked(r0)) => l0 == r0,
+            (Timeout(l0), Timeout(r0)) => l0 == r0,
+            (MultipleErrors(l0), MultipleErrors(r0)) => l0 == r0,
             _ => false,
         }
     }
 }
 
 impl Eq for NzcpError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_shareable<E: std::error::Error + Clone + Send + Sync + 'static>() {}
+
+    #[test]
+    fn errors_are_shareable() {
+        assert_shareable::<NzcpError>();
+        assert_shareable::<DecentralizedIdentifierError>();
+        assert_shareable::<DecentralizedIdentifierParseError>();
+        assert_shareable::<QrBarcodeError>();
+        assert_shareable::<BatchDecoderError>();
+        assert_shareable::<CanonicalCborError>();
+        assert_shareable::<CoseVerificationError>();
+        assert_shareable::<CwtValidationError>();
+        assert_shareable::<DecodeError>();
+        assert_shareable::<KeyStoreError>();
+        assert_shareable::<IssuerSnapshotError>();
+        assert_shareable::<JwksError>();
+        assert_shareable::<TrustedIssuersError>();
+        assert_shareable::<RevokedPassRegistryError>();
+        assert_shareable::<SignError>();
+        assert_shareable::<CborCodecError>();
+        assert_shareable::<JsonConversionError>();
+        assert_shareable::<PublicCovidPassError>();
+        assert_shareable::<VerifierConfigError>();
+    }
+
+    #[test]
+    fn boxed_errors() {
+        fn resolve() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Err(DecentralizedIdentifierError::EmptyDocument)?
+        }
+        fn verify() -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+            Err(NzcpError::InvalidSignature(
+                DecentralizedIdentifierError::EmptyDocument.into(),
+            ))?
+        }
+
+        let error = resolve().unwrap_err();
+        assert_eq!(
+            error.downcast_ref::<DecentralizedIdentifierError>(),
+            Some(&DecentralizedIdentifierError::EmptyDocument)
+        );
+
+        let error = verify().unwrap_err();
+        assert!(error.downcast_ref::<NzcpError>().is_some());
+        let cose = error.source().unwrap();
+        assert!(cose.downcast_ref::<CoseVerificationError>().is_some());
+        assert_eq!(
+            cose.source().unwrap().downcast_ref::<DecentralizedIdentifierError>(),
+            Some(&DecentralizedIdentifierError::EmptyDocument)
+        );
+    }
+}
@@ -0,0 +1,449 @@
+use std::fmt;
+
+use super::{
+    CanonicalCborError, CoseVerificationError, CwtValidationError, DecentralizedIdentifierError, NzcpError,
+    QrBarcodeError,
+};
+
+/// A stable, machine-readable code for why verification failed, see `NzcpError::code`, e.g. to log or count failures
+/// without depending on the wording of error messages.
+///
+/// Each code is a dotted string (e.g. `signature.invalid`) whose first part names the stage of verification it
+/// belongs to, following the sections of the specification: the 2D barcode encoding, the CBOR and COSE structure, the
+/// signature and issuer, DID resolution, the CWT claims, and finally the pass's validity period and revocation.
+///
+/// Codes aren't keyed to the specification's section numbers: the specification's sections are headings that have
+/// been reordered between revisions, and several failures (e.g. DID resolution, revocation, or non-canonical CBOR)
+/// aren't covered by a single requirement, so a number wouldn't stay stable or identify a failure on its own.
+///
+/// Codes are stable: once released, a code's string is never changed or reused for a different failure. New codes may
+/// be added (e.g. for new failures), so the enum is non-exhaustive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorCode {
+    /// The barcode didn't start with `NZCP:/<version-identifier>/`.
+    BarcodePrefix,
+    /// The barcode's version-identifier isn't supported.
+    BarcodeVersion,
+    /// The barcode's payload wasn't base32 encoded.
+    BarcodeBase32,
+    /// The payload wasn't a well-formed COSE_Sign1 structure holding CWT claims.
+    CborMalformed,
+    /// The payload wasn't canonical CBOR, see `VerifierConfig::strict_cbor`.
+    CborNonCanonical,
+    /// The COSE `alg` header was missing or not ES256.
+    CoseAlgorithm,
+    /// The COSE `kid` header was missing, not UTF-8, or conflicting.
+    CoseKid,
+    /// The signature didn't match the issuer's key.
+    SignatureInvalid,
+    /// The signature wasn't the 64 bytes of an ES256 signature.
+    SignatureLength,
+    /// The signing key didn't match any of the issuer's pinned keys.
+    SignatureUnpinnedKey,
+    /// The pass wasn't issued by a trusted issuer.
+    IssuerUntrusted,
+    /// The issuer's DID document couldn't be fetched.
+    DidResolution,
+    /// Too many DID documents were being resolved at once.
+    DidRateLimited,
+    /// The issuer's key couldn't be resolved in time.
+    DidTimeout,
+    /// The signing key wasn't in the issuer's DID document (or the configured snapshot or JWKS).
+    DidKeyNotFound,
+    /// The issuer's DID document, or the signing key in it, was invalid.
+    DidInvalidDocument,
+    /// The credential's version is invalid or unsupported.
+    ClaimsVersion,
+    /// The credential's `@context` is invalid.
+    ClaimsContext,
+    /// The credential's `type` is invalid.
+    ClaimsType,
+    /// The `cti` claim isn't a UUID.
+    ClaimsTokenId,
+    /// A standard claim used a string rather than integer key.
+    ClaimsKey,
+    /// The pass expires before it becomes active.
+    ClaimsValidityPeriod,
+    /// The pass isn't active yet.
+    PassNotActive,
+    /// The pass doesn't become active until too far in the future.
+    PassTooFarInFuture,
+    /// The pass has expired.
+    PassExpired,
+    /// The pass has been revoked.
+    PassRevoked,
+    /// There was more than one problem with the pass, see `NzcpError::MultipleErrors`.
+    Multiple,
+}
+
+impl ErrorCode {
+    /// The code as a string, e.g. `signature.invalid`.
+    pub fn as_str(&self) -> &'static str {
+        use ErrorCode::*;
+        match self {
+            BarcodePrefix => "barcode.prefix",
+            BarcodeVersion => "barcode.version",
+            BarcodeBase32 => "barcode.base32",
+            CborMalformed => "cbor.malformed",
+            CborNonCanonical => "cbor.non_canonical",
+            CoseAlgorithm => "cose.algorithm",
+            CoseKid => "cose.kid",
+            SignatureInvalid => "signature.invalid",
+            SignatureLength => "signature.length",
+            SignatureUnpinnedKey => "signature.unpinned_key",
+            IssuerUntrusted => "issuer.untrusted",
+            DidResolution => "did.resolution",
+            DidRateLimited => "did.rate_limited",
+            DidTimeout => "did.timeout",
+            DidKeyNotFound => "did.key_not_found",
+            DidInvalidDocument => "did.invalid_document",
+            ClaimsVersion => "claims.version",
+            ClaimsContext => "claims.context",
+            ClaimsType => "claims.type",
+            ClaimsTokenId => "claims.token_id",
+            ClaimsKey => "claims.key",
+            ClaimsValidityPeriod => "claims.validity_period",
+            PassNotActive => "pass.not_active",
+            PassTooFarInFuture => "pass.too_far_in_future",
+            PassExpired => "pass.expired",
+            PassRevoked => "pass.revoked",
+            Multiple => "multiple",
+        }
+    }
+}
+
+impl fmt::Display for ErrorCode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl NzcpError {
+    /// The stable code for why verification failed, see `ErrorCode`.
+    pub fn code(&self) -> ErrorCode {
+        match self {
+            NzcpError::QrBarcode(error) => barcode_code(error),
+            NzcpError::InvalidPayload(_) => ErrorCode::CborMalformed,
+            NzcpError::NonCanonicalCbor(error) => canonical_code(error),
+            NzcpError::InvalidSignature(error) => cose_code(error),
+            NzcpError::InvalidCWT(error) => cwt_code(error),
+            NzcpError::UnsupportedCredentialVersion(_) => ErrorCode::ClaimsVersion,
+            NzcpError::PassNotYetActive { .. } => ErrorCode::PassNotActive,
+            NzcpError::PassTooFarInFuture { .. } => ErrorCode::PassTooFarInFuture,
+            NzcpError::PassExpired { .. } => ErrorCode::PassExpired,
+            NzcpError::PassRevoked(_) => ErrorCode::PassRevoked,
+            NzcpError::Timeout(_) => ErrorCode::DidTimeout,
+            NzcpError::MultipleErrors(_) => ErrorCode::Multiple,
+        }
+    }
+}
+
+fn barcode_code(error: &QrBarcodeError) -> ErrorCode {
+    match error {
+        QrBarcodeError::MissingNzcpPrefix | QrBarcodeError::MalformedPrefix => ErrorCode::BarcodePrefix,
+        QrBarcodeError::UnsupportedVersion { .. } => ErrorCode::BarcodeVersion,
+        QrBarcodeError::InvalidBase32 | QrBarcodeError::NonAscii { .. } => ErrorCode::BarcodeBase32,
+    }
+}
+
+fn canonical_code(error: &CanonicalCborError) -> ErrorCode {
+    match error {
+        CanonicalCborError::Malformed(_) | CanonicalCborError::TooDeeplyNested(_) => ErrorCode::CborMalformed,
+        CanonicalCborError::IndefiniteLength(_)
+        | CanonicalCborError::NonShortestForm { .. }
+        | CanonicalCborError::DuplicateKey(_) => ErrorCode::CborNonCanonical,
+    }
+}
+
+fn cose_code(error: &CoseVerificationError) -> ErrorCode {
+    match error {
+        CoseVerificationError::SignatureVerificationFailed => ErrorCode::SignatureInvalid,
+        CoseVerificationError::InvalidSignatureLength { .. } => ErrorCode::SignatureLength,
+        CoseVerificationError::CwtValidation(error) => cwt_code(error),
+        CoseVerificationError::UntrustedIssuer(_) => ErrorCode::IssuerUntrusted,
+        CoseVerificationError::DecentralizedIdentifierResolution(error) => did_code(error),
+        CoseVerificationError::UnpinnedKey(_) => ErrorCode::SignatureUnpinnedKey,
+        CoseVerificationError::UnsupportedAlgorithm { .. } | CoseVerificationError::MissingAlgorithm => {
+            ErrorCode::CoseAlgorithm
+        }
+        CoseVerificationError::MissingKid
+        | CoseVerificationError::NonUtf8Kid(_)
+        | CoseVerificationError::ConflictingKid { .. } => ErrorCode::CoseKid,
+    }
+}
+
+fn did_code(error: &DecentralizedIdentifierError) -> ErrorCode {
+    use DecentralizedIdentifierError::*;
+    match error {
+        ResolutionError(_)
+        | DocumentNotFound
+        | CertificatePinMismatch { .. }
+        | PinnedHostRedirected { .. }
+        | InsecureResolution(_)
+        | ResponseTooLarge(_) => ErrorCode::DidResolution,
+        RateLimited => ErrorCode::DidRateLimited,
+        MissingAssertionMethods
+        | MissingAssertionMethod { .. }
+        | MissingVerificationMethods
+        | MissingVerificationMethod { .. }
+        | IssuerNotInSnapshot(_)
+        | KeyNotInJwks(_) => ErrorCode::DidKeyNotFound,
+        EmptyDocument | NotJsonWebKey2020 | MissingJWK | JWKNotEllipticCurve | JWKMissingX | JWKMissingY
+        | JWKWrongCurve | InvalidJWK => ErrorCode::DidInvalidDocument,
+    }
+}
+
+fn cwt_code(error: &CwtValidationError) -> ErrorCode {
+    match error {
+        CwtValidationError::InvalidVersion(_) => ErrorCode::ClaimsVersion,
+        CwtValidationError::InvalidContext { .. } => ErrorCode::ClaimsContext,
+        CwtValidationError::InvalidType { .. } => ErrorCode::ClaimsType,
+        CwtValidationError::InvalidTokenId { .. } => ErrorCode::ClaimsTokenId,
+        CwtValidationError::StringClaimKey { .. } => ErrorCode::ClaimsKey,
+        CwtValidationError::ExpiresBeforeActive { .. } => ErrorCode::ClaimsValidityPeriod,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{collections::HashSet, sync::Arc, time::Duration};
+
+    use chrono::Utc;
+    use serde::de::Error;
+
+    use super::*;
+    use crate::CredentialVersion;
+
+    /// One of every variant of every error that can end up in an `NzcpError`, with its expected code.
+    fn every_error() -> Vec<(NzcpError, &'static str)> {
+        let now = Utc::now();
+        let text = String::new;
+        let barcode = |error| NzcpError::QrBarcode(error);
+        let cose = |error| NzcpError::InvalidSignature(error);
+        let did = |error| NzcpError::InvalidSignature(CoseVerificationError::DecentralizedIdentifierResolution(error));
+        let cwt = |error| NzcpError::InvalidCWT(error);
+
+        vec![
+            (barcode(QrBarcodeError::MissingNzcpPrefix), "barcode.prefix"),
+            (barcode(QrBarcodeError::MalformedPrefix), "barcode.prefix"),
+            (
+                barcode(QrBarcodeError::UnsupportedVersion { found: 2 }),
+                "barcode.version",
+            ),
+            (barcode(QrBarcodeError::InvalidBase32), "barcode.base32"),
+            (barcode(QrBarcodeError::NonAscii { offset: 0 }), "barcode.base32"),
+            (
+                NzcpError::InvalidPayload(Arc::new(serde_cbor::Error::custom("malformed"))),
+                "cbor.malformed",
+            ),
+            (
+                NzcpError::NonCanonicalCbor(CanonicalCborError::Malformed(text())),
+                "cbor.malformed",
+            ),
+            (
+                NzcpError::NonCanonicalCbor(CanonicalCborError::TooDeeplyNested(text())),
+                "cbor.malformed",
+            ),
+            (
+                NzcpError::NonCanonicalCbor(CanonicalCborError::IndefiniteLength(text())),
+                "cbor.non_canonical",
+            ),
+            (
+                NzcpError::NonCanonicalCbor(CanonicalCborError::NonShortestForm {
+                    kind: "integer",
+                    path: text(),
+                }),
+                "cbor.non_canonical",
+            ),
+            (
+                NzcpError::NonCanonicalCbor(CanonicalCborError::DuplicateKey(text())),
+                "cbor.non_canonical",
+            ),
+            (
+                cose(CoseVerificationError::SignatureVerificationFailed),
+                "signature.invalid",
+            ),
+            (
+                cose(CoseVerificationError::InvalidSignatureLength { found: 63 }),
+                "signature.length",
+            ),
+            (
+                cose(CoseVerificationError::CwtValidation(
+                    CwtValidationError::InvalidTokenId { length: 0 },
+                )),
+                "claims.token_id",
+            ),
+            (cose(CoseVerificationError::UntrustedIssuer(text())), "issuer.untrusted"),
+            (
+                cose(CoseVerificationError::UnpinnedKey(text())),
+                "signature.unpinned_key",
+            ),
+            (
+                cose(CoseVerificationError::UnsupportedAlgorithm { found: -35 }),
+                "cose.algorithm",
+            ),
+            (cose(CoseVerificationError::MissingAlgorithm), "cose.algorithm"),
+            (cose(CoseVerificationError::MissingKid), "cose.kid"),
+            (cose(CoseVerificationError::NonUtf8Kid(vec![0xff])), "cose.kid"),
+            (
+                cose(CoseVerificationError::ConflictingKid {
+                    protected: text(),
+                    unprotected: text(),
+                }),
+                "cose.kid",
+            ),
+            (
+                did(DecentralizedIdentifierError::ResolutionError(text())),
+                "did.resolution",
+            ),
+            (did(DecentralizedIdentifierError::DocumentNotFound), "did.resolution"),
+            (
+                did(DecentralizedIdentifierError::CertificatePinMismatch { host: text() }),
+                "did.resolution",
+            ),
+            (
+                did(DecentralizedIdentifierError::PinnedHostRedirected {
+                    host: text(),
+                    location: text(),
+                }),
+                "did.resolution",
+            ),
+            (
+                did(DecentralizedIdentifierError::InsecureResolution(text())),
+                "did.resolution",
+            ),
+            (did(DecentralizedIdentifierError::ResponseTooLarge(0)), "did.resolution"),
+            (did(DecentralizedIdentifierError::RateLimited), "did.rate_limited"),
+            (
+                did(DecentralizedIdentifierError::MissingAssertionMethods),
+                "did.key_not_found",
+            ),
+            (
+                did(DecentralizedIdentifierError::MissingAssertionMethod {
+                    expected: text(),
+                    document: text(),
+                }),
+                "did.key_not_found",
+            ),
+            (
+                did(DecentralizedIdentifierError::MissingVerificationMethods),
+                "did.key_not_found",
+            ),
+            (
+                did(DecentralizedIdentifierError::MissingVerificationMethod {
+                    expected: text(),
+                    document: text(),
+                }),
+                "did.key_not_found",
+            ),
+            (
+                did(DecentralizedIdentifierError::IssuerNotInSnapshot(text())),
+                "did.key_not_found",
+            ),
+            (
+                did(DecentralizedIdentifierError::KeyNotInJwks(text())),
+                "did.key_not_found",
+            ),
+            (did(DecentralizedIdentifierError::EmptyDocument), "did.invalid_document"),
+            (
+                did(DecentralizedIdentifierError::NotJsonWebKey2020),
+                "did.invalid_document",
+            ),
+            (did(DecentralizedIdentifierError::MissingJWK), "did.invalid_document"),
+            (
+                did(DecentralizedIdentifierError::JWKNotEllipticCurve),
+                "did.invalid_document",
+            ),
+            (did(DecentralizedIdentifierError::JWKMissingX), "did.invalid_document"),
+            (did(DecentralizedIdentifierError::JWKMissingY), "did.invalid_document"),
+            (did(DecentralizedIdentifierError::JWKWrongCurve), "did.invalid_document"),
+            (did(DecentralizedIdentifierError::InvalidJWK), "did.invalid_document"),
+            (
+                NzcpError::UnsupportedCredentialVersion(CredentialVersion {
+                    major: 2,
+                    minor: 0,
+                    patch: 0,
+                }),
+                "claims.version",
+            ),
+            (cwt(CwtValidationError::InvalidVersion(text())), "claims.version"),
+            (
+                cwt(CwtValidationError::InvalidContext {
+                    found: vec![],
+                    missing: vec![],
+                    unexpected: vec![],
+                }),
+                "claims.context",
+            ),
+            (
+                cwt(CwtValidationError::InvalidType {
+                    expected: vec![],
+                    found: vec![],
+                }),
+                "claims.type",
+            ),
+            (cwt(CwtValidationError::InvalidTokenId { length: 0 }), "claims.token_id"),
+            (
+                cwt(CwtValidationError::StringClaimKey {
+                    key: text(),
+                    expected: 4,
+                }),
+                "claims.key",
+            ),
+            (
+                cwt(CwtValidationError::ExpiresBeforeActive {
+                    not_before: now,
+                    expiry: now,
+                }),
+                "claims.validity_period",
+            ),
+            (
+                NzcpError::PassNotYetActive {
+                    not_before: now,
+                    checked_at: now,
+                },
+                "pass.not_active",
+            ),
+            (
+                NzcpError::PassTooFarInFuture {
+                    not_before: now,
+                    checked_at: now,
+                },
+                "pass.too_far_in_future",
+            ),
+            (
+                NzcpError::PassExpired {
+                    expiry: now,
+                    checked_at: now,
+                },
+                "pass.expired",
+            ),
+            (NzcpError::PassRevoked(uuid::Uuid::nil()), "pass.revoked"),
+            (NzcpError::Timeout(Duration::from_secs(1)), "did.timeout"),
+            (
+                NzcpError::MultipleErrors(vec![
+                    NzcpError::PassRevoked(uuid::Uuid::nil()),
+                    NzcpError::Timeout(Duration::from_secs(1)),
+                ]),
+                "multiple",
+            ),
+        ]
+    }
+
+    #[test]
+    fn every_error_has_a_code() {
+        let mut codes = HashSet::new();
+        for (error, expected) in every_error() {
+            let code = error.code();
+            assert_eq!(code.as_str(), expected, "{:?}", error);
+            assert_eq!(code.to_string(), expected);
+            codes.insert(code);
+        }
+
+        // every code is used by some error, and no two codes share a string
+        assert_eq!(codes.len(), 27);
+        let strings: HashSet<_> = codes.iter().map(ErrorCode::as_str).collect();
+        assert_eq!(strings.len(), codes.len());
+    }
+}
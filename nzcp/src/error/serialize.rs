@@ -0,0 +1,508 @@
+//! Errors serialize as objects for structured logging, e.g. as JSON:
+//!
+//! ```notrust
+//! {
+//!   "kind": "invalid_signature",
+//!   "code": "did.key_not_found",
+//!   "message": "Invalid signature: ...",
+//!   "source": {
+//!     "kind": "did_resolution",
+//!     "message": "DID resolution failed: ...",
+//!     "source": {
+//!       "kind": "missing_assertion_method",
+//!       "expected": "did:web:nzcp.covid19.health.nz#key-2",
+//!       "document": "did:web:nzcp.covid19.health.nz",
+//!       "message": "expected ..."
+//!     }
+//!   }
+//! }
+//! ```
+//!
+//! Every object has a `kind` (the variant in snake_case) and the error's `message`, with the variant's fields in
+//! camelCase between them, and the error it wraps, if any, as its `source`. Dates are RFC 3339 strings. The top level
+//! `NzcpError` also has its stable `code`, see `ErrorCode`. Kinds and field names are stable, though new kinds and
+//! fields may be added; messages aren't.
+
+use std::fmt::Display;
+
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{ser::SerializeMap, Serialize, Serializer};
+
+use super::{
+    CanonicalCborError, CoseVerificationError, CwtValidationError, DecentralizedIdentifierError, ErrorCode, NzcpError,
+    QrBarcodeError,
+};
+
+/// Serialize an error as a map of its `kind`, the entries written by `fields`, its `message`, and then its `source`.
+fn serialize_error<S, F, T>(
+    serializer: S,
+    kind: &str,
+    error: &dyn Display,
+    fields: F,
+    source: Option<&T>,
+) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    F: FnOnce(&mut S::SerializeMap) -> Result<(), S::Error>,
+    T: Serialize,
+{
+    let mut map = serializer.serialize_map(None)?;
+    map.serialize_entry("kind", kind)?;
+    fields(&mut map)?;
+    map.serialize_entry("message", &error.to_string())?;
+    if let Some(source) = source {
+        map.serialize_entry("source", source)?;
+    }
+    map.end()
+}
+
+/// Serialize an error that doesn't wrap another.
+fn serialize_leaf<S, F>(serializer: S, kind: &str, error: &dyn Display, fields: F) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+    F: FnOnce(&mut S::SerializeMap) -> Result<(), S::Error>,
+{
+    serialize_error(serializer, kind, error, fields, None::<&()>)
+}
+
+fn no_fields<M: SerializeMap>(_: &mut M) -> Result<(), M::Error> {
+    Ok(())
+}
+
+fn rfc_3339(date: &DateTime<Utc>) -> String {
+    date.to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+impl Serialize for ErrorCode {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl NzcpError {
+    /// The variant in snake_case, as serialized in `kind`, e.g. to trace a failure without its message.
+    pub(crate) fn kind(&self) -> &'static str {
+        use NzcpError::*;
+        match self {
+            QrBarcode(_) => "qr_barcode",
+            InvalidPayload(_) => "invalid_payload",
+            NonCanonicalCbor(_) => "non_canonical_cbor",
+            InvalidSignature(_) => "invalid_signature",
+            InvalidCWT(_) => "invalid_cwt",
+            UnsupportedCredentialVersion(_) => "unsupported_credential_version",
+            PassNotYetActive { .. } => "pass_not_yet_active",
+            PassTooFarInFuture { .. } => "pass_too_far_in_future",
+            PassExpired { .. } => "pass_expired",
+            PassRevoked(_) => "pass_revoked",
+            Timeout(_) => "timeout",
+            MultipleErrors(_) => "multiple_errors",
+        }
+    }
+}
+
+impl Serialize for NzcpError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use NzcpError::*;
+
+        let kind = self.kind();
+        let code = self.code();
+        let with_code = |map: &mut S::SerializeMap| map.serialize_entry("code", &code);
+        match self {
+            QrBarcode(error) => serialize_error(serializer, kind, self, with_code, Some(error)),
+            InvalidPayload(error) => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("offset", &error.offset())
+            }),
+            NonCanonicalCbor(error) => serialize_error(serializer, kind, self, with_code, Some(error)),
+            InvalidSignature(error) => serialize_error(serializer, kind, self, with_code, Some(error)),
+            InvalidCWT(error) => serialize_error(serializer, kind, self, with_code, Some(error)),
+            UnsupportedCredentialVersion(version) => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("version", &version.to_string())
+            }),
+            PassNotYetActive { not_before, checked_at } => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("notBefore", &rfc_3339(not_before))?;
+                map.serialize_entry("checkedAt", &rfc_3339(checked_at))
+            }),
+            PassTooFarInFuture { not_before, checked_at } => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("notBefore", &rfc_3339(not_before))?;
+                map.serialize_entry("checkedAt", &rfc_3339(checked_at))
+            }),
+            PassExpired { expiry, checked_at } => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("expiry", &rfc_3339(expiry))?;
+                map.serialize_entry("checkedAt", &rfc_3339(checked_at))
+            }),
+            PassRevoked(token_id) => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("tokenId", &token_id.to_urn().to_string())
+            }),
+            Timeout(timeout) => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("timeoutMs", &(timeout.as_millis() as u64))
+            }),
+            MultipleErrors(errors) => serialize_leaf(serializer, kind, self, |map| {
+                with_code(map)?;
+                map.serialize_entry("errors", errors)
+            }),
+        }
+    }
+}
+
+impl Serialize for QrBarcodeError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use QrBarcodeError::*;
+
+        match self {
+            InvalidBase32 => serialize_leaf(serializer, "invalid_base32", self, no_fields),
+            UnsupportedVersion { found } => serialize_leaf(serializer, "unsupported_version", self, |map| {
+                map.serialize_entry("version", found)
+            }),
+            MalformedPrefix => serialize_leaf(serializer, "malformed_prefix", self, no_fields),
+            MissingNzcpPrefix => serialize_leaf(serializer, "missing_nzcp_prefix", self, no_fields),
+            NonAscii { offset } => serialize_leaf(serializer, "non_ascii", self, |map| {
+                map.serialize_entry("offset", offset)
+            }),
+        }
+    }
+}
+
+impl Serialize for CanonicalCborError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use CanonicalCborError::*;
+
+        match self {
+            IndefiniteLength(path) => serialize_leaf(serializer, "indefinite_length", self, |map| {
+                map.serialize_entry("path", path)
+            }),
+            // the kind of item is `item`, as `kind` is the kind of error
+            NonShortestForm { kind, path } => serialize_leaf(serializer, "non_shortest_form", self, |map| {
+                map.serialize_entry("item", kind)?;
+                map.serialize_entry("path", path)
+            }),
+            DuplicateKey(path) => serialize_leaf(serializer, "duplicate_key", self, |map| {
+                map.serialize_entry("path", path)
+            }),
+            Malformed(path) => serialize_leaf(serializer, "malformed", self, |map| map.serialize_entry("path", path)),
+            TooDeeplyNested(path) => serialize_leaf(serializer, "too_deeply_nested", self, |map| {
+                map.serialize_entry("path", path)
+            }),
+        }
+    }
+}
+
+impl Serialize for CoseVerificationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use CoseVerificationError::*;
+
+        match self {
+            SignatureVerificationFailed => serialize_leaf(serializer, "verification_failed", self, no_fields),
+            InvalidSignatureLength { found } => serialize_leaf(serializer, "invalid_signature_length", self, |map| {
+                map.serialize_entry("length", found)
+            }),
+            CwtValidation(error) => serialize_error(serializer, "cwt_validation", self, no_fields, Some(error)),
+            UntrustedIssuer(issuer) => serialize_leaf(serializer, "untrusted_issuer", self, |map| {
+                map.serialize_entry("issuer", issuer)
+            }),
+            DecentralizedIdentifierResolution(error) => {
+                serialize_error(serializer, "did_resolution", self, no_fields, Some(error))
+            }
+            UnpinnedKey(kid) => serialize_leaf(serializer, "unpinned_key", self, |map| map.serialize_entry("kid", kid)),
+            UnsupportedAlgorithm { found } => serialize_leaf(serializer, "unsupported_algorithm", self, |map| {
+                map.serialize_entry("algorithm", found)
+            }),
+            MissingAlgorithm => serialize_leaf(serializer, "missing_algorithm", self, no_fields),
+            MissingKid => serialize_leaf(serializer, "missing_kid", self, no_fields),
+            NonUtf8Kid(kid) => serialize_leaf(serializer, "non_utf8_kid", self, |map| {
+                let kid = kid.iter().map(|byte| format!("{:02x}", byte)).collect::<String>();
+                map.serialize_entry("kid", &kid)
+            }),
+            ConflictingKid { protected, unprotected } => serialize_leaf(serializer, "conflicting_kid", self, |map| {
+                map.serialize_entry("protected", protected)?;
+                map.serialize_entry("unprotected", unprotected)
+            }),
+        }
+    }
+}
+
+impl Serialize for CwtValidationError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use CwtValidationError::*;
+
+        match self {
+            InvalidVersion(version) => serialize_leaf(serializer, "invalid_version", self, |map| {
+                map.serialize_entry("version", version)
+            }),
+            InvalidContext {
+                found,
+                missing,
+                unexpected,
+            } => serialize_leaf(serializer, "invalid_context", self, |map| {
+                map.serialize_entry("found", found)?;
+                map.serialize_entry("missing", missing)?;
+                map.serialize_entry("unexpected", unexpected)
+            }),
+            InvalidType { expected, found } => serialize_leaf(serializer, "invalid_type", self, |map| {
+                map.serialize_entry("expected", expected)?;
+                map.serialize_entry("found", found)
+            }),
+            InvalidTokenId { length } => serialize_leaf(serializer, "invalid_token_id", self, |map| {
+                map.serialize_entry("length", length)
+            }),
+            StringClaimKey { key, expected } => serialize_leaf(serializer, "string_claim_key", self, |map| {
+                map.serialize_entry("key", key)?;
+                // as a plain integer, as some serializers don't support 128 bit integers
+                map.serialize_entry("expected", &(*expected as i64))
+            }),
+            ExpiresBeforeActive { not_before, expiry } => {
+                serialize_leaf(serializer, "expires_before_active", self, |map| {
+                    map.serialize_entry("notBefore", &rfc_3339(not_before))?;
+                    map.serialize_entry("expiry", &rfc_3339(expiry))
+                })
+            }
+        }
+    }
+}
+
+impl Serialize for DecentralizedIdentifierError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        use DecentralizedIdentifierError::*;
+
+        match self {
+            ResolutionError(reason) => serialize_leaf(serializer, "resolution_error", self, |map| {
+                map.serialize_entry("reason", reason)
+            }),
+            DocumentNotFound => serialize_leaf(serializer, "document_not_found", self, no_fields),
+            EmptyDocument => serialize_leaf(serializer, "empty_document", self, no_fields),
+            MissingAssertionMethods => serialize_leaf(serializer, "missing_assertion_methods", self, no_fields),
+            MissingAssertionMethod { expected, document } => {
+                serialize_leaf(serializer, "missing_assertion_method", self, |map| {
+                    map.serialize_entry("expected", expected)?;
+                    map.serialize_entry("document", document)
+                })
+            }
+            MissingVerificationMethods => serialize_leaf(serializer, "missing_verification_methods", self, no_fields),
+            MissingVerificationMethod { expected, document } => {
+                serialize_leaf(serializer, "missing_verification_method", self, |map| {
+                    map.serialize_entry("expected", expected)?;
+                    map.serialize_entry("document", document)
+                })
+            }
+            NotJsonWebKey2020 => serialize_leaf(serializer, "not_json_web_key_2020", self, no_fields),
+            MissingJWK => serialize_leaf(serializer, "missing_jwk", self, no_fields),
+            JWKNotEllipticCurve => serialize_leaf(serializer, "jwk_not_elliptic_curve", self, no_fields),
+            JWKMissingX => serialize_leaf(serializer, "jwk_missing_x", self, no_fields),
+            JWKMissingY => serialize_leaf(serializer, "jwk_missing_y", self, no_fields),
+            JWKWrongCurve => serialize_leaf(serializer, "jwk_wrong_curve", self, no_fields),
+            InvalidJWK => serialize_leaf(serializer, "invalid_jwk", self, no_fields),
+            IssuerNotInSnapshot(issuer) => serialize_leaf(serializer, "issuer_not_in_snapshot", self, |map| {
+                map.serialize_entry("issuer", issuer)
+            }),
+            KeyNotInJwks(kid) => serialize_leaf(serializer, "key_not_in_jwks", self, |map| {
+                map.serialize_entry("kid", kid)
+            }),
+            CertificatePinMismatch { host } => serialize_leaf(serializer, "certificate_pin_mismatch", self, |map| {
+                map.serialize_entry("host", host)
+            }),
+            PinnedHostRedirected { host, location } => {
+                serialize_leaf(serializer, "pinned_host_redirected", self, |map| {
+                    map.serialize_entry("host", host)?;
+                    map.serialize_entry("location", location)
+                })
+            }
+            InsecureResolution(url) => serialize_leaf(serializer, "insecure_resolution", self, |map| {
+                map.serialize_entry("url", url)
+            }),
+            ResponseTooLarge(max_size) => serialize_leaf(serializer, "response_too_large", self, |map| {
+                map.serialize_entry("maxSize", max_size)
+            }),
+            RateLimited => serialize_leaf(serializer, "rate_limited", self, no_fields),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{sync::Arc, time::Duration};
+
+    use chrono::TimeZone;
+    use serde_json::{json, Value};
+
+    use super::*;
+    use crate::CredentialVersion;
+
+    fn to_json<T: Serialize>(error: &T) -> Value {
+        serde_json::to_value(error).unwrap()
+    }
+
+    #[test]
+    fn nested_errors() {
+        let error = NzcpError::InvalidSignature(CoseVerificationError::DecentralizedIdentifierResolution(
+            DecentralizedIdentifierError::MissingAssertionMethod {
+                expected: String::from("did:web:example.com#key-2"),
+                document: String::from("did:web:example.com"),
+            },
+        ));
+        assert_eq!(
+            to_json(&error),
+            json!({
+                "kind": "invalid_signature",
+                "code": "did.key_not_found",
+                "message": error.to_string(),
+                "source": {
+                    "kind": "did_resolution",
+                    "message": "DID resolution failed: MissingAssertionMethod { \
+                        expected: \"did:web:example.com#key-2\", document: \"did:web:example.com\" }",
+                    "source": {
+                        "kind": "missing_assertion_method",
+                        "expected": "did:web:example.com#key-2",
+                        "document": "did:web:example.com",
+                        "message": "expected 'did:web:example.com#key-2' but document 'did:web:example.com' had no \
+                            such assertion method",
+                    },
+                },
+            })
+        );
+
+        let error = NzcpError::InvalidCWT(CwtValidationError::InvalidType {
+            expected: vec![String::from("VerifiableCredential"), String::from("PublicCovidPass")],
+            found: vec![String::from("VerifiableCredential")],
+        });
+        assert_eq!(
+            to_json(&error)["source"],
+            json!({
+                "kind": "invalid_type",
+                "expected": ["VerifiableCredential", "PublicCovidPass"],
+                "found": ["VerifiableCredential"],
+                "message": "credential has type [\"VerifiableCredential\"], \
+                    expected [\"VerifiableCredential\", \"PublicCovidPass\"]",
+            })
+        );
+    }
+
+    #[test]
+    fn timestamps() {
+        let error = NzcpError::PassExpired {
+            expiry: Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            checked_at: Utc.ymd(2032, 1, 1).and_hms(0, 0, 0),
+        };
+        assert_eq!(
+            to_json(&error),
+            json!({
+                "kind": "pass_expired",
+                "code": "pass.expired",
+                "expiry": "2031-11-02T20:05:30Z",
+                "checkedAt": "2032-01-01T00:00:00Z",
+                "message": error.to_string(),
+            })
+        );
+
+        let error = CwtValidationError::ExpiresBeforeActive {
+            not_before: Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            expiry: Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+        };
+        assert_eq!(to_json(&error)["notBefore"], json!("2031-11-02T20:05:30Z"));
+        assert_eq!(to_json(&error)["expiry"], json!("2021-11-02T20:05:30Z"));
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(
+            to_json(&NzcpError::QrBarcode(QrBarcodeError::NonAscii { offset: 12 }))["source"],
+            json!({
+                "kind": "non_ascii",
+                "offset": 12,
+                "message": "The QR Code contained a non-ASCII byte at offset 12",
+            })
+        );
+        assert_eq!(
+            to_json(&CoseVerificationError::UntrustedIssuer(String::from(
+                "did:web:example.com"
+            ))),
+            json!({
+                "kind": "untrusted_issuer",
+                "issuer": "did:web:example.com",
+                "message": "provided issuer is not trusted: did:web:example.com",
+            })
+        );
+        assert_eq!(
+            to_json(&CoseVerificationError::NonUtf8Kid(vec![0xff, 0x01])),
+            json!({
+                "kind": "non_utf8_kid",
+                "kid": "ff01",
+                "message": "key ID (kid) header is not valid UTF-8: [ff, 01]",
+            })
+        );
+        assert_eq!(
+            to_json(&CanonicalCborError::NonShortestForm {
+                kind: "integer",
+                path: String::from("claims.4"),
+            }),
+            json!({
+                "kind": "non_shortest_form",
+                "item": "integer",
+                "path": "claims.4",
+                "message": "non-canonical integer at claims.4",
+            })
+        );
+        assert_eq!(
+            to_json(&DecentralizedIdentifierError::ResponseTooLarge(65536)),
+            json!({
+                "kind": "response_too_large",
+                "maxSize": 65536,
+                "message": "the DID document was larger than the 65536 byte limit",
+            })
+        );
+        assert_eq!(
+            to_json(&DecentralizedIdentifierError::RateLimited),
+            json!({
+                "kind": "rate_limited",
+                "message": "too many DID documents are being resolved, try again later",
+            })
+        );
+        assert_eq!(
+            to_json(&NzcpError::UnsupportedCredentialVersion(CredentialVersion {
+                major: 2,
+                minor: 1,
+                patch: 0,
+            })),
+            json!({
+                "kind": "unsupported_credential_version",
+                "code": "claims.version",
+                "version": "2.1.0",
+                "message": "Unsupported credential version 2.1.0 (only 1.x.x is supported)",
+            })
+        );
+
+        let error = NzcpError::InvalidPayload(Arc::new(serde_cbor::from_slice::<Value>(&[0x82, 0x01]).unwrap_err()));
+        assert_eq!(
+            to_json(&error),
+            json!({
+                "kind": "invalid_payload",
+                "code": "cbor.malformed",
+                "offset": 2,
+                "message": error.to_string(),
+            })
+        );
+        assert_eq!(
+            to_json(&NzcpError::Timeout(Duration::from_secs(5)))["timeoutMs"],
+            json!(5000)
+        );
+        assert_eq!(
+            to_json(&NzcpError::PassRevoked(uuid::Uuid::nil()))["tokenId"],
+            json!("urn:uuid:00000000-0000-0000-0000-000000000000")
+        );
+
+        let error = NzcpError::MultipleErrors(vec![
+            NzcpError::PassRevoked(uuid::Uuid::nil()),
+            NzcpError::Timeout(Duration::from_secs(5)),
+        ]);
+        let json = to_json(&error);
+        assert_eq!(json["kind"], json!("multiple_errors"));
+        assert_eq!(json["code"], json!("multiple"));
+        assert_eq!(json["errors"][0]["kind"], json!("pass_revoked"));
+        assert_eq!(json["errors"][1]["timeoutMs"], json!(5000));
+    }
+}
@@ -0,0 +1,211 @@
+//! Conversion from JSON to the pass types, the reverse of their `Serialize` impls, e.g. for passes stored as JSON
+//! after they were verified.
+//!
+//! Passes are converted with their `Deserialize` impls. The claims conversion checks each field, naming the first one
+//! that's missing or invalid. Unknown fields are ignored.
+
+use std::{convert::TryFrom, str::FromStr};
+
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::{
+    decentralised_identifier::DecentralizedIdentifier, pass::public_covid_pass::PublicCovidPass,
+    payload::cwt::CwtClaims,
+};
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum JsonConversionError {
+    #[error("expected a JSON object, found {found}")]
+    NotAnObject { found: &'static str },
+    #[error("missing required field '{field}'")]
+    MissingField { field: &'static str },
+    #[error("field '{field}' must be {expected}, found {found}")]
+    InvalidType {
+        field: &'static str,
+        expected: &'static str,
+        found: &'static str,
+    },
+    #[error("field '{field}' is invalid: {reason}")]
+    InvalidValue { field: &'static str, reason: String },
+    #[error("invalid pass: {reason}")]
+    InvalidPass { reason: String },
+}
+
+fn type_name(value: &Value) -> &'static str {
+    match value {
+        Value::Null => "null",
+        Value::Bool(_) => "a boolean",
+        Value::Number(_) => "a number",
+        Value::String(_) => "a string",
+        Value::Array(_) => "an array",
+        Value::Object(_) => "an object",
+    }
+}
+
+fn object(value: &Value) -> Result<&Map<String, Value>, JsonConversionError> {
+    value.as_object().ok_or(JsonConversionError::NotAnObject {
+        found: type_name(value),
+    })
+}
+
+/// The string value of a field, or `None` if it's missing or null.
+fn optional_string<'a>(
+    object: &'a Map<String, Value>,
+    field: &'static str,
+) -> Result<Option<&'a str>, JsonConversionError> {
+    match object.get(field) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::String(value)) => Ok(Some(value)),
+        Some(value) => Err(JsonConversionError::InvalidType {
+            field,
+            expected: "a string",
+            found: type_name(value),
+        }),
+    }
+}
+
+fn string<'a>(object: &'a Map<String, Value>, field: &'static str) -> Result<&'a str, JsonConversionError> {
+    optional_string(object, field)?.ok_or(JsonConversionError::MissingField { field })
+}
+
+/// Parse a string field, with the parse error as the reason it's invalid.
+fn parse<T, E, F>(object: &Map<String, Value>, field: &'static str, parse: F) -> Result<T, JsonConversionError>
+where
+    F: FnOnce(&str) -> Result<T, E>,
+    E: ToString,
+{
+    parse(string(object, field)?).map_err(|error| JsonConversionError::InvalidValue {
+        field,
+        reason: error.to_string(),
+    })
+}
+
+impl TryFrom<&Value> for PublicCovidPass {
+    type Error = JsonConversionError;
+
+    /// Convert the credential subject of a pass from JSON, as serialized (e.g.
+    /// `{"givenName": "Jack", "familyName": "Sparrow", "dob": "1960-04-16"}`).
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        PublicCovidPass::deserialize(value).map_err(invalid_pass)
+    }
+}
+
+impl TryFrom<Value> for PublicCovidPass {
+    type Error = JsonConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        serde_json::from_value(value).map_err(invalid_pass)
+    }
+}
+
+fn invalid_pass(error: serde_json::Error) -> JsonConversionError {
+    JsonConversionError::InvalidPass {
+        reason: error.to_string(),
+    }
+}
+
+impl TryFrom<&Value> for CwtClaims {
+    type Error = JsonConversionError;
+
+    /// Convert the claims of a pass from JSON, as serialized, with the dates as RFC 3339 strings and the token ID as a
+    /// UUID.
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let object = object(value)?;
+        Ok(CwtClaims {
+            issuer: parse(object, "issuer", DecentralizedIdentifier::from_str)?,
+            not_before: parse(object, "notBefore", parse_rfc_3339)?,
+            expiry: parse(object, "expiry", parse_rfc_3339)?,
+            cwt_token_id: parse(object, "cwtTokenId", Uuid::parse_str)?,
+        })
+    }
+}
+
+impl TryFrom<Value> for CwtClaims {
+    type Error = JsonConversionError;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        CwtClaims::try_from(&value)
+    }
+}
+
+fn parse_rfc_3339(value: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
+    DateTime::parse_from_rfc3339(value).map(|date| date.with_timezone(&Utc))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn public_covid_pass() {
+        let pass = PublicCovidPass {
+            given_name: String::from("Jack"),
+            family_name: Some(String::from("Sparrow")),
+            date_of_birth: NaiveDate::from_ymd(1960, 4, 16),
+        };
+        let json = serde_json::to_value(&pass).unwrap();
+        assert_eq!(PublicCovidPass::try_from(json), Ok(pass));
+
+        let pass = PublicCovidPass::try_from(json!({"givenName": "Jack", "familyName": null, "dob": "1960-04-16"}));
+        assert_eq!(pass.unwrap().family_name, None);
+    }
+
+    #[test]
+    fn cwt_claims() {
+        let claims = CwtClaims {
+            issuer: "did:web:nzcp.covid19.health.nz".parse().unwrap(),
+            not_before: Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+            expiry: Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            cwt_token_id: Uuid::parse_str("60a4f54d-4e30-4332-be33-ad78b1eafa4b").unwrap(),
+        };
+        let json = serde_json::to_value(&claims).unwrap();
+        assert_eq!(CwtClaims::try_from(json), Ok(claims));
+    }
+
+    #[test]
+    fn invalid_fields() {
+        let error = |json: Value| PublicCovidPass::try_from(&json).unwrap_err().to_string();
+        assert_eq!(
+            error(json!("Jack")),
+            "invalid pass: invalid type: string \"Jack\", expected struct PublicCovidPass"
+        );
+        assert_eq!(
+            error(json!({"dob": "1960-04-16"})),
+            "invalid pass: missing field `givenName`"
+        );
+        assert_eq!(
+            error(json!({"givenName": "Jack", "familyName": 1, "dob": "1960-04-16"})),
+            "invalid pass: invalid type: integer `1`, expected a string"
+        );
+        assert_eq!(
+            error(json!({"givenName": "Jack", "dob": "1960-4-16"})),
+            "invalid pass: The given date of birth was not a full ISO 8601 date (YYYY-MM-DD)."
+        );
+        assert_eq!(
+            PublicCovidPass::try_from(json!({"dob": "1960-04-16"})),
+            PublicCovidPass::try_from(&json!({"dob": "1960-04-16"}))
+        );
+
+        let claims = json!({
+            "issuer": "did:web:nzcp.covid19.health.nz",
+            "notBefore": "2021-11-02T20:05:30Z",
+            "expiry": "2031-11-02",
+            "cwtTokenId": "60a4f54d-4e30-4332-be33-ad78b1eafa4b",
+        });
+        assert!(matches!(
+            CwtClaims::try_from(claims),
+            Err(JsonConversionError::InvalidValue { field: "expiry", .. })
+        ));
+        assert!(matches!(
+            CwtClaims::try_from(json!({"issuer": "https://nzcp.covid19.health.nz"})),
+            Err(JsonConversionError::InvalidValue { field: "issuer", .. })
+        ));
+    }
+}
@@ -0,0 +1,262 @@
+use std::convert::TryFrom;
+
+use p256::ecdsa::VerifyingKey;
+use serde::Deserialize;
+use ssi::jwk::Base64urlUInt;
+use thiserror::Error;
+
+use crate::decentralised_identifier::{jwk_verifying_key, DecentralizedIdentifier, DecentralizedIdentifierError};
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum JwksError {
+    #[error("JWKS could not be parsed: {0}")]
+    Malformed(String),
+}
+
+/// Why a key in a JWKS was skipped.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum JwksWarning {
+    #[error("key {index}: unsupported key type '{kty}' (must be 'EC')")]
+    UnsupportedKeyType { index: usize, kty: String },
+    #[error("key {index}: unsupported curve '{crv}' (must be 'P-256')")]
+    UnsupportedCurve { index: usize, crv: String },
+    #[error("key {index}: missing 'kid', or an issuer DID to go with it")]
+    MissingKeyId { index: usize },
+    #[error("key {index}: issuer '{issuer}' is not a valid DID")]
+    InvalidIssuer { index: usize, issuer: String },
+    #[error("key {index}: invalid key: {error}")]
+    InvalidKey {
+        index: usize,
+        error: DecentralizedIdentifierError,
+    },
+}
+
+/// A key loaded from a JWKS.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JwksKey {
+    pub issuer: DecentralizedIdentifier,
+    pub kid: String,
+    pub verifying_key: VerifyingKey,
+}
+
+/// A fixed set of issuer keys loaded from a JWKS file, for verifying passes without resolving DID documents.
+///
+/// The file is either a JWKS (`{"keys": [...]}`) or a bare array of JWKs. Each JWK must be an EC P-256 key, with
+/// either an `issuer` DID and a `kid`, or a `kid` of the form `did:web:example.com#key-1`:
+///
+/// ```notrust
+/// {
+///     "keys": [
+///         {
+///             "kty": "EC",
+///             "crv": "P-256",
+///             "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+///             "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0",
+///             "issuer": "did:web:nzcp.covid19.health.nz",
+///             "kid": "key-1"
+///         }
+///     ]
+/// }
+/// ```
+///
+/// Keys which cannot be used are skipped, see `Jwks::warnings`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Jwks {
+    keys: Vec<JwksKey>,
+    warnings: Vec<JwksWarning>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum JwksFile {
+    Set { keys: Vec<JwkEntry> },
+    Keys(Vec<JwkEntry>),
+}
+
+#[derive(Deserialize)]
+struct JwkEntry {
+    kty: Option<String>,
+    crv: Option<String>,
+    x: Option<String>,
+    y: Option<String>,
+    kid: Option<String>,
+    issuer: Option<String>,
+}
+
+impl Jwks {
+    pub fn from_json_str(json: &str) -> Result<Self, JwksError> {
+        let entries = match serde_json::from_str(json).map_err(|err| JwksError::Malformed(err.to_string()))? {
+            JwksFile::Set { keys } => keys,
+            JwksFile::Keys(keys) => keys,
+        };
+
+        let mut jwks = Jwks::default();
+        for (index, entry) in entries.into_iter().enumerate() {
+            match JwksKey::from_entry(index, entry) {
+                Ok(key) => jwks.keys.push(key),
+                Err(warning) => jwks.warnings.push(warning),
+            }
+        }
+
+        Ok(jwks)
+    }
+
+    pub fn keys(&self) -> &[JwksKey] {
+        &self.keys
+    }
+
+    /// The keys that were skipped while loading, and why.
+    pub fn warnings(&self) -> &[JwksWarning] {
+        &self.warnings
+    }
+
+    pub(crate) fn verifying_key(
+        &self,
+        issuer: &DecentralizedIdentifier,
+        kid: &str,
+    ) -> Result<VerifyingKey, DecentralizedIdentifierError> {
+        self.keys
+            .iter()
+            .find(|key| &key.issuer == issuer && key.kid == kid)
+            .map(|key| key.verifying_key)
+            .ok_or_else(|| DecentralizedIdentifierError::KeyNotInJwks(format!("{}#{}", issuer, kid)))
+    }
+}
+
+impl JwksKey {
+    fn from_entry(index: usize, entry: JwkEntry) -> Result<Self, JwksWarning> {
+        use JwksWarning::*;
+
+        let kty = entry.kty.unwrap_or_default();
+        if kty != "EC" {
+            return Err(UnsupportedKeyType { index, kty });
+        }
+        let crv = entry.crv.unwrap_or_default();
+        if crv != "P-256" {
+            return Err(UnsupportedCurve { index, crv });
+        }
+
+        let kid = entry.kid.ok_or(MissingKeyId { index })?;
+        let (issuer, kid) = match kid.rsplit_once('#') {
+            Some((issuer, kid)) => (issuer.to_owned(), kid.to_owned()),
+            None => (entry.issuer.ok_or(MissingKeyId { index })?, kid),
+        };
+        let issuer = DecentralizedIdentifier::parse(&issuer).ok_or(InvalidIssuer { index, issuer })?;
+
+        let invalid_key = |error| InvalidKey { index, error };
+        let coordinate = |coordinate: Option<String>, missing| {
+            let coordinate = coordinate.ok_or_else(|| invalid_key(missing))?;
+            Base64urlUInt::try_from(coordinate).map_err(|_| invalid_key(DecentralizedIdentifierError::InvalidJWK))
+        };
+        let x = coordinate(entry.x, DecentralizedIdentifierError::JWKMissingX)?;
+        let y = coordinate(entry.y, DecentralizedIdentifierError::JWKMissingY)?;
+        let verifying_key = jwk_verifying_key(&x.0, &y.0).map_err(invalid_key)?;
+
+        Ok(JwksKey {
+            issuer,
+            kid,
+            verifying_key,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{CoseVerificationError, NzcpError},
+        testing::{example_issuer, VALID_PASS},
+        verifier::Verifier,
+        PublicCovidPass,
+    };
+
+    const EXAMPLE_JWKS: &str = r##"{
+        "keys": [
+            {
+                "kty": "RSA",
+                "n": "0vx7agoebGcQSuuPiLJXZptN9nndrQmbXEps2aiAFbWhM78LhWx4cbbfAAtVT86zwu1RK7aPFFxuhDR1L6tSoc_BJECPebWKRXjBZCiFV4n3oknjhMstn64tZ_2W-5JsGY4Hc5n9yBXArwl93lqt7_RN5w6Cf0h4QyQ5v-65YGjQR0_FDW2QvzqY368QQMicAtaSqzs8KJZgnYb9c7d0zgdAZHzu6qMQvRL5hajrn1n91CbOpbISD08qNLyrdkt-bFTWhAI4vMQFh6WeZu0fM4lFd2NcRwr3XPksINHaQ-G_xBniIqbw0Ls1jF44-csFCur-kEgU8awapJzKnqDKgw",
+                "e": "AQAB",
+                "kid": "did:web:nzcp.covid19.health.nz#rsa-1"
+            },
+            {
+                "kty": "EC",
+                "crv": "P-384",
+                "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+                "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0",
+                "kid": "did:web:nzcp.covid19.health.nz#key-384"
+            },
+            {
+                "kty": "EC",
+                "crv": "P-256",
+                "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+                "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0",
+                "issuer": "did:web:nzcp.covid19.health.nz",
+                "kid": "key-1"
+            }
+        ]
+    }"##;
+
+    #[tokio::test]
+    async fn verify_from_jwks() {
+        let jwks = Jwks::from_json_str(EXAMPLE_JWKS).unwrap();
+        assert_eq!(jwks.keys().len(), 1);
+        assert_eq!(
+            jwks.warnings(),
+            [
+                JwksWarning::UnsupportedKeyType {
+                    index: 0,
+                    kty: String::from("RSA")
+                },
+                JwksWarning::UnsupportedCurve {
+                    index: 1,
+                    crv: String::from("P-384")
+                },
+            ]
+        );
+
+        let verifier = Verifier::new(vec![example_issuer()]).with_jwks(jwks);
+        let pass: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        assert_eq!(pass.given_name, "Jack");
+    }
+
+    #[tokio::test]
+    async fn kid_not_in_jwks() {
+        let jwks = Jwks::from_json_str(&EXAMPLE_JWKS.replace("\"key-1\"", "\"key-2\"")).unwrap();
+        let verifier = Verifier::new(vec![example_issuer()]).with_jwks(jwks);
+
+        assert_eq!(
+            verifier.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await,
+            Err(NzcpError::InvalidSignature(
+                CoseVerificationError::DecentralizedIdentifierResolution(DecentralizedIdentifierError::KeyNotInJwks(
+                    String::from("did:web:nzcp.covid19.health.nz#key-1")
+                ))
+            ))
+        );
+    }
+
+    #[test]
+    fn jwks_key_ids() {
+        let jwks = Jwks::from_json_str(
+            r#"[
+                {"kty": "EC", "crv": "P-256", "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760", "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0", "kid": "did:web:nzcp.covid19.health.nz#key-1"},
+                {"kty": "EC", "crv": "P-256", "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760", "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0", "kid": "key-2"},
+                {"kty": "EC", "crv": "P-256", "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760", "kid": "did:web:nzcp.covid19.health.nz#key-3"}
+            ]"#,
+        )
+        .unwrap();
+
+        assert_eq!(jwks.keys()[0].issuer, example_issuer());
+        assert_eq!(jwks.keys()[0].kid, "key-1");
+        assert_eq!(
+            jwks.warnings(),
+            [
+                JwksWarning::MissingKeyId { index: 1 },
+                JwksWarning::InvalidKey {
+                    index: 2,
+                    error: DecentralizedIdentifierError::JWKMissingY
+                },
+            ]
+        );
+        assert!(matches!(Jwks::from_json_str("{}"), Err(JwksError::Malformed(_))));
+    }
+}
@@ -0,0 +1,59 @@
+//! Helpers for working with issuer keys, e.g. for tools that publish keys alongside a verifier.
+
+use p256::ecdsa::VerifyingKey;
+use ssi::jwk::Base64urlUInt;
+
+use crate::verifier::KeyPin;
+
+/// NZCP helpers for P-256 verifying keys.
+pub trait VerifyingKeyExt {
+    /// The key as a JWK, as it would be published in the `publicKeyJwk` of an issuer's DID document.
+    fn to_jwk(&self) -> serde_json::Value;
+
+    /// A key ID for the key, the base64url encoded SHA-256 JWK thumbprint (RFC 7638), so the same key always gets
+    /// the same ID (e.g. `did:web:example.com#<hint>`).
+    fn did_fragment_hint(&self) -> String;
+}
+
+impl VerifyingKeyExt for VerifyingKey {
+    fn to_jwk(&self) -> serde_json::Value {
+        let point = self.to_encoded_point(false);
+        let coordinate = |bytes: &[u8]| String::from(&Base64urlUInt(bytes.to_vec()));
+        // a verifying key is never the identity point, so always has coordinates
+        serde_json::json!({
+            "kty": "EC",
+            "crv": "P-256",
+            "x": coordinate(point.x().expect("identity point")),
+            "y": coordinate(point.y().expect("identity point")),
+        })
+    }
+
+    fn did_fragment_hint(&self) -> String {
+        KeyPin::from_verifying_key(self).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::{did_document_from_json, example_verifying_key, EXAMPLE_DID_DOCUMENT};
+
+    #[test]
+    fn to_jwk() {
+        let document = serde_json::to_value(did_document_from_json(EXAMPLE_DID_DOCUMENT).unwrap()).unwrap();
+        assert_eq!(
+            example_verifying_key().to_jwk(),
+            document["verificationMethod"][0]["publicKeyJwk"]
+        );
+    }
+
+    #[test]
+    fn did_fragment_hint() {
+        let hint = example_verifying_key().did_fragment_hint();
+        assert_eq!(hint.len(), 43);
+        assert_eq!(
+            KeyPin::from_base64url(&hint),
+            Some(KeyPin::from_verifying_key(&example_verifying_key()))
+        );
+    }
+}
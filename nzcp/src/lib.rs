@@ -12,13 +12,68 @@
 //! let barcode = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIDJOA6Y524TD3AZRM263WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX";
 //! let pass: PublicCovidPass = verify_pass_uri(barcode).await?;
 //! ```
+//!
+//! ## Errors
+//!
+//! Verification fails with an `error::NzcpError`, which wraps the more specific errors (e.g. a
+//! `DecentralizedIdentifierError` when the issuer's DID document couldn't be resolved). All error types are
+//! re-exported from the `error` module, see there for an example of matching on them.
 
+#[cfg(feature = "blocking")]
+pub use self::blocking::{verify_pass_uri_blocking, verify_pass_with_config_blocking};
+#[cfg(any(test, feature = "signing"))]
+pub use self::sign::sign_pass;
+#[cfg(feature = "metrics")]
+pub use self::verifier::VerifierMetrics;
 pub use self::{
-    decentralised_identifier::DecentralizedIdentifier,
-    pass::{public_covid_pass::PublicCovidPass, verify_pass_uri, verify_pass_uri_with_trusted_issuers},
+    decentralised_identifier::{
+        public_keys_from_document, CertificatePin, DecentralizedIdentifier, DocumentKeys, DocumentResolver,
+        ResolutionRateLimiter, WebResolver,
+    },
+    jwks::{Jwks, JwksKey},
+    pass::{
+        ministry_of_health_issuer,
+        public_covid_pass::{MatchResult, PublicCovidPass},
+        verify_many, verify_pass_all_errors, verify_pass_and_log, verify_pass_bytes, verify_pass_cbor, verify_pass_uri,
+        verify_pass_uri_with_trusted_issuers, verify_pass_with_config, verify_pass_with_timeout,
+    },
+    payload::{
+        barcode::canonicalize_pass_uri,
+        batch::{BatchDecoder, ParsedNzcpPass},
+        cose::KidHeader,
+        cwt::{
+            validation::{verify_all_claims, verify_all_claims_at, ClaimVerificationConfig, ContextPolicy},
+            version::CredentialVersion,
+            CwtClaims,
+        },
+        inspector::PassInspector,
+    },
+    revocation::RevokedPassRegistry,
+    snapshot::{export_snapshot, IssuerSnapshot, SnapshotEntry},
+    trusted_issuers::{TrustedIssuer, TrustedIssuers},
+    verifier::{
+        health_check, AuditContext, AuditEntry, AuditLogger, HealthCheckResult, IssuerHealth, IssuerStatus, KeyPin,
+        KeyStore, MemoryKeyStore, RawCose, RefreshEvent, RefreshHandle, StoredKey, VerificationObserver,
+        VerificationOutcome, VerificationSummary, VerifiedCredential, Verifier, VerifierConfig,
+        MAX_CLOCK_SKEW_LEEWAY_SECS,
+    },
 };
 
+#[cfg(feature = "blocking")]
+mod blocking;
+pub mod codec;
 mod decentralised_identifier;
 pub mod error;
+mod json;
+mod jwks;
+pub mod key;
 mod pass;
 mod payload;
+mod revocation;
+#[cfg(any(test, feature = "signing"))]
+mod sign;
+mod snapshot;
+#[cfg(any(test, feature = "test-utils"))]
+pub mod testing;
+mod trusted_issuers;
+mod verifier;
@@ -1,9 +1,11 @@
+use std::time::Duration;
+
 use serde::de::DeserializeOwned;
 
 use crate::{
     decentralised_identifier::DecentralizedIdentifier,
     error::NzcpError,
-    payload::{barcode::QrBarcode, cose::CoseStructure},
+    verifier::{AuditContext, AuditLogger, VerifiedCredential, Verifier, VerifierConfig},
 };
 
 pub(crate) mod public_covid_pass;
@@ -14,10 +16,19 @@ pub trait Pass: DeserializeOwned {
 
     /// The JSON-LD context URL (e.g. `https://nzcp.covid19.health.nz/contexts/v1`)
     const CONTEXT_URL: &'static str;
+
+    /// Normalize the names on the pass to Unicode NFC, returning whether any changed, see
+    /// `VerifierConfig::normalize_names`. Passes without names leave this as is.
+    fn normalize_names(&mut self) -> bool {
+        false
+    }
 }
 
-const MINISTRY_OF_HEALTH_ISSUER: DecentralizedIdentifier<'static> =
-    DecentralizedIdentifier::Web("nzcp.identity.health.nz");
+/// The production MoH issuer, `did:web:nzcp.identity.health.nz`, e.g. to tell production passes from the examples
+/// in the specification when both issuers are trusted.
+pub fn ministry_of_health_issuer() -> DecentralizedIdentifier {
+    DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz"))
+}
 
 /// Verify a pass barcode URI (from a scanned QR code), returning the pass if verified or failing if not.
 ///
@@ -25,7 +36,68 @@ const MINISTRY_OF_HEALTH_ISSUER: DecentralizedIdentifier<'static> =
 ///
 /// Trusts only the MoH `nzcp.identity.health.nz` issuer.
 pub async fn verify_pass_uri<P: Pass>(uri: &str) -> Result<P, NzcpError> {
-    verify_pass_uri_with_trusted_issuers(uri, &[MINISTRY_OF_HEALTH_ISSUER]).await
+    Verifier::default().verify_pass_uri(uri).await
+}
+
+/// Verify a pass barcode URI like `verify_pass_uri`, logging the outcome to `logger` for an audit trail, see
+/// `Verifier::verify_pass_and_log`.
+///
+/// Trusts only the MoH `nzcp.identity.health.nz` issuer.
+pub async fn verify_pass_and_log<P: Pass>(
+    uri: &str,
+    context: &AuditContext,
+    logger: &dyn AuditLogger,
+) -> Result<VerifiedCredential<P>, NzcpError> {
+    Verifier::default().verify_pass_and_log(uri, context, logger).await
+}
+
+/// Verify a pass barcode URI like `verify_pass_uri`, failing with `NzcpError::Timeout` if verification takes longer
+/// than `timeout`.
+pub async fn verify_pass_with_timeout<P: Pass>(uri: &str, timeout: Duration) -> Result<P, NzcpError> {
+    Verifier::default().verify_pass_uri_with_timeout(uri, timeout).await
+}
+
+/// Verify a pass barcode URI (from a scanned QR code) with the given options, returning the pass if verified or failing
+/// if not.
+///
+/// Creates a new `Verifier` for each call, so keys are not cached between calls.
+pub async fn verify_pass_with_config<P: Pass>(uri: &str, config: &VerifierConfig) -> Result<P, NzcpError> {
+    Verifier::from_config(config.clone()).verify_pass_uri(uri).await
+}
+
+/// Verify a pass barcode URI with the given options, failing with every problem found rather than the first, see
+/// `Verifier::verify_credential_uri_all_errors`.
+pub async fn verify_pass_all_errors<P: Pass>(
+    uri: &str,
+    config: &VerifierConfig,
+) -> Result<VerifiedCredential<P>, Vec<NzcpError>> {
+    Verifier::from_config(config.clone())
+        .verify_credential_uri_all_errors(uri)
+        .await
+}
+
+/// Verify a batch of pass barcode URIs with the given options, returning the result for each in the same order, see
+/// `Verifier::verify_many`.
+///
+/// Keys are cached across the batch, but not between calls.
+pub async fn verify_many<P: Pass, S: AsRef<str>>(uris: &[S], config: &VerifierConfig) -> Vec<Result<P, NzcpError>> {
+    Verifier::from_config(config.clone()).verify_many(uris).await
+}
+
+/// Verify a pass barcode URI as the bytes read from a QR code with the given options, without converting them to a
+/// string first, see `Verifier::verify_pass_bytes`.
+///
+/// Creates a new `Verifier` for each call, so keys are not cached between calls.
+pub async fn verify_pass_bytes<P: Pass>(bytes: &[u8], config: &VerifierConfig) -> Result<P, NzcpError> {
+    Verifier::from_config(config.clone()).verify_pass_bytes(bytes).await
+}
+
+/// Verify a CBOR encoded pass (the base 32 decoded data of a barcode URI) with the given options, returning the pass
+/// if verified or failing if not.
+///
+/// Creates a new `Verifier` for each call, so keys are not cached between calls.
+pub async fn verify_pass_cbor<P: Pass>(cbor: &[u8], config: &VerifierConfig) -> Result<P, NzcpError> {
+    Verifier::from_config(config.clone()).verify_pass_cbor(cbor).await
 }
 
 /// Verify a pass barcode, returning the pass if verified or failing if not.
@@ -34,19 +106,9 @@ pub async fn verify_pass_uri<P: Pass>(uri: &str) -> Result<P, NzcpError> {
 #[doc(hidden)]
 pub async fn verify_pass_uri_with_trusted_issuers<P: Pass>(
     barcode_str: &str,
-    trusted_issuers: &[DecentralizedIdentifier<'_>],
+    trusted_issuers: &[DecentralizedIdentifier],
 ) -> Result<P, NzcpError> {
-    // extract the decoded data from the barcode string
-    let barcode: QrBarcode = barcode_str.parse()?;
-
-    // deserialize the barcode data to COSE
-    let cose: CoseStructure<'_, P> = serde_cbor::from_slice(&barcode.0)?;
-
-    // verify the COST signature and get the inner CWT
-    let cwt = cose.verified_claims(trusted_issuers).await?;
-
-    // validate the CWT and get the inner pass
-    let pass = cwt.validated_credential_subject()?;
-
-    Ok(pass)
+    Verifier::new(trusted_issuers.to_vec())
+        .verify_pass_uri(barcode_str)
+        .await
 }
@@ -1,24 +1,39 @@
-use chrono::NaiveDate;
+use chrono::{Datelike, NaiveDate};
 use serde::{de::Error, Deserialize, Deserializer, Serialize, Serializer};
+use subtle::ConstantTimeEq;
 use thiserror::Error;
+use unicode_normalization::UnicodeNormalization;
 
 use super::Pass;
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum PublicCovidPassError {
-    #[error("The given date of birth was invalid.")]
+    // the value is left out, as it's the holder's date of birth
+    #[error("The given date of birth was not a full ISO 8601 date (YYYY-MM-DD).")]
     InvalidDateOfBirth,
 }
 
+/// How closely a pass matches the surname and year of birth on another identity document, see
+/// `PublicCovidPass::match_identity`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchResult {
+    /// Both the surname and the year of birth match.
+    Full,
+    /// Only one of the surname and the year of birth match.
+    Partial,
+    /// Neither the surname nor the year of birth match.
+    Mismatch,
+}
+
 /// See: https://nzcp.covid19.health.nz/#publiccovidpass
-#[derive(Debug, Serialize, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct PublicCovidPass {
     /// Given name(s) of the subject of the pass.
     #[serde(rename = "givenName")]
     pub given_name: String,
 
-    /// Family name(s) of the subject of the pass.
-    #[serde(rename = "familyName")]
+    /// Family name(s) of the subject of the pass, if they have one.
+    #[serde(rename = "familyName", default, skip_serializing_if = "Option::is_none")]
     pub family_name: Option<String>,
 
     /// Date of birth of the subject of the pass.
@@ -30,26 +45,130 @@ pub struct PublicCovidPass {
     pub date_of_birth: NaiveDate,
 }
 
+impl PublicCovidPass {
+    /// The given and family names of the subject separated by a space, or only the given name if there is no family
+    /// name, for display.
+    pub fn full_name(&self) -> String {
+        match &self.family_name {
+            Some(family_name) => format!("{} {}", self.given_name, family_name),
+            None => self.given_name.clone(),
+        }
+    }
+
+    /// Whether the names on the pass match those on an ID document, ignoring case and how diacritics are encoded
+    /// (e.g. `Ā` matches `ā`, whether the macron is part of the character or combined with it). A pass without a
+    /// family name matches an empty `family`.
+    ///
+    /// The names are compared in constant time, so timing doesn't reveal how much of a name matched.
+    pub fn matches_name(&self, given: &str, family: &str) -> bool {
+        let given_matches = fold(&self.given_name).as_bytes().ct_eq(fold(given).as_bytes());
+        let family_name = self.family_name.as_deref().unwrap_or_default();
+        let family_matches = fold(family_name).as_bytes().ct_eq(fold(family).as_bytes());
+
+        (given_matches & family_matches).into()
+    }
+
+    /// The date of birth as written on the pass (e.g. `1960-04-16`), for display.
+    pub fn date_of_birth_iso_8601(&self) -> String {
+        // only dates in exactly this format are accepted, so this is the original string
+        self.date_of_birth.format(ISO_8601_DATE).to_string()
+    }
+
+    /// Whether the date of birth on the pass matches the given one, compared in constant time.
+    pub fn matches_dob(&self, year: u16, month: u8, day: u8) -> bool {
+        let dob = self.date_of_birth;
+        let year_matches = (dob.year() as u32).ct_eq(&year.into());
+        let month_matches = dob.month().ct_eq(&month.into());
+        let day_matches = dob.day().ct_eq(&day.into());
+
+        (year_matches & month_matches & day_matches).into()
+    }
+
+    /// How closely the pass matches an identity document (e.g. a passport) with the given surname and year of birth.
+    ///
+    /// Surnames are compared ignoring case and diacritics (e.g. `Tuilāgi` matches `TUILAGI`), as documents may spell
+    /// them differently. A pass without a family name only matches an empty `surname`.
+    pub fn match_identity(&self, surname: &str, dob_year: u16) -> MatchResult {
+        let family_name = self.family_name.as_deref().unwrap_or_default();
+        let surname_matches: bool = fold_diacritics(family_name)
+            .as_bytes()
+            .ct_eq(fold_diacritics(surname).as_bytes())
+            .into();
+        let year_matches: bool = (self.date_of_birth.year() as u32).ct_eq(&dob_year.into()).into();
+
+        match (surname_matches, year_matches) {
+            (true, true) => MatchResult::Full,
+            (true, false) | (false, true) => MatchResult::Partial,
+            (false, false) => MatchResult::Mismatch,
+        }
+    }
+}
+
+/// Lowercase a name in NFC, so names differing only by case or Unicode normalization form compare equal.
+fn fold(name: &str) -> String {
+    name.to_lowercase().nfc().collect()
+}
+
+/// Lowercase a name without diacritics, by decomposing it in NFKD and dropping the combining marks, so names spelled
+/// with and without diacritics compare equal.
+fn fold_diacritics(name: &str) -> String {
+    name.nfkd()
+        .filter(|c| !unicode_normalization::char::is_combining_mark(*c))
+        .collect::<String>()
+        .to_lowercase()
+}
+
 impl Pass for PublicCovidPass {
     const CREDENTIAL_TYPE: &'static str = "PublicCovidPass";
 
     const CONTEXT_URL: &'static str = "https://nzcp.covid19.health.nz/contexts/v1";
+
+    fn normalize_names(&mut self) -> bool {
+        let given_changed = normalize(&mut self.given_name);
+        let family_changed = self.family_name.as_mut().is_some_and(normalize);
+        given_changed || family_changed
+    }
+}
+
+/// Convert a name to NFC in place, returning whether it changed.
+fn normalize(name: &mut String) -> bool {
+    if unicode_normalization::is_nfc(name) {
+        return false;
+    }
+    let normalized = name.nfc().collect::<String>();
+    let changed = normalized != *name;
+    *name = normalized;
+    changed
+}
+
+const ISO_8601_DATE: &str = "%Y-%m-%d";
+
+/// Parse a `YYYY-MM-DD` date, rejecting other forms chrono accepts (e.g. two digit years or unpadded months).
+pub(crate) fn parse_iso_8601_date(value: &str) -> Result<NaiveDate, PublicCovidPassError> {
+    let well_formed = value.len() == 10
+        && value.bytes().enumerate().all(|(i, byte)| match i {
+            4 | 7 => byte == b'-',
+            _ => byte.is_ascii_digit(),
+        });
+    well_formed
+        .then(|| NaiveDate::parse_from_str(value, ISO_8601_DATE).ok())
+        .flatten()
+        .ok_or(PublicCovidPassError::InvalidDateOfBirth)
 }
 
 fn deserialize_iso_8601_date<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let string: &str = Deserialize::deserialize(deserializer)?;
-    NaiveDate::parse_from_str(string, "%Y-%m-%d")
-        .map_err(|_| D::Error::custom(PublicCovidPassError::InvalidDateOfBirth))
+    let string = String::deserialize(deserializer)?;
+    parse_iso_8601_date(&string).map_err(D::Error::custom)
 }
 
 fn serialize_iso_8601_date<S>(date: &NaiveDate, serializer: S) -> Result<S::Ok, S::Error>
 where
     S: Serializer,
 {
-    date.format("%Y-%m-%d").to_string().serialize(serializer)
+    date.format(ISO_8601_DATE).to_string().serialize(serializer)
 }
 
 #[cfg(test)]
@@ -70,12 +189,165 @@ mod tests {
             PublicCovidPass {
                 given_name: String::from("John Andrew"),
                 family_name: Some(String::from("Doe")),
-                date_of_birth: NaiveDate::from_ymd(1979, 04, 14),
+                date_of_birth: NaiveDate::from_ymd(1979, 4, 14),
             }
         );
         assert_eq!(
             serde_json::from_str::<serde_json::Value>(json).unwrap(),
             serde_json::to_value(&pass).unwrap()
         );
+        // the date of birth isn't borrowed from the input
+        assert_eq!(
+            serde_json::from_value::<PublicCovidPass>(serde_json::to_value(&pass).unwrap()).unwrap(),
+            pass
+        );
+        assert_eq!(
+            serde_json::from_reader::<_, PublicCovidPass>(json.as_bytes()).unwrap(),
+            pass
+        );
+    }
+
+    #[test]
+    fn without_family_name() {
+        let json = r#"{"givenName": "Moana", "dob": "1979-04-14"}"#;
+
+        let pass: PublicCovidPass = serde_json::from_str(json).unwrap();
+        assert_eq!(pass.family_name, None);
+        assert_eq!(pass.full_name(), "Moana");
+        assert_eq!(
+            serde_json::to_value(&pass).unwrap(),
+            serde_json::from_str::<serde_json::Value>(json).unwrap()
+        );
+
+        let pass = PublicCovidPass {
+            family_name: Some(String::from("Doe")),
+            ..pass
+        };
+        assert_eq!(pass.full_name(), "Moana Doe");
+
+        assert!(serde_json::from_str::<PublicCovidPass>(r#"{"dob": "1979-04-14"}"#).is_err());
+        assert!(serde_json::from_str::<PublicCovidPass>(r#"{"givenName": "Moana"}"#).is_err());
+    }
+
+    #[test]
+    fn date_of_birth() {
+        let pass = |dob: &str| {
+            serde_json::from_str::<PublicCovidPass>(&format!(r#"{{"givenName": "Jack", "dob": "{}"}}"#, dob))
+        };
+
+        let example = pass("1960-04-16").unwrap();
+        assert_eq!(example.date_of_birth, NaiveDate::from_ymd(1960, 4, 16));
+        assert_eq!(example.date_of_birth_iso_8601(), "1960-04-16");
+        assert_eq!(
+            pass("2000-02-29").unwrap().date_of_birth,
+            NaiveDate::from_ymd(2000, 2, 29)
+        );
+
+        for invalid in [
+            "2001-02-29",
+            "1960-04-16T00:00:00",
+            "1960-04-16 00:00",
+            "60-04-16",
+            "0060-4-16",
+            "1960-4-16",
+            "+1960-04-16",
+            "1960-04",
+            "",
+        ] {
+            assert_eq!(
+                parse_iso_8601_date(invalid).unwrap_err(),
+                PublicCovidPassError::InvalidDateOfBirth
+            );
+            assert!(!pass(invalid)
+                .unwrap_err()
+                .to_string()
+                .contains(&format!("'{}'", invalid)));
+        }
+    }
+
+    #[test]
+    fn matches_name_and_dob() {
+        let pass = PublicCovidPass {
+            given_name: String::from("M\u{101}ui"),
+            family_name: Some(String::from("Tuil\u{101}gi")),
+            date_of_birth: NaiveDate::from_ymd(1979, 4, 14),
+        };
+
+        assert!(pass.matches_name("M\u{101}ui", "Tuil\u{101}gi"));
+        assert!(pass.matches_name("M\u{100}UI", "TUIL\u{100}GI"));
+        // macrons as combining characters
+        assert!(pass.matches_name("Ma\u{304}ui", "TUILA\u{304}GI"));
+        assert!(!pass.matches_name("Maui", "Tuil\u{101}gi"));
+        assert!(!pass.matches_name("M\u{101}ui", "Tuil\u{101}g"));
+
+        let without_family_name = PublicCovidPass {
+            family_name: None,
+            ..pass.clone()
+        };
+        assert!(without_family_name.matches_name("m\u{101}ui", ""));
+        assert!(!without_family_name.matches_name("m\u{101}ui", "Tuil\u{101}gi"));
+
+        assert!(pass.matches_dob(1979, 4, 14));
+        assert!(!pass.matches_dob(1979, 4, 15));
+        assert!(!pass.matches_dob(1978, 4, 14));
+        assert!(!pass.matches_dob(1979, 14, 4));
+    }
+
+    #[test]
+    fn match_identity() {
+        let pass = PublicCovidPass {
+            given_name: String::from("M\u{101}ui"),
+            family_name: Some(String::from("Tuil\u{101}gi")),
+            date_of_birth: NaiveDate::from_ymd(1979, 4, 14),
+        };
+
+        assert_eq!(pass.match_identity("Tuil\u{101}gi", 1979), MatchResult::Full);
+        assert_eq!(pass.match_identity("TUILAGI", 1979), MatchResult::Full);
+        assert_eq!(pass.match_identity("Tuila\u{304}gi", 1979), MatchResult::Full);
+        assert_eq!(pass.match_identity("Tuil\u{e0}gi", 1979), MatchResult::Full);
+        // compatibility forms, e.g. fullwidth letters, are decomposed too
+        assert_eq!(
+            pass.match_identity("\u{ff34}\u{ff55}\u{ff49}\u{ff4c}\u{ff41}\u{ff47}\u{ff49}", 1979),
+            MatchResult::Full
+        );
+
+        assert_eq!(pass.match_identity("Tuilagi", 1978), MatchResult::Partial);
+        assert_eq!(pass.match_identity("Tuilag", 1979), MatchResult::Partial);
+        assert_eq!(pass.match_identity("M\u{101}ui", 1978), MatchResult::Mismatch);
+
+        let without_family_name = PublicCovidPass {
+            family_name: None,
+            ..pass
+        };
+        assert_eq!(without_family_name.match_identity("", 1979), MatchResult::Full);
+        assert_eq!(
+            without_family_name.match_identity("Tuilagi", 1979),
+            MatchResult::Partial
+        );
+    }
+
+    #[test]
+    fn normalize_names() {
+        let pass = |given_name: &str, family_name: Option<&str>| PublicCovidPass {
+            given_name: String::from(given_name),
+            family_name: family_name.map(String::from),
+            date_of_birth: NaiveDate::from_ymd(1979, 4, 14),
+        };
+
+        let mut precomposed = pass("\u{100}miria", Some("Tuil\u{101}gi"));
+        let mut combining = pass("A\u{304}miria", Some("Tuila\u{304}gi"));
+        assert_ne!(precomposed, combining);
+        assert!(!precomposed.normalize_names());
+        assert!(combining.normalize_names());
+        assert_eq!(precomposed, combining);
+        assert!(!combining.normalize_names());
+
+        let mut family_only = pass("\u{100}miria", Some("Tuila\u{304}gi"));
+        assert!(family_only.normalize_names());
+        assert_eq!(family_only, precomposed);
+
+        let mut without_family_name = pass("A\u{304}miria", None);
+        assert!(without_family_name.normalize_names());
+        assert_eq!(without_family_name, pass("\u{100}miria", None));
     }
 }
@@ -1,3 +1,7 @@
 pub mod barcode;
+pub mod batch;
+pub mod canonical;
 pub mod cose;
 pub mod cwt;
+pub mod diagnostic;
+pub mod inspector;
@@ -3,50 +3,127 @@ use std::str::FromStr;
 use base32::Alphabet::RFC4648;
 use thiserror::Error;
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum QrBarcodeError {
     #[error("The payload of the QR Code MUST be base32 encoded")]
     InvalidBase32,
-    #[error("The version-identifier portion of the payload for the specification MUST be 1")]
-    InvalidVersion,
+    #[error("The version-identifier portion of the payload for the specification MUST be 1, found {found}")]
+    UnsupportedVersion { found: u32 },
+    #[error("The payload of the QR Code MUST begin with the prefix of `NZCP:/<version-identifier>/`")]
+    MalformedPrefix,
     #[error("The payload of the QR Code MUST begin with the prefix of `NZCP:/`")]
     MissingNzcpPrefix,
+    #[error("The QR Code contained a non-ASCII byte at offset {offset}")]
+    NonAscii { offset: usize },
 }
 
+/// The only version-identifier in the specification.
+pub const SUPPORTED_VERSION: u32 = 1;
+
 /// Expects a valid encoded pass from 2D barcode format encoded as:
 /// `NZCP:/<version-identifier>/<base32-encoded-CWT>`
 ///
-/// Holds the decoded CBOR bytes, which can then be deserialized.
+/// Holds the version-identifier and the decoded CBOR bytes, which can then be deserialized.
+///
+/// The prefix and the base32 payload are case-insensitive, as some QR code scanners lowercase the text they read.
+/// Trailing `=` padding is ignored, though the specification omits it, as is surrounding whitespace (e.g. the newline
+/// some scanners end their input with).
 ///
 /// Implements `FromStr`, so use as follows:
 ///
 /// ```ignore
 /// let barcode: QrBarcode = "NZCP:/1/2KCEVIQ...".parse().unwrap;
 /// ```
-pub struct QrBarcode(pub Vec<u8>);
+pub struct QrBarcode {
+    pub version: u32,
+    pub cbor: Vec<u8>,
+}
 
-impl FromStr for QrBarcode {
-    type Err = QrBarcodeError;
+impl QrBarcode {
+    /// Parse the bytes read from a QR code, as a scanner's decoder returns them, without converting them to a string
+    /// first. Any non-ASCII byte fails with `QrBarcodeError::NonAscii`.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, QrBarcodeError> {
+        if let Some(offset) = bytes.iter().position(|byte| !byte.is_ascii()) {
+            return Err(QrBarcodeError::NonAscii { offset });
+        }
+        Self::from_ascii(std::str::from_utf8(bytes).expect("ASCII is valid UTF-8"))
+    }
 
-    fn from_str(string: &str) -> Result<Self, Self::Err> {
+    /// Encode the barcode as the specification writes it: the `NZCP:/<version-identifier>/` prefix followed by the
+    /// CBOR bytes as uppercase base32, without padding.
+    pub fn to_uri(&self) -> String {
+        format!(
+            "NZCP:/{}/{}",
+            self.version,
+            base32::encode(RFC4648 { padding: false }, &self.cbor)
+        )
+    }
+
+    fn from_ascii(string: &str) -> Result<Self, QrBarcodeError> {
         use QrBarcodeError::*;
 
-        let base32_encoded_cwt = string
-            .strip_prefix("NZCP:/")
+        let string = string.trim_matches(|c: char| c.is_ascii_whitespace());
+        let (version, base32_encoded_cwt) = strip_prefix_ignore_case(string, "NZCP:/")
             .ok_or(MissingNzcpPrefix)?
-            .strip_prefix("1/")
-            .ok_or(InvalidVersion)?;
+            .split_once('/')
+            .ok_or(MalformedPrefix)?;
 
-        let cbor_array = base32::decode(RFC4648 { padding: false }, base32_encoded_cwt).ok_or(InvalidBase32)?;
-        Ok(QrBarcode(cbor_array))
+        // the version-identifier is a positive decimal integer, without a sign or leading zeroes
+        if version.starts_with('0') || !version.bytes().all(|byte| byte.is_ascii_digit()) {
+            return Err(MalformedPrefix);
+        }
+        let version = version.parse().map_err(|_| MalformedPrefix)?;
+        if version != SUPPORTED_VERSION {
+            return Err(UnsupportedVersion { found: version });
+        }
+
+        // the decoder ignores case, but accepts `=` padding anywhere in the input
+        let base32_encoded_cwt = base32_encoded_cwt.trim_end_matches('=');
+        if !base32_encoded_cwt
+            .bytes()
+            .all(|byte| byte.is_ascii_alphabetic() || (b'2'..=b'7').contains(&byte))
+        {
+            return Err(InvalidBase32);
+        }
+        let cbor = base32::decode(RFC4648 { padding: false }, base32_encoded_cwt).ok_or(InvalidBase32)?;
+        Ok(QrBarcode { version, cbor })
     }
 }
 
+impl FromStr for QrBarcode {
+    type Err = QrBarcodeError;
+
+    fn from_str(string: &str) -> Result<Self, Self::Err> {
+        Self::from_bytes(string.as_bytes())
+    }
+}
+
+/// Normalise a scanned pass URI into the canonical `NZCP:/1/...` form, e.g. to deduplicate or store passes, without
+/// verifying it.
+///
+/// Surrounding whitespace, `=` padding, and lowercase are accepted as when verifying, and the decoded COSE bytes are
+/// re-encoded unchanged, so the canonical form verifies if and only if the original does. Fails if the payload can't
+/// be decoded at all.
+pub fn canonicalize_pass_uri(uri: &str) -> Result<String, QrBarcodeError> {
+    let barcode: QrBarcode = uri.parse()?;
+    Ok(barcode.to_uri())
+}
+
+fn strip_prefix_ignore_case<'a>(string: &'a str, prefix: &str) -> Option<&'a str> {
+    string
+        .get(..prefix.len())
+        .filter(|start| start.eq_ignore_ascii_case(prefix))
+        .map(|_| &string[prefix.len()..])
+}
+
 #[cfg(test)]
 mod tests {
     use std::fmt::Write;
 
+    use proptest::prelude::*;
+
     use super::*;
+    use crate::testing::VALID_PASS;
 
     #[test]
     fn deserialize_barcode() {
@@ -55,7 +132,8 @@ mod tests {
         let payload: QrBarcode = encoded.parse().unwrap();
 
         let mut hex_str = String::new();
-        for byte in payload.0 {
+        assert_eq!(payload.version, 1);
+        for byte in payload.cbor {
             write!(&mut hex_str, "{:02x}", byte).unwrap();
         }
 
@@ -64,4 +142,176 @@ mod tests {
             "d2844aa204456b65792d310126a059011fa501781e6469643a7765623a6e7a63702e636f76696431392e6865616c74682e6e7a051a61819a0a041a7450400a627663a46840636f6e7465787482782668747470733a2f2f7777772e77332e6f72672f323031382f63726564656e7469616c732f7631782a68747470733a2f2f6e7a63702e636f76696431392e6865616c74682e6e7a2f636f6e74657874732f76316776657273696f6e65312e302e306474797065827456657269666961626c6543726564656e7469616c6f5075626c6963436f766964506173737163726564656e7469616c5375626a656374a369676976656e4e616d65644a61636b6a66616d696c794e616d656753706172726f7763646f626a313936302d30342d3136075060a4f54d4e304332be33ad78b1eafa4b5840d2e07b1dd7263d833166bdbb4f1a093837a905d7eca2ee836b6b2ada23c23154fba88a529f675d6686ee632b09ec581ab08f72b458904bb3396d10fa66d11477"
         )
     }
+
+    #[test]
+    fn barcode_versions() {
+        let parse = |uri: &str| uri.parse::<QrBarcode>().map(|barcode| barcode.version);
+
+        assert_eq!(parse("NZCP:/1/AAAA"), Ok(1));
+        assert_eq!(
+            parse("NZCP:/2/AAAA"),
+            Err(QrBarcodeError::UnsupportedVersion { found: 2 })
+        );
+        assert_eq!(parse("NZCP:/01/AAAA"), Err(QrBarcodeError::MalformedPrefix));
+        assert_eq!(parse("NZCP://AAAA"), Err(QrBarcodeError::MalformedPrefix));
+        assert_eq!(parse("NZCP:/AAAA"), Err(QrBarcodeError::MalformedPrefix));
+        assert_eq!(parse("NZCP:/one/AAAA"), Err(QrBarcodeError::MalformedPrefix));
+        assert_eq!(parse("NZCP:/+1/AAAA"), Err(QrBarcodeError::MalformedPrefix));
+        assert_eq!(parse("NZCP:/99999999999/AAAA"), Err(QrBarcodeError::MalformedPrefix));
+        assert_eq!(parse("HC1:/1/AAAA"), Err(QrBarcodeError::MissingNzcpPrefix));
+    }
+
+    #[test]
+    fn case_insensitive() {
+        let uppercase: QrBarcode = VALID_PASS.parse().unwrap();
+
+        let lowercase: QrBarcode = VALID_PASS.to_lowercase().parse().unwrap();
+        assert_eq!(lowercase.cbor, uppercase.cbor);
+
+        let mixed_case: String = VALID_PASS
+            .chars()
+            .enumerate()
+            .map(|(i, c)| {
+                if i % 2 == 0 {
+                    c.to_ascii_lowercase()
+                }
+                else {
+                    c
+                }
+            })
+            .collect();
+        let mixed_case: QrBarcode = mixed_case.parse().unwrap();
+        assert_eq!(mixed_case.cbor, uppercase.cbor);
+    }
+
+    #[test]
+    fn invalid_base32() {
+        for uri in ["NZCP:/1/AAAA1AAA", "NZCP:/1/AAAA=AAA", "NZCP:/1/AAAA AAA"] {
+            assert_eq!(
+                uri.parse::<QrBarcode>().map(|barcode| barcode.cbor),
+                Err(QrBarcodeError::InvalidBase32),
+                "{}",
+                uri
+            );
+        }
+    }
+
+    #[test]
+    fn from_bytes() {
+        let barcode = QrBarcode::from_bytes(VALID_PASS.as_bytes()).unwrap();
+        assert_eq!(barcode.cbor, VALID_PASS.parse::<QrBarcode>().unwrap().cbor);
+
+        let parse = |bytes: &[u8]| QrBarcode::from_bytes(bytes).map(|barcode| barcode.cbor);
+        assert_eq!(
+            parse(b"NZCP:/1/AAAA\xffAAA"),
+            Err(QrBarcodeError::NonAscii { offset: 12 })
+        );
+        assert_eq!(
+            parse(b"\xef\xbb\xbfNZCP:/1/AAAA"),
+            Err(QrBarcodeError::NonAscii { offset: 0 })
+        );
+        assert_eq!(
+            "NZCP:/1/AAAAÀAAA".parse::<QrBarcode>().map(|barcode| barcode.cbor),
+            Err(QrBarcodeError::NonAscii { offset: 12 })
+        );
+    }
+
+    #[test]
+    fn padding() {
+        // trimmed so the unpadded payloads need each amount of padding RFC 4648 allows
+        for trim in [0, 1, 3, 4, 6] {
+            let uri = &VALID_PASS[..VALID_PASS.len() - trim];
+            let unpadded: QrBarcode = uri.parse().unwrap();
+            let padding = "=".repeat(trim);
+
+            let padded: QrBarcode = format!("{}{}", uri, padding).parse().unwrap();
+            assert_eq!(padded.cbor, unpadded.cbor);
+
+            let over_padded: QrBarcode = format!("{}{}========", uri, padding).parse().unwrap();
+            assert_eq!(over_padded.cbor, unpadded.cbor);
+        }
+
+        let (start, end) = VALID_PASS.split_at(VALID_PASS.len() / 2);
+        assert_eq!(
+            format!("{}======{}", start, end)
+                .parse::<QrBarcode>()
+                .map(|barcode| barcode.cbor),
+            Err(QrBarcodeError::InvalidBase32)
+        );
+    }
+
+    #[test]
+    fn canonicalize() {
+        assert_eq!(canonicalize_pass_uri(VALID_PASS).unwrap(), VALID_PASS);
+        assert_eq!(
+            canonicalize_pass_uri(&format!(" \t{}===\r\n", VALID_PASS.to_lowercase())).unwrap(),
+            VALID_PASS
+        );
+        assert_eq!(canonicalize_pass_uri("nzcp:/1/aaaa"), Ok(String::from("NZCP:/1/AAAA")));
+
+        assert_eq!(
+            canonicalize_pass_uri("NZCP:/1/AAAA1AAA"),
+            Err(QrBarcodeError::InvalidBase32)
+        );
+        assert_eq!(
+            canonicalize_pass_uri("NZCP:/2/AAAA"),
+            Err(QrBarcodeError::UnsupportedVersion { found: 2 })
+        );
+        assert_eq!(
+            canonicalize_pass_uri("HC1:/1/AAAA"),
+            Err(QrBarcodeError::MissingNzcpPrefix)
+        );
+    }
+
+    /// A URI for `cbor` as a scanner might read it, with some characters lowercased, padding, and whitespace.
+    fn scanned_uri(cbor: &[u8], lowercase: &[bool], padding: usize, leading: &str, trailing: &str) -> String {
+        let uri: String = QrBarcode {
+            version: SUPPORTED_VERSION,
+            cbor: cbor.to_vec(),
+        }
+        .to_uri()
+        .chars()
+        .zip(lowercase.iter().chain(std::iter::repeat(&false)))
+        .map(|(c, lowercase)| {
+            if *lowercase {
+                c.to_ascii_lowercase()
+            }
+            else {
+                c
+            }
+        })
+        .collect();
+        format!("{}{}{}{}", leading, uri, "=".repeat(padding), trailing)
+    }
+
+    proptest! {
+        #[test]
+        fn canonicalize_preserves_bytes(
+            cbor in prop::collection::vec(any::<u8>(), 0..256),
+            lowercase in prop::collection::vec(any::<bool>(), 0..512),
+            padding in 0usize..8,
+            leading in "[ \t\r\n]{0,3}",
+            trailing in "[ \t\r\n]{0,3}",
+        ) {
+            let scanned = scanned_uri(&cbor, &lowercase, padding, &leading, &trailing);
+
+            let canonical = canonicalize_pass_uri(&scanned).unwrap();
+            prop_assert!(canonical.starts_with("NZCP:/1/"));
+            prop_assert!(!canonical.ends_with('='));
+            prop_assert_eq!(canonical.to_uppercase(), canonical.clone());
+            prop_assert_eq!(canonical.parse::<QrBarcode>().unwrap().cbor, cbor);
+            prop_assert_eq!(canonicalize_pass_uri(&canonical).unwrap(), canonical);
+        }
+
+        #[test]
+        fn canonicalize_is_idempotent(uri in "(?i)(NZCP:/1/)?[A-Z2-7= ]{0,64}") {
+            if let Ok(canonical) = canonicalize_pass_uri(&uri) {
+                prop_assert_eq!(canonicalize_pass_uri(&canonical), Ok(canonical.clone()));
+                prop_assert_eq!(
+                    canonical.parse::<QrBarcode>().unwrap().cbor,
+                    uri.parse::<QrBarcode>().unwrap().cbor
+                );
+            }
+        }
+    }
 }
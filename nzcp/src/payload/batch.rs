@@ -0,0 +1,220 @@
+use std::sync::Arc;
+
+use futures_util::{stream, Stream};
+use thiserror::Error;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt};
+
+use super::barcode::{QrBarcode, QrBarcodeError};
+
+/// The longest line read by `BatchDecoder` by default, far longer than any pass fits in a QR code.
+const DEFAULT_MAX_LINE_LENGTH: usize = 8 * 1024;
+
+#[derive(Debug, Clone, Error)]
+pub enum BatchDecoderError {
+    /// Reading the next line failed. The stream ends after this error.
+    ///
+    /// The IO error is not `Clone` itself, so is shared behind an `Arc`.
+    #[error("line {line}: read failed: {error}")]
+    Read { line: usize, error: Arc<std::io::Error> },
+    /// The line was longer than the maximum line length, and was skipped.
+    #[error("line {line}: longer than {max_line_length} bytes")]
+    LineTooLong { line: usize, max_line_length: usize },
+    /// The line wasn't a valid barcode URI.
+    #[error("line {line}: {error}")]
+    InvalidBarcode { line: usize, error: QrBarcodeError },
+}
+
+/// A barcode URI decoded by `BatchDecoder`, ready to be verified with `Verifier::verify_pass_cbor`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParsedNzcpPass {
+    /// The line of the input the URI was on, counting from 1.
+    pub line: usize,
+    /// The version-identifier of the URI.
+    pub version: u32,
+    /// The base32 decoded payload of the URI, the CBOR encoded COSE_Sign1 structure.
+    pub cbor: Vec<u8>,
+}
+
+/// Decodes a newline-delimited file of pass barcode URIs (e.g. an event's entry log) one line at a time, so only one
+/// line is held in memory however large the file is.
+///
+/// Surrounding whitespace and blank lines are ignored. Each line is decoded as `QrBarcode` does, without verifying
+/// the pass:
+///
+/// ```ignore
+/// let file = tokio::io::BufReader::new(tokio::fs::File::open("passes.txt").await?);
+/// let mut passes = Box::pin(BatchDecoder::new(file).into_stream());
+/// while let Some(pass) = passes.next().await {
+///     let pass = pass?;
+///     let result = verifier.verify_pass_cbor::<PublicCovidPass>(&pass.cbor).await;
+/// }
+/// ```
+#[derive(Debug)]
+pub struct BatchDecoder<R> {
+    reader: R,
+    max_line_length: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> BatchDecoder<R> {
+    /// Decode the URIs read from `reader`, one per line, with the default maximum line length. Blank lines are
+    /// skipped, and nothing is read until the decoder is turned into a stream with `into_stream`.
+    pub fn new(reader: R) -> Self {
+        BatchDecoder {
+            reader,
+            max_line_length: DEFAULT_MAX_LINE_LENGTH,
+        }
+    }
+
+    /// The longest line that will be read, in bytes (defaults to 8 KiB). Longer lines fail with `LineTooLong` as soon
+    /// as the limit is passed, and the rest of the line is skipped without being held in memory.
+    pub fn with_max_line_length(mut self, max_line_length: usize) -> Self {
+        self.max_line_length = max_line_length;
+        self
+    }
+
+    /// Decode the lines as they're read, yielding each pass or the problem with its line.
+    pub fn into_stream(self) -> impl Stream<Item = Result<ParsedNzcpPass, BatchDecoderError>> {
+        stream::unfold(Some((self, 0)), |state| async move {
+            let (mut decoder, mut line) = state?;
+            loop {
+                line += 1;
+                match decoder.read_line(line).await {
+                    Ok(None) => return None,
+                    Ok(Some(bytes)) => {
+                        let uri = bytes.trim_ascii();
+                        if uri.is_empty() {
+                            continue;
+                        }
+                        let pass = QrBarcode::from_bytes(uri)
+                            .map(|barcode| ParsedNzcpPass {
+                                line,
+                                version: barcode.version,
+                                cbor: barcode.cbor,
+                            })
+                            .map_err(|error| BatchDecoderError::InvalidBarcode { line, error });
+                        return Some((pass, Some((decoder, line))));
+                    }
+                    Err(error @ BatchDecoderError::LineTooLong { .. }) => {
+                        return Some((Err(error), Some((decoder, line))));
+                    }
+                    Err(error) => return Some((Err(error), None)),
+                }
+            }
+        })
+    }
+
+    /// Read the next line without its newline, or `None` at the end of the input.
+    async fn read_line(&mut self, line: usize) -> Result<Option<Vec<u8>>, BatchDecoderError> {
+        let read_error = |error| BatchDecoderError::Read {
+            line,
+            error: Arc::new(error),
+        };
+
+        let mut bytes = Vec::new();
+        let limit = self.max_line_length as u64 + 1;
+        let read = (&mut self.reader)
+            .take(limit)
+            .read_until(b'\n', &mut bytes)
+            .await
+            .map_err(read_error)?;
+        if read == 0 {
+            return Ok(None);
+        }
+
+        if bytes.last() == Some(&b'\n') {
+            bytes.pop();
+        }
+        else if bytes.len() > self.max_line_length {
+            // skip the rest of the line, a buffer at a time
+            loop {
+                let buffer = self.reader.fill_buf().await.map_err(read_error)?;
+                if buffer.is_empty() {
+                    break;
+                }
+                match buffer.iter().position(|byte| *byte == b'\n') {
+                    Some(end) => {
+                        self.reader.consume(end + 1);
+                        break;
+                    }
+                    None => {
+                        let length = buffer.len();
+                        self.reader.consume(length);
+                    }
+                }
+            }
+            return Err(BatchDecoderError::LineTooLong {
+                line,
+                max_line_length: self.max_line_length,
+            });
+        }
+        Ok(Some(bytes))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use futures_util::StreamExt;
+
+    use super::*;
+    use crate::testing::VALID_PASS;
+
+    async fn decode(input: &[u8], max_line_length: usize) -> Vec<Result<ParsedNzcpPass, String>> {
+        BatchDecoder::new(input)
+            .with_max_line_length(max_line_length)
+            .into_stream()
+            .map(|result| result.map_err(|error| error.to_string()))
+            .collect()
+            .await
+    }
+
+    #[tokio::test]
+    async fn decodes_lines() {
+        let cbor = VALID_PASS.parse::<QrBarcode>().unwrap().cbor;
+        let pass = |line| {
+            Ok(ParsedNzcpPass {
+                line,
+                version: 1,
+                cbor: cbor.clone(),
+            })
+        };
+        let input = format!(
+            "{}\n\n  {}  \r\nNZCP:/2/AAAA\n{}",
+            VALID_PASS,
+            VALID_PASS.to_lowercase(),
+            VALID_PASS
+        );
+
+        assert_eq!(
+            decode(input.as_bytes(), DEFAULT_MAX_LINE_LENGTH).await,
+            vec![
+                pass(1),
+                pass(3),
+                Err(String::from(
+                    "line 4: The version-identifier portion of the payload for the specification MUST be 1, found 2"
+                )),
+                pass(5),
+            ]
+        );
+        assert_eq!(decode(b"", DEFAULT_MAX_LINE_LENGTH).await, vec![]);
+    }
+
+    #[tokio::test]
+    async fn long_lines() {
+        let input = format!("{}\nNZCP:/1/AAAA\n{}", "A".repeat(100), "A".repeat(100));
+        assert_eq!(
+            decode(input.as_bytes(), 16).await,
+            vec![
+                Err(String::from("line 1: longer than 16 bytes")),
+                Ok(ParsedNzcpPass {
+                    line: 2,
+                    version: 1,
+                    cbor: vec![0, 0],
+                }),
+                Err(String::from("line 3: longer than 16 bytes")),
+            ]
+        );
+
+        // a line of exactly the maximum length is read
+        assert_eq!(decode(b"NZCP:/1/AAAA\n", 12).await.len(), 1);
+    }
+}
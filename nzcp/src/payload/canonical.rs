@@ -0,0 +1,352 @@
+use std::fmt;
+
+use thiserror::Error;
+
+/// COSE tag for a COSE_Sign1 structure.
+const COSE_SIGN1_TAG: u64 = 18;
+
+/// Names of the COSE_Sign1 elements, in order.
+const COSE_SIGN1_ELEMENTS: [&str; 4] = ["protected headers", "unprotected headers", "claims", "signature"];
+
+/// How deeply arrays, maps, and tags may be nested, the same limit `serde_cbor` applies when deserializing.
+pub(super) const MAX_DEPTH: usize = 128;
+
+/// The pass is not encoded as canonical CBOR, see `VerifierConfig::strict_cbor`.
+///
+/// Each variant holds the path to the offending element, e.g. `claims key 4`.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum CanonicalCborError {
+    #[error("indefinite length item at {0}")]
+    IndefiniteLength(String),
+    #[error("non-canonical {kind} at {path}")]
+    NonShortestForm { kind: &'static str, path: String },
+    #[error("duplicate map key at {0}")]
+    DuplicateKey(String),
+    #[error("malformed CBOR at {0}")]
+    Malformed(String),
+    #[error("CBOR nested more than 128 levels deep at {0}")]
+    TooDeeplyNested(String),
+}
+
+/// Check a CBOR encoded COSE_Sign1 structure uses definite lengths and shortest-form integers, lengths, and tags
+/// throughout, with no duplicate map keys. The protected headers and claims, which are CBOR encoded within the
+/// structure, are checked too.
+///
+/// Map key order isn't checked, as the examples in the specification don't sort their keys.
+pub fn check_cose(cbor: &[u8]) -> Result<(), CanonicalCborError> {
+    let mut reader = Reader::new(cbor);
+    let path = &Path::Root("COSE_Sign1");
+
+    let mut header = reader.header(path)?;
+    if header.major == 6 && header.argument == COSE_SIGN1_TAG {
+        header = reader.header(path)?;
+    }
+    if header.major != 4 {
+        return reader.item_with_header(header, path).map(|_| ());
+    }
+
+    for index in 0..header.argument {
+        let element = match COSE_SIGN1_ELEMENTS.get(index as usize) {
+            Some(name) => Path::Root(name),
+            None => Path::Index(path, index),
+        };
+        reader.depth = 1;
+        match reader.item(&element)? {
+            Item::Bytes(encoded) if index == 0 || index == 2 => check_item(encoded, &element)?,
+            _ => {}
+        }
+    }
+    reader.finish(path)
+}
+
+/// Check a whole byte string is a single canonical CBOR item. An empty byte string is accepted, as COSE uses one for
+/// empty protected headers.
+fn check_item(cbor: &[u8], path: &Path<'_>) -> Result<(), CanonicalCborError> {
+    if cbor.is_empty() {
+        return Ok(());
+    }
+    let mut reader = Reader::new(cbor);
+    reader.item(path)?;
+    reader.finish(path)
+}
+
+/// The path to an item, e.g. `claims key "vc" key "type" [1]`, only formatted if the item is the cause of an error.
+enum Path<'a> {
+    Root(&'a str),
+    Index(&'a Path<'a>, u64),
+    /// A map key, before it's been read.
+    Key(&'a Path<'a>),
+    IntegerKey(&'a Path<'a>, i128),
+    TextKey(&'a Path<'a>, &'a str),
+    /// A key that's neither an integer nor text, by its position in the map.
+    OtherKey(&'a Path<'a>, usize),
+}
+
+impl fmt::Display for Path<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Path::Root(name) => f.write_str(name),
+            Path::Index(parent, index) => write!(f, "{} [{}]", parent, index),
+            Path::Key(parent) => write!(f, "{} key", parent),
+            Path::IntegerKey(parent, key) => write!(f, "{} key {}", parent, key),
+            Path::TextKey(parent, key) => write!(f, "{} key {:?}", parent, key),
+            Path::OtherKey(parent, position) => write!(f, "{} key #{}", parent, position),
+        }
+    }
+}
+
+struct Header {
+    major: u8,
+    argument: u64,
+}
+
+enum Item<'a> {
+    Integer(i128),
+    Text(&'a str),
+    Bytes(&'a [u8]),
+    Other,
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    /// How many arrays, maps, and tags the current item is nested within.
+    depth: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        Reader {
+            bytes,
+            position: 0,
+            depth: 0,
+        }
+    }
+
+    fn take(&mut self, length: u64, path: &Path<'_>) -> Result<&'a [u8], CanonicalCborError> {
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| self.position.checked_add(length))
+            .filter(|end| *end <= self.bytes.len())
+            .ok_or_else(|| CanonicalCborError::Malformed(path.to_string()))?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Ok(taken)
+    }
+
+    fn header(&mut self, path: &Path<'_>) -> Result<Header, CanonicalCborError> {
+        let initial = self.take(1, path)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+
+        let (argument, minimum) = match info {
+            0..=23 => (u64::from(info), 0),
+            24 => (u64::from(self.take(1, path)?[0]), 24),
+            25 => (self.argument(2, path)?, 1 << 8),
+            26 => (self.argument(4, path)?, 1 << 16),
+            27 => (self.argument(8, path)?, 1 << 32),
+            31 if (2..=5).contains(&major) => return Err(CanonicalCborError::IndefiniteLength(path.to_string())),
+            _ => return Err(CanonicalCborError::Malformed(path.to_string())),
+        };
+
+        // floats use the same additional information values, but aren't integers
+        let is_float = major == 7 && info >= 25;
+        if argument < minimum && !is_float {
+            let kind = match major {
+                0 | 1 => "integer",
+                6 => "tag",
+                7 => "simple value",
+                _ => "length",
+            };
+            return Err(CanonicalCborError::NonShortestForm {
+                kind,
+                path: path.to_string(),
+            });
+        }
+
+        Ok(Header { major, argument })
+    }
+
+    fn argument(&mut self, length: u64, path: &Path<'_>) -> Result<u64, CanonicalCborError> {
+        Ok(self
+            .take(length, path)?
+            .iter()
+            .fold(0, |argument, byte| argument << 8 | u64::from(*byte)))
+    }
+
+    fn item(&mut self, path: &Path<'_>) -> Result<Item<'a>, CanonicalCborError> {
+        let header = self.header(path)?;
+        self.item_with_header(header, path)
+    }
+
+    fn item_with_header(&mut self, header: Header, path: &Path<'_>) -> Result<Item<'a>, CanonicalCborError> {
+        if (4..=6).contains(&header.major) {
+            if self.depth == MAX_DEPTH {
+                return Err(CanonicalCborError::TooDeeplyNested(path.to_string()));
+            }
+            self.depth += 1;
+            let item = self.nested_item(header, path);
+            self.depth -= 1;
+            return item;
+        }
+
+        match header.major {
+            0 => Ok(Item::Integer(header.argument.into())),
+            1 => Ok(Item::Integer(-1 - i128::from(header.argument))),
+            2 => Ok(Item::Bytes(self.take(header.argument, path)?)),
+            3 => {
+                let text = self.take(header.argument, path)?;
+                let text = std::str::from_utf8(text).map_err(|_| CanonicalCborError::Malformed(path.to_string()))?;
+                Ok(Item::Text(text))
+            }
+            _ => {
+                // simple values and floats carry no further data
+                Ok(Item::Other)
+            }
+        }
+    }
+
+    /// Read the contents of an array, map, or tag.
+    fn nested_item(&mut self, header: Header, path: &Path<'_>) -> Result<Item<'a>, CanonicalCborError> {
+        match header.major {
+            4 => {
+                for index in 0..header.argument {
+                    self.item(&Path::Index(path, index))?;
+                }
+            }
+            5 => {
+                let mut keys: Vec<&[u8]> = Vec::new();
+                for _ in 0..header.argument {
+                    let start = self.position;
+                    let key = self.item(&Path::Key(path))?;
+                    let key_path = match key {
+                        Item::Integer(key) => Path::IntegerKey(path, key),
+                        Item::Text(key) => Path::TextKey(path, key),
+                        _ => Path::OtherKey(path, keys.len()),
+                    };
+
+                    let encoded_key = &self.bytes[start..self.position];
+                    if keys.contains(&encoded_key) {
+                        return Err(CanonicalCborError::DuplicateKey(key_path.to_string()));
+                    }
+                    keys.push(encoded_key);
+
+                    self.item(&key_path)?;
+                }
+            }
+            _ => {
+                self.item(path)?;
+            }
+        }
+        Ok(Item::Other)
+    }
+
+    fn finish(&self, path: &Path<'_>) -> Result<(), CanonicalCborError> {
+        if self.position == self.bytes.len() {
+            Ok(())
+        }
+        else {
+            Err(CanonicalCborError::Malformed(path.to_string()))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        payload::barcode::QrBarcode,
+        sign::sign_cose,
+        testing::{test_signing_key, VALID_PASS},
+    };
+
+    fn signed(claims: &str) -> Vec<u8> {
+        sign_cose(&hex::decode(claims).unwrap(), &test_signing_key(1), "key-1").unwrap()
+    }
+
+    #[test]
+    fn canonical_pass() {
+        let barcode: QrBarcode = VALID_PASS.parse().unwrap();
+        assert_eq!(check_cose(&barcode.cbor), Ok(()));
+    }
+
+    #[test]
+    fn non_canonical_claims() {
+        // {4: 1951416330} with the expiry encoded as 8 rather than 4 bytes
+        assert_eq!(
+            check_cose(&signed("a1041b000000007450400a")).unwrap_err().to_string(),
+            "non-canonical integer at claims key 4"
+        );
+        // {4: 10} with the expiry encoded as 1 byte rather than in the initial byte
+        assert_eq!(
+            check_cose(&signed("a104180a")),
+            Err(CanonicalCborError::NonShortestForm {
+                kind: "integer",
+                path: String::from("claims key 4")
+            })
+        );
+        // {"vc": {}} with the length of "vc" encoded as 1 byte
+        assert_eq!(
+            check_cose(&signed("a178027663a0")),
+            Err(CanonicalCborError::NonShortestForm {
+                kind: "length",
+                path: String::from("claims key")
+            })
+        );
+        // {"vc": {"type": [_ ]}} with an indefinite length array
+        assert_eq!(
+            check_cose(&signed("a1627663a164747970659fff")),
+            Err(CanonicalCborError::IndefiniteLength(String::from(
+                "claims key \"vc\" key \"type\""
+            )))
+        );
+        // {4: 1, 4: 2}
+        assert_eq!(
+            check_cose(&signed("a204010402")),
+            Err(CanonicalCborError::DuplicateKey(String::from("claims key 4")))
+        );
+        // {5: 1, 4: 2}, unsorted keys are accepted
+        assert_eq!(check_cose(&signed("a205010402")), Ok(()));
+    }
+
+    #[test]
+    fn non_canonical_cose() {
+        // the COSE_Sign1 tag encoded as 1 byte
+        let mut cbor = signed("a0");
+        cbor.splice(0..1, [0xd8, 0x12]);
+        assert_eq!(
+            check_cose(&cbor),
+            Err(CanonicalCborError::NonShortestForm {
+                kind: "tag",
+                path: String::from("COSE_Sign1")
+            })
+        );
+
+        let mut cbor = signed("a0");
+        cbor.push(0);
+        assert_eq!(
+            check_cose(&cbor),
+            Err(CanonicalCborError::Malformed(String::from("COSE_Sign1")))
+        );
+    }
+
+    #[test]
+    fn deeply_nested() {
+        // a COSE_Sign1 structure whose claims are 100000 nested arrays
+        let cbor = [&[0xd2, 0x84, 0x40, 0xa0][..], &[0x81; 100_000]].concat();
+        assert_eq!(
+            check_cose(&cbor),
+            Err(CanonicalCborError::TooDeeplyNested(format!(
+                "claims{}",
+                " [0]".repeat(MAX_DEPTH - 1)
+            )))
+        );
+
+        // nesting within the claims is counted from the claims, and tags count as nesting
+        let nested = |depth: usize| format!("{}00", "c1".repeat(depth));
+        assert_eq!(check_cose(&signed(&nested(MAX_DEPTH))), Ok(()));
+        assert!(matches!(
+            check_cose(&signed(&nested(MAX_DEPTH + 1))),
+            Err(CanonicalCborError::TooDeeplyNested(_))
+        ));
+    }
+}
@@ -1,70 +1,144 @@
 use std::{fmt, marker::PhantomData};
 
 use serde::{
-    de::{self, Error, IgnoredAny, Visitor},
-    Deserialize, Deserializer,
+    de::{self, Error, Visitor},
+    Deserialize, Deserializer, Serialize,
 };
 use serde_cbor::tags::Tagged;
 
 use self::{
     protected_headers::ProtectedHeaders,
-    signature::{verify::CoseVerificationError, CoseSignStructure, CoseSignature},
+    signature::{verify::CoseVerificationError, CoseSignStructure, CoseSignature, SignatureAlgorithm},
+    unprotected_headers::{IgnoredUnprotectedHeaders, UnprotectedHeaders},
+};
+use super::{cwt::Cwt, diagnostic::diagnostic};
+use crate::{
+    decentralised_identifier::DecentralizedIdentifier,
+    pass::Pass,
+    verifier::{KeyPin, Verifier},
 };
-use super::cwt::CwtClaims;
-use crate::{decentralised_identifier::DecentralizedIdentifier, pass::Pass};
 
 mod protected_headers;
 pub mod signature;
+mod unprotected_headers;
+
+/// Which COSE header a pass's `kid` was read from, see `VerifierConfig::unprotected_kid`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum KidHeader {
+    Protected,
+    Unprotected,
+}
 
 #[derive(Debug)]
 pub struct CoseStructure<'a, T> {
     protected_headers: ProtectedHeaders<'a>,
-    cwt_claims: CwtClaims<'a, T>,
+    unprotected_headers: UnprotectedHeaders<'a>,
+    cwt_claims: Cwt<'a, T>,
     signature: CoseSignature<'a>,
 }
 
 impl<'a, T: Pass> CoseStructure<'a, T> {
-    /// Get the CWT payload iff the signature is valid.
-    pub async fn verified_claims(
-        self,
-        trusted_issuers: &[DecentralizedIdentifier<'_>],
-    ) -> Result<CwtClaims<'a, T>, CoseVerificationError> {
-        // TODO: caching
-        let verifying_key = self
-            .cwt_claims
-            .verify_issuer(trusted_issuers)?
-            .resolve_verifying_key(self.protected_headers.kid)
-            .await?;
+    /// Check the signature is valid and the issuer trusted, returning the pin the signing key matched (if the issuer's
+    /// keys are pinned) and the header the `kid` was read from. The claims are kept either way, see
+    /// `into_unverified_claims`.
+    pub async fn verify(&self, verifier: &Verifier) -> Result<(Option<KeyPin>, KidHeader), CoseVerificationError> {
+        SignatureAlgorithm::try_from(self.protected_headers.algorithm)?;
+        let (kid, kid_header) = self.kid(verifier.config().unprotected_kid)?;
+
+        let issuer = self.cwt_claims.verify_issuer(verifier.trusted_issuers())?;
+        let verifying_key = verifier.verifying_key(issuer, kid).await?;
 
         self.verify_signature(&verifying_key)?;
+        let matched_pin = verifier.check_pinned_key(issuer, kid, &verifying_key)?;
 
-        Ok(self.cwt_claims)
+        Ok((matched_pin, kid_header))
+    }
+
+    /// The CWT payload, whether or not the signature is valid.
+    pub fn into_unverified_claims(self) -> Cwt<'a, T> {
+        self.cwt_claims
+    }
+
+    /// The issuer the CWT payload claims to be from, whether or not the signature is valid.
+    pub fn issuer(&self) -> &DecentralizedIdentifier {
+        self.cwt_claims.issuer()
+    }
+
+    /// The CBOR encoded protected headers, as signed.
+    pub fn protected_headers_raw(&self) -> &'a [u8] {
+        self.signature.protected_headers_raw
+    }
+
+    /// The CBOR encoded CWT claims, as signed.
+    pub fn cwt_claims_raw(&self) -> &'a [u8] {
+        self.signature.cwt_claims_raw
+    }
+
+    /// The signature over the protected headers and claims.
+    pub fn signature_bytes(&self) -> &'a [u8] {
+        self.signature.bytes
+    }
+
+    /// The `kid` from the protected headers, or if `unprotected` is allowed and there isn't one there, from the
+    /// unprotected headers. When both are present (and `unprotected` is allowed), they must match.
+    fn kid(&self, unprotected: bool) -> Result<(&'a str, KidHeader), CoseVerificationError> {
+        let unprotected_kid = self.unprotected_headers.kid.filter(|_| unprotected);
+
+        let protected_kid = self.protected_headers.kid.map(|kid| kid.as_str()).transpose()?;
+        let unprotected_kid = unprotected_kid.map(|kid| kid.as_str()).transpose()?;
+
+        match (protected_kid, unprotected_kid) {
+            (Some(protected), Some(unprotected)) if protected != unprotected => {
+                Err(CoseVerificationError::ConflictingKid {
+                    protected: protected.to_owned(),
+                    unprotected: unprotected.to_owned(),
+                })
+            }
+            (Some(protected), _) => Ok((protected, KidHeader::Protected)),
+            (None, Some(unprotected)) => Ok((unprotected, KidHeader::Unprotected)),
+            (None, None) => Err(CoseVerificationError::MissingKid),
+        }
     }
 }
 
-impl<'de: 'a, 'a, T> Deserialize<'de> for CoseStructure<'a, T>
-where
-    T: Deserialize<'de>,
-{
-    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+impl<'a, T: Deserialize<'a>> CoseStructure<'a, T> {
+    /// Deserialize a COSE structure, only reading the `kid` from the unprotected headers if `unprotected_kid` is set
+    /// (see `VerifierConfig::unprotected_kid`), so otherwise nothing in them is decoded.
+    pub fn from_slice(cbor: &'a [u8], unprotected_kid: bool) -> Result<Self, serde_cbor::Error> {
+        if unprotected_kid {
+            Self::from_sections(serde_cbor::from_slice::<Tagged<CoseStructureSections<'a, T>>>(cbor)?)
+        }
+        else {
+            Self::from_sections(serde_cbor::from_slice::<
+                Tagged<CoseStructureSections<'a, T, IgnoredUnprotectedHeaders>>,
+            >(cbor)?)
+        }
+    }
+}
+
+impl<'a, T> CoseStructure<'a, T> {
+    fn from_sections<U, E>(tagged: Tagged<CoseStructureSections<'a, T, U>>) -> Result<Self, E>
     where
-        D: Deserializer<'de>,
+        U: Into<UnprotectedHeaders<'a>>,
+        E: de::Error,
     {
-        let tagged: Tagged<CoseStructureSections<'_, T>> = Deserialize::deserialize(deserializer)?;
         let CoseStructureSections {
             protected_headers_raw,
             cwt_claims_raw,
             protected_headers,
+            unprotected_headers,
             cwt_claims,
             signature,
         } = tagged.value;
 
         Ok(CoseStructure {
             protected_headers,
+            unprotected_headers: unprotected_headers.into(),
             cwt_claims,
             signature: CoseSignature {
                 bytes: signature,
-                sign_structure: CoseSignStructure::try_from(tagged.tag).map_err(D::Error::custom)?,
+                sign_structure: CoseSignStructure::try_from(tagged.tag).map_err(E::custom)?,
                 protected_headers_raw,
                 cwt_claims_raw,
             },
@@ -72,24 +146,38 @@ where
     }
 }
 
+impl<'de: 'a, 'a, T> Deserialize<'de> for CoseStructure<'a, T>
+where
+    T: Deserialize<'de>,
+{
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        Self::from_sections(Tagged::<CoseStructureSections<'_, T>>::deserialize(deserializer)?)
+    }
+}
+
 /// As the CBOR tag cannot be fetched within field deserialization we first extract the sections,
 /// then when deserializing `CoseStructure` we merge these sections with the sign structure tag.
 #[derive(Debug)]
-struct CoseStructureSections<'a, T> {
+struct CoseStructureSections<'a, T, U = UnprotectedHeaders<'a>> {
     protected_headers_raw: &'a [u8],
     cwt_claims_raw: &'a [u8],
     protected_headers: ProtectedHeaders<'a>,
-    cwt_claims: CwtClaims<'a, T>,
+    unprotected_headers: U,
+    cwt_claims: Cwt<'a, T>,
     signature: &'a [u8],
 }
 
-struct CoseStructureVisitor<T>(PhantomData<fn() -> T>);
+struct CoseStructureVisitor<T, U>(PhantomData<fn() -> (T, U)>);
 
-impl<'de, T> Visitor<'de> for CoseStructureVisitor<T>
+impl<'de, T, U> Visitor<'de> for CoseStructureVisitor<T, U>
 where
     T: Deserialize<'de>,
+    U: Deserialize<'de>,
 {
-    type Value = CoseStructureSections<'de, T>;
+    type Value = CoseStructureSections<'de, T, U>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
         formatter.write_str("COSE structure")
@@ -113,20 +201,22 @@ where
         };
 
         let protected_headers_raw = bytes("protected headers", &mut seq)?;
-        let protected_headers = serde_cbor::from_slice(protected_headers_raw).map_err(A::Error::custom)?;
+        let protected_headers = serde_cbor::from_slice(protected_headers_raw)
+            .map_err(|error| section_error("protected headers", protected_headers_raw, error))?;
 
-        // unprotected headers are empty in spec, just skip them
-        let _: IgnoredAny = seq
+        let unprotected_headers = seq
             .next_element()?
             .ok_or_else(|| A::Error::custom("malformed COSE data (missing unprotected headers)"))?;
 
         let cwt_claims_raw = bytes("CWT claims", &mut seq)?;
-        let cwt_claims = serde_cbor::from_slice(cwt_claims_raw).map_err(A::Error::custom)?;
+        let cwt_claims = serde_cbor::from_slice(cwt_claims_raw)
+            .map_err(|error| section_error("CWT claims", cwt_claims_raw, error))?;
         let signature = bytes("signature", &mut seq)?;
 
         Ok(CoseStructureSections {
             protected_headers,
             protected_headers_raw,
+            unprotected_headers,
             cwt_claims,
             cwt_claims_raw,
             signature,
@@ -134,22 +224,42 @@ where
     }
 }
 
-impl<'de: 'a, 'a, T> Deserialize<'de> for CoseStructureSections<'a, T>
+/// An error for a section of the COSE structure that is itself CBOR encoded, showing the start of the section in
+/// diagnostic notation to help debug malformed passes.
+fn section_error<E: de::Error>(name: &str, cbor: &[u8], error: serde_cbor::Error) -> E {
+    E::custom(format!("invalid {}: {}, got: {}", name, error, diagnostic(cbor)))
+}
+
+impl<'de: 'a, 'a, T, U> Deserialize<'de> for CoseStructureSections<'a, T, U>
 where
     T: Deserialize<'de>,
+    U: Deserialize<'de>,
 {
     fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_seq(CoseStructureVisitor::<T>(PhantomData))
+        deserializer.deserialize_seq(CoseStructureVisitor::<T, U>(PhantomData))
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use std::{collections::BTreeMap, sync::Arc};
+
+    use serde_cbor::Value;
+
     use super::*;
-    use crate::payload::cose::signature::SignatureAlgorithm;
+    use crate::{
+        error::NzcpError,
+        payload::{barcode::QrBarcode, cose::protected_headers::KeyId},
+        sign::{sign_cose, sign_cose_with_headers},
+        testing::{
+            example_issuer, resolver_with_key, test_signing_key, test_vectors::scanned_at, verifier_with_key,
+            MockDIDResolver, TestPass, EXAMPLE_DID_DOCUMENT, VALID_PASS,
+        },
+        PublicCovidPass, VerifierConfig,
+    };
 
     #[test]
     fn deserialize_cose() {
@@ -160,9 +270,247 @@ mod tests {
         assert_eq!(
             structure.protected_headers,
             ProtectedHeaders {
-                kid: "key-1",
-                algorithm: SignatureAlgorithm::Es256
+                kid: Some(KeyId(b"key-1")),
+                algorithm: Some(-7)
             }
         )
     }
+
+    #[test]
+    fn section_errors() {
+        let key = test_signing_key(1);
+        let error = |claims: &str| {
+            let cbor = sign_cose(&hex::decode(claims).unwrap(), &key, "key-1").unwrap();
+            serde_cbor::from_slice::<CoseStructure<'_, Value>>(&cbor)
+                .unwrap_err()
+                .to_string()
+        };
+
+        assert_eq!(
+            error("820102"),
+            "invalid CWT claims: invalid type: sequence, expected CWT claims, got: [1, 2]"
+        );
+        // {4: 1951416330, 5: with the value missing
+        assert_eq!(
+            error("a2041a7450400a05"),
+            "invalid CWT claims: EOF while parsing a value at offset 8, got: {4: 1951416330, 5: ..."
+        );
+    }
+
+    #[tokio::test]
+    async fn unsupported_algorithms() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+        let cbor = VALID_PASS.parse::<QrBarcode>().unwrap().cbor;
+        // the protected headers of the worked example are {4: "key-1", 1: -7}
+        let with_protected_headers = |protected_headers: &str| {
+            let cbor = hex::encode(&cbor).replacen("4aa204456b65792d310126", protected_headers, 1);
+            hex::decode(cbor).unwrap()
+        };
+        let verifier = &verifier;
+        let verify = |cbor: Vec<u8>| async move { verifier.verify_pass_cbor::<PublicCovidPass>(&cbor).await };
+
+        let ps256 = verify(with_protected_headers("4ba204456b65792d31013824"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            ps256,
+            NzcpError::InvalidSignature(CoseVerificationError::UnsupportedAlgorithm { found: -37 })
+        );
+        assert_eq!(
+            CoseVerificationError::UnsupportedAlgorithm { found: -37 }.to_string(),
+            "unsupported signature algorithm PS256 (-37), only ES256 (-7) is supported"
+        );
+
+        assert_eq!(
+            verify(with_protected_headers("4aa204456b65792d310127")).await,
+            Err(NzcpError::InvalidSignature(
+                CoseVerificationError::UnsupportedAlgorithm { found: -8 }
+            ))
+        );
+
+        let unregistered = CoseVerificationError::UnsupportedAlgorithm { found: -65000 };
+        assert_eq!(
+            verify(with_protected_headers("4ca204456b65792d310139fde7")).await,
+            Err(NzcpError::InvalidSignature(unregistered.clone()))
+        );
+        assert_eq!(
+            unregistered.to_string(),
+            "unsupported signature algorithm -65000, only ES256 (-7) is supported"
+        );
+
+        assert_eq!(
+            verify(with_protected_headers("48a104456b65792d31")).await,
+            Err(NzcpError::InvalidSignature(CoseVerificationError::MissingAlgorithm))
+        );
+    }
+
+    #[tokio::test]
+    async fn signature_lengths() {
+        let key = test_signing_key(1);
+        let cbor = TestPass::default().sign_cbor(&key);
+        let signature = &cbor[cbor.len() - 64..];
+        let verify = |signature: &[u8]| {
+            let mut structure: CoseStructure<'_, PublicCovidPass> = serde_cbor::from_slice(&cbor).unwrap();
+            structure.signature.bytes = signature;
+            structure.verify_signature(&key.verifying_key())
+        };
+
+        assert_eq!(verify(signature), Ok(()));
+        let mut modified = signature.to_vec();
+        modified[63] ^= 1;
+        assert_eq!(
+            verify(&modified),
+            Err(CoseVerificationError::SignatureVerificationFailed)
+        );
+        assert_eq!(
+            verify(&[0; 64]),
+            Err(CoseVerificationError::SignatureVerificationFailed)
+        );
+        assert_eq!(
+            verify(&[]),
+            Err(CoseVerificationError::InvalidSignatureLength { found: 0 })
+        );
+        assert_eq!(
+            verify(&signature[..63]),
+            Err(CoseVerificationError::InvalidSignatureLength { found: 63 })
+        );
+        let padded = [signature, &[0]].concat();
+        assert_eq!(
+            verify(&padded),
+            Err(CoseVerificationError::InvalidSignatureLength { found: 65 })
+        );
+        assert_eq!(
+            CoseVerificationError::InvalidSignatureLength { found: 63 }.to_string(),
+            "signature is 63 bytes, but ES256 signatures are 64 bytes"
+        );
+
+        // the signature is the last item of the structure, a 64 byte string (0x5840)
+        let verifier = verifier_with_key(&key);
+        let truncated = [&cbor[..cbor.len() - 66], &[0x58, 0x3f], &signature[..63]].concat();
+        assert_eq!(
+            verifier.verify_pass_cbor::<PublicCovidPass>(&truncated).await,
+            Err(NzcpError::InvalidSignature(
+                CoseVerificationError::InvalidSignatureLength { found: 63 }
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn kid_headers() {
+        let key = test_signing_key(1);
+        let resolver = resolver_with_key(&key);
+        let strict = Verifier::new(vec![example_issuer()]).with_resolver(resolver.clone());
+        let lenient = Verifier::from_config(VerifierConfig {
+            trusted_issuers: vec![example_issuer()],
+            unprotected_kid: true,
+            ..VerifierConfig::default()
+        })
+        .with_resolver(resolver);
+
+        let claims = serde_cbor::to_vec(&TestPass::default().claims()).unwrap();
+        let sign = |protected_kid: Option<&str>, unprotected_kid: Option<&str>| {
+            let headers = |kid: Option<&str>| {
+                kid.map(|kid| (Value::Integer(4), Value::Bytes(kid.as_bytes().to_vec())))
+                    .into_iter()
+                    .collect::<BTreeMap<_, _>>()
+            };
+            let mut protected_headers = headers(protected_kid);
+            protected_headers.insert(Value::Integer(1), Value::Integer(-7));
+            sign_cose_with_headers(&claims, &key, protected_headers, headers(unprotected_kid)).unwrap()
+        };
+
+        let protected_only = sign(Some("key-1"), None);
+        for verifier in [&strict, &lenient] {
+            let credential = verifier
+                .verify_credential_cbor_at::<PublicCovidPass>(&protected_only, scanned_at())
+                .await
+                .unwrap();
+            assert_eq!(credential.kid_header, KidHeader::Protected);
+        }
+
+        let unprotected_only = sign(None, Some("key-1"));
+        assert_eq!(
+            strict
+                .verify_credential_cbor_at::<PublicCovidPass>(&unprotected_only, scanned_at())
+                .await,
+            Err(NzcpError::InvalidSignature(CoseVerificationError::MissingKid))
+        );
+        let credential = lenient
+            .verify_credential_cbor_at::<PublicCovidPass>(&unprotected_only, scanned_at())
+            .await
+            .unwrap();
+        assert_eq!(credential.kid_header, KidHeader::Unprotected);
+
+        let both_matching = sign(Some("key-1"), Some("key-1"));
+        let credential = lenient
+            .verify_credential_cbor_at::<PublicCovidPass>(&both_matching, scanned_at())
+            .await
+            .unwrap();
+        assert_eq!(credential.kid_header, KidHeader::Protected);
+
+        let both_conflicting = sign(Some("key-1"), Some("key-2"));
+        assert_eq!(
+            lenient
+                .verify_credential_cbor_at::<PublicCovidPass>(&both_conflicting, scanned_at())
+                .await,
+            Err(NzcpError::InvalidSignature(CoseVerificationError::ConflictingKid {
+                protected: String::from("key-1"),
+                unprotected: String::from("key-2")
+            }))
+        );
+        // the unprotected header isn't read unless allowed
+        assert!(strict
+            .verify_credential_cbor_at::<PublicCovidPass>(&both_conflicting, scanned_at())
+            .await
+            .is_ok());
+    }
+
+    #[test]
+    fn unprotected_kid_only_decoded_if_allowed() {
+        let claims = serde_cbor::to_vec(&TestPass::default().claims()).unwrap();
+        let protected_headers = BTreeMap::from([
+            (Value::Integer(1), Value::Integer(-7)),
+            (Value::Integer(4), Value::Bytes(b"key-1".to_vec())),
+        ]);
+        // not a valid kid, which must be a byte or text string
+        let unprotected_headers = BTreeMap::from([(Value::Integer(4), Value::Integer(1))]);
+        let cbor =
+            sign_cose_with_headers(&claims, &test_signing_key(1), protected_headers, unprotected_headers).unwrap();
+
+        let structure = CoseStructure::<PublicCovidPass>::from_slice(&cbor, false).unwrap();
+        assert_eq!(structure.unprotected_headers, UnprotectedHeaders::default());
+        assert!(CoseStructure::<PublicCovidPass>::from_slice(&cbor, true).is_err());
+    }
+
+    #[tokio::test]
+    async fn kid_encodings() {
+        let key = test_signing_key(1);
+        let verifier = verifier_with_key(&key);
+
+        let claims = serde_cbor::to_vec(&TestPass::default().claims()).unwrap();
+        let verify = |kid: Value| {
+            let protected_headers = BTreeMap::from([(Value::Integer(1), Value::Integer(-7)), (Value::Integer(4), kid)]);
+            let cbor = sign_cose_with_headers(&claims, &key, protected_headers, BTreeMap::new()).unwrap();
+            let verifier = &verifier;
+            async move {
+                verifier
+                    .verify_credential_cbor_at::<PublicCovidPass>(&cbor, scanned_at())
+                    .await
+            }
+        };
+
+        assert!(verify(Value::Text(String::from("key-1"))).await.is_ok());
+        assert!(verify(Value::Bytes(b"key-1".to_vec())).await.is_ok());
+        assert_eq!(
+            verify(Value::Bytes(vec![0x6b, 0x65, 0x79, 0xff])).await,
+            Err(NzcpError::InvalidSignature(CoseVerificationError::NonUtf8Kid(vec![
+                0x6b, 0x65, 0x79, 0xff
+            ])))
+        );
+        assert!(matches!(
+            verify(Value::Integer(1)).await,
+            Err(NzcpError::InvalidPayload(_))
+        ));
+    }
 }
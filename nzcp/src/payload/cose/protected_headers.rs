@@ -5,15 +5,17 @@ use serde::{
     Deserialize, Deserializer,
 };
 
-use super::signature::SignatureAlgorithm;
+use super::signature::verify::CoseVerificationError;
 
 const KID_KEY: u8 = 4;
 const ALG_KEY: u8 = 1;
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct ProtectedHeaders<'a> {
-    pub kid: &'a str,
-    pub algorithm: SignatureAlgorithm,
+    /// The `kid` header, if it's protected (see `CoseStructure::kid`).
+    pub kid: Option<KeyId<'a>>,
+    /// The `alg` header, checked before the signature is, so an unsupported algorithm gets its own error.
+    pub algorithm: Option<i64>,
 }
 
 struct ProtectedHeadersVisitor;
@@ -35,16 +37,12 @@ impl<'de> Visitor<'de> for ProtectedHeadersVisitor {
         while let Some(key) = map.next_key()? {
             match key {
                 KID_KEY => kid = Some(map.next_value()?),
-                ALG_KEY => algorithm = Some(map.next_value::<i8>()?.try_into().map_err(A::Error::custom)?),
+                ALG_KEY => algorithm = Some(map.next_value()?),
                 _ => return Err(A::Error::unknown_field(&format!("{}", key), &["4 (kid)", "1 (alg)"])),
             }
         }
 
-        match (kid, algorithm) {
-            (Some(kid), Some(algorithm)) => Ok(ProtectedHeaders { kid, algorithm }),
-            (_, None) => Err(A::Error::missing_field("1 (alg)")),
-            (None, _) => Err(A::Error::missing_field("4 (kid)")),
-        }
+        Ok(ProtectedHeaders { kid, algorithm })
     }
 }
 
@@ -56,3 +54,41 @@ impl<'de: 'a, 'a> Deserialize<'de> for ProtectedHeaders<'a> {
         deserializer.deserialize_map(ProtectedHeadersVisitor)
     }
 }
+
+/// A `kid` header, which COSE defines as a byte string, though the specification's examples hold UTF-8 text, and some
+/// libraries encode it as a text string. Either encoding is accepted, and checked to be UTF-8 once used.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyId<'a>(pub &'a [u8]);
+
+impl<'a> KeyId<'a> {
+    pub fn as_str(&self) -> Result<&'a str, CoseVerificationError> {
+        std::str::from_utf8(self.0).map_err(|_| CoseVerificationError::NonUtf8Kid(self.0.to_vec()))
+    }
+}
+
+struct KeyIdVisitor;
+
+impl<'de> Visitor<'de> for KeyIdVisitor {
+    type Value = KeyId<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("a COSE key ID byte or text string")
+    }
+
+    fn visit_borrowed_bytes<E: Error>(self, bytes: &'de [u8]) -> Result<Self::Value, E> {
+        Ok(KeyId(bytes))
+    }
+
+    fn visit_borrowed_str<E: Error>(self, text: &'de str) -> Result<Self::Value, E> {
+        Ok(KeyId(text.as_bytes()))
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for KeyId<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_bytes(KeyIdVisitor)
+    }
+}
@@ -1,5 +1,6 @@
 use thiserror::Error;
 
+use self::verify::CoseVerificationError;
 
 pub mod verify;
 
@@ -18,7 +19,7 @@ pub enum CoseSignStructure {
     Sign1,
 }
 
-#[derive(Debug, Error)]
+#[derive(Debug, Clone, Error)]
 pub enum CoseSignStructureError {
     #[error("missing CBOR tag (infering the sign structure)")]
     MissingTag,
@@ -38,26 +39,42 @@ impl TryFrom<Option<u64>> for CoseSignStructure {
     }
 }
 
-const ES256_ID: i8 = -7;
+const ES256_ID: i64 = -7;
 
 #[derive(Debug, PartialEq, Eq)]
 pub enum SignatureAlgorithm {
     Es256,
 }
 
-#[derive(Debug, Error)]
-pub enum SignatureAlgorithmError {
-    #[error("invalid CBOR signature algorithm (must be ES256)")]
-    SignatureAlgorithm,
-}
-
-impl TryFrom<i8> for SignatureAlgorithm {
-    type Error = SignatureAlgorithmError;
+impl TryFrom<Option<i64>> for SignatureAlgorithm {
+    type Error = CoseVerificationError;
 
-    fn try_from(value: i8) -> Result<Self, Self::Error> {
+    fn try_from(value: Option<i64>) -> Result<Self, Self::Error> {
         match value {
-            ES256_ID => Ok(SignatureAlgorithm::Es256),
-            _ => Err(SignatureAlgorithmError::SignatureAlgorithm),
+            Some(ES256_ID) => Ok(SignatureAlgorithm::Es256),
+            Some(found) => Err(CoseVerificationError::UnsupportedAlgorithm { found }),
+            None => Err(CoseVerificationError::MissingAlgorithm),
         }
     }
 }
+
+/// The IANA name of a registered COSE signature algorithm.
+///
+/// See: https://www.iana.org/assignments/cose/cose.xhtml#algorithms
+pub(crate) fn algorithm_name(algorithm: i64) -> Option<&'static str> {
+    match algorithm {
+        -7 => Some("ES256"),
+        -8 => Some("EdDSA"),
+        -35 => Some("ES384"),
+        -36 => Some("ES512"),
+        -37 => Some("PS256"),
+        -38 => Some("PS384"),
+        -39 => Some("PS512"),
+        -47 => Some("ES256K"),
+        -257 => Some("RS256"),
+        -258 => Some("RS384"),
+        -259 => Some("RS512"),
+        -65535 => Some("RS1"),
+        _ => None,
+    }
+}
@@ -6,38 +6,71 @@ use p256::ecdsa::{
 use serde::Serialize;
 use thiserror::Error;
 
-use super::{CoseSignStructure, CoseSignature};
+use super::{algorithm_name, CoseSignStructure, CoseSignature};
 use crate::{
     decentralised_identifier::DecentralizedIdentifierError,
     payload::{cose::CoseStructure, cwt::validation::CwtValidationError},
 };
 
+/// The length of an ES256 signature, the raw `r` and `s` values concatenated.
+const ES256_SIGNATURE_LENGTH: usize = 64;
+
 /// A deliberately opaque signature error
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum CoseVerificationError {
     #[error("signature verification failed")]
-    VerificationFailed,
+    SignatureVerificationFailed,
+    #[error("signature is {found} bytes, but ES256 signatures are 64 bytes")]
+    InvalidSignatureLength { found: usize },
     #[error("CWT validation failed: {0:?}")]
     CwtValidation(#[from] CwtValidationError),
     #[error("provided issuer is not trusted: {0}")]
     UntrustedIssuer(String),
     #[error("DID resolution failed: {0:?}")]
     DecentralizedIdentifierResolution(#[from] DecentralizedIdentifierError),
+    #[error("signing key {0} does not match any of the issuer's pinned keys")]
+    UnpinnedKey(String),
+    #[error("unsupported signature algorithm {}, only ES256 (-7) is supported", describe_algorithm(*found))]
+    UnsupportedAlgorithm { found: i64 },
+    #[error("missing signature algorithm (alg) protected header")]
+    MissingAlgorithm,
+    #[error("missing key ID (kid) header")]
+    MissingKid,
+    #[error("key ID (kid) header is not valid UTF-8: {0:02x?}")]
+    NonUtf8Kid(Vec<u8>),
+    #[error("protected key ID {protected} conflicts with unprotected key ID {unprotected}")]
+    ConflictingKid { protected: String, unprotected: String },
+}
+
+fn describe_algorithm(algorithm: i64) -> String {
+    match algorithm_name(algorithm) {
+        Some(name) => format!("{} ({})", name, algorithm),
+        None => algorithm.to_string(),
+    }
 }
 
 impl<'a, T> CoseStructure<'a, T> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "verify_signature", level = "debug", skip_all)
+    )]
     pub fn verify_signature(&self, verifying_key: &VerifyingKey) -> Result<(), CoseVerificationError> {
-        use CoseVerificationError::VerificationFailed;
+        use CoseVerificationError::{InvalidSignatureLength, SignatureVerificationFailed};
+
+        if self.signature.bytes.len() != ES256_SIGNATURE_LENGTH {
+            return Err(InvalidSignatureLength {
+                found: self.signature.bytes.len(),
+            });
+        }
 
         let sig_structure = self.signature.sig_structure();
-        let to_be_signed = serde_cbor::to_vec(&sig_structure).map_err(|_| VerificationFailed)?;
+        let to_be_signed = serde_cbor::to_vec(&sig_structure).map_err(|_| SignatureVerificationFailed)?;
 
+        // a 64 byte signature can still be invalid, e.g. if `r` or `s` is zero
+        let signature = ecdsa::Signature::from_bytes(self.signature.bytes).map_err(|_| SignatureVerificationFailed)?;
         verifying_key
-            .verify(
-                &to_be_signed,
-                &ecdsa::Signature::from_bytes(self.signature.bytes).map_err(|_| VerificationFailed)?,
-            )
-            .map_err(|_| VerificationFailed)?;
+            .verify(&to_be_signed, &signature)
+            .map_err(|_| SignatureVerificationFailed)?;
 
         Ok(())
     }
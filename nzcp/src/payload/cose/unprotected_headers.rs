@@ -0,0 +1,83 @@
+use std::fmt;
+
+use serde::{
+    de::{self, IgnoredAny, Visitor},
+    Deserialize, Deserializer,
+};
+use serde_cbor::Value;
+
+use super::protected_headers::KeyId;
+
+const KID_KEY: i128 = 4;
+
+/// The unprotected headers, which are empty in the specification, but may hold the `kid` from some issuers (see
+/// `VerifierConfig::unprotected_kid`). Other headers are ignored, as they aren't covered by the signature.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct UnprotectedHeaders<'a> {
+    pub kid: Option<KeyId<'a>>,
+}
+
+/// The unprotected headers when the `kid` isn't to be read from them, which are checked to be a map but otherwise
+/// skipped.
+#[derive(Debug)]
+pub struct IgnoredUnprotectedHeaders;
+
+impl From<IgnoredUnprotectedHeaders> for UnprotectedHeaders<'_> {
+    fn from(_: IgnoredUnprotectedHeaders) -> Self {
+        UnprotectedHeaders::default()
+    }
+}
+
+struct UnprotectedHeadersVisitor {
+    read_kid: bool,
+}
+
+impl<'de> Visitor<'de> for UnprotectedHeadersVisitor {
+    type Value = UnprotectedHeaders<'de>;
+
+    fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
+        formatter.write_str("COSE unprotected headers")
+    }
+
+    fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
+    where
+        A: de::MapAccess<'de>,
+    {
+        let mut headers = UnprotectedHeaders::default();
+        if !self.read_kid {
+            while map.next_entry::<IgnoredAny, IgnoredAny>()?.is_some() {}
+            return Ok(headers);
+        }
+
+        while let Some(key) = map.next_key::<Value>()? {
+            if key == Value::Integer(KID_KEY) {
+                headers.kid = Some(map.next_value()?);
+            }
+            else {
+                map.next_value::<IgnoredAny>()?;
+            }
+        }
+
+        Ok(headers)
+    }
+}
+
+impl<'de: 'a, 'a> Deserialize<'de> for UnprotectedHeaders<'a> {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer.deserialize_map(UnprotectedHeadersVisitor { read_kid: true })
+    }
+}
+
+impl<'de> Deserialize<'de> for IgnoredUnprotectedHeaders {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        deserializer
+            .deserialize_map(UnprotectedHeadersVisitor { read_kid: false })
+            .map(|_| IgnoredUnprotectedHeaders)
+    }
+}
@@ -1,55 +1,191 @@
-use std::{fmt, marker::PhantomData};
+use std::{collections::BTreeMap, fmt, marker::PhantomData};
 
-use chrono::{DateTime, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDateTime, SecondsFormat, Utc};
 use serde::{
     de::{self, Error, Visitor},
-    Deserialize, Deserializer,
+    ser::SerializeStruct,
+    Deserialize, Deserializer, Serialize, Serializer,
 };
 use uuid::Uuid;
 
-use self::validation::CwtValidationError;
-use crate::{decentralised_identifier::DecentralizedIdentifier, pass::Pass};
+use self::validation::{ClaimVerificationConfig, CwtValidationError};
+use crate::{codec, decentralised_identifier::DecentralizedIdentifier, error::NzcpError, pass::Pass};
 
 pub mod validation;
+pub mod version;
 
 const CWT_TOKEN_CLAIM_KEY: i128 = 7;
 const ISSUER_CLAIM_KEY: i128 = 1;
 const NOT_BEFORE_CLAIM_KEY: i128 = 5;
 const EXPIRY_CLAIM_KEY: i128 = 4;
-const VERIFIABLE_CREDENTIAL_KEY: &'static str = "vc";
-const EXPECTED_KEYS: [&'static str; 5] = ["7 (cwt)", "1 (iss)", "5 (nbf)", "4 (exp)", "vc"];
+const VERIFIABLE_CREDENTIAL_KEY: &str = "vc";
+const EXPECTED_KEYS: [&str; 5] = ["7 (cwt)", "1 (iss)", "5 (nbf)", "4 (exp)", "vc"];
+/// The names of the standard claims, which some tools use as keys instead of the integers the specification requires
+/// (see `ClaimVerificationConfig::allow_string_claim_keys`).
+const STRING_CLAIM_KEYS: [(&str, i128); 4] = [
+    ("cti", CWT_TOKEN_CLAIM_KEY),
+    ("iss", ISSUER_CLAIM_KEY),
+    ("nbf", NOT_BEFORE_CLAIM_KEY),
+    ("exp", EXPIRY_CLAIM_KEY),
+];
+
+/// The claims of a pass, as decoded from its CWT.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CwtClaims {
+    /// The issuer that signed the pass (`iss`).
+    pub issuer: DecentralizedIdentifier,
+
+    /// When the pass becomes active (`nbf`).
+    pub not_before: DateTime<Utc>,
+
+    /// When the pass expires (`exp`).
+    pub expiry: DateTime<Utc>,
+
+    /// The unique ID of the pass (`jti`, given as `cti` in the CWT).
+    pub cwt_token_id: Uuid,
+}
+
+impl CwtClaims {
+    /// The unique ID of the pass as a `jti` URN, e.g. `urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b`.
+    pub fn jti(&self) -> String {
+        self.cwt_token_id.to_urn().to_string()
+    }
+}
+
+impl Serialize for CwtClaims {
+    /// Serialize the claims with camelCase field names, the dates as RFC 3339 strings, and the token ID as a
+    /// hyphenated UUID.
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut claims = serializer.serialize_struct("CwtClaims", 4)?;
+        claims.serialize_field("issuer", &self.issuer)?;
+        claims.serialize_field("notBefore", &self.not_before.to_rfc3339_opts(SecondsFormat::Secs, true))?;
+        claims.serialize_field("expiry", &self.expiry.to_rfc3339_opts(SecondsFormat::Secs, true))?;
+        claims.serialize_field("cwtTokenId", &self.cwt_token_id)?;
+        claims.end()
+    }
+}
 
 #[derive(Debug, PartialEq, Eq)]
-pub struct CwtClaims<'a, T> {
-    cwt_token_id: Uuid,
-    issuer: DecentralizedIdentifier<'a>,
+pub struct Cwt<'a, T> {
+    issuer: DecentralizedIdentifier,
     not_before: DateTime<Utc>,
     expiry: DateTime<Utc>,
+    /// The raw `cti` claim, only checked to be a UUID once the signature has been, so a malformed ID gets its own error.
+    cwt_token_id: &'a [u8],
     verifiable_credential: VerifiableCredential<'a, T>,
+    /// The standard claims given by name rather than integer key, with the key they should have had.
+    string_keyed_claims: Vec<(&'static str, i128)>,
 }
 
-impl<'a, T: Pass> CwtClaims<'a, T> {
-    pub fn validated_credential_subject(self) -> Result<T, CwtValidationError> {
-        self.validate()?;
-        Ok(self.verifiable_credential.credential_subject)
+impl<'a, T> Cwt<'a, T> {
+    /// The issuer the payload claims to be from (`iss`), whether or not it's trusted.
+    pub fn issuer(&self) -> &DecentralizedIdentifier {
+        &self.issuer
+    }
+
+    /// Get the claims, failing if the `cti` claim isn't a 16 byte UUID.
+    pub fn claims(&self) -> Result<CwtClaims, CwtValidationError> {
+        let cwt_token_id = Uuid::from_slice(self.cwt_token_id).map_err(|_| CwtValidationError::InvalidTokenId {
+            length: self.cwt_token_id.len(),
+        })?;
+
+        Ok(CwtClaims {
+            issuer: self.issuer.clone(),
+            not_before: self.not_before,
+            expiry: self.expiry,
+            cwt_token_id,
+        })
     }
 }
 
-fn utc_from_timestamp(epoch_seconds: i64) -> DateTime<Utc> {
-    DateTime::from_utc(NaiveDateTime::from_timestamp(epoch_seconds, 0), Utc)
+impl<'a, T: Pass> Cwt<'a, T> {
+    /// Validate the claims as of `now`, give or take `leeway`, returning the credential subject and how much of the
+    /// leeway was needed.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "validate_claims", level = "debug", skip_all, fields(issuer = %self.issuer))
+    )]
+    pub fn validated_credential_subject(
+        self,
+        now: DateTime<Utc>,
+        leeway: Duration,
+        config: &ClaimVerificationConfig,
+    ) -> Result<(T, Option<Duration>), NzcpError> {
+        let leeway_used = self.validate(now, leeway, config)?;
+        let credential_subject = serde_cbor::value::from_value(self.verifiable_credential.credential_subject)?;
+        Ok((credential_subject, leeway_used))
+    }
+
+    /// Validate the claims and get the credential subject like `validated_credential_subject`, failing with every
+    /// problem found rather than the first.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "validate_claims", level = "debug", skip_all, fields(issuer = %self.issuer))
+    )]
+    pub(crate) fn validated_credential_subject_all_errors(
+        self,
+        now: DateTime<Utc>,
+        leeway: Duration,
+        config: &ClaimVerificationConfig,
+    ) -> Result<(T, Option<Duration>), Vec<NzcpError>> {
+        let (leeway_used, mut errors) = self.validation_errors(now, leeway, config);
+        match serde_cbor::value::from_value(self.verifiable_credential.credential_subject) {
+            Ok(credential_subject) if errors.is_empty() => Ok((credential_subject, leeway_used)),
+            Ok(_) => Err(errors),
+            Err(error) => {
+                errors.push(error.into());
+                Err(errors)
+            }
+        }
+    }
+}
+
+/// The `vc` claim of CBOR encoded CWT claims as JSON (see the `codec` module for how CBOR maps onto JSON), or `None` if
+/// it can't be represented as JSON.
+pub fn verifiable_credential_json(cwt_claims_raw: &[u8]) -> Option<serde_json::Value> {
+    let mut claims: BTreeMap<serde_cbor::Value, serde_cbor::Value> = serde_cbor::from_slice(cwt_claims_raw).ok()?;
+    let verifiable_credential = claims.remove(&serde_cbor::Value::Text(String::from(VERIFIABLE_CREDENTIAL_KEY)))?;
+    codec::to_json(verifiable_credential).ok()
+}
+
+/// Convert a NumericDate claim (seconds since the unix epoch) to a date, failing for dates before the epoch or too far
+/// in the future to represent.
+fn utc_from_timestamp<E: de::Error>(epoch_seconds: i64) -> Result<DateTime<Utc>, E> {
+    (epoch_seconds >= 0)
+        .then(|| NaiveDateTime::from_timestamp_opt(epoch_seconds, 0))
+        .flatten()
+        .map(|date| DateTime::from_utc(date, Utc))
+        .ok_or_else(|| {
+            E::invalid_value(
+                de::Unexpected::Signed(epoch_seconds),
+                &"a NumericDate between 1970 and the year 262143",
+            )
+        })
+}
+
+/// Set a claim, failing if it was already set (e.g. by both its integer and string key).
+fn set_claim<T, E: de::Error>(claim: &mut Option<T>, value: T, name: &str) -> Result<(), E> {
+    if claim.is_some() {
+        return Err(E::custom(format!("duplicate claim {}", name)));
+    }
+    *claim = Some(value);
+    Ok(())
 }
 
 /// CWT claims contain integer keys, so we need to manually deserialize.
-struct CwtClaimsVisitor<T>(PhantomData<fn() -> T>);
+struct CwtVisitor<T>(PhantomData<fn() -> T>);
 
-impl<'de, T> Visitor<'de> for CwtClaimsVisitor<T>
+impl<'de, T> Visitor<'de> for CwtVisitor<T>
 where
     T: Deserialize<'de>,
 {
-    type Value = CwtClaims<'de, T>;
+    type Value = Cwt<'de, T>;
 
     fn expecting(&self, formatter: &mut fmt::Formatter<'_>) -> fmt::Result {
-        formatter.write_str("COSE protected headers")
+        formatter.write_str("CWT claims")
     }
 
     fn visit_map<A>(self, mut map: A) -> Result<Self::Value, A::Error>
@@ -61,34 +197,45 @@ where
         let mut not_before = None;
         let mut expiry = None;
         let mut verifiable_credential = None;
+        let mut string_keyed_claims = Vec::new();
 
         while let Some(key) = map.next_key()? {
             use serde_cbor::Value::{Integer, Text};
-            match key {
-                Integer(CWT_TOKEN_CLAIM_KEY) => cwt_token_id = Some(map.next_value()?),
-                Integer(ISSUER_CLAIM_KEY) => issuer = Some(map.next_value()?),
-                Integer(NOT_BEFORE_CLAIM_KEY) => not_before = Some(utc_from_timestamp(map.next_value()?)),
-                Integer(EXPIRY_CLAIM_KEY) => expiry = Some(utc_from_timestamp(map.next_value()?)),
-                Text(text_key) => {
-                    if text_key == VERIFIABLE_CREDENTIAL_KEY {
-                        verifiable_credential = Some(map.next_value()?);
-                    }
-                    else {
-                        return Err(A::Error::unknown_field(&text_key, &EXPECTED_KEYS));
-                    }
+            // string keys are accepted here, and rejected in validation unless allowed
+            let claim_key = match key {
+                Integer(key) => key,
+                Text(text_key) if text_key == VERIFIABLE_CREDENTIAL_KEY => {
+                    set_claim(&mut verifiable_credential, map.next_value()?, VERIFIABLE_CREDENTIAL_KEY)?;
+                    continue;
                 }
+                Text(text_key) => match STRING_CLAIM_KEYS.iter().find(|(name, _)| *name == text_key) {
+                    Some(&string_key) => {
+                        string_keyed_claims.push(string_key);
+                        string_key.1
+                    }
+                    None => return Err(A::Error::unknown_field(&text_key, &EXPECTED_KEYS)),
+                },
                 _ => return Err(A::Error::unknown_field(&format!("{:?}", key), &EXPECTED_KEYS)),
+            };
+
+            match claim_key {
+                CWT_TOKEN_CLAIM_KEY => set_claim(&mut cwt_token_id, map.next_value()?, "7 (cti)")?,
+                ISSUER_CLAIM_KEY => set_claim(&mut issuer, map.next_value()?, "1 (iss)")?,
+                NOT_BEFORE_CLAIM_KEY => set_claim(&mut not_before, utc_from_timestamp(map.next_value()?)?, "5 (nbf)")?,
+                EXPIRY_CLAIM_KEY => set_claim(&mut expiry, utc_from_timestamp(map.next_value()?)?, "4 (exp)")?,
+                _ => return Err(A::Error::unknown_field(&claim_key.to_string(), &EXPECTED_KEYS)),
             }
         }
 
         match (cwt_token_id, issuer, not_before, expiry, verifiable_credential) {
             (Some(cwt_token_id), Some(issuer), Some(not_before), Some(expiry), Some(verifiable_credential)) => {
-                Ok(CwtClaims {
-                    cwt_token_id,
+                Ok(Cwt {
                     issuer,
                     not_before,
                     expiry,
+                    cwt_token_id,
                     verifiable_credential,
+                    string_keyed_claims,
                 })
             }
             (None, ..) => Err(A::Error::missing_field("7 (cwt)")),
@@ -100,7 +247,7 @@ where
     }
 }
 
-impl<'de: 'a, 'a, T> Deserialize<'de> for CwtClaims<'a, T>
+impl<'de: 'a, 'a, T> Deserialize<'de> for Cwt<'a, T>
 where
     T: Deserialize<'de>,
 {
@@ -108,7 +255,7 @@ where
     where
         D: Deserializer<'de>,
     {
-        deserializer.deserialize_map(CwtClaimsVisitor::<T>(PhantomData))
+        deserializer.deserialize_map(CwtVisitor::<T>(PhantomData))
     }
 }
 
@@ -129,20 +276,36 @@ struct VerifiableCredential<'a, T> {
     /// ```notrust
     /// ["VerifiableCredential", "PublicCovidPass"]
     /// ```
+    ///
+    /// Either order is accepted, as some issuers list the pass type first.
     #[serde(rename = "type")]
-    _type: (&'a str, &'a str),
+    _type: Vec<&'a str>,
 
     /// Version property of the New Zealand Covid Pass. This property MUST be present and its value MUST be a string who’s value corresponds to a valid version identifier as defined by semver. For the purposes of this version of the specification this value MUST be 1.0.0.
+    ///
+    /// Any `1.x.x` version is accepted, see `CredentialVersion`.
     version: &'a str,
 
     /// Credential Subject property MUST be present and its value MUST be a JSON object with properties determined by the declared pass type for the pass.
+    ///
+    /// Only deserialized as the pass type `T` once the credential's type is known to match, so a different type of pass
+    /// fails with `CwtValidationError::InvalidType` rather than on its fields.
     #[serde(rename = "credentialSubject")]
-    credential_subject: T,
+    credential_subject: serde_cbor::Value,
+
+    #[serde(skip)]
+    pass_type: PhantomData<fn() -> T>,
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::BTreeMap;
+
+    use chrono::TimeZone;
+    use serde_cbor::Value;
+
     use super::*;
+    use crate::{testing::test_vectors::scanned_at, PublicCovidPass};
 
     #[test]
     fn deserialize_cbor() {
@@ -150,25 +313,190 @@ mod tests {
 
         let value: serde_cbor::Value = serde_cbor::from_slice(&bytes).unwrap();
         dbg!(value);
-        let claims: CwtClaims<'_, &'_ str> = serde_cbor::from_slice(&bytes).unwrap();
+        let cwt: Cwt<'_, &'_ str> = serde_cbor::from_slice(&bytes).unwrap();
 
         assert_eq!(
-            claims,
-            CwtClaims {
-                cwt_token_id: Uuid::parse_str("urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b").unwrap(),
-                issuer: DecentralizedIdentifier::Web("nzcp.covid19.health.nz"),
-                not_before: utc_from_timestamp(1635883530),
-                expiry: utc_from_timestamp(1951416330),
+            cwt,
+            Cwt {
+                issuer: DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz")),
+                not_before: Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+                expiry: Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+                cwt_token_id: &hex::decode("60a4f54d4e304332be33ad78b1eafa4b").unwrap(),
                 verifiable_credential: VerifiableCredential {
                     context: vec![
                         "https://www.w3.org/2018/credentials/v1",
                         "https://nzcp.covid19.health.nz/contexts/v1"
                     ],
-                    _type: ("VerifiableCredential", "PublicCovidPass"),
+                    _type: vec!["VerifiableCredential", "PublicCovidPass"],
                     version: "1.0.0",
-                    credential_subject: "helloworld",
-                }
+                    credential_subject: serde_cbor::Value::Text(String::from("helloworld")),
+                    pass_type: PhantomData,
+                },
+                string_keyed_claims: Vec::new(),
             }
-        )
+        );
+        let claims = cwt.claims().unwrap();
+        assert_eq!(
+            claims.cwt_token_id,
+            Uuid::parse_str("60a4f54d-4e30-4332-be33-ad78b1eafa4b").unwrap()
+        );
+        assert_eq!(claims.jti(), "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b");
+    }
+
+    #[test]
+    fn token_id_length() {
+        // the claims from `deserialize_cbor`, with the given cti
+        let token_id = |cwt_token_id: Value| {
+            let mut bytes = hex::decode("a501781e6469643a7765623a6e7a63702e636f76696431392e6865616c74682e6e7a051a61819a0a041a7450400a627663a46840636f6e7465787482782668747470733a2f2f7777772e77332e6f72672f323031382f63726564656e7469616c732f7631782a68747470733a2f2f6e7a63702e636f76696431392e6865616c74682e6e7a2f636f6e74657874732f76316776657273696f6e65312e302e306474797065827456657269666961626c6543726564656e7469616c6f5075626c6963436f766964506173737163726564656e7469616c5375626a6563746A68656C6C6F776F726C6407").unwrap();
+            bytes.extend(serde_cbor::to_vec(&cwt_token_id).unwrap());
+            serde_cbor::from_slice::<Cwt<'_, &str>>(&bytes)
+                .unwrap()
+                .claims()
+                .map(|claims| claims.cwt_token_id)
+        };
+
+        assert_eq!(token_id(Value::Bytes(vec![1; 16])), Ok(Uuid::from_bytes([1; 16])));
+        assert_eq!(
+            token_id(Value::Bytes(vec![1; 15])),
+            Err(CwtValidationError::InvalidTokenId { length: 15 })
+        );
+        // the text form of a UUID isn't accepted, as the claim is its bytes
+        assert_eq!(
+            token_id(Value::Text(String::from("60a4f54d-4e30-4332-be33-ad78b1eafa4b"))),
+            Err(CwtValidationError::InvalidTokenId { length: 36 })
+        );
+    }
+
+    #[test]
+    fn string_claim_keys() {
+        let text = |text: &str| Value::Text(String::from(text));
+        let claims = |keys: &[Value]| {
+            let values = [
+                Value::Bytes(vec![1; 16]),
+                text("did:web:nzcp.covid19.health.nz"),
+                Value::Integer(1635883530),
+                Value::Integer(1951416330),
+            ];
+            let verifiable_credential = Value::Map(BTreeMap::from([
+                (
+                    text("@context"),
+                    Value::Array(vec![
+                        text("https://www.w3.org/2018/credentials/v1"),
+                        text(PublicCovidPass::CONTEXT_URL),
+                    ]),
+                ),
+                (text("version"), text("1.0.0")),
+                (
+                    text("type"),
+                    Value::Array(vec![
+                        text("VerifiableCredential"),
+                        text(PublicCovidPass::CREDENTIAL_TYPE),
+                    ]),
+                ),
+                (
+                    text("credentialSubject"),
+                    Value::Map(BTreeMap::from([
+                        (text("givenName"), text("Jack")),
+                        (text("dob"), text("1960-04-16")),
+                    ])),
+                ),
+            ]));
+            // encoded by hand, as a map would merge duplicate keys, giving the keys the cti, iss, nbf, and exp values in turn
+            let mut bytes = vec![0xa0 + keys.len() as u8 + 1];
+            for (key, value) in keys.iter().zip(values.iter().cycle()) {
+                bytes.extend(serde_cbor::to_vec(key).unwrap());
+                bytes.extend(serde_cbor::to_vec(value).unwrap());
+            }
+            bytes.extend(serde_cbor::to_vec(&text("vc")).unwrap());
+            bytes.extend(serde_cbor::to_vec(&verifiable_credential).unwrap());
+            bytes
+        };
+        let validate = |bytes: &[u8], allow_string_claim_keys: bool| {
+            let config = ClaimVerificationConfig {
+                allow_string_claim_keys,
+                ..Default::default()
+            };
+            serde_cbor::from_slice::<Cwt<'_, PublicCovidPass>>(bytes)
+                .map_err(|error| error.to_string())?
+                .validate(scanned_at(), Duration::zero(), &config)
+                .map_err(|error| error.to_string())
+        };
+        let integer_keys = [
+            Value::Integer(7),
+            Value::Integer(1),
+            Value::Integer(5),
+            Value::Integer(4),
+        ];
+        let string_keys = [text("cti"), text("iss"), text("nbf"), text("exp")];
+
+        let integer_keyed = claims(&integer_keys);
+        assert_eq!(validate(&integer_keyed, false), Ok(None));
+        assert_eq!(validate(&integer_keyed, true), Ok(None));
+
+        let string_keyed = claims(&string_keys);
+        assert_eq!(
+            validate(&string_keyed, false),
+            Err(String::from(
+                "Invalid CWT: StringClaimKey { key: \"cti\", expected: 7 }"
+            ))
+        );
+        assert_eq!(validate(&string_keyed, true), Ok(None));
+        assert_eq!(
+            serde_cbor::from_slice::<Cwt<'_, PublicCovidPass>>(&string_keyed)
+                .unwrap()
+                .claims()
+                .unwrap(),
+            serde_cbor::from_slice::<Cwt<'_, PublicCovidPass>>(&integer_keyed)
+                .unwrap()
+                .claims()
+                .unwrap()
+        );
+
+        // cti given by both its integer and string key
+        let mixed = claims(&[
+            Value::Integer(7),
+            Value::Integer(1),
+            Value::Integer(5),
+            Value::Integer(4),
+            text("cti"),
+        ]);
+        for allow_string_claim_keys in [false, true] {
+            assert!(validate(&mixed, allow_string_claim_keys)
+                .unwrap_err()
+                .contains("duplicate claim 7 (cti)"));
+        }
+        let duplicated = claims(&[
+            Value::Integer(7),
+            Value::Integer(1),
+            Value::Integer(5),
+            Value::Integer(4),
+            Value::Integer(7),
+        ]);
+        assert!(validate(&duplicated, true)
+            .unwrap_err()
+            .contains("duplicate claim 7 (cti)"));
+    }
+
+    #[test]
+    fn numeric_dates_out_of_range() {
+        let claims = |not_before: i64, expiry: i64| {
+            let claims: BTreeMap<Value, Value> = vec![
+                (Value::Integer(7), Value::Bytes(vec![0; 16])),
+                (
+                    Value::Integer(1),
+                    Value::Text(String::from("did:web:nzcp.covid19.health.nz")),
+                ),
+                (Value::Integer(5), Value::Integer(not_before.into())),
+                (Value::Integer(4), Value::Integer(expiry.into())),
+                (Value::Text(String::from("vc")), Value::Null),
+            ]
+            .into_iter()
+            .collect();
+            serde_cbor::to_vec(&claims).unwrap()
+        };
+        let error = |bytes: Vec<u8>| serde_cbor::from_slice::<Cwt<'_, &str>>(&bytes).unwrap_err().to_string();
+
+        assert!(error(claims(-1, 1951416330)).contains("invalid value: integer `-1`"));
+        assert!(error(claims(1635883530, i64::MAX)).contains("a NumericDate between 1970"));
     }
 }
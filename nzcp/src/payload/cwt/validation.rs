@@ -1,78 +1,701 @@
-use chrono::{DateTime, Utc};
+use chrono::{DateTime, Duration, Utc};
 use thiserror::Error;
+use uuid::Uuid;
 
-use super::{CwtClaims, DecentralizedIdentifier, VerifiableCredential};
-use crate::{pass::Pass, payload::cose::signature::verify::CoseVerificationError};
+use super::{version::CredentialVersion, Cwt, CwtClaims, DecentralizedIdentifier, VerifiableCredential};
+use crate::{error::NzcpError, pass::Pass, payload::cose::signature::verify::CoseVerificationError};
 
-#[derive(Debug, Error, PartialEq, Eq)]
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
 pub enum CwtValidationError {
-    #[error("token not yet valid as the 'not before date' is in the future (not before: {0:?})")]
-    NotYetActive(DateTime<Utc>),
-    #[error("token has expired (expired: {0:?})")]
-    Expired(DateTime<Utc>),
-    #[error("credential uses an unsupported version: {0}")]
-    UnsupportedVersion(String),
-    #[error("credential uses invalid contexts: {0:?}")]
-    InvalidContext(Vec<String>),
-    #[error("credential uses invalid type: [{0:?}, {0:?}]")]
-    InvalidType(String, String),
+    #[error("credential version {0:?} is not a major.minor.patch version")]
+    InvalidVersion(String),
+    #[error("credential uses invalid contexts {found:?} (missing {missing:?}, unexpected {unexpected:?})")]
+    InvalidContext {
+        found: Vec<String>,
+        missing: Vec<String>,
+        unexpected: Vec<String>,
+    },
+    #[error("credential has type {found:?}, expected {expected:?}")]
+    InvalidType { expected: Vec<String>, found: Vec<String> },
+    #[error("token ID (cti) must be a 16 byte UUID, found {length} bytes")]
+    InvalidTokenId { length: usize },
+    #[error("claim {key:?} must use the integer key {expected}")]
+    StringClaimKey { key: String, expected: i128 },
+    #[error("pass expires ({expiry}) before it becomes active ({not_before})")]
+    ExpiresBeforeActive {
+        not_before: DateTime<Utc>,
+        expiry: DateTime<Utc>,
+    },
 }
 
-impl<'a, P: Pass> CwtClaims<'a, P> {
+/// Which of the claim checks in `verify_all_claims` are run, see `VerifierConfig::claim_verification` (defaults to
+/// all of them).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimVerificationConfig {
+    /// Accept passes that aren't active yet (`nbf`), e.g. for testing with passes issued in the future.
+    pub skip_not_before_check: bool,
+
+    /// Accept expired passes (`exp`), e.g. for testing with old example passes.
+    pub skip_expiry_check: bool,
+
+    /// Accept the standard claims keyed by name (`"iss"`, `"nbf"`, `"exp"`, and `"cti"`) rather than by integer, as
+    /// some test tooling does. A claim given by both its name and integer key is rejected either way.
+    pub allow_string_claim_keys: bool,
+
+    /// Accept credentials with types other than `VerifiableCredential` and the pass's type in `vc.type`, which the
+    /// specification requires to hold only those two. The two required types are accepted in either order regardless.
+    pub allow_extra_credential_types: bool,
+
+    /// How strictly `vc.@context` is checked (defaults to `ContextPolicy::RequireBase`).
+    pub context_policy: ContextPolicy,
+}
+
+/// How strictly a credential's `@context` is checked, see `ClaimVerificationConfig::context_policy`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ContextPolicy {
+    /// Require exactly the W3C credentials context followed by the pass's context, as the specification does.
+    Exact,
+    /// Require the W3C credentials context and the pass's context in any order, accepting extension contexts.
+    #[default]
+    RequireBase,
+    /// Don't check the contexts.
+    Ignore,
+}
+
+/// Check a pass's claims as of now, failing with the first check that doesn't pass.
+///
+/// The issuer and token ID are already known to be a `did:web` DID and a UUID, as `CwtClaims` can't hold anything else,
+/// and whether the issuer is trusted is checked along with the signature. The verifiable credential is checked
+/// separately, as it isn't one of the `CwtClaims`.
+pub fn verify_all_claims(claims: &CwtClaims, config: &ClaimVerificationConfig) -> Result<(), NzcpError> {
+    verify_all_claims_at(claims, config, Utc::now(), Duration::zero()).map(|_| ())
+}
+
+/// Check a pass's claims like `verify_all_claims`, but as of `now`, give or take `leeway`, returning how much of the
+/// leeway was needed to accept the pass, if any.
+pub fn verify_all_claims_at(
+    claims: &CwtClaims,
+    config: &ClaimVerificationConfig,
+    now: DateTime<Utc>,
+    leeway: Duration,
+) -> Result<Option<Duration>, NzcpError> {
+    first_error(all_claim_errors(claims, config, now, leeway))
+}
+
+/// Run every check `verify_all_claims_at` would, returning how much leeway was needed and each failure in the order they
+/// are checked, rather than stopping at the first.
+pub(crate) fn all_claim_errors(
+    claims: &CwtClaims,
+    config: &ClaimVerificationConfig,
+    now: DateTime<Utc>,
+    leeway: Duration,
+) -> (Option<Duration>, Vec<NzcpError>) {
+    let mut errors = Vec::new();
+    errors.extend(check_validity_period(claims).err().map(NzcpError::from));
+    let mut leeway_used = |check: Result<Option<Duration>, NzcpError>| match check {
+        Ok(leeway_used) => leeway_used,
+        Err(error) => {
+            errors.push(error);
+            None
+        }
+    };
+    let not_before_leeway = match config.skip_not_before_check {
+        true => None,
+        false => leeway_used(check_not_before(claims, now, leeway)),
+    };
+    let expiry_leeway = match config.skip_expiry_check {
+        true => None,
+        false => leeway_used(check_expiry(claims, now, leeway)),
+    };
+    (not_before_leeway.or(expiry_leeway), errors)
+}
+
+/// The first of the errors from checks that collect every failure, or the result if there were none.
+pub(crate) fn first_error<T>((result, errors): (T, Vec<NzcpError>)) -> Result<T, NzcpError> {
+    match errors.into_iter().next() {
+        Some(error) => Err(error),
+        None => Ok(result),
+    }
+}
+
+/// Check the pass is active at some point, i.e. it doesn't expire before (or as soon as) it becomes active.
+fn check_validity_period(claims: &CwtClaims) -> Result<(), CwtValidationError> {
+    if claims.expiry <= claims.not_before {
+        Err(CwtValidationError::ExpiresBeforeActive {
+            not_before: claims.not_before,
+            expiry: claims.expiry,
+        })
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Check the pass becomes active no more than `max_future_nbf` after `now`, see `VerifierConfig::max_future_nbf`.
+pub(crate) fn check_max_future_nbf(
+    claims: &CwtClaims,
+    now: DateTime<Utc>,
+    max_future_nbf: Duration,
+) -> Result<(), NzcpError> {
+    let not_before = claims.not_before;
+    // an overflow is far enough in the future to allow any pass
+    if now
+        .checked_add_signed(max_future_nbf)
+        .is_some_and(|latest| latest < not_before)
+    {
+        Err(NzcpError::PassTooFarInFuture {
+            not_before,
+            checked_at: now,
+        })
+    }
+    else {
+        Ok(())
+    }
+}
+
+/// Check the pass is active as of `now`, give or take `leeway`.
+fn check_not_before(claims: &CwtClaims, now: DateTime<Utc>, leeway: Duration) -> Result<Option<Duration>, NzcpError> {
+    let not_before = claims.not_before;
+    // an overflow is far enough in the future to not need the leeway
+    if now.checked_add_signed(leeway).is_some_and(|now| now < not_before) {
+        Err(NzcpError::PassNotYetActive {
+            not_before,
+            checked_at: now,
+        })
+    }
+    else if now < not_before {
+        Ok(Some(not_before - now))
+    }
+    else {
+        Ok(None)
+    }
+}
+
+/// Check the pass hasn't expired as of `now`, give or take `leeway`.
+fn check_expiry(claims: &CwtClaims, now: DateTime<Utc>, leeway: Duration) -> Result<Option<Duration>, NzcpError> {
+    let expiry = claims.expiry;
+    if expiry.checked_add_signed(leeway).is_some_and(|expiry| expiry <= now) {
+        Err(NzcpError::PassExpired {
+            expiry,
+            checked_at: now,
+        })
+    }
+    else if expiry <= now {
+        Ok(Some(now - expiry))
+    }
+    else {
+        Ok(None)
+    }
+}
+
+impl<'a, P: Pass> Cwt<'a, P> {
     /// Get the issuer of the payload, failing if it is not trusted.
     pub fn verify_issuer(
         &self,
-        trusted_issuers: &[DecentralizedIdentifier<'_>],
-    ) -> Result<DecentralizedIdentifier<'_>, CoseVerificationError> {
+        trusted_issuers: &[DecentralizedIdentifier],
+    ) -> Result<&DecentralizedIdentifier, CoseVerificationError> {
         if !trusted_issuers.contains(&self.issuer) {
             Err(CoseVerificationError::UntrustedIssuer(self.issuer.to_string()))
         }
         else {
-            Ok(self.issuer)
+            Ok(&self.issuer)
         }
     }
-    pub fn validate(&self) -> Result<(), CwtValidationError> {
-        use CwtValidationError::*;
-
-        self.verifiable_credential.validate()?;
+    /// Check the credential is valid, and the claims pass the checks in `config` as of `now`, give or take `leeway`
+    /// (see `verify_all_claims_at`).
+    ///
+    /// Returns how much of the leeway was needed to accept the pass, if any. The pass being out of its validity period
+    /// fails with `NzcpError::PassNotYetActive` or `NzcpError::PassExpired` rather than a `CwtValidationError`.
+    pub fn validate(
+        &self,
+        now: DateTime<Utc>,
+        leeway: Duration,
+        config: &ClaimVerificationConfig,
+    ) -> Result<Option<Duration>, NzcpError> {
+        first_error(self.validation_errors(now, leeway, config))
+    }
 
-        // issuer would already have been verified here
-        let now = Utc::now();
-        if now < self.not_before {
-            Err(NotYetActive(self.not_before))
-        }
-        else if self.expiry <= now {
-            Err(Expired(self.expiry))
-        }
-        else {
-            Ok(())
+    /// Run every check `validate` would, returning how much leeway was needed and each failure in the order they are
+    /// checked, rather than stopping at the first.
+    pub(crate) fn validation_errors(
+        &self,
+        now: DateTime<Utc>,
+        leeway: Duration,
+        config: &ClaimVerificationConfig,
+    ) -> (Option<Duration>, Vec<NzcpError>) {
+        let mut errors = Vec::new();
+        errors.extend(self.verifiable_credential.validate(config).err());
+        if let Some(&(key, expected)) = self
+            .string_keyed_claims
+            .first()
+            .filter(|_| !config.allow_string_claim_keys)
+        {
+            errors.push(
+                CwtValidationError::StringClaimKey {
+                    key: key.to_owned(),
+                    expected,
+                }
+                .into(),
+            );
         }
+        let claims = self.claims().unwrap_or_else(|error| {
+            errors.push(error.into());
+            // the token ID isn't used by the claim checks
+            CwtClaims {
+                issuer: self.issuer.clone(),
+                not_before: self.not_before,
+                expiry: self.expiry,
+                cwt_token_id: Uuid::nil(),
+            }
+        });
+
+        let (leeway_used, claim_errors) = all_claim_errors(&claims, config, now, leeway);
+        errors.extend(claim_errors);
+        (leeway_used, errors)
     }
 }
 
-const MANDATAORY_CONTEXT_URL: &'static str = "https://www.w3.org/2018/credentials/v1";
-const MANDATAORY_TYPE: &'static str = "VerifiableCredential";
+/// The context every verifiable credential has, before that of its pass type.
+pub(crate) const MANDATORY_CONTEXT_URL: &str = "https://www.w3.org/2018/credentials/v1";
+/// The type every verifiable credential has, before that of its pass type.
+pub(crate) const MANDATORY_TYPE: &str = "VerifiableCredential";
 
 impl<'a, T> VerifiableCredential<'a, T>
 where
     T: Pass,
 {
-    pub fn validate(&self) -> Result<(), CwtValidationError> {
-        use CwtValidationError::*;
+    /// Check the credential's type, version and context, in that order, stopping at the first that's invalid.
+    ///
+    /// An unsupported version fails with `NzcpError::UnsupportedCredentialVersion` rather than a
+    /// `CwtValidationError`, as the rest of the claims can't be relied on.
+    pub fn validate(&self, config: &ClaimVerificationConfig) -> Result<(), NzcpError> {
+        // the type is checked first, as a different type of pass may have a different context too
+        if !self.has_expected_types(config.allow_extra_credential_types) {
+            return Err(CwtValidationError::InvalidType {
+                expected: vec![MANDATORY_TYPE.to_owned(), T::CREDENTIAL_TYPE.to_owned()],
+                found: self._type.iter().map(|str| String::from(*str)).collect(),
+            }
+            .into());
+        }
+
+        let version = CredentialVersion::parse(self.version)?;
+        if !version.is_supported() {
+            return Err(NzcpError::UnsupportedCredentialVersion(version));
+        }
+        Ok(self.check_context(config.context_policy)?)
+    }
+
+    fn check_context(&self, policy: ContextPolicy) -> Result<(), CwtValidationError> {
+        let required = [MANDATORY_CONTEXT_URL, T::CONTEXT_URL];
+        let valid = match policy {
+            ContextPolicy::Exact => self.context == required,
+            ContextPolicy::RequireBase => required.iter().all(|context| self.context.contains(context)),
+            ContextPolicy::Ignore => true,
+        };
+        if valid {
+            return Ok(());
+        }
+
+        let strings = |contexts: Vec<&str>| contexts.into_iter().map(String::from).collect();
+        let unexpected = match policy {
+            ContextPolicy::Exact => self
+                .context
+                .iter()
+                .filter(|context| !required.contains(context))
+                .copied()
+                .collect(),
+            _ => Vec::new(),
+        };
+        Err(CwtValidationError::InvalidContext {
+            found: strings(self.context.clone()),
+            missing: strings(
+                required
+                    .into_iter()
+                    .filter(|context| !self.context.contains(context))
+                    .collect(),
+            ),
+            unexpected: strings(unexpected),
+        })
+    }
+
+    /// Whether `vc.type` holds `VerifiableCredential` and the pass's type in any order, and nothing else unless
+    /// `allow_extra` (duplicates count as extras).
+    fn has_expected_types(&self, allow_extra: bool) -> bool {
+        let has_required = self._type.contains(&MANDATORY_TYPE) && self._type.contains(&T::CREDENTIAL_TYPE);
+        has_required && (allow_extra || self._type.len() == 2)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
 
-        if self.version != "1.0.0" {
-            Err(UnsupportedVersion(self.version.to_owned()))
+    use super::*;
+    use crate::PublicCovidPass;
+
+    fn cwt(not_before: DateTime<Utc>, expiry: DateTime<Utc>) -> Cwt<'static, PublicCovidPass> {
+        Cwt {
+            issuer: DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz")),
+            not_before,
+            expiry,
+            cwt_token_id: &[0; 16],
+            verifiable_credential: VerifiableCredential {
+                context: vec![MANDATORY_CONTEXT_URL, PublicCovidPass::CONTEXT_URL],
+                _type: vec![MANDATORY_TYPE, PublicCovidPass::CREDENTIAL_TYPE],
+                version: "1.0.0",
+                credential_subject: serde_json::from_str(
+                    r#"{"givenName": "Jack", "familyName": "Sparrow", "dob": "1960-04-16"}"#,
+                )
+                .unwrap(),
+                pass_type: std::marker::PhantomData,
+            },
+            string_keyed_claims: Vec::new(),
         }
-        else if self.context.get(0) != Some(&MANDATAORY_CONTEXT_URL) || self.context.get(1) != Some(&T::CONTEXT_URL) {
-            Err(InvalidContext(
-                self.context.iter().map(|str| String::from(*str)).collect(),
-            ))
+    }
+
+    /// The `CwtValidationError` a failed `VerifiableCredential::validate` wraps.
+    fn cwt_error(error: NzcpError) -> CwtValidationError {
+        match error {
+            NzcpError::InvalidCWT(error) => error,
+            error => panic!("expected a CWT validation error, found {:?}", error),
         }
-        else if self._type != (MANDATAORY_TYPE, &T::CREDENTIAL_TYPE) {
-            Err(InvalidType(self._type.0.to_owned(), self._type.1.to_owned()))
+    }
+
+    fn validate(
+        cwt: &Cwt<'_, PublicCovidPass>,
+        now: DateTime<Utc>,
+        leeway: Duration,
+    ) -> Result<Option<Duration>, NzcpError> {
+        cwt.validate(now, leeway, &ClaimVerificationConfig::default())
+    }
+
+    #[test]
+    fn not_before_leeway() {
+        let not_before = Utc.ymd(2021, 11, 2).and_hms(20, 5, 30);
+        let cwt = cwt(not_before, not_before + Duration::days(365));
+        let leeway = Duration::seconds(30);
+
+        assert_eq!(validate(&cwt, not_before, Duration::zero()), Ok(None));
+        assert_eq!(
+            validate(&cwt, not_before - Duration::seconds(1), Duration::zero()),
+            Err(NzcpError::PassNotYetActive {
+                not_before,
+                checked_at: not_before - Duration::seconds(1)
+            })
+        );
+        assert_eq!(
+            validate(&cwt, not_before - Duration::seconds(20), leeway),
+            Ok(Some(Duration::seconds(20)))
+        );
+        assert_eq!(validate(&cwt, not_before - leeway, leeway), Ok(Some(leeway)));
+        assert_eq!(
+            validate(&cwt, not_before - leeway - Duration::seconds(1), leeway),
+            Err(NzcpError::PassNotYetActive {
+                not_before,
+                checked_at: not_before - leeway - Duration::seconds(1)
+            })
+        );
+    }
+
+    #[test]
+    fn expiry_leeway() {
+        let expiry = Utc.ymd(2031, 11, 2).and_hms(20, 5, 30);
+        let cwt = cwt(expiry - Duration::days(365), expiry);
+        let leeway = Duration::seconds(30);
+
+        assert_eq!(
+            validate(&cwt, expiry - Duration::seconds(1), Duration::zero()),
+            Ok(None)
+        );
+        assert_eq!(
+            validate(&cwt, expiry, Duration::zero()),
+            Err(NzcpError::PassExpired {
+                expiry,
+                checked_at: expiry
+            })
+        );
+        assert_eq!(validate(&cwt, expiry, leeway), Ok(Some(Duration::zero())));
+        assert_eq!(
+            validate(&cwt, expiry + leeway - Duration::seconds(1), leeway),
+            Ok(Some(leeway - Duration::seconds(1)))
+        );
+        assert_eq!(
+            validate(&cwt, expiry + leeway, leeway),
+            Err(NzcpError::PassExpired {
+                expiry,
+                checked_at: expiry + leeway
+            })
+        );
+    }
+
+    #[test]
+    fn now() {
+        let now = Utc::now();
+        let config = ClaimVerificationConfig::default();
+
+        let claims = cwt(now - Duration::days(1), now + Duration::days(1)).claims().unwrap();
+        assert_eq!(verify_all_claims(&claims, &config), Ok(()));
+        let claims = cwt(now + Duration::days(1), now + Duration::days(2)).claims().unwrap();
+        assert!(matches!(
+            verify_all_claims(&claims, &config),
+            Err(NzcpError::PassNotYetActive { .. })
+        ));
+        let claims = cwt(now - Duration::days(2), now - Duration::days(1)).claims().unwrap();
+        assert!(matches!(
+            verify_all_claims(&claims, &config),
+            Err(NzcpError::PassExpired { .. })
+        ));
+    }
+
+    #[test]
+    fn skipped_checks() {
+        let not_before = Utc.ymd(2021, 11, 2).and_hms(20, 5, 30);
+        let expiry = Utc.ymd(2031, 11, 2).and_hms(20, 5, 30);
+        let claims = cwt(not_before, expiry).claims().unwrap();
+        let verify = |skip_not_before_check, skip_expiry_check, now| {
+            let config = ClaimVerificationConfig {
+                skip_not_before_check,
+                skip_expiry_check,
+                ..Default::default()
+            };
+            verify_all_claims_at(&claims, &config, now, Duration::zero())
+        };
+        let before = not_before - Duration::days(1);
+        let after = expiry + Duration::days(1);
+
+        assert!(matches!(
+            verify(false, true, before),
+            Err(NzcpError::PassNotYetActive { .. })
+        ));
+        assert_eq!(verify(true, false, before), Ok(None));
+        assert!(matches!(verify(true, false, after), Err(NzcpError::PassExpired { .. })));
+        assert_eq!(verify(false, true, after), Ok(None));
+        assert_eq!(verify(true, true, after), Ok(None));
+    }
+
+    #[test]
+    fn max_future_nbf() {
+        let not_before = Utc.ymd(2021, 11, 2).and_hms(20, 5, 30);
+        let claims = cwt(not_before, not_before + Duration::days(365)).claims().unwrap();
+        let check = |now| check_max_future_nbf(&claims, now, Duration::days(1));
+
+        assert_eq!(check(not_before - Duration::days(1)), Ok(()));
+        assert_eq!(check(not_before), Ok(()));
+        assert_eq!(check(not_before + Duration::days(1)), Ok(()));
+        let checked_at = not_before - Duration::days(1) - Duration::seconds(1);
+        assert_eq!(
+            check(checked_at),
+            Err(NzcpError::PassTooFarInFuture { not_before, checked_at })
+        );
+        assert_eq!(
+            check(checked_at).unwrap_err().to_string(),
+            "Pass is not active until 2 November 2021 20:05:30 UTC, too long after it was checked at 1 November 2021 \
+             20:05:29 UTC"
+        );
+    }
+
+    #[test]
+    fn expires_before_active() {
+        let not_before = Utc.ymd(2021, 11, 2).and_hms(20, 5, 30);
+        let error = CwtValidationError::ExpiresBeforeActive {
+            not_before,
+            expiry: not_before,
+        };
+
+        let claims = cwt(not_before, not_before).claims().unwrap();
+        assert_eq!(check_validity_period(&claims), Err(error.clone()));
+        // checked even when the validity period isn't
+        let config = ClaimVerificationConfig {
+            skip_not_before_check: true,
+            skip_expiry_check: true,
+            ..Default::default()
+        };
+        assert_eq!(
+            verify_all_claims_at(&claims, &config, not_before, Duration::zero()),
+            Err(NzcpError::InvalidCWT(error))
+        );
+
+        let claims = cwt(not_before, not_before + Duration::seconds(1)).claims().unwrap();
+        assert_eq!(check_validity_period(&claims), Ok(()));
+    }
+
+    #[test]
+    fn credential_types() {
+        let validate = |types: &[&'static str], allow_extra_credential_types| {
+            let mut cwt = cwt(
+                Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+                Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            );
+            cwt.verifiable_credential._type = types.to_vec();
+            let config = ClaimVerificationConfig {
+                allow_extra_credential_types,
+                ..Default::default()
+            };
+            cwt.verifiable_credential.validate(&config).map_err(cwt_error)
+        };
+        let invalid_type = |found: &[&str]| {
+            Err(CwtValidationError::InvalidType {
+                expected: vec![String::from("VerifiableCredential"), String::from("PublicCovidPass")],
+                found: found.iter().map(|str| String::from(*str)).collect(),
+            })
+        };
+
+        for allow_extra in [false, true] {
+            assert_eq!(
+                validate(&["VerifiableCredential", "PublicCovidPass"], allow_extra),
+                Ok(())
+            );
+            assert_eq!(
+                validate(&["PublicCovidPass", "VerifiableCredential"], allow_extra),
+                Ok(())
+            );
+            assert_eq!(
+                validate(&["PublicCovidPass", "PublicCovidPass"], allow_extra),
+                invalid_type(&["PublicCovidPass", "PublicCovidPass"])
+            );
+            assert_eq!(
+                validate(&["PublicCovidPass"], allow_extra),
+                invalid_type(&["PublicCovidPass"])
+            );
         }
-        else {
-            Ok(())
+
+        let extra = ["VerifiableCredential", "ExampleCredential", "PublicCovidPass"];
+        assert_eq!(validate(&extra, false), invalid_type(&extra));
+        assert_eq!(validate(&extra, true), Ok(()));
+        assert_eq!(
+            validate(&extra, false).unwrap_err().to_string(),
+            "credential has type [\"VerifiableCredential\", \"ExampleCredential\", \"PublicCovidPass\"], expected \
+             [\"VerifiableCredential\", \"PublicCovidPass\"]"
+        );
+    }
+
+    #[test]
+    fn versions() {
+        let validate = |version: &'static str| {
+            let mut cwt = cwt(
+                Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+                Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            );
+            cwt.verifiable_credential.version = version;
+            cwt.verifiable_credential.validate(&ClaimVerificationConfig::default())
+        };
+
+        assert_eq!(validate("1.0.0"), Ok(()));
+        assert_eq!(validate("1.1.0"), Ok(()));
+        assert_eq!(
+            validate("2.0.0"),
+            Err(NzcpError::UnsupportedCredentialVersion(CredentialVersion {
+                major: 2,
+                minor: 0,
+                patch: 0
+            }))
+        );
+        assert_eq!(
+            validate("0.1.0"),
+            Err(NzcpError::UnsupportedCredentialVersion(CredentialVersion {
+                major: 0,
+                minor: 1,
+                patch: 0
+            }))
+        );
+        assert_eq!(
+            validate("2.0.0").unwrap_err().to_string(),
+            "Unsupported credential version 2.0.0 (only 1.x.x is supported)"
+        );
+        assert_eq!(
+            validate("1.0"),
+            Err(NzcpError::InvalidCWT(CwtValidationError::InvalidVersion(String::from(
+                "1.0"
+            ))))
+        );
+    }
+
+    #[test]
+    fn contexts() {
+        const EXTENSION: &str = "https://example.com/contexts/v1";
+        let validate = |contexts: &[&'static str], context_policy| {
+            let mut cwt = cwt(
+                Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+                Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            );
+            cwt.verifiable_credential.context = contexts.to_vec();
+            let config = ClaimVerificationConfig {
+                context_policy,
+                ..Default::default()
+            };
+            cwt.verifiable_credential.validate(&config).map_err(cwt_error)
+        };
+        let invalid_context = |found: &[&str], missing: &[&str], unexpected: &[&str]| {
+            let strings = |contexts: &[&str]| contexts.iter().map(|str| String::from(*str)).collect();
+            Err(CwtValidationError::InvalidContext {
+                found: strings(found),
+                missing: strings(missing),
+                unexpected: strings(unexpected),
+            })
+        };
+
+        let canonical = [MANDATORY_CONTEXT_URL, PublicCovidPass::CONTEXT_URL];
+        let extended = [MANDATORY_CONTEXT_URL, PublicCovidPass::CONTEXT_URL, EXTENSION];
+        let reordered = [PublicCovidPass::CONTEXT_URL, MANDATORY_CONTEXT_URL];
+        let missing_base = [PublicCovidPass::CONTEXT_URL, EXTENSION];
+
+        assert_eq!(
+            ClaimVerificationConfig::default().context_policy,
+            ContextPolicy::RequireBase
+        );
+
+        assert_eq!(validate(&canonical, ContextPolicy::Exact), Ok(()));
+        assert_eq!(
+            validate(&extended, ContextPolicy::Exact),
+            invalid_context(&extended, &[], &[EXTENSION])
+        );
+        assert_eq!(
+            validate(&reordered, ContextPolicy::Exact),
+            invalid_context(&reordered, &[], &[])
+        );
+        assert_eq!(
+            validate(&missing_base, ContextPolicy::Exact),
+            invalid_context(&missing_base, &[MANDATORY_CONTEXT_URL], &[EXTENSION])
+        );
+
+        assert_eq!(validate(&canonical, ContextPolicy::RequireBase), Ok(()));
+        assert_eq!(validate(&extended, ContextPolicy::RequireBase), Ok(()));
+        assert_eq!(validate(&reordered, ContextPolicy::RequireBase), Ok(()));
+        assert_eq!(
+            validate(&missing_base, ContextPolicy::RequireBase),
+            invalid_context(&missing_base, &[MANDATORY_CONTEXT_URL], &[])
+        );
+        assert_eq!(
+            validate(&missing_base, ContextPolicy::RequireBase)
+                .unwrap_err()
+                .to_string(),
+            "credential uses invalid contexts [\"https://nzcp.covid19.health.nz/contexts/v1\", \
+             \"https://example.com/contexts/v1\"] (missing [\"https://www.w3.org/2018/credentials/v1\"], unexpected [])"
+        );
+
+        for contexts in [&canonical[..], &extended, &reordered, &missing_base, &[]] {
+            assert_eq!(validate(contexts, ContextPolicy::Ignore), Ok(()));
         }
     }
+
+    #[test]
+    fn validity_period_errors_display_dates() {
+        let expired = NzcpError::PassExpired {
+            expiry: Utc.ymd(2022, 3, 5).and_hms(9, 30, 0),
+            checked_at: Utc.ymd(2022, 3, 6).and_hms(14, 0, 5),
+        };
+        assert_eq!(
+            expired.to_string(),
+            "Pass expired on 5 March 2022 09:30:00 UTC (checked at 6 March 2022 14:00:05 UTC)"
+        );
+
+        let not_yet_active = NzcpError::PassNotYetActive {
+            not_before: Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+            checked_at: Utc.ymd(2021, 11, 1).and_hms(8, 0, 0),
+        };
+        assert_eq!(
+            not_yet_active.to_string(),
+            "Pass is not active until 2 November 2021 20:05:30 UTC (checked at 1 November 2021 08:00:00 UTC)"
+        );
+    }
 }
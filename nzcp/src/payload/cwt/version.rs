@@ -0,0 +1,105 @@
+use std::{fmt, str::FromStr};
+
+use super::validation::CwtValidationError;
+
+/// The `vc.version` of a credential, a semver `major.minor.patch` version (e.g. `1.0.0`).
+///
+/// Versions with the same major version are compatible, so any `1.x.x` credential is supported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct CredentialVersion {
+    pub major: u64,
+    pub minor: u64,
+    pub patch: u64,
+}
+
+impl CredentialVersion {
+    /// The major version of the credentials this library supports.
+    pub const SUPPORTED_MAJOR: u64 = 1;
+
+    /// Parse a `major.minor.patch` version, failing with `CwtValidationError::InvalidVersion` for anything else
+    /// (including pre-release or build suffixes, and numbers with leading zeros).
+    pub fn parse(version: &str) -> Result<Self, CwtValidationError> {
+        let invalid = || CwtValidationError::InvalidVersion(version.to_owned());
+        let number = |part: Option<&str>| {
+            let part = part.filter(|part| {
+                !part.is_empty()
+                    && part.bytes().all(|byte| byte.is_ascii_digit())
+                    && (part.len() == 1 || !part.starts_with('0'))
+            });
+            part.and_then(|part| part.parse().ok()).ok_or_else(invalid)
+        };
+
+        let mut parts = version.split('.');
+        let version = CredentialVersion {
+            major: number(parts.next())?,
+            minor: number(parts.next())?,
+            patch: number(parts.next())?,
+        };
+        match parts.next() {
+            Some(_) => Err(invalid()),
+            None => Ok(version),
+        }
+    }
+
+    /// Whether credentials of this version can be verified, i.e. it has the supported major version.
+    pub fn is_supported(&self) -> bool {
+        self.major == Self::SUPPORTED_MAJOR
+    }
+}
+
+impl FromStr for CredentialVersion {
+    type Err = CwtValidationError;
+
+    fn from_str(version: &str) -> Result<Self, Self::Err> {
+        Self::parse(version)
+    }
+}
+
+impl fmt::Display for CredentialVersion {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}.{}.{}", self.major, self.minor, self.patch)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        let version = |major, minor, patch| CredentialVersion { major, minor, patch };
+
+        assert_eq!(CredentialVersion::parse("1.0.0"), Ok(version(1, 0, 0)));
+        assert_eq!("1.12.3".parse(), Ok(version(1, 12, 3)));
+        assert_eq!(version(1, 12, 3).to_string(), "1.12.3");
+        assert!(version(1, 2, 0) > version(1, 1, 9));
+
+        for invalid in [
+            "",
+            "1",
+            "1.0",
+            "1.0.0.0",
+            "1.0.0-beta",
+            "1.0.0+build",
+            "01.0.0",
+            "1.00.0",
+            "v1.0.0",
+            "1..0",
+            "-1.0.0",
+            " 1.0.0",
+        ] {
+            assert_eq!(
+                CredentialVersion::parse(invalid),
+                Err(CwtValidationError::InvalidVersion(String::from(invalid)))
+            );
+        }
+    }
+
+    #[test]
+    fn supported() {
+        assert!(CredentialVersion::parse("1.0.0").unwrap().is_supported());
+        assert!(CredentialVersion::parse("1.4.2").unwrap().is_supported());
+        assert!(!CredentialVersion::parse("0.9.0").unwrap().is_supported());
+        assert!(!CredentialVersion::parse("2.0.0").unwrap().is_supported());
+    }
+}
@@ -0,0 +1,257 @@
+use std::fmt::Write;
+
+/// The most bytes shown in a diagnostic, to keep error messages short.
+pub const EXCERPT_LENGTH: usize = 64;
+
+/// Write the start of some CBOR in diagnostic notation (RFC 8949, section 8), e.g. `{4: h'6B65792D31', 1: -7}`, to
+/// show in error messages.
+///
+/// Only the first `EXCERPT_LENGTH` bytes are shown. Anything after them, or after a malformed item, is written as
+/// `...`, so truncated or malformed CBOR still shows as much as could be read.
+pub fn diagnostic(cbor: &[u8]) -> String {
+    let mut writer = Writer {
+        bytes: &cbor[..cbor.len().min(EXCERPT_LENGTH)],
+        position: 0,
+        output: String::new(),
+    };
+
+    let mut complete = true;
+    while writer.position < writer.bytes.len() {
+        if writer.position > 0 {
+            writer.output.push_str(", ");
+        }
+        if writer.item().is_none() {
+            complete = false;
+            break;
+        }
+    }
+    if !complete || cbor.len() > EXCERPT_LENGTH {
+        writer.output.push_str("...");
+    }
+    writer.output
+}
+
+struct Writer<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    output: String,
+}
+
+impl<'a> Writer<'a> {
+    fn take(&mut self, length: u64) -> Option<&'a [u8]> {
+        let end = usize::try_from(length)
+            .ok()
+            .and_then(|length| self.position.checked_add(length))
+            .filter(|end| *end <= self.bytes.len())?;
+        let taken = &self.bytes[self.position..end];
+        self.position = end;
+        Some(taken)
+    }
+
+    fn argument(&mut self, length: u64) -> Option<u64> {
+        Some(
+            self.take(length)?
+                .iter()
+                .fold(0, |argument, byte| argument << 8 | u64::from(*byte)),
+        )
+    }
+
+    /// Write the next item, returning `None` if it's truncated or malformed, having written as much as could be read.
+    fn item(&mut self) -> Option<()> {
+        let initial = self.take(1)?[0];
+        let (major, info) = (initial >> 5, initial & 0x1f);
+
+        if info == 31 {
+            return self.indefinite(major);
+        }
+        let argument = match info {
+            0..=23 => u64::from(info),
+            24 => self.argument(1)?,
+            25 if major == 7 => return self.float(2),
+            26 if major == 7 => return self.float(4),
+            27 if major == 7 => return self.float(8),
+            25 => self.argument(2)?,
+            26 => self.argument(4)?,
+            27 => self.argument(8)?,
+            _ => return None,
+        };
+
+        match major {
+            0 => write!(self.output, "{}", argument).ok(),
+            1 => write!(self.output, "{}", -1 - i128::from(argument)).ok(),
+            2 => self.byte_string(argument),
+            3 => self.text_string(argument),
+            4 => {
+                self.output.push('[');
+                for index in 0..argument {
+                    if index > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.item()?;
+                }
+                self.output.push(']');
+                Some(())
+            }
+            5 => {
+                self.output.push('{');
+                for index in 0..argument {
+                    if index > 0 {
+                        self.output.push_str(", ");
+                    }
+                    self.item()?;
+                    self.output.push_str(": ");
+                    self.item()?;
+                }
+                self.output.push('}');
+                Some(())
+            }
+            6 => {
+                write!(self.output, "{}(", argument).ok()?;
+                self.item()?;
+                self.output.push(')');
+                Some(())
+            }
+            _ => {
+                match argument {
+                    20 => self.output.push_str("false"),
+                    21 => self.output.push_str("true"),
+                    22 => self.output.push_str("null"),
+                    23 => self.output.push_str("undefined"),
+                    _ => write!(self.output, "simple({})", argument).ok()?,
+                }
+                Some(())
+            }
+        }
+    }
+
+    /// Write an indefinite length item, which runs until a break (`0xff`).
+    fn indefinite(&mut self, major: u8) -> Option<()> {
+        let (open, close) = match major {
+            2 | 3 => ("(_ ", ")"),
+            4 => ("[_ ", "]"),
+            5 => ("{_ ", "}"),
+            _ => return None,
+        };
+        self.output.push_str(open);
+
+        let mut index = 0;
+        while *self.bytes.get(self.position)? != 0xff {
+            if index > 0 {
+                self.output.push_str(", ");
+            }
+            self.item()?;
+            if major == 5 {
+                self.output.push_str(": ");
+                self.item()?;
+            }
+            index += 1;
+        }
+        self.position += 1;
+
+        self.output.push_str(close);
+        Some(())
+    }
+
+    fn byte_string(&mut self, length: u64) -> Option<()> {
+        self.output.push_str("h'");
+        let available = (self.bytes.len() - self.position) as u64;
+        let bytes = self.take(length.min(available))?;
+        for byte in bytes {
+            write!(self.output, "{:02X}", byte).ok()?;
+        }
+        if length > available {
+            return None;
+        }
+        self.output.push('\'');
+        Some(())
+    }
+
+    fn text_string(&mut self, length: u64) -> Option<()> {
+        let available = (self.bytes.len() - self.position) as u64;
+        let text = self.take(length.min(available))?;
+        // the JSON escapes diagnostic notation uses are close enough to Rust's
+        let text = format!("{:?}", String::from_utf8_lossy(text));
+        if length > available {
+            self.output.push_str(text.strip_suffix('"').unwrap_or(&text));
+            return None;
+        }
+        self.output.push_str(&text);
+        Some(())
+    }
+
+    fn float(&mut self, length: u64) -> Option<()> {
+        let bits = self.argument(length)?;
+        let value = match length {
+            2 => half_to_f64(bits as u16),
+            4 => f64::from(f32::from_bits(bits as u32)),
+            _ => f64::from_bits(bits),
+        };
+
+        if value.is_nan() {
+            self.output.push_str("NaN");
+        }
+        else if value.is_infinite() {
+            self.output.push_str(if value > 0.0 { "Infinity" } else { "-Infinity" });
+        }
+        else {
+            write!(self.output, "{:?}", value).ok()?;
+        }
+        Some(())
+    }
+}
+
+/// Convert an IEEE 754 half-precision float to an `f64`.
+fn half_to_f64(half: u16) -> f64 {
+    let sign = if half & 0x8000 == 0 { 1.0 } else { -1.0 };
+    let exponent = i32::from((half >> 10) & 0x1f);
+    let mantissa = f64::from(half & 0x3ff);
+
+    sign * match exponent {
+        0 => mantissa * 2f64.powi(-24),
+        31 if mantissa == 0.0 => f64::INFINITY,
+        31 => f64::NAN,
+        _ => (1024.0 + mantissa) * 2f64.powi(exponent - 25),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn diagnose(hex: &str) -> String {
+        diagnostic(&hex::decode(hex).unwrap())
+    }
+
+    #[test]
+    fn items() {
+        // the protected headers of the example pass
+        assert_eq!(diagnose("a204456b65792d310126"), "{4: h'6B65792D31', 1: -7}");
+        assert_eq!(diagnose("d28201820203"), "18([1, [2, 3]])");
+        assert_eq!(diagnose("a1627663a0"), r#"{"vc": {}}"#);
+        assert_eq!(diagnose("1b000000007450400a"), "1951416330");
+        assert_eq!(diagnose("3bffffffffffffffff"), "-18446744073709551616");
+        assert_eq!(diagnose("f4f5f6f7f0"), "false, true, null, undefined, simple(16)");
+        assert_eq!(diagnose("f93e00fa47c35000fb3ff199999999999a"), "1.5, 100000.0, 1.1");
+        assert_eq!(diagnose("f97c00f9fc00f97e00"), "Infinity, -Infinity, NaN");
+        assert_eq!(diagnose("9f0102ff"), "[_ 1, 2]");
+        assert_eq!(diagnose("bf6161f5ff"), r#"{_ "a": true}"#);
+        assert_eq!(diagnose("5f4101420203ff"), "(_ h'01', h'0203')");
+        assert_eq!(diagnose("63225c0a"), r#""\"\\\n""#);
+        assert_eq!(diagnose(""), "");
+    }
+
+    #[test]
+    fn truncated() {
+        assert_eq!(diagnose("a204456b6579"), "{4: h'6B6579...");
+        assert_eq!(diagnose("a1627663"), r#"{"vc": ..."#);
+        assert_eq!(diagnose("a16276"), r#"{"v..."#);
+        assert_eq!(diagnose("9f01"), "[_ 1...");
+        assert_eq!(diagnose("82011c"), "[1, ...");
+
+        let long = [vec![0x98, 100], vec![0; 100]].concat();
+        let diagnostic = diagnostic(&long);
+        assert!(diagnostic.starts_with("[0, 0, "), "{}", diagnostic);
+        assert!(diagnostic.ends_with(", 0, ..."), "{}", diagnostic);
+        assert_eq!(diagnostic.matches('0').count(), EXCERPT_LENGTH - 2);
+    }
+}
@@ -0,0 +1,364 @@
+use std::str::{self, FromStr};
+
+use serde_cbor::Value;
+use thiserror::Error;
+
+use super::{
+    barcode::{QrBarcode, QrBarcodeError},
+    canonical::MAX_DEPTH,
+};
+
+const KID_KEY: i128 = 4;
+const ISSUER_CLAIM_KEY: i128 = 1;
+const NOT_BEFORE_CLAIM_KEY: i128 = 5;
+const EXPIRY_CLAIM_KEY: i128 = 4;
+const VERIFIABLE_CREDENTIAL_KEY: &str = "vc";
+const CREDENTIAL_SUBJECT_KEY: &str = "credentialSubject";
+
+/// Why `PassInspector::new` couldn't decode anything from a pass.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum DecodeError {
+    #[error("Invalid QR barcode: {0:?}")]
+    QrBarcode(#[from] QrBarcodeError),
+    #[error("the payload is not a CBOR encoded COSE structure")]
+    NotCose,
+}
+
+/// Extracts whatever fields can be decoded from a pass **without verifying it**.
+///
+/// Intended for forensic analysis of passes which fail verification (e.g. tampered or truncated passes), analogous
+/// to a JWT library's "decode without verify" mode. Each section and claim is decoded independently, so a malformed
+/// field only hides that field. Nothing returned here can be trusted, use `verify_pass_uri` for that.
+///
+/// ```ignore
+/// let inspector = PassInspector::new("NZCP:/1/2KCEVIQ...")?;
+/// println!("issued by {:?} with key {:?}", inspector.issuer(), inspector.key_id());
+/// ```
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PassInspector {
+    key_id: Option<String>,
+    issuer: Option<String>,
+    not_before: Option<u64>,
+    expiry: Option<u64>,
+    credential_subject_raw: Option<Vec<u8>>,
+}
+
+impl PassInspector {
+    /// Decode as much as possible from a pass barcode URI.
+    ///
+    /// Only fails if the URI itself is invalid or the payload does not start like a COSE structure.
+    pub fn new(payload: &str) -> Result<PassInspector, DecodeError> {
+        let barcode = QrBarcode::from_str(payload)?;
+        let mut sections = cose_sections(&barcode.cbor).ok_or(DecodeError::NotCose)?;
+
+        let mut inspector = PassInspector::default();
+
+        if let Some(protected_headers) = sections.next().and_then(byte_string) {
+            for (key, value) in MapEntries::new(protected_headers) {
+                if decode(key) == Some(Value::Integer(KID_KEY)) {
+                    inspector.key_id = match decode(value) {
+                        Some(Value::Text(kid)) => Some(kid),
+                        Some(Value::Bytes(kid)) => String::from_utf8(kid).ok(),
+                        _ => None,
+                    };
+                }
+            }
+        }
+
+        // unprotected headers are empty in spec
+        let _ = sections.next();
+
+        if let Some(cwt_claims) = sections.next().and_then(byte_string) {
+            for (key, value) in MapEntries::new(cwt_claims) {
+                match decode(key) {
+                    Some(Value::Integer(ISSUER_CLAIM_KEY)) => inspector.issuer = decode_text(value),
+                    Some(Value::Integer(NOT_BEFORE_CLAIM_KEY)) => inspector.not_before = decode_timestamp(value),
+                    Some(Value::Integer(EXPIRY_CLAIM_KEY)) => inspector.expiry = decode_timestamp(value),
+                    Some(Value::Text(key)) if key == VERIFIABLE_CREDENTIAL_KEY => {
+                        inspector.credential_subject_raw = MapEntries::new(value)
+                            .find(|(key, _)| decode_text(key).as_deref() == Some(CREDENTIAL_SUBJECT_KEY))
+                            .map(|(_, subject)| subject.to_vec());
+                    }
+                    _ => {}
+                }
+            }
+        }
+
+        Ok(inspector)
+    }
+
+    /// The `iss` claim, e.g. `did:web:nzcp.identity.health.nz`.
+    pub fn issuer(&self) -> Option<&str> {
+        self.issuer.as_deref()
+    }
+
+    /// The `kid` protected header.
+    pub fn key_id(&self) -> Option<&str> {
+        self.key_id.as_deref()
+    }
+
+    /// The `nbf` claim, in seconds since the unix epoch.
+    pub fn not_before(&self) -> Option<u64> {
+        self.not_before
+    }
+
+    /// The `exp` claim, in seconds since the unix epoch.
+    pub fn expiry(&self) -> Option<u64> {
+        self.expiry
+    }
+
+    /// The CBOR bytes of `vc.credentialSubject`, exactly as they appear in the pass.
+    pub fn credential_subject_raw(&self) -> Option<&[u8]> {
+        self.credential_subject_raw.as_deref()
+    }
+}
+
+fn decode(bytes: &[u8]) -> Option<Value> {
+    serde_cbor::from_slice(bytes).ok()
+}
+
+fn decode_text(bytes: &[u8]) -> Option<String> {
+    match decode(bytes)? {
+        Value::Text(text) => Some(text),
+        _ => None,
+    }
+}
+
+fn decode_timestamp(bytes: &[u8]) -> Option<u64> {
+    match decode(bytes)? {
+        Value::Integer(timestamp) => timestamp.try_into().ok(),
+        _ => None,
+    }
+}
+
+/// Get the content of the byte string at the start of `bytes`.
+fn byte_string(bytes: &[u8]) -> Option<&[u8]> {
+    match item_header(bytes)? {
+        (2, Some(len), header_len) => bytes.get(header_len..header_len.checked_add(len)?),
+        _ => None,
+    }
+}
+
+/// Iterate the (tagged) COSE array elements, stopping at the first element which is truncated.
+fn cose_sections(bytes: &[u8]) -> Option<Items<'_>> {
+    let mut bytes = bytes;
+    while let (6, _, header_len) = item_header(bytes)? {
+        bytes = &bytes[header_len..];
+    }
+
+    match item_header(bytes)? {
+        (4, len, header_len) => Some(Items {
+            bytes: &bytes[header_len..],
+            remaining: len,
+        }),
+        _ => None,
+    }
+}
+
+/// Iterates the raw items of a CBOR array, `remaining` is `None` for indefinite length arrays.
+struct Items<'a> {
+    bytes: &'a [u8],
+    remaining: Option<usize>,
+}
+
+impl<'a> Iterator for Items<'a> {
+    type Item = &'a [u8];
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.remaining {
+            Some(0) => return None,
+            Some(ref mut remaining) => *remaining -= 1,
+            None if self.bytes.first() == Some(&BREAK) => return None,
+            None => {}
+        }
+
+        let len = item_len(self.bytes)?;
+        let (item, rest) = self.bytes.split_at(len);
+        self.bytes = rest;
+        Some(item)
+    }
+}
+
+/// Iterates the raw key/value pairs of an encoded CBOR map, stopping at the first pair which is truncated.
+struct MapEntries<'a>(Items<'a>);
+
+impl<'a> MapEntries<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        match item_header(bytes) {
+            Some((5, len, header_len)) => MapEntries(Items {
+                bytes: &bytes[header_len..],
+                remaining: len.and_then(|len| len.checked_mul(2)),
+            }),
+            _ => MapEntries(Items {
+                bytes: &[],
+                remaining: Some(0),
+            }),
+        }
+    }
+}
+
+impl<'a> Iterator for MapEntries<'a> {
+    type Item = (&'a [u8], &'a [u8]);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        Some((self.0.next()?, self.0.next()?))
+    }
+}
+
+const BREAK: u8 = 0xff;
+
+/// Decode the initial byte(s) of a CBOR data item into its major type, argument (`None` if indefinite length), and
+/// the length of the header.
+fn item_header(bytes: &[u8]) -> Option<(u8, Option<usize>, usize)> {
+    let initial = *bytes.first()?;
+    let major = initial >> 5;
+    let additional = initial & 0x1f;
+
+    let (argument, header_len) = match additional {
+        0..=23 => (Some(u64::from(additional)), 1),
+        24..=27 => {
+            let len = 1 << (additional - 24);
+            let argument = bytes
+                .get(1..=len)?
+                .iter()
+                .fold(0u64, |acc, byte| (acc << 8) | u64::from(*byte));
+            (Some(argument), 1 + len)
+        }
+        31 if (2..=5).contains(&major) => (None, 1),
+        _ => return None,
+    };
+
+    let argument = match argument {
+        Some(argument) => Some(usize::try_from(argument).ok()?),
+        None => None,
+    };
+
+    Some((major, argument, header_len))
+}
+
+/// The total encoded length of the CBOR data item at the start of `bytes`, or `None` if it is truncated, invalid, or
+/// nested more than `MAX_DEPTH` deep.
+fn item_len(bytes: &[u8]) -> Option<usize> {
+    nested_item_len(bytes, 0)
+}
+
+/// The length of an item within `depth` arrays, maps, tags, or indefinite length strings, see `item_len`.
+fn nested_item_len(bytes: &[u8], depth: usize) -> Option<usize> {
+    if depth > MAX_DEPTH {
+        return None;
+    }
+    let (major, argument, header_len) = item_header(bytes)?;
+
+    let len = match (major, argument) {
+        (0 | 1 | 7, _) => header_len,
+        (2 | 3, Some(len)) => header_len.checked_add(len)?,
+        (4 | 5, Some(len)) => {
+            let count = if major == 5 { len.checked_mul(2)? } else { len };
+            let mut offset = header_len;
+            for _ in 0..count {
+                offset += nested_item_len(bytes.get(offset..)?, depth + 1)?;
+            }
+            offset
+        }
+        (6, _) => header_len + nested_item_len(&bytes[header_len..], depth + 1)?,
+        // indefinite length strings, arrays and maps run until a break
+        (_, None) => {
+            let mut offset = header_len;
+            while *bytes.get(offset)? != BREAK {
+                offset += nested_item_len(&bytes[offset..], depth + 1)?;
+            }
+            offset + 1
+        }
+        _ => return None,
+    };
+
+    (len <= bytes.len()).then_some(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const VALID_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIDJOA6Y524TD3AZRM263WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX";
+
+    fn barcode(bytes: &[u8]) -> String {
+        format!(
+            "NZCP:/1/{}",
+            base32::encode(base32::Alphabet::RFC4648 { padding: false }, bytes)
+        )
+    }
+
+    #[test]
+    fn inspect_valid_pass() {
+        let inspector = PassInspector::new(VALID_PASS).unwrap();
+
+        assert_eq!(inspector.issuer(), Some("did:web:nzcp.covid19.health.nz"));
+        assert_eq!(inspector.key_id(), Some("key-1"));
+        assert_eq!(inspector.not_before(), Some(1635883530));
+        assert_eq!(inspector.expiry(), Some(1951416330));
+        assert_eq!(
+            inspector.credential_subject_raw(),
+            Some(hex::decode("a369676976656e4e616d65644a61636b6a66616d696c794e616d656753706172726f7763646f626a313936302d30342d3136").unwrap().as_slice())
+        );
+    }
+
+    #[test]
+    fn inspect_truncated_pass() {
+        let mut bytes: QrBarcode = VALID_PASS.parse().unwrap();
+        // cut off part way through the signature
+        bytes.cbor.truncate(bytes.cbor.len() - 10);
+
+        let inspector = PassInspector::new(&barcode(&bytes.cbor)).unwrap();
+
+        assert_eq!(inspector.issuer(), Some("did:web:nzcp.covid19.health.nz"));
+        assert_eq!(inspector.key_id(), Some("key-1"));
+        assert_eq!(inspector.expiry(), Some(1951416330));
+    }
+
+    #[test]
+    fn inspect_malformed_fields() {
+        // protected headers: { 4: 1 } (kid is not a string)
+        // claims: { 1: h'00', 4: 1951416330, "vc": "invalid" }
+        let bytes = hex::decode("d28443a10401a055a3014100041a7450400a62766367696e76616c696440").unwrap();
+
+        let inspector = PassInspector::new(&barcode(&bytes)).unwrap();
+
+        assert_eq!(inspector.key_id(), None);
+        assert_eq!(inspector.issuer(), None);
+        assert_eq!(inspector.not_before(), None);
+        assert_eq!(inspector.expiry(), Some(1951416330));
+        assert_eq!(inspector.credential_subject_raw(), None);
+    }
+
+    #[test]
+    fn inspect_deeply_nested() {
+        // unprotected headers nested `depth` arrays deep, followed by claims { 1: "did:web:x" }
+        let nested = |depth: usize| {
+            let cbor = [
+                &[0xd2, 0x84, 0x40][..],
+                &vec![0x81; depth],
+                &hex::decode("004ca101696469643a7765623a7840").unwrap(),
+            ]
+            .concat();
+            PassInspector::new(&barcode(&cbor)).unwrap()
+        };
+
+        assert_eq!(nested(MAX_DEPTH).issuer(), Some("did:web:x"));
+        // the unprotected headers are too deep to skip, so the claims after them can't be found
+        assert_eq!(nested(MAX_DEPTH + 1).issuer(), None);
+        // rather than overflowing the stack
+        assert_eq!(nested(100_000), PassInspector::default());
+    }
+
+    #[test]
+    fn inspect_not_cose() {
+        assert_eq!(
+            PassInspector::new(&barcode(&hex::decode("a0").unwrap())),
+            Err(DecodeError::NotCose)
+        );
+        assert_eq!(
+            PassInspector::new("NZCP:/2/AAAA"),
+            Err(DecodeError::QrBarcode(QrBarcodeError::UnsupportedVersion { found: 2 }))
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use std::{collections::HashSet, iter::FromIterator};
+
+use thiserror::Error;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum RevokedPassRegistryError {
+    #[error("revoked passes could not be parsed: {0}")]
+    Malformed(String),
+    #[error("revoked pass {index}: '{jti}' is not a UUID")]
+    InvalidJti { index: usize, jti: String },
+}
+
+/// The token IDs (`jti`) of passes that have been revoked before they expire, see `Verifier::with_revoked_passes`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RevokedPassRegistry {
+    revoked: HashSet<Uuid>,
+}
+
+impl RevokedPassRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Load a JSON array of revoked token IDs, either as `urn:uuid:` URIs (as in the `jti` claim) or bare UUIDs.
+    ///
+    /// ```notrust
+    /// ["urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b", "d6f4fb49-ed4f-4c5e-9a66-f4ba3a6e3ad6"]
+    /// ```
+    pub fn from_json(json: &str) -> Result<Self, RevokedPassRegistryError> {
+        let jtis: Vec<String> =
+            serde_json::from_str(json).map_err(|err| RevokedPassRegistryError::Malformed(err.to_string()))?;
+
+        jtis.into_iter()
+            .enumerate()
+            .map(|(index, jti)| Uuid::parse_str(&jti).map_err(|_| RevokedPassRegistryError::InvalidJti { index, jti }))
+            .collect()
+    }
+
+    pub fn revoke(&mut self, jti: Uuid) {
+        self.revoked.insert(jti);
+    }
+
+    pub fn is_revoked(&self, jti: &Uuid) -> bool {
+        self.revoked.contains(jti)
+    }
+
+    pub fn len(&self) -> usize {
+        self.revoked.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.revoked.is_empty()
+    }
+}
+
+impl FromIterator<Uuid> for RevokedPassRegistry {
+    fn from_iter<I: IntoIterator<Item = Uuid>>(iter: I) -> Self {
+        RevokedPassRegistry {
+            revoked: iter.into_iter().collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        error::NzcpError,
+        testing::{example_issuer, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+        PublicCovidPass, Verifier,
+    };
+
+    #[tokio::test]
+    async fn revoked_pass() {
+        let registry = RevokedPassRegistry::from_json(
+            r#"["urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b", "d6f4fb49-ed4f-4c5e-9a66-f4ba3a6e3ad6"]"#,
+        )
+        .unwrap();
+        assert_eq!(registry.len(), 2);
+
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(Arc::new(resolver))
+            .with_revoked_passes(Arc::new(registry));
+
+        assert_eq!(
+            verifier.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await,
+            Err(NzcpError::PassRevoked(
+                Uuid::parse_str("60a4f54d-4e30-4332-be33-ad78b1eafa4b").unwrap()
+            ))
+        );
+    }
+
+    #[test]
+    fn invalid_jti() {
+        assert_eq!(
+            RevokedPassRegistry::from_json(r#"["urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b", "60a4f54d"]"#),
+            Err(RevokedPassRegistryError::InvalidJti {
+                index: 1,
+                jti: String::from("60a4f54d")
+            })
+        );
+    }
+}
@@ -0,0 +1,180 @@
+use std::{collections::BTreeMap, sync::Arc};
+
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use serde::Serialize;
+use serde_cbor::{tags::Tagged, Value};
+use thiserror::Error;
+
+use crate::{
+    pass::Pass,
+    payload::cwt::{
+        validation::{MANDATORY_CONTEXT_URL, MANDATORY_TYPE},
+        CwtClaims,
+    },
+};
+
+/// COSE tag for a COSE_Sign1 structure.
+const COSE_SIGN1_TAG: u64 = 18;
+/// COSE algorithm ID for ECDSA with SHA-256.
+const ES256: i128 = -7;
+
+#[derive(Debug, Clone, Error)]
+pub enum SignError {
+    #[error("Unable to encode the pass as CBOR: {0}")]
+    Serialization(Arc<serde_cbor::Error>),
+}
+
+impl From<serde_cbor::Error> for SignError {
+    fn from(error: serde_cbor::Error) -> Self {
+        SignError::Serialization(Arc::new(error))
+    }
+}
+
+/// Sign a pass with the given claims as the issuer would, returning its barcode URI (`NZCP:/1/...`).
+///
+/// The pass verifies against a DID document for `claims.issuer` with the verifying key as `#kid` in its assertion
+/// methods, e.g. one served by `testing::MockDIDResolver`, or with the key supplied offline in a `Jwks`. The pass is
+/// canonical CBOR, so also verifies with `VerifierConfig::strict_cbor`. Intended for generating passes for tests, not
+/// for issuing real passes.
+pub fn sign_pass<P: Pass + Serialize>(
+    pass: &P,
+    claims: &CwtClaims,
+    signing_key: &SigningKey,
+    kid: &str,
+) -> Result<String, SignError> {
+    let text = |text: &str| Value::Text(String::from(text));
+
+    let verifiable_credential = Value::Map(BTreeMap::from([
+        (
+            text("@context"),
+            Value::Array(vec![text(MANDATORY_CONTEXT_URL), text(P::CONTEXT_URL)]),
+        ),
+        (text("version"), text("1.0.0")),
+        (
+            text("type"),
+            Value::Array(vec![text(MANDATORY_TYPE), text(P::CREDENTIAL_TYPE)]),
+        ),
+        (text("credentialSubject"), serde_cbor::value::to_value(pass)?),
+    ]));
+    let claims = Value::Map(BTreeMap::from([
+        (Value::Integer(1), Value::Text(claims.issuer.to_string())),
+        (Value::Integer(5), Value::Integer(claims.not_before.timestamp().into())),
+        (Value::Integer(4), Value::Integer(claims.expiry.timestamp().into())),
+        (Value::Integer(7), Value::Bytes(claims.cwt_token_id.as_bytes().to_vec())),
+        (text("vc"), verifiable_credential),
+    ]));
+
+    let cbor = sign_cose(&serde_cbor::to_vec(&claims)?, signing_key, kid)?;
+    Ok(format!(
+        "NZCP:/1/{}",
+        base32::encode(base32::Alphabet::RFC4648 { padding: false }, &cbor)
+    ))
+}
+
+/// Wrap the CBOR encoded claims in a COSE_Sign1 structure signed with the key, returning its CBOR encoding.
+pub(crate) fn sign_cose(claims: &[u8], signing_key: &SigningKey, kid: &str) -> Result<Vec<u8>, serde_cbor::Error> {
+    let protected_headers = BTreeMap::from([
+        (Value::Integer(1), Value::Integer(ES256)),
+        (Value::Integer(4), Value::Bytes(kid.as_bytes().to_vec())),
+    ]);
+    sign_cose_with_headers(claims, signing_key, protected_headers, BTreeMap::new())
+}
+
+/// As `sign_cose`, with the given protected and unprotected headers.
+pub(crate) fn sign_cose_with_headers(
+    claims: &[u8],
+    signing_key: &SigningKey,
+    protected_headers: BTreeMap<Value, Value>,
+    unprotected_headers: BTreeMap<Value, Value>,
+) -> Result<Vec<u8>, serde_cbor::Error> {
+    let protected_headers = serde_cbor::to_vec(&Value::Map(protected_headers))?;
+
+    let sig_structure = serde_cbor::to_vec(&Value::Array(vec![
+        Value::Text(String::from("Signature1")),
+        Value::Bytes(protected_headers.clone()),
+        Value::Bytes(Vec::new()),
+        Value::Bytes(claims.to_vec()),
+    ]))?;
+    let signature: Signature = signing_key.sign(&sig_structure);
+
+    serde_cbor::to_vec(&Tagged::new(
+        Some(COSE_SIGN1_TAG),
+        Value::Array(vec![
+            Value::Bytes(protected_headers),
+            Value::Map(unprotected_headers),
+            Value::Bytes(claims.to_vec()),
+            Value::Bytes(signature.as_ref().to_vec()),
+        ]),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone, Utc};
+
+    use super::*;
+    use crate::{
+        key::VerifyingKeyExt,
+        testing::{example_issuer, test_signing_key, test_vectors::scanned_at, verifier_with_key},
+        Jwks, PublicCovidPass, Verifier, VerifierConfig,
+    };
+
+    #[tokio::test]
+    async fn sign_and_verify() {
+        let key = test_signing_key(1);
+        let pass = PublicCovidPass {
+            given_name: String::from("Jack"),
+            family_name: Some(String::from("Sparrow")),
+            date_of_birth: NaiveDate::from_ymd(1960, 4, 16),
+        };
+        let claims = CwtClaims {
+            issuer: example_issuer(),
+            not_before: Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+            expiry: Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            cwt_token_id: uuid::Uuid::parse_str("60a4f54d-4e30-4332-be33-ad78b1eafa4b").unwrap(),
+        };
+
+        let uri = sign_pass(&pass, &claims, &key, "key-1").unwrap();
+
+        let verifier = verifier_with_key(&key);
+        let credential = verifier
+            .verify_credential_uri_at::<PublicCovidPass>(&uri, scanned_at())
+            .await
+            .unwrap();
+
+        assert_eq!(credential.pass, pass);
+        assert_eq!(credential.claims, claims);
+    }
+
+    #[tokio::test]
+    async fn signed_passes_are_canonical() {
+        let key = test_signing_key(1);
+        let pass = PublicCovidPass {
+            given_name: String::from("Jack"),
+            family_name: None,
+            date_of_birth: NaiveDate::from_ymd(1960, 4, 16),
+        };
+        let claims = CwtClaims {
+            issuer: example_issuer(),
+            not_before: Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+            expiry: Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            cwt_token_id: uuid::Uuid::from_bytes([7; 16]),
+        };
+        let uri = sign_pass(&pass, &claims, &key, "key-1").unwrap();
+
+        // with the public key supplied offline, rather than resolved
+        let mut jwk = key.verifying_key().to_jwk();
+        jwk["issuer"] = serde_json::json!(example_issuer().to_string());
+        jwk["kid"] = serde_json::json!("key-1");
+        let jwks = Jwks::from_json_str(&serde_json::json!({ "keys": [jwk] }).to_string()).unwrap();
+        let verifier = Verifier::from_config(VerifierConfig {
+            trusted_issuers: vec![example_issuer()],
+            strict_cbor: true,
+            ..VerifierConfig::default()
+        })
+        .with_jwks(jwks);
+
+        let verified = verifier.verify_pass_uri_at::<PublicCovidPass>(&uri, scanned_at()).await;
+        assert_eq!(verified, Ok(pass));
+    }
+}
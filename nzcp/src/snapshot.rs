@@ -0,0 +1,286 @@
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+use crate::{
+    decentralised_identifier::{
+        parse_document, DecentralizedIdentifier, DecentralizedIdentifierError, DocumentResolver, WebResolver,
+    },
+    verifier::Verifier,
+};
+
+/// The version written to exported snapshots, loading any other version fails.
+const SNAPSHOT_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum IssuerSnapshotError {
+    #[error("snapshot could not be read: {0}")]
+    Malformed(String),
+    #[error("snapshot version {0} is not supported")]
+    UnsupportedVersion(u32),
+    #[error("snapshot issuer '{0}' is not a valid DID")]
+    InvalidIssuer(String),
+    #[error("snapshot DID document for {0} does not match its hash")]
+    HashMismatch(String),
+    #[error("snapshot DID document for {issuer} is invalid: {error}")]
+    InvalidDocument {
+        issuer: String,
+        error: DecentralizedIdentifierError,
+    },
+}
+
+/// An issuer's DID document, as resolved when the snapshot was exported.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotEntry {
+    /// The issuer DID (e.g. `did:web:nzcp.identity.health.nz`).
+    pub issuer: String,
+
+    /// When the DID document was resolved.
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub retrieved_at: DateTime<Utc>,
+
+    /// The SHA-256 hash of `document`.
+    #[serde(with = "serde_bytes")]
+    pub sha256: Vec<u8>,
+
+    /// The JSON representation of the DID document, exactly as resolved.
+    #[serde(with = "serde_bytes")]
+    pub document: Vec<u8>,
+}
+
+/// The DID documents of a set of issuers, bundled into a single CBOR file so passes can be verified on machines with
+/// no network access.
+///
+/// Export a snapshot on a connected machine, then load it with `Verifier::from_snapshot`:
+///
+/// ```ignore
+/// let snapshot = nzcp::export_snapshot(&issuers).await?;
+/// std::fs::write("issuers.snapshot", snapshot.to_bytes())?;
+///
+/// // on the offline machine
+/// let verifier = Verifier::from_snapshot(&std::fs::read("issuers.snapshot")?)?;
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct IssuerSnapshot {
+    version: u32,
+    entries: Vec<SnapshotEntry>,
+}
+
+impl IssuerSnapshot {
+    /// Resolve the DID document of each issuer with the given resolver, failing if any cannot be resolved or parsed.
+    pub async fn export(
+        issuers: &[DecentralizedIdentifier],
+        resolver: &dyn DocumentResolver,
+    ) -> Result<Self, DecentralizedIdentifierError> {
+        let mut entries = Vec::with_capacity(issuers.len());
+        for issuer in issuers {
+            let document = resolver.resolve_representation(issuer).await?;
+            // an unusable document would only be rejected once the snapshot is loaded offline
+            parse_document(&document)?;
+
+            entries.push(SnapshotEntry {
+                issuer: issuer.to_string(),
+                retrieved_at: Utc::now(),
+                sha256: Sha256::digest(&document).to_vec(),
+                document,
+            });
+        }
+
+        Ok(IssuerSnapshot {
+            version: SNAPSHOT_VERSION,
+            entries,
+        })
+    }
+
+    /// Load a snapshot, failing if any entry has been modified or cannot be used.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, IssuerSnapshotError> {
+        use IssuerSnapshotError::*;
+
+        let snapshot: IssuerSnapshot = serde_cbor::from_slice(bytes).map_err(|err| Malformed(err.to_string()))?;
+        if snapshot.version != SNAPSHOT_VERSION {
+            return Err(UnsupportedVersion(snapshot.version));
+        }
+
+        for entry in &snapshot.entries {
+            if DecentralizedIdentifier::parse(&entry.issuer).is_none() {
+                return Err(InvalidIssuer(entry.issuer.clone()));
+            }
+            if Sha256::digest(&entry.document).as_slice() != entry.sha256 {
+                return Err(HashMismatch(entry.issuer.clone()));
+            }
+            parse_document(&entry.document).map_err(|error| InvalidDocument {
+                issuer: entry.issuer.clone(),
+                error,
+            })?;
+        }
+
+        Ok(snapshot)
+    }
+
+    /// Build a snapshot from the JSON representations of DID documents already at hand (e.g. bundled with an app),
+    /// each serving the issuer named by its `id`, failing if any cannot be used.
+    pub fn from_documents<D: AsRef<[u8]>>(documents: &[D]) -> Result<Self, IssuerSnapshotError> {
+        use IssuerSnapshotError::*;
+
+        let mut entries = Vec::with_capacity(documents.len());
+        for document in documents {
+            let document = document.as_ref();
+            let issuer = parse_document(document)
+                .map_err(|error| Malformed(format!("invalid DID document: {}", error)))?
+                .id;
+            if DecentralizedIdentifier::parse(&issuer).is_none() {
+                return Err(InvalidIssuer(issuer));
+            }
+
+            entries.push(SnapshotEntry {
+                issuer,
+                retrieved_at: Utc::now(),
+                sha256: Sha256::digest(document).to_vec(),
+                document: document.to_vec(),
+            });
+        }
+
+        Ok(IssuerSnapshot {
+            version: SNAPSHOT_VERSION,
+            entries,
+        })
+    }
+
+    /// Encode the snapshot as CBOR, to be loaded with `IssuerSnapshot::from_bytes`.
+    pub fn to_bytes(&self) -> Vec<u8> {
+        serde_cbor::to_vec(self).expect("snapshot serialization failed")
+    }
+
+    pub fn entries(&self) -> &[SnapshotEntry] {
+        &self.entries
+    }
+}
+
+/// Resolve the DID documents of the given issuers over HTTPS (with the default HTTP client) into a snapshot.
+pub async fn export_snapshot(
+    issuers: &[DecentralizedIdentifier],
+) -> Result<IssuerSnapshot, DecentralizedIdentifierError> {
+    IssuerSnapshot::export(issuers, &WebResolver::default()).await
+}
+
+/// Serves DID documents from the snapshot, failing with `IssuerNotInSnapshot` for any other issuer.
+#[async_trait]
+impl DocumentResolver for IssuerSnapshot {
+    async fn resolve_representation(
+        &self,
+        did: &DecentralizedIdentifier,
+    ) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        let did = did.to_string();
+        self.entries
+            .iter()
+            .find(|entry| entry.issuer == did)
+            .map(|entry| entry.document.clone())
+            .ok_or(DecentralizedIdentifierError::IssuerNotInSnapshot(did))
+    }
+}
+
+impl Verifier {
+    /// Create a verifier trusting only the MoH `nzcp.identity.health.nz` issuer, resolving its DID document from the
+    /// given snapshot (see `IssuerSnapshot`) rather than over the network.
+    ///
+    /// To trust other issuers, use `Verifier::new(issuers).with_resolver(Arc::new(IssuerSnapshot::from_bytes(..)?))`.
+    pub fn from_snapshot(bytes: &[u8]) -> Result<Self, IssuerSnapshotError> {
+        let snapshot = IssuerSnapshot::from_bytes(bytes)?;
+        Ok(Verifier::default().with_resolver(Arc::new(snapshot)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        error::{CoseVerificationError, NzcpError},
+        testing::{example_issuer, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+        PublicCovidPass,
+    };
+
+    async fn example_snapshot() -> IssuerSnapshot {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        IssuerSnapshot::export(&[example_issuer()], &resolver).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn verify_from_snapshot() {
+        let bytes = example_snapshot().await.to_bytes();
+
+        let snapshot = IssuerSnapshot::from_bytes(&bytes).unwrap();
+        assert_eq!(snapshot.entries()[0].issuer, "did:web:nzcp.covid19.health.nz");
+        assert_eq!(snapshot.entries()[0].document, EXAMPLE_DID_DOCUMENT.as_bytes());
+
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(snapshot));
+        let pass: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        assert_eq!(pass.given_name, "Jack");
+    }
+
+    #[tokio::test]
+    async fn snapshot_from_documents() {
+        let snapshot = IssuerSnapshot::from_documents(&[EXAMPLE_DID_DOCUMENT]).unwrap();
+        assert_eq!(snapshot.entries()[0].issuer, "did:web:nzcp.covid19.health.nz");
+        assert_eq!(IssuerSnapshot::from_bytes(&snapshot.to_bytes()), Ok(snapshot.clone()));
+
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(snapshot));
+        let pass: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        assert_eq!(pass.given_name, "Jack");
+
+        assert!(matches!(
+            IssuerSnapshot::from_documents(&["{}"]),
+            Err(IssuerSnapshotError::Malformed(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn issuer_not_in_snapshot() {
+        let snapshot = IssuerSnapshot::export(&[], &MockDIDResolver::new()).await.unwrap();
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(snapshot));
+
+        assert_eq!(
+            verifier.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await,
+            Err(NzcpError::InvalidSignature(
+                CoseVerificationError::DecentralizedIdentifierResolution(
+                    DecentralizedIdentifierError::IssuerNotInSnapshot(String::from("did:web:nzcp.covid19.health.nz"))
+                )
+            ))
+        );
+    }
+
+    #[tokio::test]
+    async fn modified_snapshot() {
+        let mut snapshot = example_snapshot().await;
+        snapshot.entries[0].document.push(b'\n');
+
+        assert_eq!(
+            IssuerSnapshot::from_bytes(&snapshot.to_bytes()),
+            Err(IssuerSnapshotError::HashMismatch(String::from(
+                "did:web:nzcp.covid19.health.nz"
+            )))
+        );
+    }
+
+    #[tokio::test]
+    async fn invalid_snapshot_document() {
+        let mut snapshot = example_snapshot().await;
+        snapshot.entries[0].document = b"{}".to_vec();
+        snapshot.entries[0].sha256 = Sha256::digest(b"{}").to_vec();
+
+        assert_eq!(
+            IssuerSnapshot::from_bytes(&snapshot.to_bytes()),
+            Err(IssuerSnapshotError::InvalidDocument {
+                issuer: String::from("did:web:nzcp.covid19.health.nz"),
+                error: DecentralizedIdentifierError::EmptyDocument,
+            })
+        );
+        assert!(matches!(
+            Verifier::from_snapshot(b"not a snapshot"),
+            Err(IssuerSnapshotError::Malformed(_))
+        ));
+    }
+}
@@ -0,0 +1,317 @@
+//! Utilities for testing pass verification without network access (requires the `test-utils` feature).
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc, RwLock,
+    },
+};
+
+use async_trait::async_trait;
+use p256::ecdsa::{SigningKey, VerifyingKey};
+use serde_cbor::Value;
+use ssi::did::Document;
+
+use crate::{
+    decentralised_identifier::{
+        normalize_context, DecentralizedIdentifier, DecentralizedIdentifierError, DocumentResolver,
+    },
+    key::VerifyingKeyExt,
+    sign::sign_cose,
+    Verifier,
+};
+
+pub mod test_passes;
+pub mod test_vectors;
+
+/// The issuer of the example passes in the specification.
+pub fn example_issuer() -> DecentralizedIdentifier {
+    DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz"))
+}
+
+/// The DID document of the example issuer, as published at `https://nzcp.covid19.health.nz/.well-known/did.json`.
+pub const EXAMPLE_DID_DOCUMENT: &str = r##"{
+    "@context": "https://w3.org/ns/did/v1",
+    "id": "did:web:nzcp.covid19.health.nz",
+    "verificationMethod": [
+        {
+            "id": "did:web:nzcp.covid19.health.nz#key-1",
+            "controller": "did:web:nzcp.covid19.health.nz",
+            "type": "JsonWebKey2020",
+            "publicKeyJwk": {
+                "kty": "EC",
+                "crv": "P-256",
+                "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+                "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0"
+            }
+        }
+    ],
+    "assertionMethod": ["did:web:nzcp.covid19.health.nz#key-1"]
+}"##;
+
+/// The key the example issuer signs the example passes with, `key-1` in `EXAMPLE_DID_DOCUMENT`.
+pub fn example_verifying_key() -> VerifyingKey {
+    // SEC1 encoded
+    let key = "BM0UflxrAqddlb24LouAw+junKpoXz7lzIYtTsT5fO+tIv5SU6FuW+TRYh5/GOrJlcV/gpF/GpFQhCOD8LSk3T0=";
+    VerifyingKey::from_sec1_bytes(&base64::decode(key).unwrap()).expect("invalid example key")
+}
+
+/// https://nzcp.covid19.health.nz/#valid-worked-example
+pub const VALID_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIDJOA6Y524TD3AZRM263WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX";
+
+/// A `DocumentResolver` serving fixed DID documents, counting the resolutions made. Documents are checked with
+/// `did_document_from_json` when they're added, so a broken fixture fails where it's written rather than as a
+/// resolution error, use `with_raw_document` to serve a malformed one.
+///
+/// ```ignore
+/// let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+/// let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+/// ```
+#[derive(Debug, Default)]
+pub struct MockDIDResolver {
+    documents: RwLock<HashMap<String, Vec<u8>>>,
+    resolutions: AtomicUsize,
+}
+
+impl MockDIDResolver {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Serve `document` when `did` is resolved.
+    ///
+    /// # Panics
+    ///
+    /// If `document` isn't a DID document, see `did_document_from_json`.
+    pub fn with_document(self, did: &DecentralizedIdentifier, document: &str) -> Self {
+        self.set_document(did, document);
+        self
+    }
+
+    /// Serve `document` when `did` is resolved from now on, replacing any previous document.
+    ///
+    /// # Panics
+    ///
+    /// If `document` isn't a DID document, see `did_document_from_json`.
+    pub fn set_document(&self, did: &DecentralizedIdentifier, document: &str) {
+        if let Err(error) = did_document_from_json(document) {
+            panic!("invalid DID document for {}: {}", did, error);
+        }
+        self.set_raw_document(did, document);
+    }
+
+    /// Serve `document` as is when `did` is resolved, e.g. to test malformed documents.
+    pub fn with_raw_document(self, did: &DecentralizedIdentifier, document: &str) -> Self {
+        self.set_raw_document(did, document);
+        self
+    }
+
+    /// Serve `document` as is when `did` is resolved from now on, replacing any previous document.
+    pub fn set_raw_document(&self, did: &DecentralizedIdentifier, document: &str) {
+        self.documents
+            .write()
+            .unwrap()
+            .insert(did.to_string(), document.as_bytes().to_vec());
+    }
+
+    /// The number of times a document has been resolved (including unknown DIDs).
+    pub fn resolution_count(&self) -> usize {
+        self.resolutions.load(Ordering::SeqCst)
+    }
+}
+
+#[async_trait]
+impl DocumentResolver for MockDIDResolver {
+    async fn resolve_representation(
+        &self,
+        did: &DecentralizedIdentifier,
+    ) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        self.resolutions.fetch_add(1, Ordering::SeqCst);
+        self.documents
+            .read()
+            .unwrap()
+            .get(&did.to_string())
+            .cloned()
+            .ok_or(DecentralizedIdentifierError::DocumentNotFound)
+    }
+}
+
+/// A resolver that never finishes resolving, e.g. to test timeouts.
+pub struct HangingResolver;
+
+#[async_trait]
+impl DocumentResolver for HangingResolver {
+    async fn resolve_representation(
+        &self,
+        _did: &DecentralizedIdentifier,
+    ) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+        std::future::pending().await
+    }
+}
+
+/// A deterministic signing key for test passes, different for each `seed`.
+pub fn test_signing_key(seed: u8) -> SigningKey {
+    SigningKey::from_bytes(&[seed.max(1); 32]).expect("invalid test signing key")
+}
+
+/// Parse a DID document written as JSON, normalizing its `@context` as is done when resolving documents (so
+/// `https://w3.org/ns/did/v1` is accepted), as `MockDIDResolver` does to check the documents it serves.
+pub fn did_document_from_json(json: &str) -> Result<Document, serde_json::Error> {
+    let mut document: serde_json::Value = serde_json::from_str(json)?;
+    normalize_context(&mut document);
+    serde_json::from_value(document)
+}
+
+/// A DID document for `issuer` with each of the given keys as an assertion method.
+pub fn did_document(issuer: &DecentralizedIdentifier, keys: &[(&str, VerifyingKey)]) -> String {
+    let methods = keys
+        .iter()
+        .map(|(kid, key)| {
+            serde_json::json!({
+                "id": format!("{}#{}", issuer, kid),
+                "controller": issuer.to_string(),
+                "type": "JsonWebKey2020",
+                "publicKeyJwk": key.to_jwk(),
+            })
+        })
+        .collect::<Vec<_>>();
+    let assertion_methods = keys
+        .iter()
+        .map(|(kid, _)| format!("{}#{}", issuer, kid))
+        .collect::<Vec<_>>();
+
+    serde_json::json!({
+        "@context": "https://w3.org/ns/did/v1",
+        "id": issuer.to_string(),
+        "verificationMethod": methods,
+        "assertionMethod": assertion_methods,
+    })
+    .to_string()
+}
+
+/// A resolver serving a DID document for the example issuer with `key` as `key-1`, the key ID of `TestPass`.
+pub fn resolver_with_key(key: &SigningKey) -> Arc<MockDIDResolver> {
+    let document = did_document(&example_issuer(), &[("key-1", key.verifying_key())]);
+    Arc::new(MockDIDResolver::new().with_document(&example_issuer(), &document))
+}
+
+/// A verifier trusting only the example issuer, with keys resolved by `resolver_with_key`.
+///
+/// ```ignore
+/// let key = test_signing_key(1);
+/// let verifier = verifier_with_key(&key);
+/// let pass = verifier.verify_pass_uri_at::<PublicCovidPass>(&TestPass::default().sign(&key), scanned_at()).await;
+/// ```
+pub fn verifier_with_key(key: &SigningKey) -> Verifier {
+    Verifier::new(vec![example_issuer()]).with_resolver(resolver_with_key(key))
+}
+
+/// The contents of a pass to be signed with a test key, defaulting to those of `VALID_PASS`.
+#[derive(Debug, Clone)]
+pub struct TestPass {
+    pub issuer: DecentralizedIdentifier,
+    pub kid: String,
+    /// `nbf` as a unix timestamp.
+    pub not_before: i64,
+    /// `exp` as a unix timestamp.
+    pub expiry: i64,
+    pub cwt_token_id: [u8; 16],
+    pub credential_subject: Value,
+}
+
+impl Default for TestPass {
+    fn default() -> Self {
+        let text = |text: &str| Value::Text(String::from(text));
+
+        TestPass {
+            issuer: example_issuer(),
+            kid: String::from("key-1"),
+            not_before: 1635883530,
+            expiry: 1951416330,
+            // urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b
+            cwt_token_id: [
+                0x60, 0xa4, 0xf5, 0x4d, 0x4e, 0x30, 0x43, 0x32, 0xbe, 0x33, 0xad, 0x78, 0xb1, 0xea, 0xfa, 0x4b,
+            ],
+            credential_subject: Value::Map(BTreeMap::from([
+                (text("givenName"), text("Jack")),
+                (text("familyName"), text("Sparrow")),
+                (text("dob"), text("1960-04-16")),
+            ])),
+        }
+    }
+}
+
+impl TestPass {
+    /// The CWT claims of the pass.
+    pub fn claims(&self) -> Value {
+        let text = |text: &str| Value::Text(String::from(text));
+
+        let verifiable_credential = Value::Map(BTreeMap::from([
+            (
+                text("@context"),
+                Value::Array(vec![
+                    text("https://www.w3.org/2018/credentials/v1"),
+                    text("https://nzcp.covid19.health.nz/contexts/v1"),
+                ]),
+            ),
+            (text("version"), text("1.0.0")),
+            (
+                text("type"),
+                Value::Array(vec![text("VerifiableCredential"), text("PublicCovidPass")]),
+            ),
+            (text("credentialSubject"), self.credential_subject.clone()),
+        ]));
+
+        Value::Map(BTreeMap::from([
+            (Value::Integer(1), Value::Text(self.issuer.to_string())),
+            (Value::Integer(5), Value::Integer(self.not_before.into())),
+            (Value::Integer(4), Value::Integer(self.expiry.into())),
+            (Value::Integer(7), Value::Bytes(self.cwt_token_id.to_vec())),
+            (text("vc"), verifiable_credential),
+        ]))
+    }
+
+    /// Sign the pass as a COSE_Sign1 structure, returning its CBOR encoding.
+    pub fn sign_cbor(&self, signing_key: &SigningKey) -> Vec<u8> {
+        let claims = serde_cbor::to_vec(&self.claims()).unwrap();
+        sign_cose(&claims, signing_key, &self.kid).unwrap()
+    }
+
+    /// Sign the pass, returning its barcode URI.
+    pub fn sign(&self, signing_key: &SigningKey) -> String {
+        format!(
+            "NZCP:/1/{}",
+            base32::encode(
+                base32::Alphabet::RFC4648 { padding: false },
+                &self.sign_cbor(signing_key)
+            )
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn fixtures_are_valid_documents() {
+        let example = did_document_from_json(EXAMPLE_DID_DOCUMENT).unwrap();
+        assert!(example_issuer().document_verifying_key(&example, "key-1").is_ok());
+
+        let generated = did_document(&example_issuer(), &[("key-2", test_signing_key(2).verifying_key())]);
+        let generated = did_document_from_json(&generated).unwrap();
+        assert_eq!(
+            example_issuer().document_verifying_key(&generated, "key-2"),
+            Ok(test_signing_key(2).verifying_key())
+        );
+
+        assert!(did_document_from_json(r#"{"@context": "https://w3.org/ns/did/v1"}"#).is_err());
+    }
+
+    #[test]
+    #[should_panic(expected = "invalid DID document for did:web:nzcp.covid19.health.nz")]
+    fn mock_resolver_rejects_invalid_documents() {
+        MockDIDResolver::new().with_document(&example_issuer(), r#"{"@context": "https://w3.org/ns/did/v1"}"#);
+    }
+}
@@ -0,0 +1,72 @@
+//! Example passes with specific defects, for testing how verification failures are handled.
+//!
+//! Apart from `malformed_cbor_pass`, these are the examples from the specification, signed by `example_issuer()`,
+//! so verify them with `MockDIDResolver` serving `EXAMPLE_DID_DOCUMENT`. See `test_vectors` for all of the examples.
+
+use super::test_vectors::{EXPIRED_PASS, MODIFIED_SIGNATURE_PASS, NOT_ACTIVE_PASS};
+
+/// A pass which expired on 2021-10-26, failing with `NzcpError::PassExpired { .. }`.
+///
+/// https://nzcp.covid19.health.nz/#expired-pass
+pub fn expired_pass() -> &'static str {
+    EXPIRED_PASS
+}
+
+/// A pass which doesn't become active until 2026-11-02, failing with `NzcpError::PassNotYetActive { .. }` before then.
+/// Verify it with `Verifier::verify_pass_uri_at` to keep tests from depending on the current date.
+///
+/// https://nzcp.covid19.health.nz/#not-active-pass
+pub fn future_pass() -> &'static str {
+    NOT_ACTIVE_PASS
+}
+
+/// A pass with part of its signature zeroed, failing with
+/// `NzcpError::InvalidSignature(CoseVerificationError::SignatureVerificationFailed)`.
+///
+/// https://nzcp.covid19.health.nz/#modified-signature
+pub fn invalid_signature_pass() -> &'static str {
+    MODIFIED_SIGNATURE_PASS
+}
+
+/// A valid barcode URI whose payload is truncated CBOR, failing with `NzcpError::InvalidPayload(_)`.
+pub fn malformed_cbor_pass() -> &'static str {
+    "NZCP:/1/2KCEVIQEIU"
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        error::{CoseVerificationError, NzcpError},
+        testing::{example_issuer, test_vectors::scanned_at, MockDIDResolver, EXAMPLE_DID_DOCUMENT},
+        PublicCovidPass, Verifier,
+    };
+
+    #[tokio::test]
+    async fn defective_passes() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+        let verify = |uri| verifier.verify_pass_uri_at::<PublicCovidPass>(uri, scanned_at());
+
+        assert!(matches!(
+            verify(expired_pass()).await,
+            Err(NzcpError::PassExpired { .. })
+        ));
+        assert!(matches!(
+            verify(future_pass()).await,
+            Err(NzcpError::PassNotYetActive { .. })
+        ));
+        assert_eq!(
+            verify(invalid_signature_pass()).await,
+            Err(NzcpError::InvalidSignature(
+                CoseVerificationError::SignatureVerificationFailed
+            ))
+        );
+        assert!(matches!(
+            verify(malformed_cbor_pass()).await,
+            Err(NzcpError::InvalidPayload(_))
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+//! The example passes from the specification, with the outcome of verifying each, e.g. as a smoke test for an
+//! integration or to check another implementation against this one.
+//!
+//! The examples are signed by `example_issuer()`, so verify them with `MockDIDResolver` serving `EXAMPLE_DID_DOCUMENT`,
+//! at `scanned_at()` so the outcomes don't depend on the current date:
+//!
+//! ```ignore
+//! let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+//! let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+//! for vector in test_vectors() {
+//!     let result = verifier.verify_pass_uri_at::<PublicCovidPass>(vector.uri, scanned_at()).await;
+//!     assert_eq!(result, vector.expected, "{}", vector.name);
+//! }
+//! ```
+
+use chrono::{DateTime, NaiveDate, TimeZone, Utc};
+
+pub use super::VALID_PASS;
+use crate::{
+    error::{CoseVerificationError, DecentralizedIdentifierError, NzcpError},
+    PublicCovidPass,
+};
+
+/// A pass signed by a key other than the one in the example issuer's DID document, with the same `kid`.
+///
+/// https://nzcp.covid19.health.nz/#bad-public-key
+pub const BAD_PUBLIC_KEY_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAY73U6TCQ3KF5KFML5LRCS5D3PCYIB2D3EOIIZRPXPUA2OR3NIYCBMGYRZUMBNBDMIA5BUOZKVOMSVFS246AMU7ADZXWBYP7N4QSKNQ4TETIF4VIRGLHOXWYMR4HGQ7KYHHU";
+
+/// A pass signed with `key-2`, which isn't in the example issuer's DID document.
+///
+/// https://nzcp.covid19.health.nz/#public-key-not-found
+pub const PUBLIC_KEY_NOT_FOUND_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGIASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVBMP3LEDMB4CLBS2I7IOYJZW46U2YIBCSOFZMQADVQGM3JKJBLCY7ATASDTUYWIP4RX3SH3IFBJ3QWPQ7FJE6RNT5MU3JHCCGKJISOLIMY3OWH5H5JFUEZKBF27OMB37H5AHF";
+
+/// A pass with a modified credential subject, so its signature no longer matches.
+///
+/// https://nzcp.covid19.health.nz/#modified-payload
+pub const MODIFIED_PAYLOAD_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEOKKALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWKU3UMV3GK2TGMFWWS3DZJZQW2ZLDIRXWKY3EN5RGUMJZGYYC2MBUFUYTMB2QMCSPKTKOGBBTFPRTVV4LD2X2JNMEAAAAAAAAAAAAAAAABPN3J4NASOBXVEC5P3FC52BWW2ZK3IR4EMKU7OUIUUU7M5OWNBXOMMVQT3CYDKYI64VULCIEXMZZNUIPUZWRCR3Q";
+
+/// A pass with part of its signature zeroed.
+///
+/// https://nzcp.covid19.health.nz/#modified-signature
+pub const MODIFIED_SIGNATURE_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIAAAAAAAAAAAAAAAAC63WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX";
+
+/// A pass which expired on 2021-10-26.
+///
+/// https://nzcp.covid19.health.nz/#expired-pass
+pub const EXPIRED_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUX5AM2FQIGTBPBPYWYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVA56TNJCCUN2NVK5NGAYOZ6VIWACYIBM3QXW7SLCMD2WTJ3GSEI5JH7RXAEURGATOHAHXC2O6BEJKBSVI25ICTBR5SFYUDSVLB2F6SJ63LWJ6Z3FWNHOXF6A2QLJNUFRQNTRU";
+
+/// A pass which doesn't become active until 2026-11-02.
+///
+/// https://nzcp.covid19.health.nz/#not-active-pass
+pub const NOT_ACTIVE_PASS: &str = "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRU2XI5UFQIGTMZIQIWYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVA27NR3GFF4CCGWF66QGMJSJIF3KYID3KTKCBUOIKIC6VZ3SEGTGM3N2JTWKGDBAPLSG76Q3MXIDJRMNLETOKAUTSBOPVQEQAX25MF77RV6QVTTSCV2ZY2VMN7FATRGO3JATR";
+
+/// An example pass from the specification, and the outcome of verifying it at `scanned_at()`.
+#[derive(Debug, Clone)]
+pub struct TestVector {
+    /// The name of the example in the specification, e.g. `Expired Pass`.
+    pub name: &'static str,
+    /// The barcode URI of the pass.
+    pub uri: &'static str,
+    /// The pass, or the exact error verification fails with.
+    pub expected: Result<PublicCovidPass, NzcpError>,
+}
+
+/// When to verify the test vectors, after the valid pass becomes active and before the not active pass does. Passes
+/// signed from `TestPass::default()` are active then too.
+pub fn scanned_at() -> DateTime<Utc> {
+    Utc.ymd(2026, 1, 1).and_hms(0, 0, 0)
+}
+
+/// Every example pass from the specification, in the order the specification gives them.
+pub fn test_vectors() -> Vec<TestVector> {
+    let checked_at = scanned_at();
+
+    vec![
+        TestVector {
+            name: "Valid Worked Example",
+            uri: VALID_PASS,
+            expected: Ok(PublicCovidPass {
+                given_name: String::from("Jack"),
+                family_name: Some(String::from("Sparrow")),
+                date_of_birth: NaiveDate::from_ymd(1960, 4, 16),
+            }),
+        },
+        TestVector {
+            name: "Bad Public Key",
+            uri: BAD_PUBLIC_KEY_PASS,
+            expected: Err(NzcpError::InvalidSignature(
+                CoseVerificationError::SignatureVerificationFailed,
+            )),
+        },
+        TestVector {
+            name: "Public Key Not Found",
+            uri: PUBLIC_KEY_NOT_FOUND_PASS,
+            expected: Err(NzcpError::InvalidSignature(
+                CoseVerificationError::DecentralizedIdentifierResolution(
+                    DecentralizedIdentifierError::MissingAssertionMethod {
+                        expected: String::from("did:web:nzcp.covid19.health.nz#key-2"),
+                        document: String::from("did:web:nzcp.covid19.health.nz"),
+                    },
+                ),
+            )),
+        },
+        TestVector {
+            name: "Modified Signature",
+            uri: MODIFIED_SIGNATURE_PASS,
+            expected: Err(NzcpError::InvalidSignature(
+                CoseVerificationError::SignatureVerificationFailed,
+            )),
+        },
+        TestVector {
+            name: "Modified Payload",
+            uri: MODIFIED_PAYLOAD_PASS,
+            expected: Err(NzcpError::InvalidSignature(
+                CoseVerificationError::SignatureVerificationFailed,
+            )),
+        },
+        TestVector {
+            name: "Expired Pass",
+            uri: EXPIRED_PASS,
+            expected: Err(NzcpError::PassExpired {
+                expiry: Utc.timestamp(1635278731, 0),
+                checked_at,
+            }),
+        },
+        TestVector {
+            name: "Not Active Pass",
+            uri: NOT_ACTIVE_PASS,
+            expected: Err(NzcpError::PassNotYetActive {
+                not_before: Utc.timestamp(1793649931, 0),
+                checked_at,
+            }),
+        },
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        canonicalize_pass_uri,
+        testing::{example_issuer, MockDIDResolver, EXAMPLE_DID_DOCUMENT},
+        Verifier,
+    };
+
+    #[tokio::test]
+    async fn conformance() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+
+        for vector in test_vectors() {
+            let result = verifier
+                .verify_pass_uri_at::<PublicCovidPass>(vector.uri, scanned_at())
+                .await;
+            assert_eq!(result, vector.expected, "{}", vector.name);
+        }
+    }
+
+    #[tokio::test]
+    async fn canonical_uris_verify_alike() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+
+        for vector in test_vectors() {
+            for scanned in [
+                format!(" {}", vector.uri),
+                format!("{}\r\n", vector.uri.to_lowercase()),
+                format!("\t{}== ", vector.uri),
+            ] {
+                let canonical = canonicalize_pass_uri(&scanned).unwrap();
+                assert_eq!(canonical, vector.uri, "{}", vector.name);
+                assert_eq!(
+                    verifier
+                        .verify_pass_uri_at::<PublicCovidPass>(&scanned, scanned_at())
+                        .await,
+                    vector.expected,
+                    "{}",
+                    vector.name
+                );
+            }
+        }
+    }
+}
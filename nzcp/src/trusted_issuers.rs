@@ -0,0 +1,207 @@
+use std::collections::{HashMap, HashSet};
+
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::{decentralised_identifier::DecentralizedIdentifier, verifier::KeyPin};
+
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum TrustedIssuersError {
+    #[error("trusted issuers could not be parsed: {0}")]
+    Malformed(String),
+    #[error("issuer {index}: '{did}' is not a valid DID (must start with 'did:web:')")]
+    InvalidDid { index: usize, did: String },
+    #[error("issuer {index}: '{did}' is listed more than once")]
+    DuplicateIssuer { index: usize, did: String },
+    #[error("issuer {index}: pin '{pin}' is not a base64url SHA-256 thumbprint")]
+    MalformedPin { index: usize, pin: String },
+}
+
+/// An issuer passes are accepted from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedIssuer {
+    pub did: DecentralizedIdentifier,
+
+    /// The thumbprints of the keys accepted from the issuer, any key in its DID document is accepted if empty.
+    pub pins: Vec<KeyPin>,
+}
+
+/// An operator editable list of trusted issuers, loaded from JSON or TOML.
+///
+/// ```toml
+/// environment = "production"
+///
+/// [[issuers]]
+/// did = "did:web:nzcp.identity.health.nz"
+/// pins = ["cn-I_WNMClehiVp51i_0VpOENW1upEerA8sEGZ4gpUs"]
+/// ```
+///
+/// Pass the issuers to a verifier with `VerifierConfig::trusted_issuers` and `VerifierConfig::pinned_keys`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TrustedIssuers {
+    /// A label for where the list is used (e.g. 'production' or 'test').
+    pub environment: Option<String>,
+
+    pub issuers: Vec<TrustedIssuer>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrustedIssuersFile {
+    environment: Option<String>,
+    issuers: Vec<TrustedIssuerEntry>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TrustedIssuerEntry {
+    did: String,
+    #[serde(default)]
+    pins: Vec<String>,
+}
+
+impl TrustedIssuers {
+    pub fn from_json_str(json: &str) -> Result<Self, TrustedIssuersError> {
+        let file = serde_json::from_str(json).map_err(|err| TrustedIssuersError::Malformed(err.to_string()))?;
+        Self::from_file(file)
+    }
+
+    pub fn from_toml_str(toml: &str) -> Result<Self, TrustedIssuersError> {
+        let file = toml::from_str(toml).map_err(|err| TrustedIssuersError::Malformed(err.to_string()))?;
+        Self::from_file(file)
+    }
+
+    fn from_file(file: TrustedIssuersFile) -> Result<Self, TrustedIssuersError> {
+        use TrustedIssuersError::*;
+
+        let mut issuers: Vec<TrustedIssuer> = Vec::with_capacity(file.issuers.len());
+        for (index, entry) in file.issuers.into_iter().enumerate() {
+            let did = DecentralizedIdentifier::parse(&entry.did).ok_or_else(|| InvalidDid {
+                index,
+                did: entry.did.clone(),
+            })?;
+            if issuers.iter().any(|issuer| issuer.did == did) {
+                return Err(DuplicateIssuer { index, did: entry.did });
+            }
+
+            let pins = entry
+                .pins
+                .into_iter()
+                .map(|pin| KeyPin::from_base64url(&pin).ok_or(MalformedPin { index, pin }))
+                .collect::<Result<_, _>>()?;
+
+            issuers.push(TrustedIssuer { did, pins });
+        }
+
+        Ok(TrustedIssuers {
+            environment: file.environment,
+            issuers,
+        })
+    }
+
+    /// The issuer DIDs, to be given to `Verifier::new` or `VerifierConfig::trusted_issuers`.
+    pub fn dids(&self) -> Vec<DecentralizedIdentifier> {
+        self.issuers.iter().map(|issuer| issuer.did.clone()).collect()
+    }
+
+    /// The pins of each issuer with any, to be given to `VerifierConfig::pinned_keys`.
+    pub fn pinned_keys(&self) -> HashMap<DecentralizedIdentifier, HashSet<KeyPin>> {
+        self.issuers
+            .iter()
+            .filter(|issuer| !issuer.pins.is_empty())
+            .map(|issuer| (issuer.did.clone(), issuer.pins.iter().copied().collect()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_trusted_issuers() {
+        let json = TrustedIssuers::from_json_str(
+            r#"{
+                "environment": "test",
+                "issuers": [
+                    {"did": "did:web:nzcp.identity.health.nz", "pins": ["cn-I_WNMClehiVp51i_0VpOENW1upEerA8sEGZ4gpUs"]},
+                    {"did": "did:web:nzcp.covid19.health.nz"}
+                ]
+            }"#,
+        )
+        .unwrap();
+        let toml = TrustedIssuers::from_toml_str(
+            r#"
+                environment = "test"
+
+                [[issuers]]
+                did = "did:web:nzcp.identity.health.nz"
+                pins = ["cn-I_WNMClehiVp51i_0VpOENW1upEerA8sEGZ4gpUs"]
+
+                [[issuers]]
+                did = "did:web:nzcp.covid19.health.nz"
+            "#,
+        )
+        .unwrap();
+
+        assert_eq!(json, toml);
+        assert_eq!(json.environment.as_deref(), Some("test"));
+        assert_eq!(
+            json.issuers[0].pins,
+            [KeyPin::from_base64url("cn-I_WNMClehiVp51i_0VpOENW1upEerA8sEGZ4gpUs").unwrap()]
+        );
+        assert_eq!(json.pinned_keys().len(), 1);
+        assert_eq!(
+            json.dids(),
+            vec![
+                DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz")),
+                DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz")),
+            ]
+        );
+    }
+
+    #[test]
+    fn invalid_did() {
+        assert_eq!(
+            TrustedIssuers::from_json_str(
+                r#"{"issuers": [{"did": "did:web:nzcp.identity.health.nz"}, {"did": "did:wbe:nzcp.covid19.health.nz"}]}"#
+            ),
+            Err(TrustedIssuersError::InvalidDid {
+                index: 1,
+                did: String::from("did:wbe:nzcp.covid19.health.nz")
+            })
+        );
+    }
+
+    #[test]
+    fn duplicate_issuer() {
+        assert_eq!(
+            TrustedIssuers::from_toml_str(
+                r#"
+                    [[issuers]]
+                    did = "did:web:nzcp.identity.health.nz"
+
+                    [[issuers]]
+                    did = "did:web:nzcp.identity.health.nz"
+                "#
+            ),
+            Err(TrustedIssuersError::DuplicateIssuer {
+                index: 1,
+                did: String::from("did:web:nzcp.identity.health.nz")
+            })
+        );
+    }
+
+    #[test]
+    fn malformed_pin() {
+        assert_eq!(
+            TrustedIssuers::from_json_str(
+                r#"{"issuers": [{"did": "did:web:nzcp.identity.health.nz", "pins": ["abc"]}]}"#
+            ),
+            Err(TrustedIssuersError::MalformedPin {
+                index: 0,
+                pin: String::from("abc")
+            })
+        );
+    }
+}
@@ -0,0 +1,1962 @@
+use std::{
+    collections::{HashMap, HashSet},
+    mem,
+    sync::{Arc, RwLock},
+};
+
+use chrono::{DateTime, Duration, Utc};
+use futures_util::{stream, Stream, StreamExt};
+use p256::ecdsa::VerifyingKey;
+use thiserror::Error;
+use tokio::sync::{watch, Mutex};
+
+#[cfg(feature = "metrics")]
+pub use self::metrics::VerifierMetrics;
+pub use self::{
+    audit::{AuditContext, AuditEntry, AuditLogger},
+    credential::{RawCose, VerifiedCredential},
+    health::{health_check, HealthCheckResult, IssuerHealth, IssuerStatus},
+    key_store::{KeyStore, KeyStoreError, MemoryKeyStore, StoredKey},
+    observer::{VerificationObserver, VerificationOutcome, VerificationSummary},
+    pins::KeyPin,
+    refresh::{RefreshEvent, RefreshHandle},
+};
+use crate::{
+    decentralised_identifier::{
+        default_http_client, DecentralizedIdentifier, DecentralizedIdentifierError, DocumentResolver,
+        ResolutionRateLimiter, WebResolver, DEFAULT_MAX_DOCUMENT_SIZE,
+    },
+    error::NzcpError,
+    jwks::Jwks,
+    pass::{ministry_of_health_issuer, Pass},
+    payload::{
+        barcode::QrBarcode,
+        canonical,
+        cose::CoseStructure,
+        cwt::{
+            validation::{check_max_future_nbf, ClaimVerificationConfig},
+            verifiable_credential_json,
+        },
+    },
+    revocation::RevokedPassRegistry,
+};
+
+mod audit;
+mod credential;
+mod health;
+mod key_store;
+#[cfg(feature = "metrics")]
+mod metrics;
+mod observer;
+mod pins;
+mod refresh;
+
+/// How long `Verifier::health_check` waits for an issuer's DID document by default.
+const DEFAULT_HEALTH_CHECK_TIMEOUT_SECS: u64 = 5;
+
+/// How many passes `Verifier::verify_many` verifies at once by default.
+const DEFAULT_BATCH_CONCURRENCY: usize = 16;
+
+/// How long a resolved key is used before the issuer's DID document is resolved again.
+const DEFAULT_KEY_TTL_HOURS: i64 = 24;
+
+/// The most a pass's `nbf` and `exp` can be off by and still be accepted, see `VerifierConfig::clock_skew_leeway`.
+pub const MAX_CLOCK_SKEW_LEEWAY_SECS: i64 = 5 * 60;
+
+/// Verifies pass barcodes, caching the keys resolved from issuers' DID documents between verifications.
+///
+/// ```ignore
+/// use nzcp::{PublicCovidPass, Verifier};
+///
+/// let verifier = Verifier::default();
+/// let pass: PublicCovidPass = verifier.verify_pass_uri(barcode).await?;
+/// ```
+pub struct Verifier {
+    config: VerifierConfig,
+    resolver: Arc<dyn DocumentResolver>,
+    key_store: Arc<dyn KeyStore>,
+    key_ttl: Duration,
+    jwks: Option<Arc<Jwks>>,
+    revoked_passes: Option<Arc<RevokedPassRegistry>>,
+    rate_limiter: Option<Arc<ResolutionRateLimiter>>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<Arc<VerifierMetrics>>,
+    observer: Option<Arc<dyn VerificationObserver>>,
+    pinned_keys: RwLock<HashMap<DecentralizedIdentifier, HashSet<KeyPin>>>,
+    /// Held while resolving each key, so concurrent verifications of passes signed with the same key resolve it once.
+    resolving: std::sync::Mutex<ResolutionLocks>,
+    /// Never sent to, refresh tasks watch for this being dropped along with the verifier.
+    dropped: watch::Sender<()>,
+}
+
+/// A lock for each issuer and `kid` being resolved, see `Verifier::verifying_key`.
+type ResolutionLocks = HashMap<(DecentralizedIdentifier, String), Arc<Mutex<()>>>;
+
+/// Removes a key's lock from the `ResolutionLocks` when dropped, if no other verification is resolving the key or
+/// waiting to.
+struct RemoveUnusedLock<'a> {
+    locks: &'a std::sync::Mutex<ResolutionLocks>,
+    key: &'a (DecentralizedIdentifier, String),
+}
+
+impl Drop for RemoveUnusedLock<'_> {
+    fn drop(&mut self) {
+        let mut locks = self.locks.lock().unwrap();
+        if locks.get(self.key).is_some_and(|lock| Arc::strong_count(lock) == 1) {
+            locks.remove(self.key);
+        }
+    }
+}
+
+/// Options for verifying passes, see `Verifier::from_config`.
+#[derive(Debug, Clone)]
+pub struct VerifierConfig {
+    /// The issuers passes are accepted from (defaults to only the MoH `nzcp.identity.health.nz` issuer).
+    pub trusted_issuers: Vec<DecentralizedIdentifier>,
+
+    /// The client DID documents are fetched with (defaults to system TLS roots, no proxy, a 30 second connect timeout,
+    /// and no redirects). Provide your own for custom TLS roots, proxies, or headers, built without redirects too, see
+    /// `WebResolver::new`.
+    pub http_client: Arc<reqwest::Client>,
+
+    /// The thumbprints of the keys accepted from each issuer, see `Verifier::pin_key` (defaults to none, accepting any
+    /// key in an issuer's DID document).
+    pub pinned_keys: HashMap<DecentralizedIdentifier, HashSet<KeyPin>>,
+
+    /// How long `Verifier::health_check` waits for each issuer's DID document (defaults to 5 seconds).
+    pub health_check_timeout: std::time::Duration,
+
+    /// Reject passes that aren't canonical CBOR, with indefinite lengths, integers or lengths not in their shortest
+    /// form, or duplicate map keys (defaults to false, accepting any well-formed CBOR). Map key order isn't checked,
+    /// as the examples in the specification don't sort their keys.
+    pub strict_cbor: bool,
+
+    /// Read the `kid` from the unprotected COSE header when the protected header has none, as some pre-production
+    /// issuers put it there (defaults to false, as the specification requires it to be protected). If both headers
+    /// have one, they must match.
+    pub unprotected_kid: bool,
+
+    /// Which claim checks are run once a pass's signature has been verified, see `verify_all_claims` (defaults to all
+    /// of them).
+    pub claim_verification: ClaimVerificationConfig,
+
+    /// Fetch DID documents only over HTTPS (defaults to true). When false, issuers on `localhost` are fetched over
+    /// plain HTTP, for testing against a local issuer, see `WebResolver::with_force_https`.
+    pub force_https: bool,
+
+    /// The largest DID document in bytes that will be fetched (defaults to 1 MiB), so a misbehaving issuer can't
+    /// exhaust memory with a huge response, see `WebResolver::with_max_document_size`.
+    pub max_did_document_size: usize,
+
+    /// How many passes `Verifier::verify_many` and `Verifier::verify_stream` verify at once (defaults to 16). At least
+    /// one pass is always verified at a time.
+    pub batch_concurrency: usize,
+
+    /// Normalize the names on verified passes to Unicode NFC, so names written with precomposed and combining
+    /// characters (e.g. `Ā` as one character or as `A` and a combining macron) compare equal (defaults to false,
+    /// returning names exactly as signed). `VerifiedCredential::names_normalized` reports whether this changed them.
+    pub normalize_names: bool,
+
+    /// Fetch the DID documents of the given DIDs (e.g. `did:web:nzcp.covid19.health.nz`) from these URLs instead of
+    /// from their issuers, e.g. to test against a local stub server, see `WebResolver::with_document_url_override`
+    /// (defaults to none). Only used if `allow_did_url_overrides` is set.
+    pub did_url_overrides: HashMap<String, String>,
+
+    /// Use `did_url_overrides` (defaults to false), so they can't take effect in production without being explicitly
+    /// allowed. `Verifier::try_from_config` fails if overrides are given without being allowed.
+    pub allow_did_url_overrides: bool,
+
+    /// Accept passes whose `nbf` is up to this far in the future, or whose `exp` is up to this far in the past, to
+    /// allow for the device's clock being off (defaults to zero). `VerifiedCredential::leeway_used` reports when this
+    /// was needed to accept a pass. `Verifier::try_from_config` fails unless it's between zero and
+    /// `MAX_CLOCK_SKEW_LEEWAY_SECS` (5 minutes).
+    pub clock_skew_leeway: Duration,
+
+    /// Reject passes that don't become active until more than this long after they're checked, with
+    /// `NzcpError::PassTooFarInFuture` rather than `NzcpError::PassNotYetActive` (defaults to `None`, accepting any
+    /// `nbf`), so a compromised issuer can't pre-issue passes far ahead. One day is a reasonable limit.
+    ///
+    /// Checked even with `ClaimVerificationConfig::skip_not_before_check`.
+    pub max_future_nbf: Option<Duration>,
+}
+
+/// Why a `VerifierConfig` can't be used, see `Verifier::try_from_config`.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+pub enum VerifierConfigError {
+    /// No issuers are trusted, so every pass would be rejected.
+    #[error("No trusted issuers")]
+    NoTrustedIssuers,
+    /// Keys are pinned for an issuer that isn't trusted.
+    #[error("Keys are pinned for {0}, which isn't a trusted issuer")]
+    PinnedUntrustedIssuer(DecentralizedIdentifier),
+    /// The maximum DID document size is zero, so no DID document could be fetched.
+    #[error("The maximum DID document size is zero")]
+    ZeroMaxDidDocumentSize,
+    /// DID document URL overrides are given, but `VerifierConfig::allow_did_url_overrides` isn't set.
+    #[error("DID document URL overrides are given but not allowed")]
+    DidUrlOverridesNotAllowed,
+    /// The DID document URL override for the DID isn't a valid URL.
+    #[error("The DID document URL override for {did} is not a valid URL: {url}")]
+    InvalidDidUrlOverride { did: String, url: String },
+    /// The clock skew leeway is negative or more than `MAX_CLOCK_SKEW_LEEWAY_SECS`.
+    #[error("The clock skew leeway must be between 0 and {max} seconds, not {0}", max = MAX_CLOCK_SKEW_LEEWAY_SECS)]
+    InvalidClockSkewLeeway(Duration),
+}
+
+impl Default for VerifierConfig {
+    fn default() -> Self {
+        VerifierConfig {
+            trusted_issuers: vec![ministry_of_health_issuer()],
+            http_client: Arc::new(default_http_client()),
+            pinned_keys: HashMap::new(),
+            health_check_timeout: std::time::Duration::from_secs(DEFAULT_HEALTH_CHECK_TIMEOUT_SECS),
+            strict_cbor: false,
+            unprotected_kid: false,
+            claim_verification: ClaimVerificationConfig::default(),
+            force_https: true,
+            max_did_document_size: DEFAULT_MAX_DOCUMENT_SIZE,
+            batch_concurrency: DEFAULT_BATCH_CONCURRENCY,
+            normalize_names: false,
+            did_url_overrides: HashMap::new(),
+            allow_did_url_overrides: false,
+            clock_skew_leeway: Duration::zero(),
+            max_future_nbf: None,
+        }
+    }
+}
+
+impl Default for Verifier {
+    /// Trusts only the MoH `nzcp.identity.health.nz` issuer.
+    fn default() -> Self {
+        Verifier::from_config(VerifierConfig::default())
+    }
+}
+
+impl Verifier {
+    /// Create a verifier trusting only the given issuers, resolving their DID documents over HTTPS and caching keys
+    /// in memory.
+    pub fn new(trusted_issuers: Vec<DecentralizedIdentifier>) -> Self {
+        Verifier::from_config(VerifierConfig {
+            trusted_issuers,
+            ..VerifierConfig::default()
+        })
+    }
+
+    /// Create a verifier with the given options, caching keys in memory.
+    pub fn from_config(config: VerifierConfig) -> Self {
+        let mut resolver = WebResolver::new(config.http_client.clone())
+            .with_force_https(config.force_https)
+            .with_max_document_size(config.max_did_document_size);
+        if config.allow_did_url_overrides {
+            for (did, url) in &config.did_url_overrides {
+                resolver = resolver.with_document_url_override(did, url);
+            }
+        }
+
+        Verifier {
+            resolver: Arc::new(resolver),
+            pinned_keys: RwLock::new(config.pinned_keys.clone()),
+            config,
+            key_store: Arc::new(MemoryKeyStore::new()),
+            key_ttl: Duration::hours(DEFAULT_KEY_TTL_HOURS),
+            jwks: None,
+            revoked_passes: None,
+            rate_limiter: None,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            observer: None,
+            resolving: std::sync::Mutex::new(HashMap::new()),
+            dropped: watch::channel(()).0,
+        }
+    }
+
+    /// Create a verifier with the given options like `from_config`, first checking that they can be used (e.g. that
+    /// at least one issuer is trusted).
+    pub fn try_from_config(config: VerifierConfig) -> Result<Self, VerifierConfigError> {
+        if config.trusted_issuers.is_empty() {
+            return Err(VerifierConfigError::NoTrustedIssuers);
+        }
+        if let Some(issuer) = config
+            .pinned_keys
+            .keys()
+            .find(|issuer| !config.trusted_issuers.contains(issuer))
+        {
+            return Err(VerifierConfigError::PinnedUntrustedIssuer(issuer.clone()));
+        }
+        if config.max_did_document_size == 0 {
+            return Err(VerifierConfigError::ZeroMaxDidDocumentSize);
+        }
+        if !config.did_url_overrides.is_empty() && !config.allow_did_url_overrides {
+            return Err(VerifierConfigError::DidUrlOverridesNotAllowed);
+        }
+        if let Some((did, url)) = config
+            .did_url_overrides
+            .iter()
+            .find(|(_, url)| reqwest::Url::parse(url).is_err())
+        {
+            return Err(VerifierConfigError::InvalidDidUrlOverride {
+                did: did.clone(),
+                url: url.clone(),
+            });
+        }
+        let leeway = config.clock_skew_leeway;
+        if leeway < Duration::zero() || leeway > Duration::seconds(MAX_CLOCK_SKEW_LEEWAY_SECS) {
+            return Err(VerifierConfigError::InvalidClockSkewLeeway(leeway));
+        }
+
+        Ok(Verifier::from_config(config))
+    }
+
+    /// Resolve DID documents using the given resolver instead of fetching them from the issuer.
+    pub fn with_resolver(mut self, resolver: Arc<dyn DocumentResolver>) -> Self {
+        self.resolver = resolver;
+        self
+    }
+
+    /// Cache resolved keys in the given store instead of in memory.
+    pub fn with_key_store(mut self, key_store: Arc<dyn KeyStore>) -> Self {
+        self.key_store = key_store;
+        self
+    }
+
+    /// Set how long resolved keys are used before being resolved again (defaults to 24 hours).
+    pub fn with_key_ttl(mut self, key_ttl: Duration) -> Self {
+        self.key_ttl = key_ttl;
+        self
+    }
+
+    /// Verify passes using only the keys in the given JWKS, instead of resolving issuers' DID documents.
+    ///
+    /// Passes signed with a key not in the JWKS fail with `KeyNotInJwks`. Issuers must still be trusted.
+    pub fn with_jwks(mut self, jwks: Jwks) -> Self {
+        self.jwks = Some(Arc::new(jwks));
+        self
+    }
+
+    /// Reject passes in the registry with `NzcpError::PassRevoked`, once their signature has been verified.
+    pub fn with_revoked_passes(mut self, revoked_passes: Arc<RevokedPassRegistry>) -> Self {
+        self.revoked_passes = Some(revoked_passes);
+        self
+    }
+
+    /// Limit how often DID documents are fetched while verifying passes, failing verifications that need a key when
+    /// the limit is reached with `DecentralizedIdentifierError::RateLimited` (defaults to no limit). Cached keys are
+    /// still used, so passes from issuers whose keys are cached keep verifying.
+    pub fn with_resolution_rate_limiter(mut self, rate_limiter: Arc<ResolutionRateLimiter>) -> Self {
+        self.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Record verification outcomes and DID resolutions in the given metrics (requires the `metrics` feature).
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: Arc<VerifierMetrics>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Call `observer` once for every pass verified, with a summary of the verification, see
+    /// `VerificationObserver`.
+    pub fn with_observer(mut self, observer: Arc<dyn VerificationObserver>) -> Self {
+        self.observer = Some(observer);
+        self
+    }
+
+    pub(crate) fn config(&self) -> &VerifierConfig {
+        &self.config
+    }
+
+    pub fn trusted_issuers(&self) -> &[DecentralizedIdentifier] {
+        &self.config.trusted_issuers
+    }
+
+    /// Verify a pass barcode URI (from a scanned QR code), returning the pass if verified or failing if not.
+    ///
+    /// A valid URI starts with `NZCP:/1/` followed by a base 32 string.
+    pub async fn verify_pass_uri<P: Pass>(&self, uri: &str) -> Result<P, NzcpError> {
+        Ok(self.verify_credential_uri(uri).await?.pass)
+    }
+
+    /// Verify a batch of pass barcode URIs like `verify_pass_uri`, returning the result for each in the same order.
+    ///
+    /// Up to `VerifierConfig::batch_concurrency` passes are verified at once, sharing the verifier's key cache, and
+    /// each key is resolved once however many passes in the batch it signed. A pass failing doesn't stop the rest
+    /// being verified.
+    pub async fn verify_many<P: Pass, S: AsRef<str>>(&self, uris: &[S]) -> Vec<Result<P, NzcpError>> {
+        stream::iter(uris)
+            .map(|uri| self.verify_pass_uri(uri.as_ref()))
+            .buffered(self.config.batch_concurrency.max(1))
+            .collect()
+            .await
+    }
+
+    /// Verify a stream of pass barcode URIs like `verify_many`, yielding the index of each URI in the stream and its
+    /// result as each verification finishes, so results needn't be held in memory.
+    ///
+    /// Up to `VerifierConfig::batch_concurrency` passes are verified at once, and no more URIs are taken from `uris`
+    /// until the results are consumed. Dropping the returned stream stops any verifications still in progress.
+    pub fn verify_stream<'a, P, S>(&'a self, uris: S) -> impl Stream<Item = (usize, Result<P, NzcpError>)> + 'a
+    where
+        P: Pass + 'a,
+        S: Stream<Item = String> + 'a,
+    {
+        uris.enumerate()
+            .map(move |(index, uri)| async move { (index, self.verify_pass_uri(&uri).await) })
+            .buffer_unordered(self.config.batch_concurrency.max(1))
+    }
+
+    /// Verify a pass barcode URI like `verify_pass_uri`, failing with `NzcpError::Timeout` if verification takes
+    /// longer than `timeout` (e.g. as the issuer's DID document is slow to resolve).
+    pub async fn verify_pass_uri_with_timeout<P: Pass>(
+        &self,
+        uri: &str,
+        timeout: std::time::Duration,
+    ) -> Result<P, NzcpError> {
+        observer::observe(self.observer.as_deref(), async {
+            match tokio::time::timeout(timeout, self.verify_credential_uri(uri)).await {
+                Ok(result) => Ok(result?.pass),
+                Err(_) => self.record_verification(Err(NzcpError::Timeout(timeout))),
+            }
+        })
+        .await
+    }
+
+    /// Verify a CBOR encoded pass (the base 32 decoded data of a barcode URI), returning the pass if verified or
+    /// failing if not.
+    pub async fn verify_pass_cbor<P: Pass>(&self, cbor: &[u8]) -> Result<P, NzcpError> {
+        Ok(self.verify_credential_cbor(cbor).await?.pass)
+    }
+
+    /// Verify a pass barcode URI like `verify_pass_uri`, returning details of the verification along with the pass.
+    pub async fn verify_credential_uri<P: Pass>(&self, uri: &str) -> Result<VerifiedCredential<P>, NzcpError> {
+        self.verify_credential_uri_at(uri, Utc::now()).await
+    }
+
+    /// Verify a CBOR encoded pass like `verify_pass_cbor`, returning details of the verification along with the pass.
+    pub async fn verify_credential_cbor<P: Pass>(&self, cbor: &[u8]) -> Result<VerifiedCredential<P>, NzcpError> {
+        self.verify_credential_cbor_at(cbor, Utc::now()).await
+    }
+
+    /// Verify a pass barcode URI like `verify_pass_uri`, checking the pass was active at the given instant rather than
+    /// now (e.g. to re-check a past scan as of when it was scanned).
+    ///
+    /// Only the pass's `nbf` and `exp` are checked against the instant, the issuer's keys are resolved and cached as
+    /// of now.
+    pub async fn verify_pass_uri_at<P: Pass>(&self, uri: &str, at: DateTime<Utc>) -> Result<P, NzcpError> {
+        Ok(self.verify_credential_uri_at(uri, at).await?.pass)
+    }
+
+    /// Verify a pass barcode URI like `verify_pass_uri_at`, returning details of the verification along with the
+    /// pass.
+    pub async fn verify_credential_uri_at<P: Pass>(
+        &self,
+        uri: &str,
+        at: DateTime<Utc>,
+    ) -> Result<VerifiedCredential<P>, NzcpError> {
+        self.verify_credential_bytes_at(uri.as_bytes(), at).await
+    }
+
+    /// Verify a pass barcode URI as the bytes read from a QR code, like `verify_pass_uri`, without converting them to a
+    /// string first. A non-ASCII byte fails with `QrBarcodeError::NonAscii`, giving its offset.
+    pub async fn verify_pass_bytes<P: Pass>(&self, bytes: &[u8]) -> Result<P, NzcpError> {
+        Ok(self.verify_credential_bytes(bytes).await?.pass)
+    }
+
+    /// Verify a pass barcode URI as the bytes read from a QR code like `verify_pass_bytes`, returning details of the
+    /// verification along with the pass.
+    pub async fn verify_credential_bytes<P: Pass>(&self, bytes: &[u8]) -> Result<VerifiedCredential<P>, NzcpError> {
+        self.verify_credential_bytes_at(bytes, Utc::now()).await
+    }
+
+    async fn verify_credential_bytes_at<P: Pass>(
+        &self,
+        bytes: &[u8],
+        at: DateTime<Utc>,
+    ) -> Result<VerifiedCredential<P>, NzcpError> {
+        self.verify_bytes_collecting(bytes, at, false)
+            .await
+            .map_err(first_error)
+    }
+
+    /// Verify a CBOR encoded pass like `verify_credential_cbor`, checking the pass was active at the given instant
+    /// rather than now.
+    pub async fn verify_credential_cbor_at<P: Pass>(
+        &self,
+        cbor: &[u8],
+        at: DateTime<Utc>,
+    ) -> Result<VerifiedCredential<P>, NzcpError> {
+        let verification = async { self.verify_cbor_collecting(cbor, at, false).await.map_err(first_error) };
+        let result = observer::observe(self.observer.as_deref(), verification).await;
+        self.record_verification(result)
+    }
+
+    /// Verify a pass barcode URI like `verify_credential_uri`, but rather than stopping at the first problem, run
+    /// every check and fail with all of the problems found, in the order they're checked (e.g. to see everything wrong
+    /// with a pass while issuing them).
+    ///
+    /// Only a barcode or payload too malformed to check any further fails with a single error. The claims are checked
+    /// even if the signature isn't valid, so the problems with an unauthentic pass are all reported too.
+    ///
+    /// `NzcpError::from` turns the problems into a single `NzcpError::MultipleErrors` (or the one problem, if there's
+    /// only one), so `?` returns them all from a function failing with an `NzcpError`.
+    pub async fn verify_credential_uri_all_errors<P: Pass>(
+        &self,
+        uri: &str,
+    ) -> Result<VerifiedCredential<P>, Vec<NzcpError>> {
+        self.verify_bytes_collecting(uri.as_bytes(), Utc::now(), true).await
+    }
+
+    /// Verify a pass barcode URI as bytes, observing and recording the verification by its first problem, see
+    /// `verify_cbor_collecting`.
+    async fn verify_bytes_collecting<P: Pass>(
+        &self,
+        bytes: &[u8],
+        at: DateTime<Utc>,
+        every_problem: bool,
+    ) -> Result<VerifiedCredential<P>, Vec<NzcpError>> {
+        let mut other_problems = Vec::new();
+        let verification = async {
+            let barcode = QrBarcode::from_bytes(bytes)?;
+            let credential = self
+                .verify_cbor_collecting(&barcode.cbor, at, every_problem)
+                .await
+                .map_err(|mut problems| {
+                    other_problems = problems.split_off(1);
+                    first_error(problems)
+                })?;
+            Ok(VerifiedCredential {
+                barcode_version: Some(barcode.version),
+                ..credential
+            })
+        };
+        let result = observer::observe(self.observer.as_deref(), verification).await;
+        self.record_verification(result)
+            .map_err(|error| std::iter::once(error).chain(other_problems).collect())
+    }
+
+    /// Verify a CBOR encoded pass, failing with the first problem found, or if `every_problem`, with every problem
+    /// found (see `verify_credential_uri_all_errors`).
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "verify_pass", level = "debug", skip_all)
+    )]
+    async fn verify_cbor_collecting<P: Pass>(
+        &self,
+        cbor: &[u8],
+        at: DateTime<Utc>,
+        every_problem: bool,
+    ) -> Result<VerifiedCredential<P>, Vec<NzcpError>> {
+        let mut problems = Problems {
+            every_problem,
+            errors: Vec::new(),
+        };
+        let cose = self.decode_cose::<P>(cbor, &mut problems)?;
+        let cwt_claims_raw = cose.cwt_claims_raw();
+        let raw_cose = RawCose {
+            protected: cose.protected_headers_raw().to_vec(),
+            payload: cwt_claims_raw.to_vec(),
+            signature: cose.signature_bytes().to_vec(),
+        };
+
+        // verify the COSE signature and get the inner CWT
+        let verified = problems.check(cose.verify(self).await)?;
+        let cwt = cose.into_unverified_claims();
+
+        // check the pass hasn't been revoked, though when collecting every problem, a malformed token ID is reported
+        // with the other claims
+        let claims = match cwt.claims() {
+            Ok(claims) => Some(claims),
+            Err(_) if every_problem => None,
+            Err(error) => return Err(problems.fail(error)),
+        };
+        if let Some(cwt_token_id) = claims.as_ref().map(|claims| claims.cwt_token_id).filter(|id| {
+            self.revoked_passes
+                .as_ref()
+                .is_some_and(|revoked| revoked.is_revoked(id))
+        }) {
+            problems.push(NzcpError::PassRevoked(cwt_token_id))?;
+        }
+
+        // check the pass wasn't issued too far ahead before checking whether it's active yet
+        if let (Some(claims), Some(max_future_nbf)) = (&claims, self.config.max_future_nbf) {
+            problems.check(check_max_future_nbf(claims, at, max_future_nbf))?;
+        }
+
+        // validate the CWT and get the inner pass
+        let (leeway, config) = (self.config.clock_skew_leeway, &self.config.claim_verification);
+        let subject = if every_problem {
+            cwt.validated_credential_subject_all_errors(at, leeway, config)
+                .map_err(|errors| problems.errors.extend(errors))
+                .ok()
+        }
+        else {
+            Some(
+                cwt.validated_credential_subject(at, leeway, config)
+                    .map_err(|error| problems.fail(error))?,
+            )
+        };
+
+        match (verified, claims, subject) {
+            (Some((matched_pin, kid_header)), Some(claims), Some((mut pass, leeway_used)))
+                if problems.errors.is_empty() =>
+            {
+                let names_normalized = self.config.normalize_names && pass.normalize_names();
+                Ok(VerifiedCredential {
+                    pass,
+                    claims,
+                    matched_pin,
+                    leeway_used,
+                    barcode_version: None,
+                    kid_header,
+                    verifiable_credential: verifiable_credential_json(cwt_claims_raw),
+                    names_normalized,
+                    raw_cose,
+                })
+            }
+            _ => Err(problems.errors),
+        }
+    }
+
+    /// Deserialize the barcode data to COSE, checking it's canonical CBOR first if required.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "decode_payload", level = "debug", skip_all, fields(length = cbor.len()))
+    )]
+    fn decode_cose<'a, P: Pass>(
+        &self,
+        cbor: &'a [u8],
+        problems: &mut Problems,
+    ) -> Result<CoseStructure<'a, P>, Vec<NzcpError>> {
+        if self.config.strict_cbor {
+            problems.check(canonical::check_cose(cbor))?;
+        }
+        let cose =
+            CoseStructure::<P>::from_slice(cbor, self.config.unprotected_kid).map_err(|error| problems.fail(error))?;
+        observer::observe_issuer(cose.issuer());
+        Ok(cose)
+    }
+
+    /// Count the outcome of a verification in the verifier's metrics, if any, and trace why it failed.
+    fn record_verification<T>(&self, result: Result<T, NzcpError>) -> Result<T, NzcpError> {
+        #[cfg(feature = "tracing")]
+        if let Err(error) = &result {
+            // the message may hold details from the pass, e.g. a malformed date of birth
+            tracing::warn!(code = %error.code(), kind = %error.kind(), "pass verification failed");
+        }
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_verification(&result);
+        }
+        result
+    }
+
+    /// Get the key an issuer signs with, from the JWKS if one was given, or the key store if present and unexpired,
+    /// otherwise from the issuer's DID document.
+    ///
+    /// Key store errors are treated as a miss, so a failing store degrades to resolving the key every time.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "resolve_did",
+            level = "debug",
+            skip_all,
+            fields(issuer = %issuer, kid = kid, cache = tracing::field::Empty)
+        )
+    )]
+    pub(crate) async fn verifying_key(
+        &self,
+        issuer: &DecentralizedIdentifier,
+        kid: &str,
+    ) -> Result<VerifyingKey, DecentralizedIdentifierError> {
+        if let Some(jwks) = &self.jwks {
+            #[cfg(feature = "tracing")]
+            tracing::Span::current().record("cache", &"jwks");
+            let verifying_key = jwks.verifying_key(issuer, kid)?;
+            observer::observe_cached_key();
+            return Ok(verifying_key);
+        }
+
+        if let Some(verifying_key) = self.stored_key(issuer, kid).await {
+            return Ok(verifying_key);
+        }
+
+        let key = (issuer.clone(), kid.to_owned());
+        // declared before the lock, so it's dropped after it, even if verification is cancelled
+        let _remove_lock = RemoveUnusedLock {
+            locks: &self.resolving,
+            key: &key,
+        };
+        let lock = Arc::clone(self.resolving.lock().unwrap().entry(key.clone()).or_default());
+        let resolved = match lock.try_lock() {
+            Ok(_resolving) => self.resolve_key(issuer, kid).await,
+            Err(_) => {
+                let _resolving = lock.lock().await;
+                // another verification resolved the key while this one waited
+                match self.stored_key(issuer, kid).await {
+                    Some(verifying_key) => Ok(verifying_key),
+                    None => self.resolve_key(issuer, kid).await,
+                }
+            }
+        };
+        resolved
+    }
+
+    /// Get a key from the key store, if present and unexpired.
+    async fn stored_key(&self, issuer: &DecentralizedIdentifier, kid: &str) -> Option<VerifyingKey> {
+        match self.key_store.get(issuer, kid).await {
+            Ok(Some(stored)) if !stored.is_expired(Utc::now()) => {
+                #[cfg(feature = "metrics")]
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_cache_hit();
+                }
+                #[cfg(feature = "tracing")]
+                tracing::Span::current().record("cache", &"hit");
+                observer::observe_cached_key();
+                Some(stored.verifying_key)
+            }
+            _ => {
+                #[cfg(feature = "tracing")]
+                tracing::Span::current().record("cache", &"miss");
+                None
+            }
+        }
+    }
+
+    /// Resolve a key from the issuer's DID document, and put it in the key store.
+    async fn resolve_key(
+        &self,
+        issuer: &DecentralizedIdentifier,
+        kid: &str,
+    ) -> Result<VerifyingKey, DecentralizedIdentifierError> {
+        if let Some(rate_limiter) = &self.rate_limiter {
+            rate_limiter.acquire().await?;
+        }
+        let now = Utc::now();
+
+        #[cfg(feature = "metrics")]
+        let started = std::time::Instant::now();
+        let resolved = issuer.resolve_verifying_key(kid, &*self.resolver).await;
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_resolution(started.elapsed());
+        }
+        #[cfg(feature = "tracing")]
+        if let Err(error) = &resolved {
+            tracing::warn!(%error, "DID resolution failed");
+        }
+        let verifying_key = resolved?;
+
+        let stored = StoredKey {
+            verifying_key,
+            resolved_at: now,
+            expires_at: now + self.key_ttl,
+        };
+        // a failure to store only means the key is resolved again next time
+        let _ = self.key_store.put(issuer, kid, stored).await;
+
+        Ok(verifying_key)
+    }
+}
+
+/// The problems found verifying a pass, see `Verifier::verify_cbor_collecting`.
+struct Problems {
+    /// Whether to carry on after a problem to find the rest, rather than stopping at the first.
+    every_problem: bool,
+    errors: Vec<NzcpError>,
+}
+
+impl Problems {
+    /// Note a problem, failing with the problems found so far unless finding every problem.
+    fn push(&mut self, error: impl Into<NzcpError>) -> Result<(), Vec<NzcpError>> {
+        self.errors.push(error.into());
+        if self.every_problem {
+            Ok(())
+        }
+        else {
+            Err(mem::take(&mut self.errors))
+        }
+    }
+
+    /// Note a problem that stops verification, returning the problems found.
+    fn fail(&mut self, error: impl Into<NzcpError>) -> Vec<NzcpError> {
+        self.errors.push(error.into());
+        mem::take(&mut self.errors)
+    }
+
+    /// The value of `result`, or `None` once its error is noted like `push`.
+    fn check<T, E: Into<NzcpError>>(&mut self, result: Result<T, E>) -> Result<Option<T>, Vec<NzcpError>> {
+        match result {
+            Ok(value) => Ok(Some(value)),
+            Err(error) => self.push(error).map(|()| None),
+        }
+    }
+}
+
+/// The first of the problems a verification failed with, as a verification that failed found at least one.
+fn first_error(problems: Vec<NzcpError>) -> NzcpError {
+    problems
+        .into_iter()
+        .next()
+        .expect("a failed verification has a problem")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{
+        atomic::{AtomicUsize, Ordering},
+        Mutex,
+    };
+
+    use async_trait::async_trait;
+    use chrono::TimeZone;
+
+    use super::*;
+    use crate::{
+        error::{CanonicalCborError, CoseVerificationError, CwtValidationError, QrBarcodeError},
+        payload::cwt::CwtClaims,
+        sign::sign_cose,
+        testing::{
+            did_document, example_issuer, example_verifying_key, resolver_with_key,
+            test_passes::{expired_pass, future_pass, invalid_signature_pass},
+            test_signing_key,
+            test_vectors::scanned_at,
+            verifier_with_key, HangingResolver, MockDIDResolver, TestPass, EXAMPLE_DID_DOCUMENT, VALID_PASS,
+        },
+        MatchResult, PublicCovidPass,
+    };
+
+    #[derive(Debug, PartialEq, Eq)]
+    enum Call {
+        Keys,
+        Get(String, String),
+        Put(String, String),
+        Remove(String, String),
+    }
+
+    /// Records every call, delegating to an in-memory store.
+    #[derive(Default)]
+    struct RecordingKeyStore {
+        calls: Mutex<Vec<Call>>,
+        inner: MemoryKeyStore,
+    }
+
+    #[async_trait]
+    impl KeyStore for RecordingKeyStore {
+        async fn get(&self, issuer: &DecentralizedIdentifier, kid: &str) -> Result<Option<StoredKey>, KeyStoreError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Call::Get(issuer.to_string(), kid.to_owned()));
+            self.inner.get(issuer, kid).await
+        }
+
+        async fn put(&self, issuer: &DecentralizedIdentifier, kid: &str, key: StoredKey) -> Result<(), KeyStoreError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Call::Put(issuer.to_string(), kid.to_owned()));
+            self.inner.put(issuer, kid, key).await
+        }
+
+        async fn remove(&self, issuer: &DecentralizedIdentifier, kid: &str) -> Result<(), KeyStoreError> {
+            self.calls
+                .lock()
+                .unwrap()
+                .push(Call::Remove(issuer.to_string(), kid.to_owned()));
+            self.inner.remove(issuer, kid).await
+        }
+
+        async fn keys(&self) -> Result<Vec<(DecentralizedIdentifier, String)>, KeyStoreError> {
+            self.calls.lock().unwrap().push(Call::Keys);
+            self.inner.keys().await
+        }
+    }
+
+    /// Fails every call.
+    struct FailingKeyStore;
+
+    #[async_trait]
+    impl KeyStore for FailingKeyStore {
+        async fn get(&self, _: &DecentralizedIdentifier, _: &str) -> Result<Option<StoredKey>, KeyStoreError> {
+            Err(KeyStoreError(String::from("connection refused")))
+        }
+
+        async fn put(&self, _: &DecentralizedIdentifier, _: &str, _: StoredKey) -> Result<(), KeyStoreError> {
+            Err(KeyStoreError(String::from("connection refused")))
+        }
+
+        async fn remove(&self, _: &DecentralizedIdentifier, _: &str) -> Result<(), KeyStoreError> {
+            Err(KeyStoreError(String::from("connection refused")))
+        }
+
+        async fn keys(&self) -> Result<Vec<(DecentralizedIdentifier, String)>, KeyStoreError> {
+            Err(KeyStoreError(String::from("connection refused")))
+        }
+    }
+
+    fn example_resolver() -> Arc<MockDIDResolver> {
+        Arc::new(MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT))
+    }
+
+    /// Yields before resolving, so concurrent verifications all miss the key cache.
+    struct YieldingResolver(Arc<MockDIDResolver>);
+
+    #[async_trait]
+    impl DocumentResolver for YieldingResolver {
+        async fn resolve_representation(
+            &self,
+            did: &DecentralizedIdentifier,
+        ) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+            tokio::task::yield_now().await;
+            self.0.resolve_representation(did).await
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_many() {
+        let resolver = example_resolver();
+        let verifier =
+            Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(YieldingResolver(resolver.clone())));
+        let mut uris = vec![VALID_PASS; 1000];
+        uris[500] = "not a pass";
+
+        let results = verifier.verify_many::<PublicCovidPass, _>(&uris).await;
+        assert_eq!(results.len(), 1000);
+        assert!(matches!(results[500], Err(NzcpError::QrBarcode(_))));
+        for (i, result) in results.iter().enumerate().filter(|(i, _)| *i != 500) {
+            assert_eq!(result.as_ref().map(|pass| &*pass.given_name), Ok("Jack"), "pass {}", i);
+        }
+        assert_eq!(resolver.resolution_count(), 1);
+        assert!(verifier.resolving.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn verify_stream() {
+        let resolver = example_resolver();
+        let verifier =
+            Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(YieldingResolver(resolver.clone())));
+        let uris = (0..300).map(|i| {
+            String::from(match i % 3 {
+                0 => VALID_PASS,
+                1 => expired_pass(),
+                _ => "not a pass",
+            })
+        });
+
+        let mut seen = vec![false; 300];
+        let mut results = Box::pin(verifier.verify_stream::<PublicCovidPass, _>(stream::iter(uris)));
+        while let Some((index, result)) = results.next().await {
+            assert!(!std::mem::replace(&mut seen[index], true), "index {} seen twice", index);
+            match index % 3 {
+                0 => assert!(result.is_ok()),
+                1 => assert!(matches!(result, Err(NzcpError::PassExpired { .. }))),
+                _ => assert!(matches!(result, Err(NzcpError::QrBarcode(_)))),
+            }
+        }
+        assert!(seen.iter().all(|seen| *seen));
+        assert_eq!(resolver.resolution_count(), 1);
+    }
+
+    /// Counts the resolutions in progress, which never finish.
+    #[derive(Default)]
+    struct CountingHangingResolver(Arc<AtomicUsize>);
+
+    #[async_trait]
+    impl DocumentResolver for CountingHangingResolver {
+        async fn resolve_representation(
+            &self,
+            _did: &DecentralizedIdentifier,
+        ) -> Result<Vec<u8>, DecentralizedIdentifierError> {
+            struct InProgress(Arc<AtomicUsize>);
+            impl Drop for InProgress {
+                fn drop(&mut self) {
+                    self.0.fetch_sub(1, Ordering::SeqCst);
+                }
+            }
+
+            self.0.fetch_add(1, Ordering::SeqCst);
+            let _in_progress = InProgress(Arc::clone(&self.0));
+            std::future::pending().await
+        }
+    }
+
+    #[tokio::test]
+    async fn dropping_verify_stream_stops_verifications() {
+        let resolver = CountingHangingResolver::default();
+        let in_progress = Arc::clone(&resolver.0);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+
+        let uris = stream::iter(vec![String::from(VALID_PASS); 10]);
+        let mut results = Box::pin(verifier.verify_stream::<PublicCovidPass, _>(uris));
+        let next = tokio::time::timeout(std::time::Duration::from_millis(10), results.next()).await;
+        assert!(next.is_err());
+        assert_eq!(in_progress.load(Ordering::SeqCst), 1);
+
+        drop(results);
+        assert_eq!(in_progress.load(Ordering::SeqCst), 0);
+        assert!(verifier.resolving.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn verify_all_errors() {
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(example_resolver());
+        let credential = verifier
+            .verify_credential_uri_all_errors::<PublicCovidPass>(VALID_PASS)
+            .await
+            .unwrap();
+        assert_eq!(credential, verifier.verify_credential_uri(VALID_PASS).await.unwrap());
+
+        // signed with the wrong key, revoked, and expiring before it's active
+        let pass = TestPass {
+            expiry: 1635883529,
+            ..TestPass::default()
+        };
+        let mut revoked = RevokedPassRegistry::new();
+        revoked.revoke(uuid::Uuid::from_bytes(pass.cwt_token_id));
+        let verifier = verifier.with_revoked_passes(Arc::new(revoked));
+        let uri = pass.sign(&test_signing_key(2));
+        let errors = verifier
+            .verify_credential_uri_all_errors::<PublicCovidPass>(&uri)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            errors,
+            vec![
+                NzcpError::InvalidSignature(CoseVerificationError::SignatureVerificationFailed),
+                NzcpError::PassRevoked(uuid::Uuid::from_bytes(pass.cwt_token_id)),
+                NzcpError::InvalidCWT(CwtValidationError::ExpiresBeforeActive {
+                    not_before: Utc.timestamp(1635883530, 0),
+                    expiry: Utc.timestamp(1635883529, 0),
+                }),
+                NzcpError::PassExpired {
+                    expiry: Utc.timestamp(1635883529, 0),
+                    checked_at: match errors[3] {
+                        NzcpError::PassExpired { checked_at, .. } => checked_at,
+                        _ => panic!("expected the pass to have expired"),
+                    },
+                },
+            ]
+        );
+        // and all of them are returned as one error, in the same order
+        assert_eq!(
+            NzcpError::from(errors.clone()),
+            NzcpError::MultipleErrors(errors.clone())
+        );
+        async fn verify(verifier: &Verifier, uri: &str) -> Result<PublicCovidPass, NzcpError> {
+            Ok(verifier.verify_credential_uri_all_errors(uri).await?.pass)
+        }
+        assert_eq!(
+            verify(&verifier, &uri).await,
+            Err(NzcpError::MultipleErrors(errors.clone()))
+        );
+
+        // the first is the error verification stops at
+        assert_eq!(
+            verifier.verify_pass_uri::<PublicCovidPass>(&uri).await,
+            Err(errors[0].clone())
+        );
+
+        // a malformed payload can't be checked any further
+        assert!(matches!(
+            verifier
+                .verify_credential_uri_all_errors::<PublicCovidPass>("not a pass")
+                .await
+                .unwrap_err()[..],
+            [NzcpError::QrBarcode(_)]
+        ));
+        // which is returned as itself
+        assert!(matches!(
+            verify(&verifier, "not a pass").await,
+            Err(NzcpError::QrBarcode(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn verify_with_timeout() {
+        let timeout = std::time::Duration::from_millis(10);
+
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(example_resolver());
+        let pass: PublicCovidPass = verifier
+            .verify_pass_uri_with_timeout(VALID_PASS, timeout)
+            .await
+            .unwrap();
+        assert_eq!(pass.given_name, "Jack");
+        assert!(matches!(
+            verifier
+                .verify_pass_uri_with_timeout::<PublicCovidPass>("not a pass", timeout)
+                .await,
+            Err(NzcpError::QrBarcode(_))
+        ));
+
+        let hanging = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(HangingResolver));
+        let result = hanging
+            .verify_pass_uri_with_timeout::<PublicCovidPass>(VALID_PASS, timeout)
+            .await;
+        assert_eq!(result, Err(NzcpError::Timeout(timeout)));
+        assert_eq!(
+            result.unwrap_err().to_string(),
+            "Timed out resolving the issuer's key after 10ms"
+        );
+    }
+
+    #[tokio::test]
+    async fn key_store_caches_resolved_keys() {
+        let resolver = example_resolver();
+        let key_store = Arc::new(RecordingKeyStore::default());
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(resolver.clone())
+            .with_key_store(key_store.clone());
+
+        for _ in 0..2 {
+            let _: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        }
+
+        let issuer = || String::from("did:web:nzcp.covid19.health.nz");
+        let kid = || String::from("key-1");
+        assert_eq!(resolver.resolution_count(), 1);
+        assert_eq!(
+            *key_store.calls.lock().unwrap(),
+            vec![
+                Call::Get(issuer(), kid()),
+                Call::Put(issuer(), kid()),
+                Call::Get(issuer(), kid())
+            ]
+        );
+    }
+
+    /// Capture the spans and events traced while running `verify`, formatted as text.
+    #[cfg(feature = "tracing")]
+    async fn traced<F: std::future::Future>(verify: F) -> String {
+        use std::io;
+
+        use tracing_subscriber::fmt::format::FmtSpan;
+
+        #[derive(Clone, Default)]
+        struct Output(Arc<Mutex<Vec<u8>>>);
+
+        impl io::Write for Output {
+            fn write(&mut self, bytes: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(bytes)
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = Output::default();
+        let writer = output.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_max_level(tracing::Level::DEBUG)
+            .with_span_events(FmtSpan::CLOSE)
+            .without_time()
+            .with_writer(move || writer.clone())
+            .finish();
+        // the test runtime runs the verification on this thread
+        let _default = tracing::subscriber::set_default(subscriber);
+        verify.await;
+
+        let output = output.0.lock().unwrap();
+        String::from_utf8(output.clone()).unwrap()
+    }
+
+    #[cfg(feature = "tracing")]
+    #[tokio::test]
+    async fn tracing_spans() {
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(example_resolver());
+        let output = traced(async {
+            let _: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        })
+        .await;
+        for span in [
+            "verify_pass:decode_payload{length=",
+            r#"verify_pass:resolve_did{issuer=did:web:nzcp.covid19.health.nz kid="key-1" cache="miss"}"#,
+            "verify_pass:verify_signature",
+            "verify_pass:validate_claims{issuer=did:web:nzcp.covid19.health.nz}",
+        ] {
+            assert!(output.contains(span), "{} not in:\n{}", span, output);
+        }
+        // no personal details are traced
+        for detail in ["Jack", "Sparrow", "1960"] {
+            assert!(!output.contains(detail), "{} in:\n{}", detail, output);
+        }
+
+        let output = traced(async {
+            let _: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        })
+        .await;
+        assert!(output.contains(r#"cache="hit""#), "{}", output);
+
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(MockDIDResolver::new()));
+        let output = traced(async {
+            let _ = verifier
+                .verify_pass_uri::<PublicCovidPass>(VALID_PASS)
+                .await
+                .unwrap_err();
+        })
+        .await;
+        assert!(output.contains("DID resolution failed"), "{}", output);
+        assert!(
+            output.contains(r#"pass verification failed code=did.resolution"#),
+            "{}",
+            output
+        );
+        assert!(!output.contains("verify_signature"), "{}", output);
+        assert!(!output.contains("validate_claims"), "{}", output);
+
+        // failures are traced without their messages, which may hold details from the pass
+        let key = test_signing_key(1);
+        let verifier = verifier_with_key(&key);
+        let mut subject = TestPass::default().credential_subject;
+        if let serde_cbor::Value::Map(subject) = &mut subject {
+            subject.insert(
+                serde_cbor::Value::Text(String::from("dob")),
+                serde_cbor::Value::Text(String::from("1960-4-16")),
+            );
+        }
+        let uri = TestPass {
+            credential_subject: subject,
+            ..TestPass::default()
+        }
+        .sign(&key);
+        let output = traced(async {
+            let _ = verifier.verify_pass_uri::<PublicCovidPass>(&uri).await.unwrap_err();
+        })
+        .await;
+        assert!(
+            output.contains("pass verification failed code=cbor.malformed kind=invalid_payload"),
+            "{}",
+            output
+        );
+        assert!(!output.contains("1960"), "{}", output);
+    }
+
+    #[tokio::test]
+    async fn observer() {
+        let summaries = Arc::new(Mutex::new(Vec::new()));
+        let recorded = summaries.clone();
+        let observer = move |summary: &VerificationSummary| recorded.lock().unwrap().push(summary.clone());
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(example_resolver())
+            .with_observer(Arc::new(observer));
+        let at = scanned_at();
+
+        for uri in [
+            VALID_PASS,
+            VALID_PASS,
+            expired_pass(),
+            invalid_signature_pass(),
+            "not a pass",
+        ] {
+            let _ = verifier.verify_pass_uri::<PublicCovidPass>(uri).await;
+        }
+        let _ = verifier.verify_pass_uri_at::<PublicCovidPass>(future_pass(), at).await;
+        let _ = verifier
+            .verify_pass_uri_with_timeout::<PublicCovidPass>(VALID_PASS, std::time::Duration::from_secs(5))
+            .await;
+        let _ = verifier
+            .verify_credential_uri_all_errors::<PublicCovidPass>(expired_pass())
+            .await;
+        let untrusted = Verifier::new(vec![]).with_observer(Arc::new(|summary: &VerificationSummary| {
+            assert_eq!(summary.outcome, VerificationOutcome::UntrustedIssuer);
+            panic!("observers can't fail verification");
+        }));
+        let _ = untrusted.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await;
+        let unresolvable = Verifier::new(vec![example_issuer()])
+            .with_resolver(Arc::new(MockDIDResolver::new()))
+            .with_observer(verifier.observer.clone().unwrap());
+        let _ = unresolvable.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await;
+
+        let summaries = summaries.lock().unwrap();
+        let outcomes = summaries
+            .iter()
+            .map(|summary| (summary.outcome, summary.key_from_cache))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            outcomes,
+            vec![
+                (VerificationOutcome::Valid, false),
+                (VerificationOutcome::Valid, true),
+                (VerificationOutcome::Expired, true),
+                (VerificationOutcome::SignatureInvalid, true),
+                (VerificationOutcome::Malformed, false),
+                (VerificationOutcome::NotYetActive, true),
+                (VerificationOutcome::Valid, true),
+                (VerificationOutcome::Expired, true),
+                (VerificationOutcome::ResolutionError, false),
+            ]
+        );
+        assert_eq!(summaries[0].issuer, Some(example_issuer()));
+        assert_eq!(summaries[4].issuer, None);
+    }
+
+    #[tokio::test]
+    async fn verify_bytes() {
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(example_resolver());
+
+        let pass: PublicCovidPass = verifier.verify_pass_bytes(VALID_PASS.as_bytes()).await.unwrap();
+        assert_eq!(pass, verifier.verify_pass_uri(VALID_PASS).await.unwrap());
+
+        let mut scanned = VALID_PASS.as_bytes().to_vec();
+        scanned[20] = 0x80;
+        assert_eq!(
+            verifier.verify_pass_bytes::<PublicCovidPass>(&scanned).await,
+            Err(NzcpError::QrBarcode(QrBarcodeError::NonAscii { offset: 20 }))
+        );
+    }
+
+    #[tokio::test]
+    async fn verify_cbor() {
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(example_resolver());
+        let barcode: QrBarcode = VALID_PASS.parse().unwrap();
+
+        let pass: PublicCovidPass = verifier.verify_pass_cbor(&barcode.cbor).await.unwrap();
+        assert_eq!(pass.given_name, "Jack");
+
+        assert!(matches!(
+            verifier.verify_pass_cbor::<PublicCovidPass>(&barcode.cbor[1..]).await,
+            Err(NzcpError::InvalidPayload(_))
+        ));
+
+        // as received from a transport that has already stripped the prefix and base32
+        let cbor = base32::decode(
+            base32::Alphabet::RFC4648 { padding: false },
+            VALID_PASS.strip_prefix("NZCP:/1/").unwrap(),
+        )
+        .unwrap();
+        let credential = verifier.verify_credential_cbor::<PublicCovidPass>(&cbor).await.unwrap();
+        assert_eq!(
+            credential,
+            VerifiedCredential {
+                barcode_version: None,
+                ..verifier.verify_credential_uri(VALID_PASS).await.unwrap()
+            }
+        );
+
+        // only COSE_Sign1 (tag 18) is accepted, not e.g. COSE_Sign (tag 98)
+        let cose_sign = [&[0xd8, 0x62][..], &cbor[1..]].concat();
+        assert!(matches!(
+            verifier.verify_pass_cbor::<PublicCovidPass>(&cose_sign).await,
+            Err(NzcpError::InvalidPayload(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn strict_cbor() {
+        let key = test_signing_key(1);
+        let resolver = resolver_with_key(&key);
+        let strict = || {
+            Verifier::from_config(VerifierConfig {
+                trusted_issuers: vec![example_issuer()],
+                strict_cbor: true,
+                ..VerifierConfig::default()
+            })
+        };
+
+        // nbf encoded as 8 rather than 4 bytes
+        let claims = serde_cbor::to_vec(&TestPass::default().claims()).unwrap();
+        let claims = hex::encode(claims).replacen("051a61819a0a", "051b0000000061819a0a", 1);
+        let cbor = sign_cose(&hex::decode(claims).unwrap(), &key, "key-1").unwrap();
+
+        let lenient = Verifier::new(vec![example_issuer()]).with_resolver(resolver.clone());
+        assert!(lenient
+            .verify_credential_cbor_at::<PublicCovidPass>(&cbor, scanned_at())
+            .await
+            .is_ok());
+        assert_eq!(
+            strict()
+                .with_resolver(resolver)
+                .verify_credential_cbor_at::<PublicCovidPass>(&cbor, scanned_at())
+                .await,
+            Err(NzcpError::NonCanonicalCbor(CanonicalCborError::NonShortestForm {
+                kind: "integer",
+                path: String::from("claims key 5")
+            }))
+        );
+
+        let verifier = strict().with_resolver(example_resolver());
+        assert!(verifier.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await.is_ok());
+
+        // deeply nested CBOR is rejected rather than overflowing the stack
+        let deep = [&[0xd2, 0x84, 0x40, 0xa0][..], &[0x81; 100_000]].concat();
+        assert!(matches!(
+            verifier.verify_pass_cbor::<PublicCovidPass>(&deep).await,
+            Err(NzcpError::NonCanonicalCbor(CanonicalCborError::TooDeeplyNested(_)))
+        ));
+        let uri = format!(
+            "NZCP:/1/{}",
+            base32::encode(base32::Alphabet::RFC4648 { padding: false }, &deep)
+        );
+        assert!(verifier
+            .verify_credential_uri_all_errors::<PublicCovidPass>(&uri)
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn try_from_config() {
+        let config = || VerifierConfig {
+            trusted_issuers: vec![example_issuer()],
+            ..VerifierConfig::default()
+        };
+        let example_key = example_verifying_key();
+        let pins =
+            |key: &VerifyingKey| HashMap::from([(example_issuer(), HashSet::from([KeyPin::from_verifying_key(key)]))]);
+
+        assert_eq!(
+            Verifier::try_from_config(VerifierConfig {
+                trusted_issuers: vec![],
+                ..config()
+            })
+            .err(),
+            Some(VerifierConfigError::NoTrustedIssuers)
+        );
+        assert_eq!(
+            Verifier::try_from_config(VerifierConfig {
+                trusted_issuers: vec![ministry_of_health_issuer()],
+                pinned_keys: pins(&example_key),
+                ..config()
+            })
+            .err(),
+            Some(VerifierConfigError::PinnedUntrustedIssuer(example_issuer()))
+        );
+        assert_eq!(
+            Verifier::try_from_config(VerifierConfig {
+                max_did_document_size: 0,
+                ..config()
+            })
+            .err(),
+            Some(VerifierConfigError::ZeroMaxDidDocumentSize)
+        );
+        let overrides = |url: &str| HashMap::from([(example_issuer().to_string(), url.to_owned())]);
+        assert_eq!(
+            Verifier::try_from_config(VerifierConfig {
+                did_url_overrides: overrides("http://localhost:8080/did.json"),
+                ..config()
+            })
+            .err(),
+            Some(VerifierConfigError::DidUrlOverridesNotAllowed)
+        );
+        assert_eq!(
+            Verifier::try_from_config(VerifierConfig {
+                did_url_overrides: overrides("not a URL"),
+                allow_did_url_overrides: true,
+                ..config()
+            })
+            .err(),
+            Some(VerifierConfigError::InvalidDidUrlOverride {
+                did: example_issuer().to_string(),
+                url: String::from("not a URL"),
+            })
+        );
+        for leeway in [Duration::seconds(-1), Duration::days(1)] {
+            assert_eq!(
+                Verifier::try_from_config(VerifierConfig {
+                    clock_skew_leeway: leeway,
+                    ..config()
+                })
+                .err(),
+                Some(VerifierConfigError::InvalidClockSkewLeeway(leeway))
+            );
+        }
+        assert!(Verifier::try_from_config(VerifierConfig {
+            did_url_overrides: overrides("http://localhost:8080/did.json"),
+            allow_did_url_overrides: true,
+            ..config()
+        })
+        .is_ok());
+
+        // strict, pinned, and with leeway, caching the key between verifications
+        let resolver = example_resolver();
+        let verifier = Verifier::try_from_config(VerifierConfig {
+            strict_cbor: true,
+            pinned_keys: pins(&example_key),
+            clock_skew_leeway: Duration::minutes(1),
+            ..config()
+        })
+        .unwrap()
+        .with_resolver(resolver.clone());
+        for _ in 0..2 {
+            let credential = verifier
+                .verify_credential_uri::<PublicCovidPass>(VALID_PASS)
+                .await
+                .unwrap();
+            assert_eq!(credential.matched_pin, Some(KeyPin::from_verifying_key(&example_key)));
+        }
+        assert_eq!(resolver.resolution_count(), 1);
+
+        // pinned to another key
+        let verifier = Verifier::try_from_config(VerifierConfig {
+            pinned_keys: pins(&test_signing_key(1).verifying_key()),
+            ..config()
+        })
+        .unwrap()
+        .with_resolver(example_resolver());
+        assert!(matches!(
+            verifier.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await,
+            Err(NzcpError::InvalidSignature(CoseVerificationError::UnpinnedKey(_)))
+        ));
+    }
+
+    #[tokio::test]
+    async fn resolution_rate_limit() {
+        let (key_1, key_2) = (test_signing_key(1), test_signing_key(2));
+        let document = did_document(
+            &example_issuer(),
+            &[("key-1", key_1.verifying_key()), ("key-2", key_2.verifying_key())],
+        );
+        let resolver = Arc::new(MockDIDResolver::new().with_document(&example_issuer(), &document));
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(resolver.clone())
+            .with_resolution_rate_limiter(Arc::new(ResolutionRateLimiter::new(0.001, 1)));
+        let pass = |kid: &str, key| {
+            TestPass {
+                kid: String::from(kid),
+                ..TestPass::default()
+            }
+            .sign(key)
+        };
+
+        assert!(verifier
+            .verify_pass_uri::<PublicCovidPass>(&pass("key-1", &key_1))
+            .await
+            .is_ok());
+        assert_eq!(
+            verifier
+                .verify_pass_uri::<PublicCovidPass>(&pass("key-2", &key_2))
+                .await,
+            Err(NzcpError::InvalidSignature(
+                CoseVerificationError::DecentralizedIdentifierResolution(DecentralizedIdentifierError::RateLimited)
+            ))
+        );
+        assert_eq!(resolver.resolution_count(), 1);
+
+        // cached keys don't need a resolution
+        assert!(verifier
+            .verify_pass_uri::<PublicCovidPass>(&pass("key-1", &key_1))
+            .await
+            .is_ok());
+    }
+
+    #[tokio::test]
+    async fn credential_claims() {
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(example_resolver());
+        let credential = verifier
+            .verify_credential_uri::<PublicCovidPass>(VALID_PASS)
+            .await
+            .unwrap();
+
+        assert_eq!(credential.claims.issuer, example_issuer());
+        assert_ne!(credential.claims.issuer, ministry_of_health_issuer());
+        assert_eq!(credential.barcode_version, Some(1));
+        assert_eq!(credential.claims.not_before.timestamp(), 1635883530);
+        assert_eq!(credential.claims.expiry.timestamp(), 1951416330);
+        assert_eq!(
+            credential.claims.cwt_token_id,
+            uuid::Uuid::parse_str("60a4f54d-4e30-4332-be33-ad78b1eafa4b").unwrap()
+        );
+        assert_eq!(credential.verify_identity_match("SPARROW", 1960), MatchResult::Full);
+    }
+
+    #[tokio::test]
+    async fn credentials_can_be_shared() {
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(example_resolver());
+        let credential = verifier
+            .verify_credential_uri::<PublicCovidPass>(VALID_PASS)
+            .await
+            .unwrap();
+
+        let last_scanned = Arc::new(std::sync::Mutex::new(None));
+        let shared = last_scanned.clone();
+        let stored = credential.clone();
+        tokio::spawn(async move { *shared.lock().unwrap() = Some(stored) })
+            .await
+            .unwrap();
+
+        assert_eq!(*last_scanned.lock().unwrap(), Some(credential));
+    }
+
+    #[tokio::test]
+    async fn clock_skew_leeway() {
+        let key = test_signing_key(1);
+        let resolver = resolver_with_key(&key);
+        let issued = TestPass {
+            not_before: Utc::now().timestamp() + 20,
+            ..TestPass::default()
+        }
+        .sign(&key);
+
+        let strict = Verifier::new(vec![example_issuer()]).with_resolver(resolver.clone());
+        assert!(matches!(
+            strict.verify_pass_uri::<PublicCovidPass>(&issued).await,
+            Err(NzcpError::PassNotYetActive { .. })
+        ));
+
+        let lenient = Verifier::try_from_config(VerifierConfig {
+            trusted_issuers: vec![example_issuer()],
+            clock_skew_leeway: Duration::minutes(1),
+            ..VerifierConfig::default()
+        })
+        .unwrap()
+        .with_resolver(resolver);
+        let credential = lenient.verify_credential_uri::<PublicCovidPass>(&issued).await.unwrap();
+        assert!(credential.leeway_used.is_some_and(|used| used > Duration::zero()));
+    }
+
+    #[tokio::test]
+    async fn max_future_nbf() {
+        let key = test_signing_key(1);
+        let resolver = resolver_with_key(&key);
+        let not_before = scanned_at() + Duration::days(2);
+        let issued = TestPass {
+            not_before: not_before.timestamp(),
+            ..TestPass::default()
+        }
+        .sign(&key);
+        let verifier = |max_future_nbf, skip_not_before_check| {
+            Verifier::from_config(VerifierConfig {
+                trusted_issuers: vec![example_issuer()],
+                max_future_nbf,
+                claim_verification: ClaimVerificationConfig {
+                    skip_not_before_check,
+                    ..Default::default()
+                },
+                ..VerifierConfig::default()
+            })
+            .with_resolver(resolver.clone())
+        };
+        let issued = issued.as_str();
+        let verify = |verifier: Verifier, at| async move {
+            verifier
+                .verify_pass_uri_at::<PublicCovidPass>(issued, at)
+                .await
+                .map(|_| ())
+        };
+        let too_far = Err(NzcpError::PassTooFarInFuture {
+            not_before,
+            checked_at: scanned_at(),
+        });
+
+        // not checked by default
+        assert_eq!(
+            verify(verifier(None, false), scanned_at()).await,
+            Err(NzcpError::PassNotYetActive {
+                not_before,
+                checked_at: scanned_at(),
+            })
+        );
+        assert_eq!(verify(verifier(None, true), scanned_at()).await, Ok(()));
+
+        let day = Some(Duration::days(1));
+        assert_eq!(verify(verifier(day, false), scanned_at()).await, too_far);
+        assert_eq!(verify(verifier(day, true), scanned_at()).await, too_far);
+        assert!(matches!(
+            verify(verifier(day, false), not_before - Duration::hours(1)).await,
+            Err(NzcpError::PassNotYetActive { .. })
+        ));
+        assert_eq!(verify(verifier(day, false), not_before).await, Ok(()));
+    }
+
+    #[tokio::test]
+    async fn raw_verifiable_credential() {
+        let key = test_signing_key(1);
+        let verifier = verifier_with_key(&key);
+
+        let text = |text: &str| serde_cbor::Value::Text(String::from(text));
+        let mut pass = TestPass::default();
+        if let serde_cbor::Value::Map(subject) = &mut pass.credential_subject {
+            subject.insert(text("vaccinations"), serde_cbor::Value::Integer(3));
+            subject.insert(text("photo"), serde_cbor::Value::Bytes(vec![0xff, 0xd8]));
+            subject.insert(
+                serde_cbor::Value::Bytes(b"extra".to_vec()),
+                serde_cbor::Value::Map([(text("nested"), serde_cbor::Value::Bool(true))].into()),
+            );
+        }
+        let credential = verifier
+            .verify_credential_cbor_at::<PublicCovidPass>(&pass.sign_cbor(&key), scanned_at())
+            .await
+            .unwrap();
+
+        assert_eq!(credential.pass.given_name, "Jack");
+        assert_eq!(
+            credential.verifiable_credential.unwrap()["credentialSubject"],
+            serde_json::json!({
+                "givenName": "Jack",
+                "familyName": "Sparrow",
+                "dob": "1960-04-16",
+                "vaccinations": 3,
+                "photo": "_9g",
+                "ZXh0cmE": {"nested": true},
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn raw_cose() {
+        use p256::ecdsa::{signature::Verifier as _, Signature};
+
+        let key = test_signing_key(1);
+        let verifier = verifier_with_key(&key);
+        let pass = TestPass::default();
+        let credential = verifier
+            .verify_credential_cbor_at::<PublicCovidPass>(&pass.sign_cbor(&key), scanned_at())
+            .await
+            .unwrap();
+        let raw_cose = credential.raw_cose();
+
+        // {1: -7, 4: h'6B65792D31'}
+        assert_eq!(raw_cose.protected, hex::decode("a2012604456b65792d31").unwrap());
+        assert_eq!(raw_cose.payload, serde_cbor::to_vec(&pass.claims()).unwrap());
+        let sig_structure = serde_cbor::to_vec(&serde_cbor::Value::Array(vec![
+            serde_cbor::Value::Text(String::from("Signature1")),
+            serde_cbor::Value::Bytes(raw_cose.protected.clone()),
+            serde_cbor::Value::Bytes(Vec::new()),
+            serde_cbor::Value::Bytes(raw_cose.payload.clone()),
+        ]))
+        .unwrap();
+        let signature = Signature::try_from(raw_cose.signature.as_slice()).unwrap();
+        assert!(key.verifying_key().verify(&sig_structure, &signature).is_ok());
+
+        assert_eq!(
+            format!("{:?}", raw_cose),
+            format!(
+                "RawCose {{ protected: 10 bytes, payload: {} bytes, signature: 64 bytes }}",
+                raw_cose.payload.len()
+            )
+        );
+    }
+
+    #[tokio::test]
+    async fn pass_types() {
+        #[derive(Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+        struct VaccinationRecord {
+            doses: u8,
+        }
+
+        impl Pass for VaccinationRecord {
+            const CREDENTIAL_TYPE: &'static str = "VaccinationRecord";
+
+            const CONTEXT_URL: &'static str = PublicCovidPass::CONTEXT_URL;
+        }
+
+        let key = test_signing_key(1);
+        let verifier = verifier_with_key(&key);
+        let claims = CwtClaims {
+            issuer: example_issuer(),
+            not_before: Utc.ymd(2021, 11, 2).and_hms(20, 5, 30),
+            expiry: Utc.ymd(2031, 11, 2).and_hms(20, 5, 30),
+            cwt_token_id: uuid::Uuid::from_bytes([1; 16]),
+        };
+        let record = VaccinationRecord { doses: 3 };
+        let uri = crate::sign_pass(&record, &claims, &key, "key-1").unwrap();
+        let at = scanned_at();
+
+        assert_eq!(
+            verifier.verify_pass_uri_at::<VaccinationRecord>(&uri, at).await,
+            Ok(record)
+        );
+
+        let error = verifier
+            .verify_pass_uri_at::<PublicCovidPass>(&uri, at)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error,
+            NzcpError::InvalidCWT(CwtValidationError::InvalidType {
+                expected: vec![String::from("VerifiableCredential"), String::from("PublicCovidPass")],
+                found: vec![String::from("VerifiableCredential"), String::from("VaccinationRecord")],
+            })
+        );
+        assert_eq!(
+            error.to_string(),
+            "Invalid CWT: InvalidType { expected: [\"VerifiableCredential\", \"PublicCovidPass\"], found: \
+             [\"VerifiableCredential\", \"VaccinationRecord\"] }"
+        );
+
+        let public_covid_pass = TestPass::default().sign(&key);
+        assert!(matches!(
+            verifier
+                .verify_pass_uri_at::<VaccinationRecord>(&public_covid_pass, at)
+                .await,
+            Err(NzcpError::InvalidCWT(CwtValidationError::InvalidType { .. }))
+        ));
+    }
+
+    #[tokio::test]
+    async fn serialize_credential() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+        let credential = verifier
+            .verify_credential_uri_at::<PublicCovidPass>(VALID_PASS, scanned_at())
+            .await
+            .unwrap();
+
+        let json = serde_json::to_value(&credential).unwrap();
+        assert_eq!(
+            json,
+            serde_json::json!({
+                "pass": {"givenName": "Jack", "familyName": "Sparrow", "dob": "1960-04-16"},
+                "claims": {
+                    "issuer": "did:web:nzcp.covid19.health.nz",
+                    "notBefore": "2021-11-02T20:05:30Z",
+                    "expiry": "2031-11-02T20:05:30Z",
+                    "cwtTokenId": "60a4f54d-4e30-4332-be33-ad78b1eafa4b",
+                },
+                "matchedPin": null,
+                "leewayUsed": null,
+                "barcodeVersion": 1,
+                "kidHeader": "protected",
+                "verifiableCredential": credential.verifiable_credential,
+                "namesNormalized": false,
+            })
+        );
+        assert_eq!(
+            serde_json::from_value::<PublicCovidPass>(json["pass"].clone()).unwrap(),
+            credential.pass
+        );
+    }
+
+    #[tokio::test]
+    async fn without_family_name() {
+        let key = test_signing_key(1);
+        let verifier = verifier_with_key(&key);
+
+        let mut pass = TestPass::default();
+        if let serde_cbor::Value::Map(subject) = &mut pass.credential_subject {
+            subject.remove(&serde_cbor::Value::Text(String::from("familyName")));
+        }
+        let pass = verifier
+            .verify_pass_uri_at::<PublicCovidPass>(&pass.sign(&key), scanned_at())
+            .await
+            .unwrap();
+
+        assert_eq!(pass.given_name, "Jack");
+        assert_eq!(pass.family_name, None);
+    }
+
+    #[tokio::test]
+    async fn normalize_names() {
+        let key = test_signing_key(1);
+        let resolver = resolver_with_key(&key);
+        let verify = |given_name: &str, normalize_names| {
+            let verifier = Verifier::from_config(VerifierConfig {
+                trusted_issuers: vec![example_issuer()],
+                normalize_names,
+                ..Default::default()
+            })
+            .with_resolver(resolver.clone());
+            let mut pass = TestPass::default();
+            if let serde_cbor::Value::Map(subject) = &mut pass.credential_subject {
+                subject.insert(
+                    serde_cbor::Value::Text(String::from("givenName")),
+                    serde_cbor::Value::Text(String::from(given_name)),
+                );
+            }
+            let cbor = pass.sign_cbor(&key);
+            async move {
+                verifier
+                    .verify_credential_cbor_at::<PublicCovidPass>(&cbor, scanned_at())
+                    .await
+                    .unwrap()
+            }
+        };
+
+        let precomposed = verify("\u{100}miria", true).await;
+        let combining = verify("A\u{304}miria", true).await;
+        assert_eq!(precomposed.pass, combining.pass);
+        assert_eq!(combining.pass.given_name, "\u{100}miria");
+        assert!(!precomposed.names_normalized);
+        assert!(combining.names_normalized);
+
+        let unnormalized = verify("A\u{304}miria", false).await;
+        assert_eq!(unnormalized.pass.given_name, "A\u{304}miria");
+        assert!(!unnormalized.names_normalized);
+    }
+
+    #[tokio::test]
+    async fn verify_at() {
+        let key = test_signing_key(1);
+        let verifier = verifier_with_key(&key);
+
+        // active for 2020
+        let expired = TestPass {
+            not_before: 1577836800,
+            expiry: 1609459200,
+            ..TestPass::default()
+        }
+        .sign(&key);
+
+        let scanned_at = Utc.ymd(2020, 6, 1).and_hms(12, 0, 0);
+        let pass: PublicCovidPass = verifier.verify_pass_uri_at(&expired, scanned_at).await.unwrap();
+        assert_eq!(pass.given_name, "Jack");
+
+        assert!(matches!(
+            verifier.verify_pass_uri::<PublicCovidPass>(&expired).await,
+            Err(NzcpError::PassExpired { expiry, .. }) if expiry == Utc.ymd(2021, 1, 1).and_hms(0, 0, 0)
+        ));
+        assert_eq!(
+            verifier
+                .verify_pass_uri_at::<PublicCovidPass>(&expired, Utc.ymd(2019, 6, 1).and_hms(12, 0, 0))
+                .await,
+            Err(NzcpError::PassNotYetActive {
+                not_before: Utc.ymd(2020, 1, 1).and_hms(0, 0, 0),
+                checked_at: Utc.ymd(2019, 6, 1).and_hms(12, 0, 0),
+            })
+        );
+
+        // the pass is active from its not before date, and inactive from its expiry date
+        let not_before = Utc.ymd(2020, 1, 1).and_hms(0, 0, 0);
+        let expiry = Utc.ymd(2021, 1, 1).and_hms(0, 0, 0);
+        assert!(verifier
+            .verify_pass_uri_at::<PublicCovidPass>(&expired, not_before)
+            .await
+            .is_ok());
+        assert_eq!(
+            verifier
+                .verify_pass_uri_at::<PublicCovidPass>(&expired, not_before - Duration::seconds(1))
+                .await,
+            Err(NzcpError::PassNotYetActive {
+                not_before,
+                checked_at: not_before - Duration::seconds(1),
+            })
+        );
+        assert!(verifier
+            .verify_pass_uri_at::<PublicCovidPass>(&expired, expiry - Duration::seconds(1))
+            .await
+            .is_ok());
+        assert_eq!(
+            verifier.verify_pass_uri_at::<PublicCovidPass>(&expired, expiry).await,
+            Err(NzcpError::PassExpired {
+                expiry,
+                checked_at: expiry,
+            })
+        );
+    }
+
+    #[tokio::test]
+    async fn expired_keys_are_resolved_again() {
+        let resolver = example_resolver();
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(resolver.clone())
+            .with_key_ttl(Duration::zero());
+
+        for _ in 0..2 {
+            let _: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        }
+
+        assert_eq!(resolver.resolution_count(), 2);
+    }
+
+    #[tokio::test]
+    async fn failing_key_store_falls_back_to_resolution() {
+        let resolver = example_resolver();
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(resolver.clone())
+            .with_key_store(Arc::new(FailingKeyStore));
+
+        for _ in 0..2 {
+            let _: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        }
+
+        assert_eq!(resolver.resolution_count(), 2);
+    }
+}
@@ -0,0 +1,182 @@
+use chrono::{DateTime, SecondsFormat, Utc};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use super::{VerificationOutcome, VerifiedCredential, Verifier};
+use crate::{
+    decentralised_identifier::DecentralizedIdentifier,
+    error::{ErrorCode, NzcpError},
+    pass::Pass,
+};
+
+/// Where a pass was verified, and by whom, recorded in each `AuditEntry`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct AuditContext {
+    /// The device or station the pass was scanned at.
+    pub station_id: String,
+
+    /// The operator who scanned the pass, if known.
+    pub operator_id: Option<String>,
+}
+
+/// Records verifications in an audit trail, see `Verifier::verify_pass_and_log`.
+///
+/// Logging can't fail the verification, so loggers that can fail (e.g. writing to a file) should handle or report
+/// their own errors.
+pub trait AuditLogger: Send + Sync {
+    fn log(&self, entry: &AuditEntry);
+}
+
+/// A record of a verification, without any of the details of the pass's subject.
+///
+/// Serializes with camelCase field names, the timestamp as an RFC 3339 string, and the outcome and error code as
+/// their snake_case and dotted names (e.g. `"outcome": "expired", "code": "pass.expired"`).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuditEntry {
+    /// When the pass was verified.
+    pub timestamp: DateTime<Utc>,
+
+    pub station_id: String,
+
+    pub operator_id: Option<String>,
+
+    pub outcome: VerificationOutcome,
+
+    /// Why verification failed, or `None` if it succeeded.
+    pub code: Option<ErrorCode>,
+
+    /// The ID of the pass as a `jti` URN (e.g. `urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b`), if verified or
+    /// revoked. Failed passes' IDs aren't recorded, as they can't be trusted.
+    pub jti: Option<String>,
+
+    /// The issuer of the pass, if verified.
+    pub issuer: Option<DecentralizedIdentifier>,
+}
+
+impl AuditEntry {
+    fn new<P>(
+        result: &Result<VerifiedCredential<P>, NzcpError>,
+        context: &AuditContext,
+        timestamp: DateTime<Utc>,
+    ) -> Self {
+        let (jti, issuer) = match result {
+            Ok(credential) => (Some(credential.claims.jti()), Some(credential.claims.issuer.clone())),
+            Err(NzcpError::PassRevoked(cwt_token_id)) => (Some(cwt_token_id.to_urn().to_string()), None),
+            Err(_) => (None, None),
+        };
+        AuditEntry {
+            timestamp,
+            station_id: context.station_id.clone(),
+            operator_id: context.operator_id.clone(),
+            outcome: VerificationOutcome::of(result),
+            code: result.as_ref().err().map(NzcpError::code),
+            jti,
+            issuer,
+        }
+    }
+}
+
+impl Serialize for AuditEntry {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut entry = serializer.serialize_struct("AuditEntry", 7)?;
+        entry.serialize_field(
+            "timestamp",
+            &self.timestamp.to_rfc3339_opts(SecondsFormat::Millis, true),
+        )?;
+        entry.serialize_field("stationId", &self.station_id)?;
+        entry.serialize_field("operatorId", &self.operator_id)?;
+        entry.serialize_field("outcome", self.outcome.as_str())?;
+        entry.serialize_field("code", &self.code)?;
+        entry.serialize_field("jti", &self.jti)?;
+        entry.serialize_field("issuer", &self.issuer)?;
+        entry.end()
+    }
+}
+
+impl Verifier {
+    /// Verify a pass barcode URI like `verify_credential_uri`, and log the outcome to `logger` before returning it, so
+    /// every verification is in the audit trail.
+    pub async fn verify_pass_and_log<P: Pass>(
+        &self,
+        uri: &str,
+        context: &AuditContext,
+        logger: &dyn AuditLogger,
+    ) -> Result<VerifiedCredential<P>, NzcpError> {
+        let timestamp = Utc::now();
+        let result = self.verify_credential_uri_at(uri, timestamp).await;
+        logger.log(&AuditEntry::new(&result, context, timestamp));
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use serde_json::json;
+
+    use super::*;
+    use crate::{
+        testing::{example_issuer, test_passes::expired_pass, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+        PublicCovidPass,
+    };
+
+    #[derive(Default)]
+    struct RecordingLogger(Mutex<Vec<AuditEntry>>);
+
+    impl AuditLogger for RecordingLogger {
+        fn log(&self, entry: &AuditEntry) {
+            self.0.lock().unwrap().push(entry.clone());
+        }
+    }
+
+    #[tokio::test]
+    async fn verify_pass_and_log() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+        let context = AuditContext {
+            station_id: String::from("entrance-1"),
+            operator_id: Some(String::from("operator-7")),
+        };
+        let logger = RecordingLogger::default();
+
+        let credential = verifier
+            .verify_pass_and_log::<PublicCovidPass>(VALID_PASS, &context, &logger)
+            .await
+            .unwrap();
+        let _ = verifier
+            .verify_pass_and_log::<PublicCovidPass>(expired_pass(), &context, &logger)
+            .await
+            .unwrap_err();
+
+        let entries = logger.0.lock().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].outcome, VerificationOutcome::Valid);
+        assert_eq!(entries[0].jti, Some(credential.claims.jti()));
+        assert_eq!(entries[1].outcome, VerificationOutcome::Expired);
+        assert_eq!(entries[1].code, Some(ErrorCode::PassExpired));
+        assert_eq!(entries[1].jti, None);
+
+        let mut json = serde_json::to_value(&entries[0]).unwrap();
+        assert!(json["timestamp"].as_str().unwrap().ends_with('Z'));
+        json["timestamp"] = json!(null);
+        assert_eq!(
+            json,
+            json!({
+                "timestamp": null,
+                "stationId": "entrance-1",
+                "operatorId": "operator-7",
+                "outcome": "valid",
+                "code": null,
+                "jti": "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b",
+                "issuer": "did:web:nzcp.covid19.health.nz",
+            })
+        );
+        assert_eq!(
+            serde_json::to_value(&entries[1]).unwrap()["code"],
+            json!("pass.expired")
+        );
+    }
+}
@@ -0,0 +1,115 @@
+use std::fmt;
+
+use chrono::Duration;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+use super::KeyPin;
+use crate::{
+    pass::public_covid_pass::{MatchResult, PublicCovidPass},
+    payload::{cose::KidHeader, cwt::CwtClaims},
+};
+
+#[cfg(feature = "json-ld")]
+mod json_ld;
+#[cfg(feature = "jwt")]
+mod jwt;
+
+/// A verified pass, along with details of how it was verified.
+///
+/// Serializes with camelCase field names (e.g. to pass results to a UI as JSON), with `pass` as its credential
+/// subject, `matchedPin` base64url encoded, and `leewayUsed` in seconds. The raw COSE structure is left out. It
+/// doesn't implement `Deserialize`, as deserialized data wouldn't have been verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedCredential<P> {
+    /// The credential subject of the pass.
+    pub pass: P,
+
+    /// The issuer, validity period, and ID of the pass. Compare `claims.issuer` with `ministry_of_health_issuer()` to
+    /// tell production passes from test passes.
+    pub claims: CwtClaims,
+
+    /// The pin matching the key the pass was signed with, if the issuer's keys are pinned.
+    pub matched_pin: Option<KeyPin>,
+
+    /// How far outside its validity period the pass was, if it was only accepted because of the verifier's clock
+    /// skew leeway.
+    pub leeway_used: Option<Duration>,
+
+    /// The version-identifier of the barcode URI the pass was verified from, or `None` if it was verified from CBOR.
+    pub barcode_version: Option<u32>,
+
+    /// The COSE header the signing key's ID was read from, see `VerifierConfig::unprotected_kid`.
+    pub kid_header: KidHeader,
+
+    /// The whole `vc` claim as JSON, including any fields `pass` doesn't have (e.g. from a later version of the pass
+    /// type). See the `codec` module for how CBOR maps onto JSON. `None` if the claim holds values JSON can't
+    /// represent, such as non-finite floats.
+    pub verifiable_credential: Option<serde_json::Value>,
+
+    /// Whether normalizing the names on the pass changed them, see `VerifierConfig::normalize_names`. Always false
+    /// when names aren't normalized.
+    pub names_normalized: bool,
+
+    pub(crate) raw_cose: RawCose,
+}
+
+impl<P> VerifiedCredential<P> {
+    /// The signed parts of the pass's COSE_Sign1 structure, e.g. to check the signature with another COSE
+    /// implementation.
+    pub fn raw_cose(&self) -> &RawCose {
+        &self.raw_cose
+    }
+}
+
+impl VerifiedCredential<PublicCovidPass> {
+    /// How closely the pass matches an identity document (e.g. a passport) with the given surname and year of birth,
+    /// see `PublicCovidPass::match_identity`.
+    pub fn verify_identity_match(&self, surname: &str, dob_year: u16) -> MatchResult {
+        self.pass.match_identity(surname, dob_year)
+    }
+}
+
+impl<P: Serialize> Serialize for VerifiedCredential<P> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: Serializer,
+    {
+        let mut credential = serializer.serialize_struct("VerifiedCredential", 8)?;
+        credential.serialize_field("pass", &self.pass)?;
+        credential.serialize_field("claims", &self.claims)?;
+        credential.serialize_field("matchedPin", &self.matched_pin.map(|pin| pin.to_string()))?;
+        credential.serialize_field("leewayUsed", &self.leeway_used.map(|leeway| leeway.num_seconds()))?;
+        credential.serialize_field("barcodeVersion", &self.barcode_version)?;
+        credential.serialize_field("kidHeader", &self.kid_header)?;
+        credential.serialize_field("verifiableCredential", &self.verifiable_credential)?;
+        credential.serialize_field("namesNormalized", &self.names_normalized)?;
+        credential.end()
+    }
+}
+
+/// The signed parts of a pass's COSE_Sign1 structure, see `VerifiedCredential::raw_cose`.
+///
+/// The signature can be checked against the issuer's key over the `Sig_structure` (RFC 8152, section 4.4)
+/// `["Signature1", protected, h'', payload]`. Only the lengths are shown when debug formatted, to keep logs short.
+#[derive(Clone, PartialEq, Eq)]
+pub struct RawCose {
+    /// The CBOR encoded protected headers.
+    pub protected: Vec<u8>,
+
+    /// The CBOR encoded CWT claims.
+    pub payload: Vec<u8>,
+
+    /// The ES256 signature, as the concatenated `r` and `s` values.
+    pub signature: Vec<u8>,
+}
+
+impl fmt::Debug for RawCose {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let bytes = |bytes: &[u8]| format!("{} bytes", bytes.len());
+        f.debug_struct("RawCose")
+            .field("protected", &format_args!("{}", bytes(&self.protected)))
+            .field("payload", &format_args!("{}", bytes(&self.payload)))
+            .field("signature", &format_args!("{}", bytes(&self.signature)))
+            .finish()
+    }
+}
@@ -0,0 +1,66 @@
+use chrono::SecondsFormat;
+use serde::Serialize;
+use serde_json::json;
+
+use super::VerifiedCredential;
+use crate::{
+    pass::Pass,
+    payload::cwt::validation::{MANDATORY_CONTEXT_URL, MANDATORY_TYPE},
+};
+
+impl<P: Pass + Serialize> VerifiedCredential<P> {
+    /// The pass as a W3C Verifiable Credential JSON-LD document, for systems that consume verifiable credentials.
+    ///
+    /// The `id` is the pass's `urn:uuid:...` ID, and `issuanceDate` and `expirationDate` are its `nbf` and `exp` (a pass
+    /// has no separate issue date). There is no `proof`, as the pass's signature only covers its CBOR encoding, so the
+    /// document must not be trusted by its consumer any more than this verifier is.
+    ///
+    /// # Panics
+    ///
+    /// If the pass can't be serialized as JSON, which is never the case for the passes in this crate.
+    pub fn to_json_ld(&self) -> serde_json::Value {
+        json!({
+            "@context": [MANDATORY_CONTEXT_URL, P::CONTEXT_URL],
+            "id": self.claims.jti(),
+            "type": [MANDATORY_TYPE, P::CREDENTIAL_TYPE],
+            "issuer": self.claims.issuer.to_string(),
+            "issuanceDate": self.claims.not_before.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "expirationDate": self.claims.expiry.to_rfc3339_opts(SecondsFormat::Secs, true),
+            "credentialSubject": serde_json::to_value(&self.pass).expect("pass is not JSON serializable"),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        testing::{example_issuer, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+        PublicCovidPass, Verifier,
+    };
+
+    #[tokio::test]
+    async fn credential_to_json_ld() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+        let credential = verifier
+            .verify_credential_uri::<PublicCovidPass>(VALID_PASS)
+            .await
+            .unwrap();
+
+        assert_eq!(
+            credential.to_json_ld(),
+            json!({
+                "@context": ["https://www.w3.org/2018/credentials/v1", "https://nzcp.covid19.health.nz/contexts/v1"],
+                "id": "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b",
+                "type": ["VerifiableCredential", "PublicCovidPass"],
+                "issuer": "did:web:nzcp.covid19.health.nz",
+                "issuanceDate": "2021-11-02T20:05:30Z",
+                "expirationDate": "2031-11-02T20:05:30Z",
+                "credentialSubject": {"givenName": "Jack", "familyName": "Sparrow", "dob": "1960-04-16"},
+            })
+        );
+    }
+}
@@ -0,0 +1,113 @@
+use hmac::{Hmac, Mac, NewMac};
+use serde::Serialize;
+use serde_json::json;
+use sha2::Sha256;
+
+use super::VerifiedCredential;
+use crate::{
+    pass::Pass,
+    payload::cwt::validation::{MANDATORY_CONTEXT_URL, MANDATORY_TYPE},
+};
+
+impl<P: Pass + Serialize> VerifiedCredential<P> {
+    /// The pass as a compact JWT signed with HS256, for systems that consume JWTs.
+    ///
+    /// The claims are `iss` (the issuer DID), `jti` (`urn:uuid:...`), `nbf` and `exp` (unix timestamps), and `vc`
+    /// (the verifiable credential, as in the W3C JWT encoding). There is no `sub`, as a pass doesn't identify its
+    /// subject other than by name.
+    ///
+    /// # Panics
+    ///
+    /// If the pass can't be serialized as JSON, which is never the case for the passes in this crate.
+    pub fn to_jwt(&self, key: &[u8]) -> String {
+        let signing_input = self.jwt_signing_input("HS256");
+        let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts keys of any length");
+        mac.update(signing_input.as_bytes());
+        let signature = mac.finalize().into_bytes();
+
+        format!("{}.{}", signing_input, base64_url(&signature))
+    }
+
+    /// As `to_jwt`, but unsigned (`"alg": "none"`) for inspection, the JWT must not be trusted by its consumer.
+    pub fn to_unsigned_jwt(&self) -> String {
+        format!("{}.", self.jwt_signing_input("none"))
+    }
+
+    fn jwt_signing_input(&self, alg: &str) -> String {
+        let header = json!({ "alg": alg, "typ": "JWT" });
+        let claims = json!({
+            "iss": self.claims.issuer.to_string(),
+            "jti": self.claims.jti(),
+            "nbf": self.claims.not_before.timestamp(),
+            "exp": self.claims.expiry.timestamp(),
+            "vc": {
+                "@context": [MANDATORY_CONTEXT_URL, P::CONTEXT_URL],
+                "type": [MANDATORY_TYPE, P::CREDENTIAL_TYPE],
+                "credentialSubject": serde_json::to_value(&self.pass).expect("pass is not JSON serializable"),
+            },
+        });
+
+        format!(
+            "{}.{}",
+            base64_url(header.to_string().as_bytes()),
+            base64_url(claims.to_string().as_bytes())
+        )
+    }
+}
+
+fn base64_url(bytes: &[u8]) -> String {
+    base64::encode_config(bytes, base64::URL_SAFE_NO_PAD)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        testing::{example_issuer, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+        PublicCovidPass, Verifier,
+    };
+
+    #[tokio::test]
+    async fn credential_to_jwt() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+        let credential = verifier
+            .verify_credential_uri::<PublicCovidPass>(VALID_PASS)
+            .await
+            .unwrap();
+
+        let jwt = credential.to_jwt(b"secret");
+        let parts: Vec<_> = jwt.split('.').collect();
+        assert_eq!(parts.len(), 3);
+
+        let decode = |part: &str| -> serde_json::Value {
+            serde_json::from_slice(&base64::decode_config(part, base64::URL_SAFE_NO_PAD).unwrap()).unwrap()
+        };
+        assert_eq!(decode(parts[0]), json!({"alg": "HS256", "typ": "JWT"}));
+        assert_eq!(
+            decode(parts[1]),
+            json!({
+                "iss": "did:web:nzcp.covid19.health.nz",
+                "jti": "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b",
+                "nbf": 1635883530,
+                "exp": 1951416330,
+                "vc": {
+                    "@context": ["https://www.w3.org/2018/credentials/v1", "https://nzcp.covid19.health.nz/contexts/v1"],
+                    "type": ["VerifiableCredential", "PublicCovidPass"],
+                    "credentialSubject": {"givenName": "Jack", "familyName": "Sparrow", "dob": "1960-04-16"},
+                },
+            })
+        );
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(b"secret").unwrap();
+        mac.update(format!("{}.{}", parts[0], parts[1]).as_bytes());
+        mac.verify(&base64::decode_config(parts[2], base64::URL_SAFE_NO_PAD).unwrap())
+            .unwrap();
+
+        let unsigned = credential.to_unsigned_jwt();
+        assert!(unsigned.ends_with('.'));
+        assert_eq!(decode(unsigned.split('.').next().unwrap())["alg"], "none");
+    }
+}
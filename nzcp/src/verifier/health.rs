@@ -0,0 +1,288 @@
+use std::{
+    fmt,
+    time::{Duration, Instant},
+};
+
+use chrono::Utc;
+use p256::ecdsa::VerifyingKey;
+use ssi::did::{Document, VerificationMethod};
+
+use super::{StoredKey, Verifier, VerifierConfig};
+use crate::decentralised_identifier::{parse_document, DecentralizedIdentifier, DecentralizedIdentifierError};
+
+/// The state of an issuer's DID document, see `Verifier::check_issuer`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IssuerStatus {
+    /// The document was resolved and has at least one usable key.
+    Healthy,
+    /// The document was resolved, but none of its assertion methods are usable P-256 keys.
+    NoUsableKeys,
+    /// The issuer's server has no DID document.
+    NotFound,
+    /// The document could not be fetched (e.g. a DNS, connection, TLS, or HTTP error).
+    Unreachable(DecentralizedIdentifierError),
+    /// The document was fetched, but could not be parsed.
+    MalformedDocument(DecentralizedIdentifierError),
+}
+
+impl fmt::Display for IssuerStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IssuerStatus::Healthy => write!(f, "healthy"),
+            IssuerStatus::NoUsableKeys => write!(f, "DID document has no usable keys"),
+            IssuerStatus::NotFound => write!(f, "DID document not found"),
+            IssuerStatus::Unreachable(error) => write!(f, "DID document unreachable: {}", error),
+            IssuerStatus::MalformedDocument(error) => write!(f, "DID document malformed: {}", error),
+        }
+    }
+}
+
+/// A report on whether passes from an issuer can currently be verified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IssuerHealth {
+    pub issuer: DecentralizedIdentifier,
+    pub status: IssuerStatus,
+
+    /// How long resolving the document took, including any failure.
+    pub latency: Duration,
+
+    /// The key IDs of the usable keys in the document.
+    pub usable_keys: Vec<String>,
+}
+
+impl IssuerHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.status == IssuerStatus::Healthy
+    }
+
+    /// Whether the document was fetched and parsed, even if it has no usable keys.
+    pub fn document_parsed(&self) -> bool {
+        matches!(self.status, IssuerStatus::Healthy | IssuerStatus::NoUsableKeys)
+    }
+}
+
+/// Whether a verifier can currently verify passes, see `health_check`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum HealthCheckResult {
+    /// Passes from every trusted issuer can be verified.
+    Healthy,
+    /// Passes from some, but not all, trusted issuers can be verified.
+    Degraded(String),
+    /// Passes from none of the trusted issuers can be verified.
+    Unhealthy(String),
+}
+
+impl HealthCheckResult {
+    pub fn is_healthy(&self) -> bool {
+        *self == HealthCheckResult::Healthy
+    }
+}
+
+impl fmt::Display for HealthCheckResult {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HealthCheckResult::Healthy => write!(f, "Healthy: every trusted issuer's DID document was resolved"),
+            HealthCheckResult::Degraded(reason) => write!(f, "Degraded: {}", reason),
+            HealthCheckResult::Unhealthy(reason) => write!(f, "Unhealthy: {}", reason),
+        }
+    }
+}
+
+/// Check that passes can currently be verified with the given options, e.g. for a readiness probe. By default this
+/// resolves the MoH `nzcp.identity.health.nz` DID document.
+///
+/// Creates a new `Verifier` for each call, so nothing is cached between checks.
+pub async fn health_check(config: &VerifierConfig) -> HealthCheckResult {
+    Verifier::from_config(config.clone()).health_check().await
+}
+
+impl Verifier {
+    /// Resolve each trusted issuer's DID document, giving up on an issuer after `VerifierConfig::health_check_timeout`.
+    /// Resolved keys aren't stored and previously stored keys aren't used, so the result reflects the issuers' current
+    /// state.
+    pub async fn health_check(&self) -> HealthCheckResult {
+        let timeout = self.config.health_check_timeout;
+
+        let mut failures = Vec::new();
+        for issuer in &self.config.trusted_issuers {
+            match tokio::time::timeout(timeout, self.check_issuer(issuer)).await {
+                Ok(health) if health.is_healthy() => {}
+                Ok(health) => failures.push(format!("{}: {}", issuer, health.status)),
+                Err(_) => failures.push(format!("{}: timed out after {:?}", issuer, timeout)),
+            }
+        }
+
+        if self.config.trusted_issuers.is_empty() {
+            HealthCheckResult::Unhealthy(String::from("no issuers are trusted"))
+        }
+        else if failures.is_empty() {
+            HealthCheckResult::Healthy
+        }
+        else if failures.len() < self.config.trusted_issuers.len() {
+            HealthCheckResult::Degraded(failures.join(", "))
+        }
+        else {
+            HealthCheckResult::Unhealthy(failures.join(", "))
+        }
+    }
+
+    /// Resolve the issuer's DID document with the verifier's resolver, reporting how long it took and which of its
+    /// keys are usable. The key store is not touched, so this can be used for a status indicator at startup.
+    pub async fn check_issuer(&self, issuer: &DecentralizedIdentifier) -> IssuerHealth {
+        self.check_issuer_keys(issuer).await.0
+    }
+
+    /// As `check_issuer`, storing the usable keys in the key store so the first verification needn't resolve them.
+    pub async fn check_issuer_and_store_keys(&self, issuer: &DecentralizedIdentifier) -> IssuerHealth {
+        let (health, keys) = self.check_issuer_keys(issuer).await;
+
+        let now = Utc::now();
+        for (kid, verifying_key) in keys {
+            let stored = StoredKey {
+                verifying_key,
+                resolved_at: now,
+                expires_at: now + self.key_ttl,
+            };
+            // a failure to store only means the key is resolved again when it is used
+            let _ = self.key_store.put(issuer, &kid, stored).await;
+        }
+
+        health
+    }
+
+    async fn check_issuer_keys(&self, issuer: &DecentralizedIdentifier) -> (IssuerHealth, Vec<(String, VerifyingKey)>) {
+        let started = Instant::now();
+        let resolved = self.resolver.resolve_representation(issuer).await;
+        let latency = started.elapsed();
+
+        let (status, keys) = match resolved.map(|document| parse_document(&document)) {
+            Ok(Ok(document)) => {
+                let keys = usable_keys(issuer, &document);
+                let status = if keys.is_empty() {
+                    IssuerStatus::NoUsableKeys
+                }
+                else {
+                    IssuerStatus::Healthy
+                };
+                (status, keys)
+            }
+            Ok(Err(error)) => (IssuerStatus::MalformedDocument(error), Vec::new()),
+            Err(DecentralizedIdentifierError::DocumentNotFound) => (IssuerStatus::NotFound, Vec::new()),
+            Err(error) => (IssuerStatus::Unreachable(error), Vec::new()),
+        };
+
+        let health = IssuerHealth {
+            issuer: issuer.clone(),
+            status,
+            latency,
+            usable_keys: keys.iter().map(|(kid, _)| kid.clone()).collect(),
+        };
+        (health, keys)
+    }
+}
+
+/// The keys of the issuer's assertion methods that passes can be verified with.
+fn usable_keys(issuer: &DecentralizedIdentifier, document: &Document) -> Vec<(String, VerifyingKey)> {
+    document
+        .assertion_method
+        .iter()
+        .flatten()
+        .filter_map(|method| match method {
+            VerificationMethod::DIDURL(url) => url.fragment.as_deref(),
+            _ => None,
+        })
+        .filter_map(|kid| {
+            let verifying_key = issuer.document_verifying_key(document, kid).ok()?;
+            Some((kid.to_owned(), verifying_key))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        testing::{example_issuer, HangingResolver, MockDIDResolver, EXAMPLE_DID_DOCUMENT},
+        verifier::MemoryKeyStore,
+        KeyStore,
+    };
+
+    #[tokio::test]
+    async fn issuer_health() {
+        let other_issuer = DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz"));
+        let malformed_issuer = DecentralizedIdentifier::Web(String::from("example.com"));
+        let resolver = MockDIDResolver::new()
+            .with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT)
+            .with_raw_document(&malformed_issuer, r#"{"id": "did:web:example.com""#);
+        let key_store = Arc::new(MemoryKeyStore::new());
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(Arc::new(resolver))
+            .with_key_store(key_store.clone());
+
+        let healthy = verifier.check_issuer(&example_issuer()).await;
+        assert_eq!(healthy.status, IssuerStatus::Healthy);
+        assert_eq!(healthy.usable_keys, ["key-1"]);
+        assert!(healthy.document_parsed());
+        assert!(key_store.keys().await.unwrap().is_empty());
+
+        let not_found = verifier.check_issuer(&other_issuer).await;
+        assert_eq!(not_found.status, IssuerStatus::NotFound);
+        assert!(!not_found.document_parsed());
+
+        let malformed = verifier.check_issuer(&malformed_issuer).await;
+        assert!(matches!(malformed.status, IssuerStatus::MalformedDocument(_)));
+        assert!(malformed.usable_keys.is_empty());
+
+        assert!(verifier
+            .check_issuer_and_store_keys(&example_issuer())
+            .await
+            .is_healthy());
+        assert_eq!(
+            key_store.keys().await.unwrap(),
+            [(example_issuer(), String::from("key-1"))]
+        );
+    }
+
+    #[tokio::test]
+    async fn health_check() {
+        let other_issuer = DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz"));
+        let resolver = Arc::new(MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT));
+
+        let healthy = Verifier::new(vec![example_issuer()]).with_resolver(resolver.clone());
+        assert_eq!(healthy.health_check().await, HealthCheckResult::Healthy);
+
+        let degraded = Verifier::new(vec![example_issuer(), other_issuer.clone()]).with_resolver(resolver.clone());
+        assert_eq!(
+            degraded.health_check().await,
+            HealthCheckResult::Degraded(String::from("did:web:nzcp.identity.health.nz: DID document not found"))
+        );
+
+        let unhealthy = Verifier::new(vec![other_issuer]).with_resolver(resolver);
+        assert!(matches!(
+            unhealthy.health_check().await,
+            HealthCheckResult::Unhealthy(_)
+        ));
+
+        let untrusting = Verifier::new(Vec::new());
+        assert_eq!(
+            untrusting.health_check().await.to_string(),
+            "Unhealthy: no issuers are trusted"
+        );
+    }
+
+    #[tokio::test]
+    async fn health_check_timeout() {
+        let verifier = Verifier::from_config(VerifierConfig {
+            trusted_issuers: vec![example_issuer()],
+            health_check_timeout: Duration::from_millis(10),
+            ..VerifierConfig::default()
+        })
+        .with_resolver(Arc::new(HangingResolver));
+        assert_eq!(
+            verifier.health_check().await,
+            HealthCheckResult::Unhealthy(String::from("did:web:nzcp.covid19.health.nz: timed out after 10ms"))
+        );
+    }
+}
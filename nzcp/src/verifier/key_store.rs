@@ -0,0 +1,173 @@
+use std::{collections::HashMap, sync::RwLock};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use p256::ecdsa::VerifyingKey;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use thiserror::Error;
+
+use crate::decentralised_identifier::DecentralizedIdentifier;
+
+/// An error reported by a `KeyStore` backend (e.g. a lost connection to an external cache).
+///
+/// The verifier treats these as cache misses and falls back to resolving the issuer's DID document.
+#[derive(Debug, Clone, Error, PartialEq, Eq)]
+#[error("key store error: {0}")]
+pub struct KeyStoreError(pub String);
+
+/// A verifying key resolved from an issuer's DID document, along with when it must be resolved again.
+///
+/// External stores can hold keys in any `serde` format, a `StoredKey` serializes as:
+///
+/// ```notrust
+/// {
+///     "key": <bytes: SEC1 uncompressed P-256 point (0x04 || x || y, 65 bytes)>,
+///     "resolved_at": <integer: unix timestamp (seconds)>,
+///     "expires_at": <integer: unix timestamp (seconds)>
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StoredKey {
+    /// The public key used to verify pass signatures.
+    #[serde(
+        rename = "key",
+        serialize_with = "serialize_sec1",
+        deserialize_with = "deserialize_sec1"
+    )]
+    pub verifying_key: VerifyingKey,
+
+    /// When the key was resolved from the DID document.
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub resolved_at: DateTime<Utc>,
+
+    /// When the key should no longer be used without resolving the DID document again.
+    #[serde(with = "chrono::serde::ts_seconds")]
+    pub expires_at: DateTime<Utc>,
+}
+
+impl StoredKey {
+    /// Whether the key must be resolved again at the given instant.
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        self.expires_at <= now
+    }
+}
+
+fn serialize_sec1<S>(key: &VerifyingKey, serializer: S) -> Result<S::Ok, S::Error>
+where
+    S: Serializer,
+{
+    serde_bytes::serialize(key.to_encoded_point(false).as_bytes(), serializer)
+}
+
+fn deserialize_sec1<'de, D>(deserializer: D) -> Result<VerifyingKey, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let bytes: serde_bytes::ByteBuf = Deserialize::deserialize(deserializer)?;
+    VerifyingKey::from_sec1_bytes(&bytes).map_err(|_| de::Error::custom("invalid SEC1 encoded P-256 key"))
+}
+
+/// Storage for resolved issuer keys, keyed by issuer and key ID.
+///
+/// Implement this to share resolved keys between verifiers (e.g. in Redis across replicas). Entries are only ever
+/// added by the verifier after a successful resolution, and any error returned is treated as a miss.
+#[async_trait]
+pub trait KeyStore: Send + Sync {
+    /// Get the stored key for the issuer and key ID, if any (expired keys may be returned).
+    async fn get(&self, issuer: &DecentralizedIdentifier, kid: &str) -> Result<Option<StoredKey>, KeyStoreError>;
+
+    /// Store (or replace) the key for the issuer and key ID.
+    async fn put(&self, issuer: &DecentralizedIdentifier, kid: &str, key: StoredKey) -> Result<(), KeyStoreError>;
+
+    /// Remove the key for the issuer and key ID, if present.
+    async fn remove(&self, issuer: &DecentralizedIdentifier, kid: &str) -> Result<(), KeyStoreError>;
+
+    /// List the issuer and key ID of every stored key.
+    async fn keys(&self) -> Result<Vec<(DecentralizedIdentifier, String)>, KeyStoreError>;
+}
+
+/// The default `KeyStore`, holding keys in memory for the lifetime of the verifier.
+#[derive(Debug, Default)]
+pub struct MemoryKeyStore {
+    keys: RwLock<HashMap<(DecentralizedIdentifier, String), StoredKey>>,
+}
+
+impl MemoryKeyStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+fn store_key(issuer: &DecentralizedIdentifier, kid: &str) -> (DecentralizedIdentifier, String) {
+    (issuer.clone(), kid.to_owned())
+}
+
+#[async_trait]
+impl KeyStore for MemoryKeyStore {
+    async fn get(&self, issuer: &DecentralizedIdentifier, kid: &str) -> Result<Option<StoredKey>, KeyStoreError> {
+        let keys = self.keys.read().map_err(|err| KeyStoreError(err.to_string()))?;
+        Ok(keys.get(&store_key(issuer, kid)).copied())
+    }
+
+    async fn put(&self, issuer: &DecentralizedIdentifier, kid: &str, key: StoredKey) -> Result<(), KeyStoreError> {
+        let mut keys = self.keys.write().map_err(|err| KeyStoreError(err.to_string()))?;
+        keys.insert(store_key(issuer, kid), key);
+        Ok(())
+    }
+
+    async fn remove(&self, issuer: &DecentralizedIdentifier, kid: &str) -> Result<(), KeyStoreError> {
+        let mut keys = self.keys.write().map_err(|err| KeyStoreError(err.to_string()))?;
+        keys.remove(&store_key(issuer, kid));
+        Ok(())
+    }
+
+    async fn keys(&self) -> Result<Vec<(DecentralizedIdentifier, String)>, KeyStoreError> {
+        let keys = self.keys.read().map_err(|err| KeyStoreError(err.to_string()))?;
+        Ok(keys.keys().cloned().collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::example_verifying_key;
+
+    #[test]
+    fn serialize_deserialize_stored_key() {
+        let key = StoredKey {
+            verifying_key: example_verifying_key(),
+            resolved_at: DateTime::from_utc(chrono::NaiveDateTime::from_timestamp(1635883530, 0), Utc),
+            expires_at: DateTime::from_utc(chrono::NaiveDateTime::from_timestamp(1635969930, 0), Utc),
+        };
+
+        let json = serde_json::to_value(key).unwrap();
+        assert_eq!(json["resolved_at"], 1635883530);
+        assert_eq!(json["expires_at"], 1635969930);
+        assert_eq!(json["key"].as_array().unwrap().len(), 65);
+
+        assert_eq!(serde_json::from_value::<StoredKey>(json).unwrap(), key);
+        assert_eq!(
+            serde_cbor::from_slice::<StoredKey>(&serde_cbor::to_vec(&key).unwrap()).unwrap(),
+            key
+        );
+    }
+
+    #[tokio::test]
+    async fn memory_key_store() {
+        let store = MemoryKeyStore::new();
+        let issuer = DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz"));
+        let key = StoredKey {
+            verifying_key: example_verifying_key(),
+            resolved_at: Utc::now(),
+            expires_at: Utc::now(),
+        };
+
+        assert_eq!(store.get(&issuer, "key-1").await, Ok(None));
+        store.put(&issuer, "key-1", key).await.unwrap();
+        assert_eq!(store.get(&issuer, "key-1").await, Ok(Some(key)));
+        assert_eq!(store.get(&issuer, "key-2").await, Ok(None));
+        assert_eq!(store.keys().await, Ok(vec![(issuer.clone(), String::from("key-1"))]));
+        store.remove(&issuer, "key-1").await.unwrap();
+        assert_eq!(store.get(&issuer, "key-1").await, Ok(None));
+    }
+}
@@ -0,0 +1,124 @@
+use std::time::Duration;
+
+use prometheus::{Histogram, HistogramOpts, IntCounter, IntCounterVec, Opts, Registry};
+
+use crate::error::{CoseVerificationError, NzcpError};
+
+/// Prometheus metrics for a verifier, see `Verifier::with_metrics` (requires the `metrics` feature).
+///
+/// Registered on a given registry rather than the global one, so several verifiers can be monitored separately:
+///
+/// - `nzcp_verify_total{result="valid"|"invalid"|"error"}`, where `error` means the pass couldn't be checked (e.g.
+///   the issuer's DID document couldn't be resolved)
+/// - `nzcp_did_resolution_duration_seconds`
+/// - `nzcp_did_cache_hits_total` and `nzcp_did_cache_misses_total`, counting key store lookups
+#[derive(Debug, Clone)]
+pub struct VerifierMetrics {
+    verify_total: IntCounterVec,
+    did_resolution_duration: Histogram,
+    did_cache_hits: IntCounter,
+    did_cache_misses: IntCounter,
+}
+
+impl VerifierMetrics {
+    /// Create the metrics and register them on `registry`, failing if metrics with the same names are already
+    /// registered.
+    pub fn register(registry: &Registry) -> prometheus::Result<Self> {
+        let metrics = VerifierMetrics {
+            verify_total: IntCounterVec::new(
+                Opts::new("nzcp_verify_total", "Passes verified, by result"),
+                &["result"],
+            )?,
+            did_resolution_duration: Histogram::with_opts(HistogramOpts::new(
+                "nzcp_did_resolution_duration_seconds",
+                "Time taken to resolve an issuer's DID document",
+            ))?,
+            did_cache_hits: IntCounter::new(
+                "nzcp_did_cache_hits_total",
+                "Verifying keys found unexpired in the key store",
+            )?,
+            did_cache_misses: IntCounter::new(
+                "nzcp_did_cache_misses_total",
+                "Verifying keys resolved from the issuer's DID document",
+            )?,
+        };
+
+        registry.register(Box::new(metrics.verify_total.clone()))?;
+        registry.register(Box::new(metrics.did_resolution_duration.clone()))?;
+        registry.register(Box::new(metrics.did_cache_hits.clone()))?;
+        registry.register(Box::new(metrics.did_cache_misses.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub(crate) fn record_verification<T>(&self, result: &Result<T, NzcpError>) {
+        let result = match result {
+            Ok(_) => "valid",
+            Err(NzcpError::InvalidSignature(CoseVerificationError::DecentralizedIdentifierResolution(_)))
+            | Err(NzcpError::Timeout(_)) => "error",
+            Err(_) => "invalid",
+        };
+        self.verify_total.with_label_values(&[result]).inc();
+    }
+
+    pub(crate) fn record_cache_hit(&self) {
+        self.did_cache_hits.inc();
+    }
+
+    pub(crate) fn record_resolution(&self, duration: Duration) {
+        self.did_cache_misses.inc();
+        self.did_resolution_duration.observe(duration.as_secs_f64());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        testing::{example_issuer, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+        DecentralizedIdentifier, PublicCovidPass, Verifier,
+    };
+
+    #[tokio::test]
+    async fn verification_metrics() {
+        let registry = Registry::new();
+        let metrics = Arc::new(VerifierMetrics::register(&registry).unwrap());
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let other_issuer = DecentralizedIdentifier::Web(String::from("nzcp.identity.health.nz"));
+        let verifier = Verifier::new(vec![example_issuer()])
+            .with_resolver(Arc::new(resolver))
+            .with_metrics(metrics.clone());
+
+        for _ in 0..2 {
+            verifier.verify_pass_uri::<PublicCovidPass>(VALID_PASS).await.unwrap();
+        }
+        assert!(verifier
+            .verify_pass_uri::<PublicCovidPass>("NZCP:/2/AAAA")
+            .await
+            .is_err());
+
+        let unresolvable = Verifier::new(vec![other_issuer.clone()])
+            .with_resolver(Arc::new(MockDIDResolver::new()))
+            .with_metrics(metrics.clone());
+        let other_pass = crate::testing::TestPass {
+            issuer: other_issuer,
+            ..Default::default()
+        }
+        .sign(&crate::testing::test_signing_key(1));
+        assert!(unresolvable
+            .verify_pass_uri::<PublicCovidPass>(&other_pass)
+            .await
+            .is_err());
+
+        assert_eq!(metrics.verify_total.with_label_values(&["valid"]).get(), 2);
+        assert_eq!(metrics.verify_total.with_label_values(&["invalid"]).get(), 1);
+        assert_eq!(metrics.verify_total.with_label_values(&["error"]).get(), 1);
+        assert_eq!(metrics.did_cache_hits.get(), 1);
+        assert_eq!(metrics.did_cache_misses.get(), 2);
+        assert_eq!(metrics.did_resolution_duration.get_sample_count(), 2);
+
+        assert!(VerifierMetrics::register(&registry).is_err());
+    }
+}
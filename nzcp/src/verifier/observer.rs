@@ -0,0 +1,163 @@
+use std::{
+    future::Future,
+    panic::{self, AssertUnwindSafe},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    decentralised_identifier::DecentralizedIdentifier,
+    error::{CoseVerificationError, NzcpError},
+};
+
+/// Called once for every pass verified by a verifier, see `Verifier::with_observer`, e.g. to count verifications by
+/// outcome:
+///
+/// ```ignore
+/// let verifier = Verifier::default().with_observer(Arc::new(move |summary: &VerificationSummary| {
+///     scans.with_label_values(&[summary.outcome.as_str()]).inc();
+/// }));
+/// ```
+///
+/// Observers are called before the verification returns, so should be quick. A panicking observer is ignored, rather
+/// than failing the verification.
+pub trait VerificationObserver: Send + Sync {
+    fn observe(&self, summary: &VerificationSummary);
+}
+
+impl<F> VerificationObserver for F
+where
+    F: Fn(&VerificationSummary) + Send + Sync,
+{
+    fn observe(&self, summary: &VerificationSummary) {
+        self(summary)
+    }
+}
+
+/// A summary of a verification, without any of the details of the pass's subject.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerificationSummary {
+    pub outcome: VerificationOutcome,
+
+    /// The issuer the pass claims to be from (`iss`), whether or not it's trusted, or `None` if the pass couldn't be
+    /// decoded. As anyone can make a pass claiming any issuer, don't use it as a metric label unless the outcome shows
+    /// it was trusted.
+    pub issuer: Option<DecentralizedIdentifier>,
+
+    /// How long the verification took in total.
+    pub duration: Duration,
+
+    /// Whether the issuer's key was already known (from the key store or a JWKS) rather than resolved. False if the
+    /// verification failed before the key was needed.
+    pub key_from_cache: bool,
+}
+
+/// Why a verification succeeded or failed, broadly.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum VerificationOutcome {
+    /// The pass was verified.
+    Valid,
+    /// The pass is authentic, but has expired.
+    Expired,
+    /// The pass is authentic, but isn't active yet (or not until too far in the future).
+    NotYetActive,
+    /// The pass is authentic, but has been revoked.
+    Revoked,
+    /// The pass wasn't issued by a trusted issuer.
+    UntrustedIssuer,
+    /// The pass's signature wasn't valid, or was by a key the issuer isn't pinned to.
+    SignatureInvalid,
+    /// The pass couldn't be decoded, or its headers or claims weren't valid.
+    Malformed,
+    /// The pass couldn't be checked, as the issuer's key couldn't be resolved or verification timed out.
+    ResolutionError,
+}
+
+impl VerificationOutcome {
+    pub(crate) fn of<T>(result: &Result<T, NzcpError>) -> Self {
+        use CoseVerificationError::*;
+
+        let error = match result {
+            Ok(_) => return VerificationOutcome::Valid,
+            Err(error) => error,
+        };
+        match error {
+            NzcpError::PassExpired { .. } => VerificationOutcome::Expired,
+            NzcpError::PassNotYetActive { .. } | NzcpError::PassTooFarInFuture { .. } => {
+                VerificationOutcome::NotYetActive
+            }
+            NzcpError::PassRevoked(_) => VerificationOutcome::Revoked,
+            NzcpError::InvalidSignature(UntrustedIssuer(_)) => VerificationOutcome::UntrustedIssuer,
+            NzcpError::InvalidSignature(
+                SignatureVerificationFailed | InvalidSignatureLength { .. } | UnpinnedKey(_),
+            ) => VerificationOutcome::SignatureInvalid,
+            NzcpError::InvalidSignature(DecentralizedIdentifierResolution(_)) | NzcpError::Timeout(_) => {
+                VerificationOutcome::ResolutionError
+            }
+            _ => VerificationOutcome::Malformed,
+        }
+    }
+
+    /// The outcome in snake_case, e.g. `not_yet_active`, for use as a metric label.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            VerificationOutcome::Valid => "valid",
+            VerificationOutcome::Expired => "expired",
+            VerificationOutcome::NotYetActive => "not_yet_active",
+            VerificationOutcome::Revoked => "revoked",
+            VerificationOutcome::UntrustedIssuer => "untrusted_issuer",
+            VerificationOutcome::SignatureInvalid => "signature_invalid",
+            VerificationOutcome::Malformed => "malformed",
+            VerificationOutcome::ResolutionError => "resolution_error",
+        }
+    }
+}
+
+tokio::task_local! {
+    /// What's known so far about the verification being observed on this task, see `observe`.
+    static OBSERVATION: Arc<Observation>;
+}
+
+#[derive(Debug, Default)]
+struct Observation {
+    issuer: Mutex<Option<DecentralizedIdentifier>>,
+    key_from_cache: AtomicBool,
+}
+
+/// Run a verification, then call `observer` with its summary. A verification within another being observed (e.g.
+/// one with a timeout) is only observed once, by the outermost.
+pub(crate) async fn observe<T, F>(observer: Option<&dyn VerificationObserver>, verification: F) -> Result<T, NzcpError>
+where
+    F: Future<Output = Result<T, NzcpError>>,
+{
+    let observer = match observer {
+        Some(observer) if OBSERVATION.try_with(|_| ()).is_err() => observer,
+        _ => return verification.await,
+    };
+
+    let observation = Arc::new(Observation::default());
+    let started = Instant::now();
+    let result = OBSERVATION.scope(Arc::clone(&observation), verification).await;
+    let summary = VerificationSummary {
+        outcome: VerificationOutcome::of(&result),
+        issuer: observation.issuer.lock().unwrap().take(),
+        duration: started.elapsed(),
+        key_from_cache: observation.key_from_cache.load(Ordering::Relaxed),
+    };
+    let _ = panic::catch_unwind(AssertUnwindSafe(|| observer.observe(&summary)));
+    result
+}
+
+/// Note the issuer of the pass being verified, if the verification is being observed.
+pub(crate) fn observe_issuer(issuer: &DecentralizedIdentifier) {
+    let _ = OBSERVATION.try_with(|observation| *observation.issuer.lock().unwrap() = Some(issuer.clone()));
+}
+
+/// Note that the key for the pass being verified was already known, if the verification is being observed.
+pub(crate) fn observe_cached_key() {
+    let _ = OBSERVATION.try_with(|observation| observation.key_from_cache.store(true, Ordering::Relaxed));
+}
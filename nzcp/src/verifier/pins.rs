@@ -0,0 +1,191 @@
+use std::{collections::HashSet, convert::TryFrom, fmt};
+
+use p256::ecdsa::VerifyingKey;
+use sha2::{Digest, Sha256};
+use ssi::jwk::Base64urlUInt;
+
+use super::Verifier;
+use crate::{decentralised_identifier::DecentralizedIdentifier, error::CoseVerificationError};
+
+/// The SHA-256 JWK thumbprint (RFC 7638) of an issuer's key, used to restrict which keys are accepted from an issuer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct KeyPin(pub [u8; 32]);
+
+impl KeyPin {
+    /// The thumbprint of a key, as it would be published in an issuer's DID document.
+    pub fn from_verifying_key(verifying_key: &VerifyingKey) -> Self {
+        let point = verifying_key.to_encoded_point(false);
+        let coordinate = |bytes: &[u8]| String::from(&Base64urlUInt(bytes.to_vec()));
+        // a verifying key is never the identity point, so always has coordinates
+        let (x, y) = (point.x().expect("identity point"), point.y().expect("identity point"));
+
+        // the required members in lexicographic order, without whitespace
+        let jwk = format!(
+            r#"{{"crv":"P-256","kty":"EC","x":"{}","y":"{}"}}"#,
+            coordinate(x),
+            coordinate(y),
+        );
+
+        KeyPin(Sha256::digest(jwk.as_bytes()).into())
+    }
+
+    /// Parse a base64url encoded thumbprint.
+    pub fn from_base64url(thumbprint: &str) -> Option<Self> {
+        let bytes = Base64urlUInt::try_from(thumbprint.to_owned()).ok()?;
+        <[u8; 32]>::try_from(bytes.0.as_slice()).ok().map(KeyPin)
+    }
+}
+
+impl fmt::Display for KeyPin {
+    /// The base64url encoded thumbprint.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", String::from(&Base64urlUInt(self.0.to_vec())))
+    }
+}
+
+impl Verifier {
+    /// Accept the key with the given thumbprint from the issuer.
+    ///
+    /// Once an issuer has a pin, only keys matching one of its pins are accepted, even if other keys are valid
+    /// assertion methods in its DID document. Pin both keys during a rotation window.
+    pub fn pin_key(&self, issuer: DecentralizedIdentifier, pin: KeyPin) {
+        self.pinned_keys
+            .write()
+            .expect("pinned keys lock poisoned")
+            .entry(issuer)
+            .or_default()
+            .insert(pin);
+    }
+
+    /// Stop accepting the key with the given thumbprint from the issuer, returning whether it was pinned.
+    ///
+    /// The issuer remains pinned after its last pin is removed, so no keys are accepted from it until another key is
+    /// pinned or `unpin_issuer` is called.
+    pub fn unpin_key(&self, issuer: &DecentralizedIdentifier, pin: &KeyPin) -> bool {
+        self.pinned_keys
+            .write()
+            .expect("pinned keys lock poisoned")
+            .get_mut(issuer)
+            .is_some_and(|pins| pins.remove(pin))
+    }
+
+    /// Remove all pins for the issuer, accepting any key in its DID document again.
+    pub fn unpin_issuer(&self, issuer: &DecentralizedIdentifier) {
+        self.pinned_keys
+            .write()
+            .expect("pinned keys lock poisoned")
+            .remove(issuer);
+    }
+
+    /// The pins for the issuer, or `None` if its keys are not pinned.
+    pub fn pinned_keys(&self, issuer: &DecentralizedIdentifier) -> Option<HashSet<KeyPin>> {
+        self.pinned_keys
+            .read()
+            .expect("pinned keys lock poisoned")
+            .get(issuer)
+            .cloned()
+    }
+
+    /// Check the key a pass was signed with against the issuer's pins, returning the matching pin (or `None` if the
+    /// issuer is not pinned).
+    pub(crate) fn check_pinned_key(
+        &self,
+        issuer: &DecentralizedIdentifier,
+        kid: &str,
+        verifying_key: &VerifyingKey,
+    ) -> Result<Option<KeyPin>, CoseVerificationError> {
+        let pinned_keys = self.pinned_keys.read().expect("pinned keys lock poisoned");
+        let pins = match pinned_keys.get(issuer) {
+            Some(pins) => pins,
+            None => return Ok(None),
+        };
+
+        let pin = KeyPin::from_verifying_key(verifying_key);
+        if pins.contains(&pin) {
+            Ok(Some(pin))
+        }
+        else {
+            Err(CoseVerificationError::UnpinnedKey(format!("{}#{}", issuer, kid)))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::{
+        error::NzcpError,
+        testing::{did_document, example_issuer, example_verifying_key, test_signing_key, MockDIDResolver, TestPass},
+        PublicCovidPass,
+    };
+
+    #[test]
+    fn key_thumbprint() {
+        let key = example_verifying_key();
+        let pin = KeyPin::from_verifying_key(&key);
+
+        assert_eq!(KeyPin::from_base64url(&pin.to_string()), Some(pin));
+        assert_eq!(KeyPin::from_base64url("abc"), None);
+    }
+
+    #[tokio::test]
+    async fn key_rotation() {
+        let (old_key, new_key, unknown_key) = (test_signing_key(1), test_signing_key(2), test_signing_key(3));
+        let document = did_document(
+            &example_issuer(),
+            &[
+                ("key-1", old_key.verifying_key()),
+                ("key-2", new_key.verifying_key()),
+                ("key-3", unknown_key.verifying_key()),
+            ],
+        );
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), &document);
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(resolver));
+
+        let old_pin = KeyPin::from_verifying_key(&old_key.verifying_key());
+        let new_pin = KeyPin::from_verifying_key(&new_key.verifying_key());
+        verifier.pin_key(example_issuer(), old_pin);
+        verifier.pin_key(example_issuer(), new_pin);
+
+        let pass = |kid: &str, key| {
+            TestPass {
+                kid: String::from(kid),
+                ..TestPass::default()
+            }
+            .sign(key)
+        };
+        let old_pass = pass("key-1", &old_key);
+        let new_pass = pass("key-2", &new_key);
+        let unknown_pass = pass("key-3", &unknown_key);
+
+        let verified = verifier
+            .verify_credential_uri::<PublicCovidPass>(&old_pass)
+            .await
+            .unwrap();
+        assert_eq!(verified.matched_pin, Some(old_pin));
+        let verified = verifier
+            .verify_credential_uri::<PublicCovidPass>(&new_pass)
+            .await
+            .unwrap();
+        assert_eq!(verified.matched_pin, Some(new_pin));
+        assert_eq!(
+            verifier.verify_pass_uri::<PublicCovidPass>(&unknown_pass).await,
+            Err(NzcpError::InvalidSignature(CoseVerificationError::UnpinnedKey(
+                String::from("did:web:nzcp.covid19.health.nz#key-3")
+            )))
+        );
+
+        // the rotation window closes
+        assert!(verifier.unpin_key(&example_issuer(), &old_pin));
+        assert!(matches!(
+            verifier.verify_pass_uri::<PublicCovidPass>(&old_pass).await,
+            Err(NzcpError::InvalidSignature(CoseVerificationError::UnpinnedKey(_)))
+        ));
+        assert!(verifier.verify_pass_uri::<PublicCovidPass>(&new_pass).await.is_ok());
+
+        verifier.unpin_issuer(&example_issuer());
+        assert!(verifier.verify_pass_uri::<PublicCovidPass>(&unknown_pass).await.is_ok());
+    }
+}
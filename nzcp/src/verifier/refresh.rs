@@ -0,0 +1,298 @@
+use std::{collections::HashMap, sync::Arc, time::Duration as StdDuration};
+
+use chrono::{Duration, Utc};
+use tokio::{
+    sync::{oneshot, watch},
+    task::JoinHandle,
+    time::{self, Instant},
+};
+
+use super::{KeyStore, KeyStoreError, StoredKey, Verifier};
+use crate::decentralised_identifier::{DecentralizedIdentifier, DecentralizedIdentifierError, DocumentResolver};
+
+/// Something of note that happened while refreshing the key store.
+#[derive(Debug, PartialEq, Eq)]
+pub enum RefreshEvent {
+    /// The issuer's DID document now has a different key for the key ID, which has replaced the stored key.
+    KeyChanged { issuer: String, kid: String },
+    /// The key ID is no longer a valid assertion method in the issuer's DID document, so was removed from the store.
+    KeyRemoved {
+        issuer: String,
+        kid: String,
+        error: DecentralizedIdentifierError,
+    },
+    /// The issuer's DID document could not be resolved, the stored keys are left in place until they expire.
+    ResolutionFailed {
+        issuer: String,
+        error: DecentralizedIdentifierError,
+    },
+    /// The key store could not be read or written.
+    KeyStoreFailed(KeyStoreError),
+}
+
+/// A handle to a task refreshing the keys in a verifier's key store, see `Verifier::spawn_refresh_task`.
+///
+/// The task stops when this handle, or the verifier it was spawned from, is dropped.
+#[derive(Debug)]
+pub struct RefreshHandle {
+    shutdown: oneshot::Sender<()>,
+    task: JoinHandle<()>,
+}
+
+impl RefreshHandle {
+    /// Stop the task, waiting for any refresh in progress to finish.
+    pub async fn shutdown(self) {
+        let _ = self.shutdown.send(());
+        let _ = self.task.await;
+    }
+}
+
+impl Verifier {
+    /// Spawn a task on the current tokio runtime which re-resolves every key in the key store each `interval`, so
+    /// keys are kept up to date without a verification having to wait for an expired key to be resolved.
+    ///
+    /// `on_event` is called with any changes or failures (pass `|_| {}` to ignore them). Issuer DID documents are
+    /// resolved without holding any lock on the key store.
+    ///
+    /// # Panics
+    ///
+    /// If called outside of a tokio runtime.
+    pub fn spawn_refresh_task<F>(&self, interval: StdDuration, on_event: F) -> RefreshHandle
+    where
+        F: Fn(RefreshEvent) + Send + Sync + 'static,
+    {
+        let refresher = Refresher {
+            resolver: self.resolver.clone(),
+            key_store: self.key_store.clone(),
+            key_ttl: self.key_ttl,
+            on_event,
+        };
+        let (shutdown, shutdown_receiver) = oneshot::channel();
+        let task = tokio::spawn(refresher.run(interval, shutdown_receiver, self.dropped.subscribe()));
+
+        RefreshHandle { shutdown, task }
+    }
+}
+
+struct Refresher<F> {
+    resolver: Arc<dyn DocumentResolver>,
+    key_store: Arc<dyn KeyStore>,
+    key_ttl: Duration,
+    on_event: F,
+}
+
+impl<F: Fn(RefreshEvent)> Refresher<F> {
+    async fn run(
+        self,
+        period: StdDuration,
+        mut shutdown: oneshot::Receiver<()>,
+        mut verifier_dropped: watch::Receiver<()>,
+    ) {
+        let mut interval = time::interval_at(Instant::now() + period, period);
+
+        loop {
+            tokio::select! {
+                _ = interval.tick() => self.refresh().await,
+                // also completes if the handle is dropped
+                _ = &mut shutdown => break,
+                // the sender is only dropped with the verifier
+                _ = verifier_dropped.changed() => break,
+            }
+        }
+    }
+
+    async fn refresh(&self) {
+        let keys = match self.key_store.keys().await {
+            Ok(keys) => keys,
+            Err(error) => return (self.on_event)(RefreshEvent::KeyStoreFailed(error)),
+        };
+
+        // resolve each issuer's document once, no matter how many of its keys are stored
+        let mut kids_by_issuer: HashMap<DecentralizedIdentifier, Vec<String>> = HashMap::new();
+        for (issuer, kid) in keys {
+            kids_by_issuer.entry(issuer).or_default().push(kid);
+        }
+
+        for (issuer, kids) in kids_by_issuer {
+            self.refresh_issuer(issuer, kids).await;
+        }
+    }
+
+    async fn refresh_issuer(&self, issuer: DecentralizedIdentifier, kids: Vec<String>) {
+        let document = match issuer.resolve_document(&*self.resolver).await {
+            Ok(document) => document,
+            Err(error) => {
+                return (self.on_event)(RefreshEvent::ResolutionFailed {
+                    issuer: issuer.to_string(),
+                    error,
+                })
+            }
+        };
+
+        let now = Utc::now();
+        for kid in kids {
+            let result = match issuer.document_verifying_key(&document, &kid) {
+                Ok(verifying_key) => self.update(&issuer, kid, verifying_key, now).await,
+                Err(error) => self.key_store.remove(&issuer, &kid).await.map(|_| {
+                    Some(RefreshEvent::KeyRemoved {
+                        issuer: issuer.to_string(),
+                        kid,
+                        error,
+                    })
+                }),
+            };
+
+            match result {
+                Ok(Some(event)) => (self.on_event)(event),
+                Ok(None) => {}
+                Err(error) => (self.on_event)(RefreshEvent::KeyStoreFailed(error)),
+            }
+        }
+    }
+
+    async fn update(
+        &self,
+        issuer: &DecentralizedIdentifier,
+        kid: String,
+        verifying_key: p256::ecdsa::VerifyingKey,
+        now: chrono::DateTime<Utc>,
+    ) -> Result<Option<RefreshEvent>, KeyStoreError> {
+        let previous = self.key_store.get(issuer, &kid).await?;
+        let stored = StoredKey {
+            verifying_key,
+            resolved_at: now,
+            expires_at: now + self.key_ttl,
+        };
+        self.key_store.put(issuer, &kid, stored).await?;
+
+        let changed = previous.is_some_and(|previous| previous.verifying_key != verifying_key);
+        Ok(changed.then(|| RefreshEvent::KeyChanged {
+            issuer: issuer.to_string(),
+            kid,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use super::*;
+    use crate::{
+        testing::{example_issuer, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+        PublicCovidPass,
+    };
+
+    /// The example document with the key replaced by the P-256 generator point.
+    fn rotated_document() -> String {
+        EXAMPLE_DID_DOCUMENT
+            .replace(
+                "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+                "axfR8uEsQkf4vOblY6RA8ncDfYEt6zOg9KE5RdiYwpY",
+            )
+            .replace(
+                "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0",
+                "T-NC4v4af5uO5-tKfA-eFivOM1drMV7Oy7ZAaDe_UfU",
+            )
+    }
+
+    async fn next_event(events: &mut tokio::sync::mpsc::UnboundedReceiver<RefreshEvent>) -> RefreshEvent {
+        time::timeout(StdDuration::from_secs(5), events.recv())
+            .await
+            .expect("no refresh event")
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn refresh_updates_key_store() {
+        let resolver = Arc::new(MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT));
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(resolver.clone());
+        let _: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+        let original = verifier
+            .key_store
+            .get(&example_issuer(), "key-1")
+            .await
+            .unwrap()
+            .unwrap();
+
+        let (sender, mut events) = tokio::sync::mpsc::unbounded_channel();
+        let handle = verifier.spawn_refresh_task(StdDuration::from_millis(10), move |event| {
+            sender.send(event).unwrap();
+        });
+
+        resolver.set_document(&example_issuer(), &rotated_document());
+        assert_eq!(
+            next_event(&mut events).await,
+            RefreshEvent::KeyChanged {
+                issuer: String::from("did:web:nzcp.covid19.health.nz"),
+                kid: String::from("key-1"),
+            }
+        );
+        let rotated = verifier
+            .key_store
+            .get(&example_issuer(), "key-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_ne!(rotated.verifying_key, original.verifying_key);
+
+        resolver.set_document(&example_issuer(), &EXAMPLE_DID_DOCUMENT.replace("key-1", "key-2"));
+        assert!(matches!(
+            next_event(&mut events).await,
+            RefreshEvent::KeyRemoved {
+                error: DecentralizedIdentifierError::MissingAssertionMethod { .. },
+                ..
+            }
+        ));
+        assert_eq!(verifier.key_store.get(&example_issuer(), "key-1").await, Ok(None));
+
+        handle.shutdown().await;
+    }
+
+    #[tokio::test]
+    async fn refresh_reports_resolution_failures() {
+        let resolver = Arc::new(MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT));
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(resolver.clone());
+        let _: PublicCovidPass = verifier.verify_pass_uri(VALID_PASS).await.unwrap();
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let recorded = events.clone();
+        let handle = verifier.spawn_refresh_task(StdDuration::from_millis(10), move |event| {
+            recorded.lock().unwrap().push(event);
+        });
+
+        resolver.set_raw_document(&example_issuer(), "");
+        while events.lock().unwrap().is_empty() {
+            time::sleep(StdDuration::from_millis(5)).await;
+        }
+        handle.shutdown().await;
+
+        assert_eq!(
+            events.lock().unwrap()[0],
+            RefreshEvent::ResolutionFailed {
+                issuer: String::from("did:web:nzcp.covid19.health.nz"),
+                error: DecentralizedIdentifierError::EmptyDocument,
+            }
+        );
+        // the stored key is still usable
+        assert!(verifier
+            .key_store
+            .get(&example_issuer(), "key-1")
+            .await
+            .unwrap()
+            .is_some());
+    }
+
+    #[tokio::test]
+    async fn refresh_stops_when_verifier_dropped() {
+        let verifier = Verifier::new(vec![example_issuer()]).with_resolver(Arc::new(MockDIDResolver::new()));
+        let handle = verifier.spawn_refresh_task(StdDuration::from_secs(60), |_| {});
+
+        drop(verifier);
+
+        time::timeout(StdDuration::from_secs(5), handle.task)
+            .await
+            .expect("refresh task still running")
+            .unwrap();
+    }
+}
@@ -0,0 +1,71 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{localhost_client, serve_did_document, serve_insecure_did_document, serve_redirect, DID_DOCUMENT};
+use nzcp::{
+    error::DecentralizedIdentifierError, CertificatePin, DecentralizedIdentifier, DocumentResolver, WebResolver,
+};
+
+const LOCALHOST_PIN: &str = "u6PlIg6yRKANVI6zbW6a0Z/WnsnRIyc+qt17Exvv138=";
+
+fn resolver() -> WebResolver {
+    WebResolver::new(Arc::new(localhost_client()))
+}
+
+#[tokio::test]
+async fn certificate_pinning() {
+    let (issuer, _) = serve_did_document().await;
+
+    let unpinned = resolver();
+    assert_eq!(
+        unpinned.resolve_representation(&issuer).await.unwrap(),
+        DID_DOCUMENT.as_bytes()
+    );
+
+    let pinned = resolver().with_certificate_pin("localhost", CertificatePin::from_base64(LOCALHOST_PIN).unwrap());
+    assert_eq!(
+        pinned.resolve_representation(&issuer).await.unwrap(),
+        DID_DOCUMENT.as_bytes()
+    );
+
+    let wrong_pin = resolver().with_certificate_pin("localhost", CertificatePin([0; 32]));
+    assert_eq!(
+        wrong_pin.resolve_representation(&issuer).await,
+        Err(DecentralizedIdentifierError::CertificatePinMismatch {
+            host: String::from("localhost")
+        })
+    );
+}
+
+#[tokio::test]
+async fn redirect_from_pinned_host() {
+    // e.g. an attacker with a mis-issued certificate for the pinned host redirecting to one they control
+    let (location, mut requests) = serve_insecure_did_document().await;
+    let redirect = serve_redirect(&location).await;
+    let issuer: DecentralizedIdentifier = "did:web:nzcp.covid19.health.nz".parse().unwrap();
+    // without HTTPS forced, the redirect to plain HTTP on 127.0.0.1 is allowed
+    let resolver = || {
+        resolver()
+            .with_force_https(false)
+            .with_document_url_override(&issuer.to_string(), &redirect)
+    };
+
+    let unpinned = resolver();
+    assert_eq!(
+        unpinned.resolve_representation(&issuer).await.unwrap(),
+        DID_DOCUMENT.as_bytes()
+    );
+    assert!(requests.recv().await.is_some());
+
+    let wrong_pin = resolver().with_certificate_pin("localhost", CertificatePin([0; 32]));
+    assert_eq!(
+        wrong_pin.resolve_representation(&issuer).await,
+        Err(DecentralizedIdentifierError::PinnedHostRedirected {
+            host: String::from("localhost"),
+            location,
+        })
+    );
+    // refused before it was followed
+    assert!(requests.try_recv().is_err());
+}
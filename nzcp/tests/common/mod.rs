@@ -0,0 +1,118 @@
+// each test uses only some of the helpers
+#![allow(dead_code)]
+
+use native_tls::Identity;
+use nzcp::DecentralizedIdentifier;
+use tokio::{
+    io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt},
+    net::TcpListener,
+    sync::mpsc,
+};
+use tokio_native_tls::TlsAcceptor;
+
+/// A self-signed certificate for localhost, valid until 2125.
+pub const LOCALHOST_CERTIFICATE: &[u8] = include_bytes!("../data/localhost.pem");
+const LOCALHOST_IDENTITY: &[u8] = include_bytes!("../data/localhost.p12");
+
+pub const DID_DOCUMENT: &str = r#"{"id": "did:web:localhost"}"#;
+
+/// Serve `DID_DOCUMENT` over HTTPS on localhost, returning the issuer it is served for and the head (request line and
+/// headers) of each request received.
+pub async fn serve_did_document() -> (DecentralizedIdentifier, mpsc::UnboundedReceiver<String>) {
+    serve(String::from(DID_DOCUMENT), false).await
+}
+
+/// Serve `body` as the DID document over HTTPS on localhost, with a `Content-Length` or, if `chunked`, as a single
+/// chunk without one, like `serve_did_document`.
+pub async fn serve(body: String, chunked: bool) -> (DecentralizedIdentifier, mpsc::UnboundedReceiver<String>) {
+    let response = if chunked {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nTransfer-Encoding: chunked\r\nConnection: close\r\n\r\n{:x}\r\n{}\r\n0\r\n\r\n",
+            body.len(),
+            body
+        )
+    }
+    else {
+        format!(
+            "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+            body.len(),
+            body
+        )
+    };
+    let (port, received) = respond(response, true).await;
+    (DecentralizedIdentifier::Web(format!("localhost%3A{}", port)), received)
+}
+
+/// Redirect every request to `location` over HTTPS on localhost, returning the URL it is served at.
+pub async fn serve_redirect(location: &str) -> String {
+    let response = format!(
+        "HTTP/1.1 302 Found\r\nLocation: {}\r\nContent-Length: 0\r\nConnection: close\r\n\r\n",
+        location
+    );
+    let (port, _) = respond(response, true).await;
+    format!("https://localhost:{}/.well-known/did.json", port)
+}
+
+/// Serve `DID_DOCUMENT` over plain HTTP on 127.0.0.1, returning its URL and the head of each request received.
+pub async fn serve_insecure_did_document() -> (String, mpsc::UnboundedReceiver<String>) {
+    let response = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        DID_DOCUMENT.len(),
+        DID_DOCUMENT
+    );
+    let (port, received) = respond(response, false).await;
+    (format!("http://127.0.0.1:{}/.well-known/did.json", port), received)
+}
+
+/// Send `response` to every request on a port of 127.0.0.1, over TLS with the localhost certificate if `tls`,
+/// returning the port and the head of each request received.
+async fn respond(response: String, tls: bool) -> (u16, mpsc::UnboundedReceiver<String>) {
+    let identity = Identity::from_pkcs12(LOCALHOST_IDENTITY, "nzcp").unwrap();
+    let acceptor = TlsAcceptor::from(native_tls::TlsAcceptor::new(identity).unwrap());
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let (requests, received) = mpsc::unbounded_channel();
+
+    tokio::spawn(async move {
+        while let Ok((stream, _)) = listener.accept().await {
+            if !tls {
+                reply(stream, &response, &requests).await;
+                continue;
+            }
+            if let Ok(stream) = acceptor.accept(stream).await {
+                reply(stream, &response, &requests).await;
+            }
+        }
+    });
+
+    (port, received)
+}
+
+async fn reply<S>(mut stream: S, response: &str, requests: &mpsc::UnboundedSender<String>)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.ends_with(b"\r\n\r\n") {
+        match stream.read(&mut buffer).await {
+            Ok(0) | Err(_) => break,
+            Ok(read) => request.extend_from_slice(&buffer[..read]),
+        }
+    }
+    let _ = requests.send(String::from_utf8_lossy(&request).into_owned());
+
+    let _ = stream.write_all(response.as_bytes()).await;
+    let _ = stream.shutdown().await;
+}
+
+/// A client trusting the localhost certificate, leaving redirects to the resolver like the default client.
+pub fn localhost_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .add_root_certificate(reqwest::Certificate::from_pem(LOCALHOST_CERTIFICATE).unwrap())
+        .no_proxy()
+        .redirect(reqwest::redirect::Policy::none())
+        .tls_info(true)
+        .build()
+        .unwrap()
+}
@@ -0,0 +1,36 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{localhost_client, serve, serve_did_document, DID_DOCUMENT};
+use nzcp::{error::DecentralizedIdentifierError, DocumentResolver, WebResolver};
+
+#[tokio::test]
+async fn max_document_size() {
+    let (with_length, _) = serve_did_document().await;
+    let (chunked, _) = serve(String::from(DID_DOCUMENT), true).await;
+    for issuer in [with_length, chunked] {
+        let resolver = WebResolver::new(Arc::new(localhost_client()));
+        assert_eq!(
+            resolver.resolve_representation(&issuer).await.unwrap(),
+            DID_DOCUMENT.as_bytes()
+        );
+        let resolver = resolver.with_max_document_size(DID_DOCUMENT.len());
+        assert_eq!(
+            resolver.resolve_representation(&issuer).await.unwrap(),
+            DID_DOCUMENT.as_bytes()
+        );
+        let resolver = resolver.with_max_document_size(DID_DOCUMENT.len() - 1);
+        assert_eq!(
+            resolver.resolve_representation(&issuer).await,
+            Err(DecentralizedIdentifierError::ResponseTooLarge(DID_DOCUMENT.len() - 1))
+        );
+    }
+
+    let (issuer, _) = serve(" ".repeat(2 * 1024 * 1024), true).await;
+    let resolver = WebResolver::new(Arc::new(localhost_client()));
+    assert_eq!(
+        resolver.resolve_representation(&issuer).await,
+        Err(DecentralizedIdentifierError::ResponseTooLarge(1024 * 1024))
+    );
+}
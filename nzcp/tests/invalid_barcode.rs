@@ -3,13 +3,15 @@ use nzcp::{
     verify_pass_uri_with_trusted_issuers, DecentralizedIdentifier, PublicCovidPass,
 };
 
-const EXAMPLE_ISSUER: DecentralizedIdentifier<'static> = DecentralizedIdentifier::Web("nzcp.covid19.health.nz");
+fn example_issuer() -> DecentralizedIdentifier {
+    DecentralizedIdentifier::Web(String::from("nzcp.covid19.health.nz"))
+}
 
 #[tokio::test]
 async fn invalid_barcode() {
     let barcode = "NZCP:/1/asdfghasSDFGHFDSADFGHFDSADFGHGFSDADFGBHFSADFGHFDSFGHFDDS0123456789";
 
-    let error: NzcpError = verify_pass_uri_with_trusted_issuers::<PublicCovidPass>(barcode, &[EXAMPLE_ISSUER])
+    let error: NzcpError = verify_pass_uri_with_trusted_issuers::<PublicCovidPass>(barcode, &[example_issuer()])
         .await
         .unwrap_err();
 
@@ -0,0 +1,66 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{localhost_client, serve_did_document, serve_insecure_did_document, serve_redirect, DID_DOCUMENT};
+use nzcp::{error::DecentralizedIdentifierError, DecentralizedIdentifier, DocumentResolver, WebResolver};
+
+fn issuer() -> DecentralizedIdentifier {
+    "did:web:nzcp.covid19.health.nz".parse().unwrap()
+}
+
+/// The URL of the DID document served for a `did:web:localhost%3A<port>` issuer.
+fn document_url(issuer: &DecentralizedIdentifier) -> String {
+    let host = issuer.to_string().trim_start_matches("did:web:").replace("%3A", ":");
+    format!("https://{}/.well-known/did.json", host)
+}
+
+/// A resolver fetching `issuer`'s document from `url`.
+fn resolver(issuer: &DecentralizedIdentifier, url: &str) -> WebResolver {
+    WebResolver::new(Arc::new(localhost_client())).with_document_url_override(&issuer.to_string(), url)
+}
+
+#[tokio::test]
+async fn redirect() {
+    let (issuer, mut requests) = serve_did_document().await;
+    let redirect = serve_redirect(&document_url(&issuer)).await;
+
+    let resolver = resolver(&issuer, &redirect);
+    assert_eq!(
+        resolver.resolve_representation(&issuer).await.unwrap(),
+        DID_DOCUMENT.as_bytes()
+    );
+    let request = requests.recv().await.unwrap().to_ascii_lowercase();
+    assert!(request.starts_with("get /.well-known/did.json http/1.1\r\n"));
+    assert!(request.contains("\r\naccept: application/json\r\n"));
+}
+
+#[tokio::test]
+async fn redirect_to_plain_http() {
+    // e.g. a compromised issuer's server downgrading the request so the document can be tampered with in transit
+    let (location, mut requests) = serve_insecure_did_document().await;
+    let redirect = serve_redirect(&location).await;
+
+    let resolver = resolver(&issuer(), &redirect);
+    assert_eq!(
+        resolver.resolve_representation(&issuer()).await,
+        Err(DecentralizedIdentifierError::InsecureResolution(location))
+    );
+    // refused before it was followed
+    assert!(requests.try_recv().is_err());
+}
+
+#[tokio::test]
+async fn too_many_redirects() {
+    let (issuer, _) = serve_did_document().await;
+    let mut redirect = document_url(&issuer);
+    for _ in 0..11 {
+        redirect = serve_redirect(&redirect).await;
+    }
+
+    let resolver = resolver(&issuer, &redirect);
+    assert!(matches!(
+        resolver.resolve_representation(&issuer).await,
+        Err(DecentralizedIdentifierError::ResolutionError(_))
+    ));
+}
@@ -0,0 +1,69 @@
+mod common;
+
+use std::sync::Arc;
+
+use common::{localhost_client, serve_did_document, serve_redirect, DID_DOCUMENT};
+use nzcp::{error::DecentralizedIdentifierError, DocumentResolver, WebResolver};
+use reqwest::header::{HeaderName, HeaderValue, USER_AGENT};
+
+#[tokio::test]
+async fn request_headers() {
+    let (issuer, mut requests) = serve_did_document().await;
+
+    let resolver = WebResolver::new(Arc::new(localhost_client()))
+        .with_header(USER_AGENT, HeaderValue::from_static("example-kiosk/2.1"))
+        .with_header(
+            HeaderName::from_static("x-kiosk-id"),
+            HeaderValue::from_static("kiosk-7"),
+        )
+        .with_request_hook(|request| request.header("x-trace-id", "4bf92f3577b34da6"));
+    assert_eq!(
+        resolver.resolve_representation(&issuer).await.unwrap(),
+        DID_DOCUMENT.as_bytes()
+    );
+
+    let request = requests.recv().await.unwrap().to_ascii_lowercase();
+    assert!(request.starts_with("get /.well-known/did.json http/1.1\r\n"));
+    assert!(request.contains("\r\nuser-agent: example-kiosk/2.1\r\n"));
+    assert!(request.contains("\r\nx-kiosk-id: kiosk-7\r\n"));
+    assert!(request.contains("\r\nx-trace-id: 4bf92f3577b34da6\r\n"));
+}
+
+#[tokio::test]
+async fn request_hook_cannot_change_url() {
+    let (issuer, _) = serve_did_document().await;
+
+    let client = Arc::new(localhost_client());
+    let redirect = Arc::clone(&client);
+    let resolver =
+        WebResolver::new(client).with_request_hook(move |_| redirect.get("https://169.254.169.254/latest/meta-data/"));
+
+    assert!(matches!(
+        resolver.resolve_representation(&issuer).await,
+        Err(DecentralizedIdentifierError::ResolutionError(_))
+    ));
+}
+
+#[tokio::test]
+async fn request_hook_cannot_be_redirected() {
+    let (issuer, mut requests) = serve_did_document().await;
+    let host = issuer.to_string().trim_start_matches("did:web:").replace("%3A", ":");
+    // on another port, so of another origin
+    let redirect = serve_redirect(&format!("https://{}/.well-known/did.json", host)).await;
+
+    let resolver =
+        || WebResolver::new(Arc::new(localhost_client())).with_document_url_override(&issuer.to_string(), &redirect);
+    assert_eq!(
+        resolver().resolve_representation(&issuer).await.unwrap(),
+        DID_DOCUMENT.as_bytes()
+    );
+    assert!(requests.recv().await.is_some());
+
+    let hooked = resolver().with_request_hook(|request| request.header("x-trace-id", "4bf92f3577b34da6"));
+    assert!(matches!(
+        hooked.resolve_representation(&issuer).await,
+        Err(DecentralizedIdentifierError::ResolutionError(_))
+    ));
+    // refused before it was followed
+    assert!(requests.try_recv().is_err());
+}
@@ -0,0 +1,94 @@
+/*
+ * C bindings for verifying NZ COVID Passes, implemented by the nzcp_ffi library.
+ *
+ * Strings passed in must be NUL-terminated UTF-8, and are only borrowed for the duration of the call. The strings in
+ * an NzcpResult are owned by the library until freed with nzcp_result_free. Verification blocks the calling thread.
+ */
+
+#ifndef NZCP_H
+#define NZCP_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum NzcpStatus {
+    NZCP_STATUS_OK = 0,
+    /*
+     * A required argument was NULL or not UTF-8, the trusted issuer wasn't a DID, the JWKS was invalid, or the time
+     * was out of range.
+     */
+    NZCP_STATUS_INVALID_ARGUMENT = 1,
+    NZCP_STATUS_QR_BARCODE = 2,
+    NZCP_STATUS_INVALID_PAYLOAD = 3,
+    NZCP_STATUS_NON_CANONICAL_CBOR = 4,
+    NZCP_STATUS_INVALID_SIGNATURE = 5,
+    /* The issuer's DID document couldn't be resolved, so the pass may be valid. */
+    NZCP_STATUS_RESOLUTION_FAILED = 6,
+    NZCP_STATUS_INVALID_CWT = 7,
+    NZCP_STATUS_PASS_NOT_YET_ACTIVE = 8,
+    NZCP_STATUS_PASS_TOO_FAR_IN_FUTURE = 9,
+    NZCP_STATUS_PASS_EXPIRED = 10,
+    NZCP_STATUS_PASS_REVOKED = 11,
+    NZCP_STATUS_PANIC = 12,
+    /* The issuer's DID document was missing the methods needed to find the pass's key. */
+    NZCP_STATUS_INVALID_DID_DOCUMENT = 13,
+    /* The issuer's key for the pass wasn't a P-256 JsonWebKey2020. */
+    NZCP_STATUS_UNSUPPORTED_KEY = 14,
+} NzcpStatus;
+
+/* Options for nzcp_verify. A NULL options pointer uses the defaults of every option. */
+typedef struct NzcpOptions {
+    /* The DID of the only issuer to accept passes from, or NULL for the MoH did:web:nzcp.identity.health.nz. */
+    const char *trusted_issuer;
+    /* A JWKS holding the issuer's keys, to verify passes offline, or NULL to resolve them from the issuer. */
+    const char *jwks;
+} NzcpOptions;
+
+/* A verified pass. Every string is owned by the library, freed by nzcp_result_free. */
+typedef struct NzcpResult {
+    char *given_name;
+    /* NULL if the pass has no family name. */
+    char *family_name;
+    /* The date of birth as YYYY-MM-DD. */
+    char *dob;
+    /* The issuer's DID, e.g. did:web:nzcp.identity.health.nz. */
+    char *issuer;
+    /* The pass's ID as a urn:uuid: URN. */
+    char *jti;
+    /* When the pass becomes active, as a unix timestamp. */
+    int64_t not_before;
+    /* When the pass expires, as a unix timestamp. */
+    int64_t expiry;
+} NzcpResult;
+
+/*
+ * Verify a pass barcode URI, filling in out_result if it is valid.
+ *
+ * out_result is always overwritten, with every string NULL unless NZCP_STATUS_OK is returned. Free a valid result
+ * with nzcp_result_free.
+ */
+NzcpStatus nzcp_verify(const char *uri, const NzcpOptions *options, NzcpResult *out_result);
+
+/*
+ * Verify a pass barcode URI like nzcp_verify, but as of at (a unix timestamp) rather than now, e.g. to check a pass
+ * as of when it was scanned, or to test with passes that have since expired.
+ */
+NzcpStatus nzcp_verify_at(const char *uri, const NzcpOptions *options, int64_t at, NzcpResult *out_result);
+
+/* Free the strings of a result, setting them to NULL. Does nothing if result is NULL, and freeing twice is safe. */
+void nzcp_result_free(NzcpResult *result);
+
+/*
+ * A static description of a status, which must not be freed. Takes an int, so any value can be passed, and values
+ * that aren't an NzcpStatus are described as an unknown status.
+ */
+const char *nzcp_status_message(int status);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* NZCP_H */
@@ -0,0 +1,551 @@
+//! C bindings for verifying passes, declared in `include/nzcp.h`.
+//!
+//! Strings passed in must be NUL-terminated UTF-8, and are only borrowed for the duration of the call. The strings in
+//! an `NzcpResult` are owned by the library until freed with `nzcp_result_free`. Panics are caught and reported as
+//! `NZCP_STATUS_PANIC`, so nothing unwinds across the boundary.
+//!
+//! Verification blocks the calling thread, and must not be called from a thread running a tokio runtime.
+//!
+//! With the `uniffi` feature, the library also exports the Kotlin and Swift bindings of the `mobile` module.
+
+use std::{
+    ffi::{c_char, c_int, CStr, CString},
+    panic::{self, AssertUnwindSafe},
+    ptr,
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use nzcp::{
+    error::{CoseVerificationError, DecentralizedIdentifierError, NzcpError},
+    ministry_of_health_issuer, DecentralizedIdentifier, Jwks, PublicCovidPass, VerifiedCredential, Verifier,
+};
+
+#[cfg(feature = "uniffi")]
+pub mod mobile;
+
+#[cfg(feature = "uniffi")]
+uniffi::setup_scaffolding!();
+
+/// The outcome of `nzcp_verify`, mirroring `NzcpError`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NzcpStatus {
+    Ok = 0,
+    /// A required argument was NULL or not UTF-8, the trusted issuer wasn't a DID, the JWKS was invalid, or the time
+    /// was out of range.
+    InvalidArgument = 1,
+    QrBarcode = 2,
+    InvalidPayload = 3,
+    NonCanonicalCbor = 4,
+    InvalidSignature = 5,
+    /// The issuer's DID document couldn't be resolved, so the pass may be valid.
+    ResolutionFailed = 6,
+    InvalidCwt = 7,
+    PassNotYetActive = 8,
+    PassTooFarInFuture = 9,
+    PassExpired = 10,
+    PassRevoked = 11,
+    Panic = 12,
+    /// The issuer's DID document was missing the methods needed to find the pass's key.
+    InvalidDidDocument = 13,
+    /// The issuer's key for the pass wasn't a P-256 `JsonWebKey2020`.
+    UnsupportedKey = 14,
+}
+
+impl NzcpStatus {
+    /// Every status, in order of their values.
+    const ALL: [NzcpStatus; 15] = [
+        NzcpStatus::Ok,
+        NzcpStatus::InvalidArgument,
+        NzcpStatus::QrBarcode,
+        NzcpStatus::InvalidPayload,
+        NzcpStatus::NonCanonicalCbor,
+        NzcpStatus::InvalidSignature,
+        NzcpStatus::ResolutionFailed,
+        NzcpStatus::InvalidCwt,
+        NzcpStatus::PassNotYetActive,
+        NzcpStatus::PassTooFarInFuture,
+        NzcpStatus::PassExpired,
+        NzcpStatus::PassRevoked,
+        NzcpStatus::Panic,
+        NzcpStatus::InvalidDidDocument,
+        NzcpStatus::UnsupportedKey,
+    ];
+
+    /// The status with the given value, `None` if there isn't one (e.g. from a newer version of the header).
+    fn from_raw(status: c_int) -> Option<Self> {
+        NzcpStatus::ALL.into_iter().find(|known| *known as c_int == status)
+    }
+}
+
+impl From<&NzcpError> for NzcpStatus {
+    fn from(error: &NzcpError) -> Self {
+        match error {
+            NzcpError::QrBarcode(_) => NzcpStatus::QrBarcode,
+            NzcpError::InvalidPayload(_) => NzcpStatus::InvalidPayload,
+            NzcpError::NonCanonicalCbor(_) => NzcpStatus::NonCanonicalCbor,
+            NzcpError::InvalidSignature(CoseVerificationError::DecentralizedIdentifierResolution(error)) => {
+                NzcpStatus::from(error)
+            }
+            NzcpError::Timeout(_) => NzcpStatus::ResolutionFailed,
+            NzcpError::InvalidSignature(_) => NzcpStatus::InvalidSignature,
+            NzcpError::InvalidCWT(_) | NzcpError::UnsupportedCredentialVersion(_) => NzcpStatus::InvalidCwt,
+            NzcpError::PassNotYetActive { .. } => NzcpStatus::PassNotYetActive,
+            NzcpError::PassTooFarInFuture { .. } => NzcpStatus::PassTooFarInFuture,
+            NzcpError::PassExpired { .. } => NzcpStatus::PassExpired,
+            NzcpError::PassRevoked(_) => NzcpStatus::PassRevoked,
+            // only returned when collecting every problem, which the FFI doesn't, so reported by the first
+            NzcpError::MultipleErrors(errors) => errors.first().map_or(NzcpStatus::InvalidPayload, NzcpStatus::from),
+        }
+    }
+}
+
+impl From<&DecentralizedIdentifierError> for NzcpStatus {
+    fn from(error: &DecentralizedIdentifierError) -> Self {
+        use DecentralizedIdentifierError::*;
+        match error {
+            ResolutionError(_)
+            | DocumentNotFound
+            | CertificatePinMismatch { .. }
+            | PinnedHostRedirected { .. }
+            | InsecureResolution(_)
+            | ResponseTooLarge(_)
+            | RateLimited
+            | IssuerNotInSnapshot(_)
+            | KeyNotInJwks(_) => NzcpStatus::ResolutionFailed,
+            EmptyDocument
+            | MissingAssertionMethods
+            | MissingAssertionMethod { .. }
+            | MissingVerificationMethods
+            | MissingVerificationMethod { .. } => NzcpStatus::InvalidDidDocument,
+            NotJsonWebKey2020 | MissingJWK | JWKNotEllipticCurve | JWKMissingX | JWKMissingY | JWKWrongCurve
+            | InvalidJWK => NzcpStatus::UnsupportedKey,
+        }
+    }
+}
+
+/// Options for `nzcp_verify`. A NULL options pointer uses the defaults of every option.
+#[repr(C)]
+#[derive(Debug)]
+pub struct NzcpOptions {
+    /// The DID of the only issuer to accept passes from, or NULL for the MoH `did:web:nzcp.identity.health.nz`.
+    pub trusted_issuer: *const c_char,
+
+    /// A JWKS holding the issuer's keys, to verify passes offline, or NULL to resolve them from the issuer's DID
+    /// document. See `nzcp::Jwks` for the format.
+    pub jwks: *const c_char,
+}
+
+/// A verified pass. Every string is NUL-terminated UTF-8 owned by the library, freed by `nzcp_result_free`.
+#[repr(C)]
+#[derive(Debug)]
+pub struct NzcpResult {
+    pub given_name: *mut c_char,
+    /// NULL if the pass has no family name.
+    pub family_name: *mut c_char,
+    /// The date of birth as `YYYY-MM-DD`.
+    pub dob: *mut c_char,
+    /// The issuer's DID, e.g. `did:web:nzcp.identity.health.nz`.
+    pub issuer: *mut c_char,
+    /// The pass's ID as a `urn:uuid:` URN.
+    pub jti: *mut c_char,
+    /// When the pass becomes active, as a unix timestamp.
+    pub not_before: i64,
+    /// When the pass expires, as a unix timestamp.
+    pub expiry: i64,
+}
+
+impl NzcpResult {
+    const EMPTY: NzcpResult = NzcpResult {
+        given_name: ptr::null_mut(),
+        family_name: ptr::null_mut(),
+        dob: ptr::null_mut(),
+        issuer: ptr::null_mut(),
+        jti: ptr::null_mut(),
+        not_before: 0,
+        expiry: 0,
+    };
+
+    /// `None` if a string holds a NUL, which C can't represent.
+    fn from_credential(credential: VerifiedCredential<PublicCovidPass>) -> Option<Self> {
+        let pass = credential.pass;
+        let claims = credential.claims;
+        let mut result = NzcpResult {
+            not_before: claims.not_before.timestamp(),
+            expiry: claims.expiry.timestamp(),
+            ..NzcpResult::EMPTY
+        };
+
+        let strings = [
+            (&mut result.given_name, Some(pass.given_name.clone())),
+            (&mut result.family_name, pass.family_name.clone()),
+            (&mut result.dob, Some(pass.date_of_birth_iso_8601())),
+            (&mut result.issuer, Some(claims.issuer.to_string())),
+            (&mut result.jti, Some(claims.jti())),
+        ];
+        let mut complete = true;
+        for (field, value) in strings {
+            match value.map(CString::new) {
+                Some(Ok(value)) => *field = value.into_raw(),
+                Some(Err(_)) => complete = false,
+                None => {}
+            }
+        }
+
+        if complete {
+            Some(result)
+        }
+        else {
+            // SAFETY: every non-NULL string was allocated above
+            unsafe { free_strings(&mut result) };
+            None
+        }
+    }
+}
+
+/// Borrow a required string argument, `None` if it is NULL or not UTF-8.
+///
+/// # Safety
+///
+/// `string` must be NULL or point to a NUL-terminated string that outlives the returned reference.
+unsafe fn borrow_str<'a>(string: *const c_char) -> Option<&'a str> {
+    if string.is_null() {
+        return None;
+    }
+    CStr::from_ptr(string).to_str().ok()
+}
+
+/// Build the verifier for the given options, `None` if they are invalid.
+///
+/// # Safety
+///
+/// `options` must be NULL or point to a valid `NzcpOptions`.
+unsafe fn verifier(options: *const NzcpOptions) -> Option<Verifier> {
+    let options = match options.as_ref() {
+        Some(options) => options,
+        None => return Some(Verifier::new(vec![ministry_of_health_issuer()])),
+    };
+
+    let issuer = match options.trusted_issuer.is_null() {
+        true => ministry_of_health_issuer(),
+        false => DecentralizedIdentifier::try_from(borrow_str(options.trusted_issuer)?).ok()?,
+    };
+    let verifier = Verifier::new(vec![issuer]);
+    match options.jwks.is_null() {
+        true => Some(verifier),
+        false => Some(verifier.with_jwks(Jwks::from_json_str(borrow_str(options.jwks)?).ok()?)),
+    }
+}
+
+/// Free the strings of a result, setting them to NULL.
+///
+/// # Safety
+///
+/// Each string must be NULL or have been allocated by `CString::into_raw`.
+unsafe fn free_strings(result: &mut NzcpResult) {
+    for string in [
+        &mut result.given_name,
+        &mut result.family_name,
+        &mut result.dob,
+        &mut result.issuer,
+        &mut result.jti,
+    ] {
+        if !string.is_null() {
+            drop(CString::from_raw(*string));
+            *string = ptr::null_mut();
+        }
+    }
+}
+
+/// Verify a pass barcode URI, filling in `out_result` if it is valid.
+///
+/// `out_result` is always overwritten, with every string NULL unless the status is `NZCP_STATUS_OK`. Free a valid
+/// result with `nzcp_result_free`.
+///
+/// # Safety
+///
+/// `uri` must be NULL or a NUL-terminated string, `options` NULL or a valid `NzcpOptions`, and `out_result` a valid
+/// pointer to an `NzcpResult` (whose previous strings are not freed).
+#[no_mangle]
+pub unsafe extern "C" fn nzcp_verify(
+    uri: *const c_char,
+    options: *const NzcpOptions,
+    out_result: *mut NzcpResult,
+) -> NzcpStatus {
+    verify_into(uri, options, None, out_result)
+}
+
+/// Verify a pass barcode URI like `nzcp_verify`, but as of `at` (a unix timestamp) rather than now, e.g. to check a
+/// pass as of when it was scanned, or to test with passes that have since expired.
+///
+/// # Safety
+///
+/// As for `nzcp_verify`.
+#[no_mangle]
+pub unsafe extern "C" fn nzcp_verify_at(
+    uri: *const c_char,
+    options: *const NzcpOptions,
+    at: i64,
+    out_result: *mut NzcpResult,
+) -> NzcpStatus {
+    match Utc.timestamp_opt(at, 0).single() {
+        Some(at) => verify_into(uri, options, Some(at), out_result),
+        None => {
+            if !out_result.is_null() {
+                out_result.write(NzcpResult::EMPTY);
+            }
+            NzcpStatus::InvalidArgument
+        }
+    }
+}
+
+/// Verify a pass barcode URI as of `at`, or now, filling in `out_result` for `nzcp_verify` and `nzcp_verify_at`.
+///
+/// # Safety
+///
+/// As for `nzcp_verify`.
+unsafe fn verify_into(
+    uri: *const c_char,
+    options: *const NzcpOptions,
+    at: Option<DateTime<Utc>>,
+    out_result: *mut NzcpResult,
+) -> NzcpStatus {
+    if out_result.is_null() {
+        return NzcpStatus::InvalidArgument;
+    }
+    out_result.write(NzcpResult::EMPTY);
+
+    let verified = panic::catch_unwind(AssertUnwindSafe(|| {
+        let uri = borrow_str(uri).ok_or(NzcpStatus::InvalidArgument)?;
+        let verifier = verifier(options).ok_or(NzcpStatus::InvalidArgument)?;
+        let credential = match at {
+            Some(at) => verifier.verify_credential_uri_at_blocking::<PublicCovidPass>(uri, at),
+            None => verifier.verify_credential_uri_blocking::<PublicCovidPass>(uri),
+        }
+        .map_err(|error| NzcpStatus::from(&error))?;
+        NzcpResult::from_credential(credential).ok_or(NzcpStatus::InvalidPayload)
+    }));
+
+    match verified {
+        Ok(Ok(result)) => {
+            out_result.write(result);
+            NzcpStatus::Ok
+        }
+        Ok(Err(status)) => status,
+        Err(_) => NzcpStatus::Panic,
+    }
+}
+
+/// Free the strings of a result filled in by `nzcp_verify`, setting them to NULL. Does nothing if `result` is NULL,
+/// and freeing a result twice is safe.
+///
+/// # Safety
+///
+/// `result` must be NULL or point to an `NzcpResult` filled in by `nzcp_verify`.
+#[no_mangle]
+pub unsafe extern "C" fn nzcp_result_free(result: *mut NzcpResult) {
+    if let Some(result) = result.as_mut() {
+        free_strings(result);
+    }
+}
+
+/// A static, NUL-terminated description of a status, which must not be freed.
+///
+/// The status is taken as an `int`, as C may pass any value for an enum, and a value that isn't one of `NzcpStatus`'s
+/// would be undefined behaviour as an `NzcpStatus`. Unknown values are described as an unknown status.
+#[no_mangle]
+pub extern "C" fn nzcp_status_message(status: c_int) -> *const c_char {
+    let message: &'static CStr = match NzcpStatus::from_raw(status) {
+        None => c"unknown status",
+        Some(NzcpStatus::Ok) => c"the pass is valid",
+        Some(NzcpStatus::InvalidArgument) => c"an argument was invalid",
+        Some(NzcpStatus::QrBarcode) => c"the barcode is not an NZCP barcode",
+        Some(NzcpStatus::InvalidPayload) => c"the pass could not be decoded",
+        Some(NzcpStatus::NonCanonicalCbor) => c"the pass is not canonical CBOR",
+        Some(NzcpStatus::InvalidSignature) => c"the pass's signature is invalid, or its issuer is not trusted",
+        Some(NzcpStatus::ResolutionFailed) => c"the issuer's keys could not be resolved",
+        Some(NzcpStatus::InvalidCwt) => c"the pass's claims are invalid",
+        Some(NzcpStatus::PassNotYetActive) => c"the pass is not active yet",
+        Some(NzcpStatus::PassTooFarInFuture) => c"the pass does not become active until too far in the future",
+        Some(NzcpStatus::PassExpired) => c"the pass has expired",
+        Some(NzcpStatus::PassRevoked) => c"the pass has been revoked",
+        Some(NzcpStatus::Panic) => c"verification failed unexpectedly",
+        Some(NzcpStatus::InvalidDidDocument) => c"the issuer's DID document is invalid",
+        Some(NzcpStatus::UnsupportedKey) => c"the issuer's key is not supported",
+    };
+    message.as_ptr()
+}
+
+#[cfg(test)]
+mod tests {
+    use nzcp::testing::{test_passes::expired_pass, test_vectors::scanned_at, VALID_PASS};
+
+    use super::*;
+
+    const EXAMPLE_JWKS: &str = r#"{"keys": [{
+        "kty": "EC",
+        "crv": "P-256",
+        "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+        "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0",
+        "kid": "did:web:nzcp.covid19.health.nz#key-1"
+    }]}"#;
+
+    fn c_string(string: &str) -> CString {
+        CString::new(string).unwrap()
+    }
+
+    /// Verify `uri` offline as from the example issuer, as of `scanned_at()`, so the example pass doesn't expire.
+    fn verify(uri: *const c_char, jwks: &str) -> (NzcpStatus, NzcpResult) {
+        let issuer = c_string("did:web:nzcp.covid19.health.nz");
+        let jwks = c_string(jwks);
+        let options = NzcpOptions {
+            trusted_issuer: issuer.as_ptr(),
+            jwks: jwks.as_ptr(),
+        };
+        let mut result = NzcpResult::EMPTY;
+        let status = unsafe { nzcp_verify_at(uri, &options, scanned_at().timestamp(), &mut result) };
+        (status, result)
+    }
+
+    fn read(string: *const c_char) -> &'static str {
+        unsafe { CStr::from_ptr(string) }.to_str().unwrap()
+    }
+
+    #[test]
+    fn verify_example_pass() {
+        let uri = c_string(VALID_PASS);
+        let (status, mut result) = verify(uri.as_ptr(), EXAMPLE_JWKS);
+
+        assert_eq!(status, NzcpStatus::Ok);
+        assert_eq!(read(result.given_name), "Jack");
+        assert_eq!(read(result.family_name), "Sparrow");
+        assert_eq!(read(result.dob), "1960-04-16");
+        assert_eq!(read(result.issuer), "did:web:nzcp.covid19.health.nz");
+        assert_eq!(read(result.jti), "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b");
+        assert_eq!(result.not_before, 1635883530);
+        assert_eq!(result.expiry, 1951416330);
+
+        unsafe { nzcp_result_free(&mut result) };
+        assert!(result.given_name.is_null() && result.jti.is_null());
+        unsafe {
+            nzcp_result_free(&mut result);
+            nzcp_result_free(ptr::null_mut());
+        }
+    }
+
+    #[test]
+    fn verification_failures() {
+        let (status, result) = verify(c_string(expired_pass()).as_ptr(), EXAMPLE_JWKS);
+        assert_eq!(status, NzcpStatus::PassExpired);
+        assert!(result.given_name.is_null());
+
+        let (status, _) = verify(c_string("https://example.com").as_ptr(), EXAMPLE_JWKS);
+        assert_eq!(status, NzcpStatus::QrBarcode);
+
+        let (status, _) = verify(c_string(VALID_PASS).as_ptr(), r#"{"keys": []}"#);
+        assert_eq!(status, NzcpStatus::ResolutionFailed);
+
+        let options = NzcpOptions {
+            trusted_issuer: ptr::null(),
+            jwks: ptr::null(),
+        };
+        let mut result = NzcpResult::EMPTY;
+        assert_eq!(
+            unsafe { nzcp_verify_at(c_string(VALID_PASS).as_ptr(), &options, i64::MAX, &mut result) },
+            NzcpStatus::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn status_messages() {
+        for (value, status) in NzcpStatus::ALL.into_iter().enumerate() {
+            assert_eq!(status as c_int, value as c_int);
+            assert_eq!(NzcpStatus::from_raw(value as c_int), Some(status));
+        }
+        assert_eq!(
+            read(nzcp_status_message(NzcpStatus::PassExpired as c_int)),
+            "the pass has expired"
+        );
+
+        // values C might pass that aren't a status
+        for value in [-1, NzcpStatus::ALL.len() as c_int, c_int::MAX] {
+            assert_eq!(read(nzcp_status_message(value)), "unknown status");
+        }
+    }
+
+    #[test]
+    fn did_document_statuses() {
+        let status = |error| {
+            NzcpStatus::from(&NzcpError::InvalidSignature(
+                CoseVerificationError::DecentralizedIdentifierResolution(error),
+            ))
+        };
+        assert_eq!(
+            status(DecentralizedIdentifierError::DocumentNotFound),
+            NzcpStatus::ResolutionFailed
+        );
+        assert_eq!(
+            status(DecentralizedIdentifierError::MissingAssertionMethods),
+            NzcpStatus::InvalidDidDocument
+        );
+        assert_eq!(
+            status(DecentralizedIdentifierError::JWKWrongCurve),
+            NzcpStatus::UnsupportedKey
+        );
+    }
+
+    #[test]
+    fn invalid_arguments() {
+        let uri = c_string(VALID_PASS);
+        assert_eq!(verify(ptr::null(), EXAMPLE_JWKS).0, NzcpStatus::InvalidArgument);
+        assert_eq!(verify(c"\xff".as_ptr(), EXAMPLE_JWKS).0, NzcpStatus::InvalidArgument);
+        assert_eq!(verify(uri.as_ptr(), "not a JWKS").0, NzcpStatus::InvalidArgument);
+
+        let options = NzcpOptions {
+            trusted_issuer: c_string("nzcp.covid19.health.nz").into_raw(),
+            jwks: ptr::null(),
+        };
+        let mut result = NzcpResult::EMPTY;
+        assert_eq!(
+            unsafe { nzcp_verify(uri.as_ptr(), &options, &mut result) },
+            NzcpStatus::InvalidArgument
+        );
+        drop(unsafe { CString::from_raw(options.trusted_issuer as *mut c_char) });
+
+        assert_eq!(
+            unsafe { nzcp_verify(uri.as_ptr(), ptr::null(), ptr::null_mut()) },
+            NzcpStatus::InvalidArgument
+        );
+    }
+
+    #[test]
+    fn strings_with_nul() {
+        use nzcp::testing::{did_document, example_issuer, test_signing_key, TestPass};
+        use serde_cbor::Value;
+
+        let key = test_signing_key(1);
+        let document = did_document(&example_issuer(), &[("key-1", key.verifying_key())]);
+        let document: serde_json::Value = serde_json::from_str(&document).unwrap();
+        let mut jwk = document["verificationMethod"][0]["publicKeyJwk"].clone();
+        jwk["kid"] = serde_json::json!("did:web:nzcp.covid19.health.nz#key-1");
+        let jwks = serde_json::json!({ "keys": [jwk] }).to_string();
+        let signed = |given_name: &str| {
+            let mut pass = TestPass::default();
+            if let Value::Map(subject) = &mut pass.credential_subject {
+                subject.insert(
+                    Value::Text(String::from("givenName")),
+                    Value::Text(String::from(given_name)),
+                );
+            }
+            c_string(&pass.sign(&key))
+        };
+
+        let (status, mut result) = verify(signed("Jack").as_ptr(), &jwks);
+        assert_eq!(status, NzcpStatus::Ok);
+        unsafe { nzcp_result_free(&mut result) };
+
+        let (status, result) = verify(signed("Jack\0").as_ptr(), &jwks);
+        assert_eq!(status, NzcpStatus::InvalidPayload);
+        assert!(result.given_name.is_null() && result.family_name.is_null());
+    }
+}
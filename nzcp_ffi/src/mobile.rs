@@ -0,0 +1,241 @@
+//! UniFFI bindings for verifying passes from Kotlin and Swift (requires the `uniffi` feature), generated from the
+//! built library by the `uniffi-bindgen` binary (requires the `uniffi-bindgen` feature):
+//!
+//! ```sh
+//! cargo build -p nzcp_ffi --features uniffi
+//! cargo run -p nzcp_ffi --features uniffi-bindgen --bin uniffi-bindgen -- \
+//!     generate --library target/debug/libnzcp_ffi.so --language kotlin --out-dir out
+//! ```
+//!
+//! Verification blocks the calling thread, so call `NzcpVerifier::verify_pass_uri` off the main thread.
+
+use std::{sync::Arc, time::SystemTime};
+
+use chrono::{DateTime, Utc};
+use nzcp::{
+    error::{CoseVerificationError, NzcpError},
+    ministry_of_health_issuer, DecentralizedIdentifier, IssuerSnapshot, PublicCovidPass, VerifiedCredential, Verifier,
+};
+use thiserror::Error;
+
+use crate::NzcpStatus;
+
+/// The time to check passes as of, e.g. from a network time source rather than the device's clock.
+#[uniffi::export(with_foreign)]
+pub trait NzcpClock: Send + Sync {
+    fn now(&self) -> SystemTime;
+}
+
+/// A verified pass.
+#[derive(Debug, Clone, PartialEq, Eq, uniffi::Record)]
+pub struct NzcpPass {
+    pub given_name: String,
+    /// `None` if the pass has no family name.
+    pub family_name: Option<String>,
+    /// The date of birth as `YYYY-MM-DD`.
+    pub dob: String,
+    /// The issuer's DID, e.g. `did:web:nzcp.identity.health.nz`.
+    pub issuer: String,
+    /// The pass's ID as a `urn:uuid:` URN.
+    pub jti: String,
+    /// When the pass becomes active.
+    pub not_before: SystemTime,
+    /// When the pass expires.
+    pub expiry: SystemTime,
+}
+
+impl From<VerifiedCredential<PublicCovidPass>> for NzcpPass {
+    fn from(credential: VerifiedCredential<PublicCovidPass>) -> Self {
+        let (pass, claims) = (credential.pass, credential.claims);
+        NzcpPass {
+            dob: pass.date_of_birth_iso_8601(),
+            given_name: pass.given_name,
+            family_name: pass.family_name,
+            issuer: claims.issuer.to_string(),
+            jti: claims.jti(),
+            not_before: claims.not_before.into(),
+            expiry: claims.expiry.into(),
+        }
+    }
+}
+
+/// Why a pass couldn't be verified, by category, with a description of the problem.
+#[derive(Debug, Clone, PartialEq, Eq, Error, uniffi::Error)]
+pub enum NzcpVerifyError {
+    /// A trusted issuer wasn't a DID, or a DID document couldn't be used.
+    #[error("invalid argument: {reason}")]
+    InvalidArgument { reason: String },
+    /// The barcode, its payload or the pass's claims were invalid.
+    #[error("malformed pass: {reason}")]
+    Malformed { reason: String },
+    /// The pass wasn't issued by a trusted issuer.
+    #[error("untrusted issuer: {reason}")]
+    UntrustedIssuer { reason: String },
+    /// The pass's signature was invalid, or its key wasn't in the issuer's DID document.
+    #[error("invalid signature: {reason}")]
+    InvalidSignature { reason: String },
+    /// The issuer's DID document couldn't be resolved, so the pass may be valid.
+    #[error("resolution failed: {reason}")]
+    ResolutionFailed { reason: String },
+    /// The pass isn't active yet.
+    #[error("not yet active: {reason}")]
+    NotYetActive { reason: String },
+    /// The pass has expired.
+    #[error("expired: {reason}")]
+    Expired { reason: String },
+    /// The pass has been revoked.
+    #[error("revoked: {reason}")]
+    Revoked { reason: String },
+}
+
+impl From<NzcpError> for NzcpVerifyError {
+    fn from(error: NzcpError) -> Self {
+        use NzcpVerifyError::*;
+
+        let reason = error.to_string();
+        if let NzcpError::InvalidSignature(CoseVerificationError::UntrustedIssuer(_)) = error {
+            return UntrustedIssuer { reason };
+        }
+        match NzcpStatus::from(&error) {
+            NzcpStatus::InvalidSignature | NzcpStatus::InvalidDidDocument | NzcpStatus::UnsupportedKey => {
+                InvalidSignature { reason }
+            }
+            NzcpStatus::ResolutionFailed => ResolutionFailed { reason },
+            NzcpStatus::PassNotYetActive | NzcpStatus::PassTooFarInFuture => NotYetActive { reason },
+            NzcpStatus::PassExpired => Expired { reason },
+            NzcpStatus::PassRevoked => Revoked { reason },
+            // the barcode, payload and claims, as verification doesn't fail with the other statuses
+            _ => Malformed { reason },
+        }
+    }
+}
+
+/// Verifies passes from a fixed set of trusted issuers.
+#[derive(uniffi::Object)]
+pub struct NzcpVerifier {
+    verifier: Verifier,
+    clock: Option<Arc<dyn NzcpClock>>,
+}
+
+#[uniffi::export]
+impl NzcpVerifier {
+    /// Create a verifier trusting the given issuer DIDs, or only the MoH `did:web:nzcp.identity.health.nz` if there
+    /// are none.
+    ///
+    /// Issuers' keys are read from `did_documents` (the JSON of each issuer's DID document) to verify passes offline,
+    /// or fetched from the issuers if there are none. Passes are checked as of the time from `clock`, or the device's
+    /// clock if there isn't one.
+    #[uniffi::constructor]
+    pub fn new(
+        trusted_issuers: Vec<String>,
+        did_documents: Vec<String>,
+        clock: Option<Arc<dyn NzcpClock>>,
+    ) -> Result<Self, NzcpVerifyError> {
+        let invalid = |reason: String| NzcpVerifyError::InvalidArgument { reason };
+
+        let issuers = match trusted_issuers.is_empty() {
+            true => vec![ministry_of_health_issuer()],
+            false => trusted_issuers
+                .iter()
+                .map(|issuer| {
+                    DecentralizedIdentifier::try_from(issuer.as_str())
+                        .map_err(|_| invalid(format!("{:?} is not a DID", issuer)))
+                })
+                .collect::<Result<_, _>>()?,
+        };
+        let mut verifier = Verifier::new(issuers);
+        if !did_documents.is_empty() {
+            let snapshot =
+                IssuerSnapshot::from_documents(&did_documents).map_err(|error| invalid(error.to_string()))?;
+            verifier = verifier.with_resolver(Arc::new(snapshot));
+        }
+        Ok(NzcpVerifier { verifier, clock })
+    }
+
+    /// Verify a pass barcode URI, blocking until done.
+    pub fn verify_pass_uri(&self, uri: String) -> Result<NzcpPass, NzcpVerifyError> {
+        let credential = match &self.clock {
+            Some(clock) => {
+                let at = DateTime::<Utc>::from(clock.now());
+                self.verifier
+                    .verify_credential_uri_at_blocking::<PublicCovidPass>(&uri, at)
+            }
+            None => self.verifier.verify_credential_uri_blocking::<PublicCovidPass>(&uri),
+        }?;
+        Ok(NzcpPass::from(credential))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use nzcp::testing::{
+        test_vectors::{scanned_at, EXPIRED_PASS},
+        EXAMPLE_DID_DOCUMENT, VALID_PASS,
+    };
+
+    use super::*;
+
+    struct FixedClock(DateTime<Utc>);
+
+    impl NzcpClock for FixedClock {
+        fn now(&self) -> SystemTime {
+            self.0.into()
+        }
+    }
+
+    /// A verifier trusting `issuer`, with the example issuer's DID document, as of `scanned_at()`.
+    fn verifier(issuer: &str) -> Result<NzcpVerifier, NzcpVerifyError> {
+        NzcpVerifier::new(
+            vec![String::from(issuer)],
+            vec![String::from(EXAMPLE_DID_DOCUMENT)],
+            Some(Arc::new(FixedClock(scanned_at()))),
+        )
+    }
+
+    #[test]
+    fn verify_example_pass() {
+        let pass = verifier("did:web:nzcp.covid19.health.nz")
+            .unwrap()
+            .verify_pass_uri(String::from(VALID_PASS))
+            .unwrap();
+
+        assert_eq!(
+            pass,
+            NzcpPass {
+                given_name: String::from("Jack"),
+                family_name: Some(String::from("Sparrow")),
+                dob: String::from("1960-04-16"),
+                issuer: String::from("did:web:nzcp.covid19.health.nz"),
+                jti: String::from("urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b"),
+                not_before: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1635883530),
+                expiry: SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(1951416330),
+            }
+        );
+    }
+
+    #[test]
+    fn errors() {
+        let verify = |issuer, uri: &str| verifier(issuer)?.verify_pass_uri(String::from(uri));
+
+        assert!(matches!(
+            verify("did:web:nzcp.covid19.health.nz", EXPIRED_PASS),
+            Err(NzcpVerifyError::Expired { .. })
+        ));
+        assert!(matches!(
+            verify("did:web:example.com", VALID_PASS),
+            Err(NzcpVerifyError::UntrustedIssuer { .. })
+        ));
+        assert!(matches!(
+            verify("did:web:nzcp.covid19.health.nz", "NZCP:/1/"),
+            Err(NzcpVerifyError::Malformed { .. })
+        ));
+        assert!(matches!(
+            verify("not a DID", VALID_PASS),
+            Err(NzcpVerifyError::InvalidArgument { .. })
+        ));
+        assert!(matches!(
+            NzcpVerifier::new(Vec::new(), vec![String::from("{}")], None),
+            Err(NzcpVerifyError::InvalidArgument { .. })
+        ));
+    }
+}
@@ -0,0 +1,38 @@
+//! Compile `harness.c` against the library and run it.
+
+use std::{env, path::PathBuf, process::Command};
+
+#[test]
+#[cfg(target_os = "linux")]
+fn c_harness() {
+    let manifest_dir = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    // the test binary is in target/<profile>/deps, next to the library's directory
+    let library_dir = env::current_exe()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .parent()
+        .unwrap()
+        .to_owned();
+    let harness = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join("nzcp_harness");
+
+    let compiled = Command::new(env::var("CC").unwrap_or_else(|_| String::from("cc")))
+        .arg(manifest_dir.join("tests/harness.c"))
+        .arg("-I")
+        .arg(manifest_dir.join("include"))
+        .arg("-L")
+        .arg(&library_dir)
+        .arg("-lnzcp_ffi")
+        .arg("-o")
+        .arg(&harness)
+        .status()
+        .expect("failed to run the C compiler");
+    assert!(compiled.success());
+
+    let output = Command::new(&harness)
+        .env("LD_LIBRARY_PATH", &library_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{}", String::from_utf8_lossy(&output.stderr));
+    assert_eq!(output.stdout, b"ok\n");
+}
@@ -0,0 +1,56 @@
+/* Verifies the example pass from the specification offline, with the example issuer's key, as of SCANNED_AT. */
+
+#include <stdio.h>
+#include <string.h>
+
+#include "nzcp.h"
+
+static const char *VALID_PASS =
+    "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFAIGTUKBAAUYTWMOSG"
+    "QQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTIOR2HA4Z2F4XW46TDOAXGG33WNFSDCO"
+    "JONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2FMZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN5"
+    "3GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTBNVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDIL"
+    "JRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIDJOA6Y524TD3AZRM263WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5Z"
+    "LIWEQJOZTS3IQ7JTNCFDX";
+
+static const char *EXAMPLE_JWKS =
+    "{\"keys\": [{\"kty\": \"EC\", \"crv\": \"P-256\", \"x\": \"zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760\", "
+    "\"y\": \"Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0\", \"kid\": \"did:web:nzcp.covid19.health.nz#key-1\"}]}";
+
+/* testing::test_vectors::scanned_at(), 2026-01-01T00:00:00Z, before the example pass expires. */
+static const int64_t SCANNED_AT = 1767225600;
+
+#define CHECK(condition)                                                  \
+    if (!(condition)) {                                                   \
+        fprintf(stderr, "%s:%d: check failed: %s\n", __FILE__, __LINE__, #condition); \
+        return 1;                                                         \
+    }
+
+int main(void) {
+    NzcpOptions options = {"did:web:nzcp.covid19.health.nz", EXAMPLE_JWKS};
+    NzcpResult result;
+
+    NzcpStatus status = nzcp_verify_at(VALID_PASS, &options, SCANNED_AT, &result);
+    CHECK(status == NZCP_STATUS_OK);
+    CHECK(strcmp(result.given_name, "Jack") == 0);
+    CHECK(strcmp(result.family_name, "Sparrow") == 0);
+    CHECK(strcmp(result.dob, "1960-04-16") == 0);
+    CHECK(strcmp(result.issuer, "did:web:nzcp.covid19.health.nz") == 0);
+    CHECK(strcmp(result.jti, "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b") == 0);
+    CHECK(result.not_before == 1635883530);
+    CHECK(result.expiry == 1951416330);
+    nzcp_result_free(&result);
+    CHECK(result.given_name == NULL);
+    nzcp_result_free(&result);
+
+    status = nzcp_verify("NZCP:/1/2KCEVIQEIU", &options, &result);
+    CHECK(status == NZCP_STATUS_INVALID_PAYLOAD);
+    CHECK(result.given_name == NULL);
+    CHECK(strlen(nzcp_status_message(status)) > 0);
+    CHECK(strcmp(nzcp_status_message(-1), "unknown status") == 0);
+
+    CHECK(nzcp_verify(NULL, &options, &result) == NZCP_STATUS_INVALID_ARGUMENT);
+
+    printf("ok\n");
+    return 0;
+}
@@ -0,0 +1,71 @@
+// Verifies the example passes from the specification offline through the generated Kotlin bindings, run under JNA by
+// the uniffi job in CI, as of testing::test_vectors::scanned_at() so the valid pass hasn't expired.
+
+import java.time.Instant
+import uniffi.nzcp_ffi.NzcpClock
+import uniffi.nzcp_ffi.NzcpVerifier
+import uniffi.nzcp_ffi.NzcpVerifyException
+
+const val VALID_PASS =
+    "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFA" +
+    "IGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTI" +
+    "OR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2F" +
+    "MZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTB" +
+    "NVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIDJO" +
+    "A6Y524TD3AZRM263WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX"
+
+const val EXPIRED_PASS =
+    "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUX5AM2FQ" +
+    "IGTBPBPYWYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTI" +
+    "OR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2F" +
+    "MZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTB" +
+    "NVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVA56TNJCCUN2NVK5NGAYOZ6VIWACYIBM3" +
+    "QXW7SLCMD2WTJ3GSEI5JH7RXAEURGATOHAHXC2O6BEJKBSVI25ICTBR5SFYUDSVLB2F6SJ63LWJ6Z3FWNHOXF6A2QLJNUFRQNTRU"
+
+const val EXAMPLE_DID_DOCUMENT = """{
+    "@context": "https://w3.org/ns/did/v1",
+    "id": "did:web:nzcp.covid19.health.nz",
+    "verificationMethod": [
+        {
+            "id": "did:web:nzcp.covid19.health.nz#key-1",
+            "controller": "did:web:nzcp.covid19.health.nz",
+            "type": "JsonWebKey2020",
+            "publicKeyJwk": {
+                "kty": "EC",
+                "crv": "P-256",
+                "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+                "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0"
+            }
+        }
+    ],
+    "assertionMethod": ["did:web:nzcp.covid19.health.nz#key-1"]
+}"""
+
+class FixedClock(private val at: Instant) : NzcpClock {
+    override fun now(): Instant = at
+}
+
+fun main() {
+    val verifier = NzcpVerifier(
+        listOf("did:web:nzcp.covid19.health.nz"),
+        listOf(EXAMPLE_DID_DOCUMENT),
+        FixedClock(Instant.ofEpochSecond(1767225600)),
+    )
+
+    val pass = verifier.verifyPassUri(VALID_PASS)
+    check(pass.givenName == "Jack")
+    check(pass.familyName == "Sparrow")
+    check(pass.dob == "1960-04-16")
+    check(pass.issuer == "did:web:nzcp.covid19.health.nz")
+    check(pass.jti == "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b")
+    check(pass.notBefore == Instant.ofEpochSecond(1635883530))
+    check(pass.expiry == Instant.ofEpochSecond(1951416330))
+
+    try {
+        verifier.verifyPassUri(EXPIRED_PASS)
+        error("the expired pass was accepted")
+    } catch (expected: NzcpVerifyException.Expired) {
+    }
+
+    println("ok")
+}
@@ -0,0 +1,5 @@
+//! Generates the Kotlin and Swift bindings from the library built with the `uniffi` feature, see `src/mobile.rs`.
+
+fn main() {
+    uniffi::uniffi_bindgen_main()
+}
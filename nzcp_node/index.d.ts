@@ -0,0 +1,40 @@
+export interface VerifyOptions {
+  /** The DIDs of the issuers to accept passes from, by default only the MoH `did:web:nzcp.identity.health.nz`. */
+  trustedIssuers?: string[];
+  /** The JSON of the issuer's DID document, to verify passes offline rather than fetching it from the issuer. */
+  didDocument?: string;
+  /** The time to check passes as of, by default now. */
+  at?: Date | number;
+}
+
+export interface VerifiedPass {
+  givenName: string;
+  familyName?: string;
+  /** The date of birth as `YYYY-MM-DD`. */
+  dob: string;
+  /** The issuer's DID, e.g. `did:web:nzcp.identity.health.nz`. */
+  issuer: string;
+  expires: Date;
+}
+
+export type NzcpErrorCode =
+  | 'MALFORMED'
+  | 'UNTRUSTED_ISSUER'
+  | 'INVALID_SIGNATURE'
+  | 'RESOLUTION_FAILED'
+  | 'NOT_YET_ACTIVE'
+  | 'EXPIRED'
+  | 'REVOKED';
+
+export class NzcpError extends Error {
+  readonly code: NzcpErrorCode;
+}
+export class MalformedPassError extends NzcpError {}
+export class UntrustedIssuerError extends NzcpError {}
+export class InvalidSignatureError extends NzcpError {}
+export class ResolutionError extends NzcpError {}
+export class NotYetActiveError extends NzcpError {}
+export class ExpiredError extends NzcpError {}
+export class RevokedError extends NzcpError {}
+
+export function verifyPassUri(uri: string, options?: VerifyOptions): Promise<VerifiedPass>;
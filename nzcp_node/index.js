@@ -0,0 +1,66 @@
+'use strict';
+
+// Verifies passes with the native addon (built by `npm run build`), throwing an `NzcpError` with a stable `code` for
+// each category of failure.
+
+const native = require('./nzcp_node.node');
+
+/** Why a pass couldn't be verified, see the subclasses for each `code`. */
+class NzcpError extends Error {
+  constructor(code, message) {
+    super(message);
+    this.name = new.target.name;
+    this.code = code;
+  }
+}
+
+/** The barcode, its payload or the pass's claims were invalid. */
+class MalformedPassError extends NzcpError {}
+/** The pass wasn't issued by a trusted issuer. */
+class UntrustedIssuerError extends NzcpError {}
+/** The pass's signature was invalid, or its key wasn't in the issuer's DID document. */
+class InvalidSignatureError extends NzcpError {}
+/** The issuer's DID document couldn't be resolved, so the pass may be valid. */
+class ResolutionError extends NzcpError {}
+/** The pass isn't active yet. */
+class NotYetActiveError extends NzcpError {}
+/** The pass has expired. */
+class ExpiredError extends NzcpError {}
+/** The pass has been revoked. */
+class RevokedError extends NzcpError {}
+
+const ERRORS = {
+  MALFORMED: MalformedPassError,
+  UNTRUSTED_ISSUER: UntrustedIssuerError,
+  INVALID_SIGNATURE: InvalidSignatureError,
+  RESOLUTION_FAILED: ResolutionError,
+  NOT_YET_ACTIVE: NotYetActiveError,
+  EXPIRED: ExpiredError,
+  REVOKED: RevokedError,
+};
+
+/**
+ * Verify a pass barcode URI, resolving to the pass, or rejecting with an `NzcpError` if it isn't valid (or a plain
+ * `Error` if the options are invalid).
+ */
+async function verifyPassUri(uri, options = {}) {
+  const at = options.at instanceof Date ? options.at.getTime() : options.at;
+  const { pass, failure } = await native.verify(uri, { ...options, at });
+  if (failure) {
+    const ErrorClass = ERRORS[failure.code] || NzcpError;
+    throw new ErrorClass(failure.code, failure.message);
+  }
+  return { ...pass, expires: new Date(pass.expires) };
+}
+
+module.exports = {
+  verifyPassUri,
+  NzcpError,
+  MalformedPassError,
+  UntrustedIssuerError,
+  InvalidSignatureError,
+  ResolutionError,
+  NotYetActiveError,
+  ExpiredError,
+  RevokedError,
+};
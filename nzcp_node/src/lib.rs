@@ -0,0 +1,161 @@
+//! Node.js bindings for verifying passes, built as the `nzcp_node.node` addon and wrapped by `index.js`, which throws
+//! a typed error with a stable `code` for each category of failure.
+//!
+//! Verification runs on the libuv thread pool, so it doesn't block the event loop.
+
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeZone, Utc};
+use napi::{bindgen_prelude::AsyncTask, Env, Error, Result, Status, Task};
+use napi_derive::napi;
+use nzcp::{
+    error::{ErrorCode, NzcpError},
+    ministry_of_health_issuer, DecentralizedIdentifier, IssuerSnapshot, PublicCovidPass, VerifiedCredential, Verifier,
+};
+
+/// Options for `verify`, each defaulting when not given.
+#[napi(object)]
+#[derive(Default)]
+pub struct VerifyOptions {
+    /// The DIDs of the issuers to accept passes from, by default only the MoH `did:web:nzcp.identity.health.nz`.
+    pub trusted_issuers: Option<Vec<String>>,
+    /// The JSON of the issuer's DID document, to verify passes offline rather than fetching it from the issuer.
+    pub did_document: Option<String>,
+    /// The time to check passes as of, in milliseconds since the Unix epoch, by default now.
+    pub at: Option<f64>,
+}
+
+/// A verified pass.
+#[napi(object)]
+pub struct VerifiedPass {
+    pub given_name: String,
+    pub family_name: Option<String>,
+    /// The date of birth as `YYYY-MM-DD`.
+    pub dob: String,
+    /// The issuer's DID, e.g. `did:web:nzcp.identity.health.nz`.
+    pub issuer: String,
+    /// When the pass expires, in milliseconds since the Unix epoch.
+    pub expires: f64,
+}
+
+impl From<VerifiedCredential<PublicCovidPass>> for VerifiedPass {
+    fn from(credential: VerifiedCredential<PublicCovidPass>) -> Self {
+        let (pass, claims) = (credential.pass, credential.claims);
+        VerifiedPass {
+            dob: pass.date_of_birth_iso_8601(),
+            given_name: pass.given_name,
+            family_name: pass.family_name,
+            issuer: claims.issuer.to_string(),
+            expires: claims.expiry.timestamp_millis() as f64,
+        }
+    }
+}
+
+/// Why a pass couldn't be verified.
+#[napi(object)]
+pub struct VerifyFailure {
+    /// The category of the failure, e.g. `EXPIRED`, see `index.js` for every category.
+    pub code: String,
+    pub message: String,
+}
+
+impl From<NzcpError> for VerifyFailure {
+    fn from(error: NzcpError) -> Self {
+        use ErrorCode::*;
+
+        let code = match error.code() {
+            PassExpired => "EXPIRED",
+            PassNotActive | PassTooFarInFuture => "NOT_YET_ACTIVE",
+            PassRevoked => "REVOKED",
+            IssuerUntrusted => "UNTRUSTED_ISSUER",
+            SignatureInvalid | SignatureLength | SignatureUnpinnedKey | DidKeyNotFound | DidInvalidDocument => {
+                "INVALID_SIGNATURE"
+            }
+            DidResolution | DidRateLimited | DidTimeout => "RESOLUTION_FAILED",
+            // the barcode, payload and claims
+            _ => "MALFORMED",
+        };
+        VerifyFailure {
+            code: String::from(code),
+            message: error.to_string(),
+        }
+    }
+}
+
+/// The pass if it is valid, otherwise why it isn't.
+#[napi(object)]
+pub struct Outcome {
+    pub pass: Option<VerifiedPass>,
+    pub failure: Option<VerifyFailure>,
+}
+
+/// Verifies a pass on the libuv thread pool, see `verify`.
+pub struct Verification {
+    verifier: Verifier,
+    uri: String,
+    at: Option<DateTime<Utc>>,
+}
+
+impl Task for Verification {
+    type Output = Outcome;
+    type JsValue = Outcome;
+
+    fn compute(&mut self) -> Result<Outcome> {
+        let verified = match self.at {
+            Some(at) => self
+                .verifier
+                .verify_credential_uri_at_blocking::<PublicCovidPass>(&self.uri, at),
+            None => self
+                .verifier
+                .verify_credential_uri_blocking::<PublicCovidPass>(&self.uri),
+        };
+        Ok(match verified {
+            Ok(credential) => Outcome {
+                pass: Some(credential.into()),
+                failure: None,
+            },
+            Err(error) => Outcome {
+                pass: None,
+                failure: Some(error.into()),
+            },
+        })
+    }
+
+    fn resolve(&mut self, _env: Env, outcome: Outcome) -> Result<Outcome> {
+        Ok(outcome)
+    }
+}
+
+/// Verify a pass barcode URI, resolving to the pass or why it isn't valid. Throws if the options are invalid.
+#[napi(ts_return_type = "Promise<Outcome>")]
+pub fn verify(uri: String, options: Option<VerifyOptions>) -> Result<AsyncTask<Verification>> {
+    let options = options.unwrap_or_default();
+    let invalid = |reason: String| Error::new(Status::InvalidArg, reason);
+
+    let issuers = match options.trusted_issuers {
+        Some(issuers) => issuers
+            .iter()
+            .map(|issuer| {
+                DecentralizedIdentifier::try_from(issuer.as_str())
+                    .map_err(|_| invalid(format!("{:?} is not a DID", issuer)))
+            })
+            .collect::<Result<_>>()?,
+        None => vec![ministry_of_health_issuer()],
+    };
+    let mut verifier = Verifier::new(issuers);
+    if let Some(document) = options.did_document {
+        let snapshot = IssuerSnapshot::from_documents(&[document]).map_err(|error| invalid(error.to_string()))?;
+        verifier = verifier.with_resolver(Arc::new(snapshot));
+    }
+    let at = options
+        .at
+        .map(|at| {
+            Utc.timestamp_millis_opt(at as i64)
+                .single()
+                .filter(|_| at.is_finite())
+                .ok_or_else(|| invalid(format!("{} is not a valid time", at)))
+        })
+        .transpose()?;
+
+    Ok(AsyncTask::new(Verification { verifier, uri, at }))
+}
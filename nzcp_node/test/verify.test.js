@@ -0,0 +1,109 @@
+'use strict';
+
+// Verifies the example passes from the specification offline, as of testing::test_vectors::scanned_at() so the
+// valid pass hasn't expired.
+
+const assert = require('node:assert');
+const { test } = require('node:test');
+
+const {
+  verifyPassUri,
+  NzcpError,
+  MalformedPassError,
+  UntrustedIssuerError,
+  InvalidSignatureError,
+  ExpiredError,
+} = require('..');
+
+const VALID_PASS =
+  'NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFA' +
+  'IGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTI' +
+  'OR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2F' +
+  'MZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTB' +
+  'NVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIDJO' +
+  'A6Y524TD3AZRM263WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX';
+
+const EXPIRED_PASS =
+  'NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUX5AM2FQ' +
+  'IGTBPBPYWYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTI' +
+  'OR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2F' +
+  'MZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTB' +
+  'NVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVA56TNJCCUN2NVK5NGAYOZ6VIWACYIBM3' +
+  'QXW7SLCMD2WTJ3GSEI5JH7RXAEURGATOHAHXC2O6BEJKBSVI25ICTBR5SFYUDSVLB2F6SJ63LWJ6Z3FWNHOXF6A2QLJNUFRQNTRU';
+
+const MODIFIED_SIGNATURE_PASS =
+  'NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFA' +
+  'IGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTI' +
+  'OR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2F' +
+  'MZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTB' +
+  'NVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIAAA' +
+  'AAAAAAAAAAAAAC63WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX';
+
+const EXAMPLE_DID_DOCUMENT = JSON.stringify({
+  '@context': 'https://w3.org/ns/did/v1',
+  id: 'did:web:nzcp.covid19.health.nz',
+  verificationMethod: [
+    {
+      id: 'did:web:nzcp.covid19.health.nz#key-1',
+      controller: 'did:web:nzcp.covid19.health.nz',
+      type: 'JsonWebKey2020',
+      publicKeyJwk: {
+        kty: 'EC',
+        crv: 'P-256',
+        x: 'zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760',
+        y: 'Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0',
+      },
+    },
+  ],
+  assertionMethod: ['did:web:nzcp.covid19.health.nz#key-1'],
+});
+
+const options = {
+  trustedIssuers: ['did:web:nzcp.covid19.health.nz'],
+  didDocument: EXAMPLE_DID_DOCUMENT,
+  at: new Date('2026-01-01T00:00:00Z'),
+};
+
+/** Check a verification rejects with an `NzcpError` of the given class and code. */
+async function rejectsWith(verification, ErrorClass, code) {
+  await assert.rejects(verification, (error) => {
+    assert.ok(error instanceof ErrorClass, `${error} is not a ${ErrorClass.name}`);
+    assert.ok(error instanceof NzcpError);
+    assert.strictEqual(error.code, code);
+    return true;
+  });
+}
+
+test('valid pass', async () => {
+  assert.deepStrictEqual(await verifyPassUri(VALID_PASS, options), {
+    givenName: 'Jack',
+    familyName: 'Sparrow',
+    dob: '1960-04-16',
+    issuer: 'did:web:nzcp.covid19.health.nz',
+    expires: new Date(1951416330 * 1000),
+  });
+});
+
+test('expired pass', async () => {
+  await rejectsWith(verifyPassUri(EXPIRED_PASS, options), ExpiredError, 'EXPIRED');
+});
+
+test('modified signature', async () => {
+  await rejectsWith(verifyPassUri(MODIFIED_SIGNATURE_PASS, options), InvalidSignatureError, 'INVALID_SIGNATURE');
+});
+
+test('untrusted issuer', async () => {
+  const untrusted = { ...options, trustedIssuers: ['did:web:example.com'] };
+  await rejectsWith(verifyPassUri(VALID_PASS, untrusted), UntrustedIssuerError, 'UNTRUSTED_ISSUER');
+});
+
+test('malformed payload', async () => {
+  await rejectsWith(verifyPassUri('NZCP:/1/', options), MalformedPassError, 'MALFORMED');
+});
+
+test('invalid options', async () => {
+  await assert.rejects(verifyPassUri(VALID_PASS, { trustedIssuers: ['not a DID'] }), (error) => {
+    assert.ok(!(error instanceof NzcpError));
+    return true;
+  });
+});
@@ -0,0 +1,228 @@
+//! Python bindings for verifying passes, built as the `nzcp` extension module with maturin (see `pyproject.toml`).
+//!
+//! Verification releases the GIL, so other Python threads run while passes are verified, and `verify_many` verifies
+//! a batch concurrently.
+
+use std::sync::{Arc, OnceLock};
+
+use chrono::{DateTime, FixedOffset, NaiveDate, Utc};
+use futures_util::{stream, StreamExt};
+use nzcp::{
+    error::{self, ErrorCode},
+    ministry_of_health_issuer, DecentralizedIdentifier, IssuerSnapshot, PublicCovidPass, VerifiedCredential, Verifier,
+    VerifierConfig,
+};
+use pyo3::{
+    create_exception,
+    exceptions::{PyException, PyValueError},
+    prelude::*,
+};
+use tokio::runtime::{Builder, Runtime};
+
+create_exception!(
+    nzcp,
+    NzcpError,
+    PyException,
+    "Why a pass couldn't be verified, see the subclasses for each category."
+);
+create_exception!(
+    nzcp,
+    MalformedPassError,
+    NzcpError,
+    "The barcode, its payload or the pass's claims were invalid."
+);
+create_exception!(
+    nzcp,
+    UntrustedIssuerError,
+    NzcpError,
+    "The pass wasn't issued by a trusted issuer."
+);
+create_exception!(
+    nzcp,
+    InvalidSignatureError,
+    NzcpError,
+    "The pass's signature was invalid, or its key wasn't in the issuer's DID document."
+);
+create_exception!(
+    nzcp,
+    ResolutionError,
+    NzcpError,
+    "The issuer's DID document couldn't be resolved, so the pass may be valid."
+);
+create_exception!(nzcp, NotYetActiveError, NzcpError, "The pass isn't active yet.");
+create_exception!(nzcp, ExpiredError, NzcpError, "The pass has expired.");
+create_exception!(nzcp, RevokedError, NzcpError, "The pass has been revoked.");
+
+/// The exception for why a pass couldn't be verified.
+fn exception(failure: error::NzcpError) -> PyErr {
+    use ErrorCode::*;
+
+    let message = failure.to_string();
+    match failure.code() {
+        PassExpired => ExpiredError::new_err(message),
+        PassNotActive | PassTooFarInFuture => NotYetActiveError::new_err(message),
+        PassRevoked => RevokedError::new_err(message),
+        IssuerUntrusted => UntrustedIssuerError::new_err(message),
+        SignatureInvalid | SignatureLength | SignatureUnpinnedKey | DidKeyNotFound | DidInvalidDocument => {
+            InvalidSignatureError::new_err(message)
+        }
+        DidResolution | DidRateLimited | DidTimeout => ResolutionError::new_err(message),
+        // the barcode, payload and claims
+        _ => MalformedPassError::new_err(message),
+    }
+}
+
+/// A verified pass.
+#[pyclass(frozen, get_all, eq, module = "nzcp")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedPass {
+    pub given_name: String,
+    /// `None` if the pass has no family name.
+    pub family_name: Option<String>,
+    pub dob: NaiveDate,
+    /// The issuer's DID, e.g. `did:web:nzcp.identity.health.nz`.
+    pub issuer: String,
+    /// The pass's ID as a `urn:uuid:` URN.
+    pub jti: String,
+    /// When the pass becomes active.
+    pub not_before: DateTime<Utc>,
+    /// When the pass expires.
+    pub expiry: DateTime<Utc>,
+}
+
+#[pymethods]
+impl VerifiedPass {
+    fn __repr__(&self) -> String {
+        let family_name = match &self.family_name {
+            Some(family_name) => format!("{:?}", family_name),
+            None => String::from("None"),
+        };
+        format!(
+            "VerifiedPass(given_name={:?}, family_name={}, dob={}, issuer={:?}, jti={:?}, not_before={}, expiry={})",
+            self.given_name,
+            family_name,
+            self.dob,
+            self.issuer,
+            self.jti,
+            self.not_before.to_rfc3339(),
+            self.expiry.to_rfc3339(),
+        )
+    }
+}
+
+impl From<VerifiedCredential<PublicCovidPass>> for VerifiedPass {
+    fn from(credential: VerifiedCredential<PublicCovidPass>) -> Self {
+        let (pass, claims) = (credential.pass, credential.claims);
+        VerifiedPass {
+            jti: claims.jti(),
+            given_name: pass.given_name,
+            family_name: pass.family_name,
+            dob: pass.date_of_birth,
+            issuer: claims.issuer.to_string(),
+            not_before: claims.not_before,
+            expiry: claims.expiry,
+        }
+    }
+}
+
+/// A verifier trusting the given issuer DIDs (or only the MoH issuer), reading keys from `did_document` if given.
+fn verifier(trusted_issuers: Option<Vec<String>>, did_document: Option<&str>) -> PyResult<Verifier> {
+    let issuers = match trusted_issuers {
+        Some(issuers) => issuers
+            .iter()
+            .map(|issuer| {
+                DecentralizedIdentifier::try_from(issuer.as_str())
+                    .map_err(|_| PyValueError::new_err(format!("{:?} is not a DID", issuer)))
+            })
+            .collect::<PyResult<_>>()?,
+        None => vec![ministry_of_health_issuer()],
+    };
+    let mut verifier = Verifier::new(issuers);
+    if let Some(document) = did_document {
+        let snapshot =
+            IssuerSnapshot::from_documents(&[document]).map_err(|error| PyValueError::new_err(error.to_string()))?;
+        verifier = verifier.with_resolver(Arc::new(snapshot));
+    }
+    Ok(verifier)
+}
+
+/// Verify a pass barcode URI, returning the pass or raising an `NzcpError` subclass if it isn't valid.
+///
+/// Passes are accepted from `trusted_issuers` (by default only the MoH `did:web:nzcp.identity.health.nz`), and
+/// checked as of the timezone-aware `at_time` (by default now). Issuers' keys are read from `did_document` (the JSON
+/// of the issuer's DID document) to verify passes offline, or fetched from the issuer if it isn't given. Raises
+/// `ValueError` if a trusted issuer isn't a DID, or the DID document can't be used.
+#[pyfunction]
+#[pyo3(signature = (uri, trusted_issuers=None, at_time=None, did_document=None))]
+fn verify_pass_uri(
+    py: Python<'_>,
+    uri: &str,
+    trusted_issuers: Option<Vec<String>>,
+    at_time: Option<DateTime<FixedOffset>>,
+    did_document: Option<&str>,
+) -> PyResult<VerifiedPass> {
+    let verifier = verifier(trusted_issuers, did_document)?;
+    let at = at_time.map_or_else(Utc::now, |at| at.with_timezone(&Utc));
+
+    py.allow_threads(|| verifier.verify_credential_uri_at_blocking::<PublicCovidPass>(uri, at))
+        .map(VerifiedPass::from)
+        .map_err(exception)
+}
+
+/// Verify a batch of pass barcode URIs concurrently like `verify_pass_uri`, returning the pass or the `NzcpError`
+/// (rather than raising it) for each, in the same order. A pass failing doesn't stop the rest being verified.
+#[pyfunction]
+#[pyo3(signature = (uris, trusted_issuers=None, at_time=None, did_document=None))]
+fn verify_many(
+    py: Python<'_>,
+    uris: Vec<String>,
+    trusted_issuers: Option<Vec<String>>,
+    at_time: Option<DateTime<FixedOffset>>,
+    did_document: Option<&str>,
+) -> PyResult<Vec<PyObject>> {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    let verifier = verifier(trusted_issuers, did_document)?;
+    let at = at_time.map_or_else(Utc::now, |at| at.with_timezone(&Utc));
+    // shared between calls rather than built for each batch
+    let runtime = RUNTIME.get_or_init(|| {
+        Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .expect("failed to build the verification runtime")
+    });
+
+    let results = py.allow_threads(|| {
+        runtime.block_on(
+            stream::iter(&uris)
+                .map(|uri| verifier.verify_credential_uri_at::<PublicCovidPass>(uri, at))
+                .buffered(VerifierConfig::default().batch_concurrency)
+                .collect::<Vec<_>>(),
+        )
+    });
+    results
+        .into_iter()
+        .map(|result| match result {
+            Ok(credential) => Ok(Py::new(py, VerifiedPass::from(credential))?.into_any()),
+            Err(failure) => Ok(exception(failure).into_value(py).into_any()),
+        })
+        .collect()
+}
+
+#[pymodule]
+#[pyo3(name = "nzcp")]
+fn nzcp_python(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add_class::<VerifiedPass>()?;
+    module.add_function(wrap_pyfunction!(verify_pass_uri, module)?)?;
+    module.add_function(wrap_pyfunction!(verify_many, module)?)?;
+    module.add("NzcpError", py.get_type_bound::<NzcpError>())?;
+    module.add("MalformedPassError", py.get_type_bound::<MalformedPassError>())?;
+    module.add("UntrustedIssuerError", py.get_type_bound::<UntrustedIssuerError>())?;
+    module.add("InvalidSignatureError", py.get_type_bound::<InvalidSignatureError>())?;
+    module.add("ResolutionError", py.get_type_bound::<ResolutionError>())?;
+    module.add("NotYetActiveError", py.get_type_bound::<NotYetActiveError>())?;
+    module.add("ExpiredError", py.get_type_bound::<ExpiredError>())?;
+    module.add("RevokedError", py.get_type_bound::<RevokedError>())?;
+    Ok(())
+}
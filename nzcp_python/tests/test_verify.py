@@ -0,0 +1,96 @@
+"""Verifies the example passes from the specification offline, as of 2026-01-01 so the valid pass hasn't expired."""
+
+import json
+from datetime import date, datetime, timezone
+
+import pytest
+
+import nzcp
+
+VALID_PASS = (
+    "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUYMBTIFA"
+    "IGTUKBAAUYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTI"
+    "OR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2F"
+    "MZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTB"
+    "NVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVAYFE6VGU4MCDGK7DHLLYWHVPUS2YIDJO"
+    "A6Y524TD3AZRM263WTY2BE4DPKIF27WKF3UDNNVSVWRDYIYVJ65IRJJJ6Z25M2DO4YZLBHWFQGVQR5ZLIWEQJOZTS3IQ7JTNCFDX"
+)
+
+EXPIRED_PASS = (
+    "NZCP:/1/2KCEVIQEIVVWK6JNGEASNICZAEP2KALYDZSGSZB2O5SWEOTOPJRXALTDN53GSZBRHEXGQZLBNR2GQLTOPICRUX5AM2FQ"
+    "IGTBPBPYWYTWMOSGQQDDN5XHIZLYOSBHQJTIOR2HA4Z2F4XXO53XFZ3TGLTPOJTS6MRQGE4C6Y3SMVSGK3TUNFQWY4ZPOYYXQKTI"
+    "OR2HA4Z2F4XW46TDOAXGG33WNFSDCOJONBSWC3DUNAXG46RPMNXW45DFPB2HGL3WGFTXMZLSONUW63TFGEXDALRQMR2HS4DFQJ2F"
+    "MZLSNFTGSYLCNRSUG4TFMRSW45DJMFWG6UDVMJWGSY2DN53GSZCQMFZXG4LDOJSWIZLOORUWC3CTOVRGUZLDOSRWSZ3JOZSW4TTB"
+    "NVSWISTBMNVWUZTBNVUWY6KOMFWWKZ2TOBQXE4TPO5RWI33CNIYTSNRQFUYDILJRGYDVA56TNJCCUN2NVK5NGAYOZ6VIWACYIBM3"
+    "QXW7SLCMD2WTJ3GSEI5JH7RXAEURGATOHAHXC2O6BEJKBSVI25ICTBR5SFYUDSVLB2F6SJ63LWJ6Z3FWNHOXF6A2QLJNUFRQNTRU"
+)
+
+EXAMPLE_DID_DOCUMENT = json.dumps(
+    {
+        "@context": "https://w3.org/ns/did/v1",
+        "id": "did:web:nzcp.covid19.health.nz",
+        "verificationMethod": [
+            {
+                "id": "did:web:nzcp.covid19.health.nz#key-1",
+                "controller": "did:web:nzcp.covid19.health.nz",
+                "type": "JsonWebKey2020",
+                "publicKeyJwk": {
+                    "kty": "EC",
+                    "crv": "P-256",
+                    "x": "zRR-XGsCp12Vvbgui4DD6O6cqmhfPuXMhi1OxPl8760",
+                    "y": "Iv5SU6FuW-TRYh5_GOrJlcV_gpF_GpFQhCOD8LSk3T0",
+                },
+            }
+        ],
+        "assertionMethod": ["did:web:nzcp.covid19.health.nz#key-1"],
+    }
+)
+
+OPTIONS = {
+    "trusted_issuers": ["did:web:nzcp.covid19.health.nz"],
+    "at_time": datetime(2026, 1, 1, tzinfo=timezone.utc),
+    "did_document": EXAMPLE_DID_DOCUMENT,
+}
+
+
+def test_valid_pass():
+    pass_ = nzcp.verify_pass_uri(VALID_PASS, **OPTIONS)
+
+    assert pass_.given_name == "Jack"
+    assert pass_.family_name == "Sparrow"
+    assert pass_.dob == date(1960, 4, 16)
+    assert pass_.issuer == "did:web:nzcp.covid19.health.nz"
+    assert pass_.jti == "urn:uuid:60a4f54d-4e30-4332-be33-ad78b1eafa4b"
+    assert pass_.not_before == datetime.fromtimestamp(1635883530, timezone.utc)
+    assert pass_.expiry == datetime.fromtimestamp(1951416330, timezone.utc)
+
+
+def test_expired_pass():
+    with pytest.raises(nzcp.ExpiredError) as error:
+        nzcp.verify_pass_uri(EXPIRED_PASS, **OPTIONS)
+
+    assert isinstance(error.value, nzcp.NzcpError)
+
+
+def test_untrusted_issuer():
+    with pytest.raises(nzcp.UntrustedIssuerError):
+        nzcp.verify_pass_uri(VALID_PASS, **{**OPTIONS, "trusted_issuers": ["did:web:example.com"]})
+
+
+def test_malformed_pass():
+    with pytest.raises(nzcp.MalformedPassError):
+        nzcp.verify_pass_uri("NZCP:/1/", **OPTIONS)
+
+
+def test_invalid_arguments():
+    with pytest.raises(ValueError):
+        nzcp.verify_pass_uri(VALID_PASS, **{**OPTIONS, "trusted_issuers": ["not a DID"]})
+    with pytest.raises(ValueError):
+        nzcp.verify_pass_uri(VALID_PASS, **{**OPTIONS, "did_document": "{}"})
+
+
+def test_verify_many():
+    valid, expired = nzcp.verify_many([VALID_PASS, EXPIRED_PASS], **OPTIONS)
+
+    assert valid == nzcp.verify_pass_uri(VALID_PASS, **OPTIONS)
+    assert isinstance(expired, nzcp.ExpiredError)
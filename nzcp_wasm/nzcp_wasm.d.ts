@@ -0,0 +1,39 @@
+/* tslint:disable */
+/* eslint-disable */
+/**
+ * Verify a pass barcode URI, trusting only the MoH issuer, returning a promise that resolves to the verified
+ * `VerifiedCredentialJs` or rejects with why the pass isn't valid as a string.
+ * @param {string} uri
+ * @returns {Promise<any>}
+ */
+export function verifyPass(uri: string): Promise<any>;
+/**
+ * A verified pass, see `verifyPass`.
+ */
+export class VerifiedCredential {
+  free(): void;
+/**
+ * Given name(s) of the subject of the pass.
+ */
+  readonly givenName: string;
+/**
+ * Family name(s) of the subject of the pass, or `undefined` if they have none.
+ */
+  readonly familyName: string | undefined;
+/**
+ * Date of birth of the subject of the pass, as `YYYY-MM-DD`.
+ */
+  readonly dateOfBirth: string;
+/**
+ * The DID of the issuer of the pass (e.g. `did:web:nzcp.identity.health.nz`).
+ */
+  readonly issuer: string;
+/**
+ * When the pass expires, as an RFC 3339 timestamp.
+ */
+  readonly expiry: string;
+/**
+ * Whether the pass has expired since it was verified.
+ */
+  readonly isExpired: boolean;
+}
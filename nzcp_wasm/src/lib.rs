@@ -1,8 +1,133 @@
+//! JavaScript bindings for verifying NZ COVID Passes, built with `wasm-pack`.
+//!
+//! ```js
+//! import { verifyPass } from "nzcp_wasm";
+//!
+//! try {
+//!     const credential = await verifyPass(uri);
+//!     console.log(credential.givenName, credential.familyName, credential.dateOfBirth);
+//! } catch (error) {
+//!     // the reason the pass isn't valid, as a string
+//! }
+//! ```
+//!
+//! The TypeScript definitions from `wasm-pack build` are checked in as `nzcp_wasm.d.ts`, so changes to the bindings
+//! show up in review. The bindings are tested in node with `wasm-pack test --node`.
+
+use chrono::{DateTime, Utc};
+use js_sys::Promise;
+use nzcp::{PublicCovidPass, VerifiedCredential, Verifier};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::future_to_promise;
+
+/// Verify a pass barcode URI, trusting only the MoH issuer, returning a promise that resolves to the verified
+/// `VerifiedCredentialJs` or rejects with why the pass isn't valid as a string.
+#[wasm_bindgen(js_name = verifyPass)]
+pub fn verify_pass_js(uri: String) -> Promise {
+    #[cfg(feature = "console_error_panic_hook")]
+    console_error_panic_hook::set_once();
+
+    future_to_promise(async move {
+        Verifier::default()
+            .verify_credential_uri::<PublicCovidPass>(&uri)
+            .await
+            .map(|credential| VerifiedCredentialJs::from(credential).into())
+            .map_err(|error| JsValue::from_str(&error.to_string()))
+    })
+}
+
+/// A verified pass, see `verifyPass`.
+#[wasm_bindgen(js_name = VerifiedCredential)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VerifiedCredentialJs {
+    given_name: String,
+    family_name: Option<String>,
+    date_of_birth: String,
+    issuer: String,
+    expiry: DateTime<Utc>,
+}
+
+impl From<VerifiedCredential<PublicCovidPass>> for VerifiedCredentialJs {
+    fn from(credential: VerifiedCredential<PublicCovidPass>) -> Self {
+        VerifiedCredentialJs {
+            date_of_birth: credential.pass.date_of_birth_iso_8601(),
+            given_name: credential.pass.given_name,
+            family_name: credential.pass.family_name,
+            issuer: credential.claims.issuer.to_string(),
+            expiry: credential.claims.expiry,
+        }
+    }
+}
+
+#[wasm_bindgen(js_class = VerifiedCredential)]
+impl VerifiedCredentialJs {
+    /// Given name(s) of the subject of the pass.
+    #[wasm_bindgen(getter = givenName)]
+    pub fn given_name(&self) -> String {
+        self.given_name.clone()
+    }
+
+    /// Family name(s) of the subject of the pass, or `undefined` if they have none.
+    #[wasm_bindgen(getter = familyName)]
+    pub fn family_name(&self) -> Option<String> {
+        self.family_name.clone()
+    }
+
+    /// Date of birth of the subject of the pass, as `YYYY-MM-DD`.
+    #[wasm_bindgen(getter = dateOfBirth)]
+    pub fn date_of_birth(&self) -> String {
+        self.date_of_birth.clone()
+    }
+
+    /// The DID of the issuer of the pass (e.g. `did:web:nzcp.identity.health.nz`).
+    #[wasm_bindgen(getter)]
+    pub fn issuer(&self) -> String {
+        self.issuer.clone()
+    }
+
+    /// When the pass expires, as an RFC 3339 timestamp.
+    #[wasm_bindgen(getter)]
+    pub fn expiry(&self) -> String {
+        self.expiry.to_rfc3339()
+    }
+
+    /// Whether the pass has expired since it was verified.
+    #[wasm_bindgen(getter = isExpired)]
+    pub fn is_expired(&self) -> bool {
+        self.is_expired_at(Utc::now())
+    }
+}
+
+impl VerifiedCredentialJs {
+    /// Whether the pass had expired by `at`.
+    fn is_expired_at(&self, at: DateTime<Utc>) -> bool {
+        self.expiry <= at
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+
+    use nzcp::testing::{example_issuer, test_vectors::scanned_at, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS};
+
+    use super::*;
+
     #[test]
-    fn it_works() {
-        let result = 2 + 2;
-        assert_eq!(result, 4);
+    fn verified_credential() {
+        let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+        let credential = Verifier::new(vec![example_issuer()])
+            .with_resolver(Arc::new(resolver))
+            .verify_credential_uri_at_blocking::<PublicCovidPass>(VALID_PASS, scanned_at())
+            .unwrap();
+
+        let credential = VerifiedCredentialJs::from(credential);
+        assert_eq!(credential.given_name(), "Jack");
+        assert_eq!(credential.family_name().as_deref(), Some("Sparrow"));
+        assert_eq!(credential.date_of_birth(), "1960-04-16");
+        assert_eq!(credential.issuer(), "did:web:nzcp.covid19.health.nz");
+        assert_eq!(credential.expiry(), "2031-11-02T20:05:30+00:00");
+        assert!(!credential.is_expired_at(scanned_at()));
+        assert!(credential.is_expired_at(credential.expiry));
     }
 }
@@ -0,0 +1,29 @@
+//! Run with `wasm-pack test --node nzcp_wasm`.
+
+#![cfg(target_arch = "wasm32")]
+
+use std::sync::Arc;
+
+use nzcp::{
+    testing::{example_issuer, test_vectors::scanned_at, MockDIDResolver, EXAMPLE_DID_DOCUMENT, VALID_PASS},
+    PublicCovidPass, Verifier,
+};
+use nzcp_wasm::VerifiedCredentialJs;
+use wasm_bindgen_test::wasm_bindgen_test;
+
+#[wasm_bindgen_test]
+async fn verify_example_pass() {
+    let resolver = MockDIDResolver::new().with_document(&example_issuer(), EXAMPLE_DID_DOCUMENT);
+    let credential = Verifier::new(vec![example_issuer()])
+        .with_resolver(Arc::new(resolver))
+        .verify_credential_uri_at::<PublicCovidPass>(VALID_PASS, scanned_at())
+        .await
+        .unwrap();
+
+    let credential = VerifiedCredentialJs::from(credential);
+    assert_eq!(credential.given_name(), "Jack");
+    assert_eq!(credential.family_name().as_deref(), Some("Sparrow"));
+    assert_eq!(credential.date_of_birth(), "1960-04-16");
+    assert_eq!(credential.issuer(), "did:web:nzcp.covid19.health.nz");
+    assert_eq!(credential.expiry(), "2031-11-02T20:05:30+00:00");
+}